@@ -0,0 +1,95 @@
+use bevy::ecs::{Command, Commands, Entity, Resources, World};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts allocations, to check that a warmed up [Commands] queue doesn't allocate
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+struct Position(f32);
+
+/// Moves an entity without changing its archetype, so applying the command doesn't allocate in the [World] either
+struct Move {
+    entity: Entity,
+    distance: f32,
+}
+
+impl Command for Move {
+    fn write(self, world: &mut World, _resources: &mut Resources) {
+        world.get_mut::<Position>(self.entity).unwrap().0 += self.distance;
+    }
+}
+
+fn run_frame(
+    commands: &mut Commands,
+    world: &mut World,
+    resources: &mut Resources,
+    entities: &[Entity],
+) {
+    for entity in entities {
+        commands.add_command(Move {
+            entity: *entity,
+            distance: 1.0,
+        });
+    }
+    commands.apply(world, resources);
+}
+
+fn bench_queue_reuse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("command_allocations");
+    for entity_count in &[100u32, 1_000, 10_000] {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        let entities = world
+            .spawn_batch((0..*entity_count).map(|i| (Position(i as f32),)))
+            .collect::<Vec<_>>();
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+
+        // the first frame grows the queue, the ones after it reuse its buffers
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        run_frame(&mut commands, &mut world, &mut resources, &entities);
+        let warmup = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        run_frame(&mut commands, &mut world, &mut resources, &entities);
+        let steady = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        println!(
+            "{} commands per frame: {} allocations in the first frame, {} after warmup",
+            entity_count, warmup, steady
+        );
+        assert_eq!(steady, 0, "a warmed up command queue shouldn't allocate");
+
+        group.bench_with_input(
+            BenchmarkId::new("reused_queue", entity_count),
+            entity_count,
+            |b, _| b.iter(|| run_frame(&mut commands, &mut world, &mut resources, &entities)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_queue_reuse);
+criterion_main!(benches);
@@ -0,0 +1,100 @@
+use bevy::ecs::{Commands, Resources, World};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+struct Position(f32);
+struct Velocity(f32);
+
+fn bench_spawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("commands_spawn");
+    for entity_count in &[100u32, 1_000, 10_000] {
+        group.bench_with_input(
+            BenchmarkId::new("spawn", entity_count),
+            entity_count,
+            |b, &entity_count| {
+                let mut world = World::new();
+                let mut resources = Resources::default();
+                let mut commands = Commands::default();
+                commands.set_entity_reserver(world.get_entity_reserver());
+                b.iter(|| {
+                    for i in 0..entity_count {
+                        commands.spawn((Position(i as f32), Velocity(1.0)));
+                    }
+                    commands.apply(&mut world, &mut resources);
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("spawn_batch", entity_count),
+            entity_count,
+            |b, &entity_count| {
+                let mut world = World::new();
+                let mut resources = Resources::default();
+                let mut commands = Commands::default();
+                commands.set_entity_reserver(world.get_entity_reserver());
+                b.iter(|| {
+                    commands.spawn_batch(
+                        (0..entity_count).map(|i| (Position(i as f32), Velocity(1.0))),
+                    );
+                    commands.apply(&mut world, &mut resources);
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+struct A(f32);
+struct B(f32);
+struct C(f32);
+struct D(f32);
+struct E(f32);
+
+fn bench_remove(c: &mut Criterion) {
+    let mut group = c.benchmark_group("commands_remove");
+    let entity_count = 1_000;
+    group.bench_function("remove_bundle", |b| {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+        b.iter(|| {
+            let entities = world
+                .spawn_batch(
+                    (0..entity_count).map(|i| (A(i as f32), B(0.0), C(0.0), D(0.0), E(0.0))),
+                )
+                .collect::<Vec<_>>();
+            for entity in entities.iter() {
+                commands.remove_bundle::<(A, B, C, D, E)>(*entity);
+            }
+            commands.apply(&mut world, &mut resources);
+            world.clear();
+        });
+    });
+    group.bench_function("remove_one_each", |b| {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+        b.iter(|| {
+            let entities = world
+                .spawn_batch(
+                    (0..entity_count).map(|i| (A(i as f32), B(0.0), C(0.0), D(0.0), E(0.0))),
+                )
+                .collect::<Vec<_>>();
+            for entity in entities.iter() {
+                commands
+                    .remove_one::<A>(*entity)
+                    .remove_one::<B>(*entity)
+                    .remove_one::<C>(*entity)
+                    .remove_one::<D>(*entity)
+                    .remove_one::<E>(*entity);
+            }
+            commands.apply(&mut world, &mut resources);
+            world.clear();
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_spawn, bench_remove);
+criterion_main!(benches);
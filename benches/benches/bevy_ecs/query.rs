@@ -0,0 +1,156 @@
+use bevy::{
+    ecs::{IntoSystem, Query, Res, Resources, World},
+    tasks::{ComputeTaskPool, ParallelIterator, TaskPoolBuilder},
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const ENTITY_COUNT: usize = 100_000;
+
+struct Position(f32);
+struct Velocity(f32);
+
+fn busy_work(n: usize) {
+    let mut i = n;
+    while i > 0 {
+        i = black_box(i - 1);
+    }
+}
+
+fn setup(thread_count: usize) -> (World, Resources) {
+    let mut world = World::new();
+    world.spawn_batch((0..ENTITY_COUNT).map(|i| (Position(0.0), Velocity(i as f32))));
+    let mut resources = Resources::default();
+    resources.insert(ComputeTaskPool(
+        TaskPoolBuilder::new().num_threads(thread_count).build(),
+    ));
+    (world, resources)
+}
+
+fn bench_query_iter(c: &mut Criterion) {
+    fn iter_system(mut query: Query<(&mut Position, &Velocity)>) {
+        for (mut position, velocity) in query.iter_mut() {
+            busy_work(100);
+            position.0 += velocity.0;
+        }
+    }
+
+    let (mut world, mut resources) = setup(1);
+    let mut system = iter_system.system();
+    system.initialize(&mut world, &mut resources);
+    system.update(&world);
+    c.bench_function("query_iter_mut", |b| {
+        b.iter(|| system.run((), &world, &resources));
+    });
+
+    // small batches spread the work evenly but pay for a task per batch, while large batches keep the overhead
+    // low but leave threads idle once the remaining batches run out
+    let mut group = c.benchmark_group("query_par_iter_mut");
+    for batch_size in &[64usize, 256, 1024, 4096, 16384] {
+        fn par_iter_system(
+            batch_size: Res<usize>,
+            pool: Res<ComputeTaskPool>,
+            mut query: Query<(&mut Position, &Velocity)>,
+        ) {
+            query
+                .par_iter_mut(*batch_size)
+                .for_each(&pool, |(mut position, velocity)| {
+                    busy_work(100);
+                    position.0 += velocity.0;
+                });
+        }
+
+        let (mut world, mut resources) = setup(8);
+        resources.insert(*batch_size);
+        let mut system = par_iter_system.system();
+        system.initialize(&mut world, &mut resources);
+        system.update(&world);
+        group.bench_with_input(
+            BenchmarkId::new("batch_size", batch_size),
+            batch_size,
+            |b, _| {
+                b.iter(|| system.run((), &world, &resources));
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_query_for_each(c: &mut Criterion) {
+    fn iter_mut_system(mut query: Query<(&mut Position, &Velocity)>) {
+        for (mut position, velocity) in query.iter_mut() {
+            position.0 += velocity.0;
+        }
+    }
+
+    fn for_each_mut_system(mut query: Query<(&mut Position, &Velocity)>) {
+        query.for_each_mut(|(mut position, velocity)| {
+            position.0 += velocity.0;
+        });
+    }
+
+    let mut world = World::new();
+    world.spawn_batch((0..1_000_000).map(|i| (Position(0.0), Velocity(i as f32))));
+    let mut resources = Resources::default();
+
+    let mut group = c.benchmark_group("query_loop");
+    let mut iter_mut = iter_mut_system.system();
+    iter_mut.initialize(&mut world, &mut resources);
+    iter_mut.update(&world);
+    group.bench_function("iter_mut", |b| {
+        b.iter(|| iter_mut.run((), &world, &resources));
+    });
+
+    let mut for_each_mut = for_each_mut_system.system();
+    for_each_mut.initialize(&mut world, &mut resources);
+    for_each_mut.update(&world);
+    group.bench_function("for_each_mut", |b| {
+        b.iter(|| for_each_mut.run((), &world, &resources));
+    });
+    group.finish();
+}
+
+fn bench_query_sorted(c: &mut Criterion) {
+    // scatters the keys, so that iteration order is far from sorted order
+    fn sort_key(velocity: &Velocity) -> i64 {
+        (velocity.0 as i64).wrapping_mul(2_654_435_761) % 1_000_003
+    }
+
+    fn collect_and_sort_system(query: Query<(&Position, &Velocity)>) {
+        let mut items = query.iter().collect::<Vec<_>>();
+        items.sort_by_key(|(_, velocity)| sort_key(velocity));
+        for (position, _) in items {
+            black_box(position.0);
+        }
+    }
+
+    fn iter_sorted_by_key_system(query: Query<(&Position, &Velocity)>) {
+        for (position, _) in query.iter_sorted_by_key(|(_, velocity)| sort_key(velocity)) {
+            black_box(position.0);
+        }
+    }
+
+    let (mut world, mut resources) = setup(1);
+    let mut group = c.benchmark_group("query_sorted");
+    let mut collect_and_sort = collect_and_sort_system.system();
+    collect_and_sort.initialize(&mut world, &mut resources);
+    collect_and_sort.update(&world);
+    group.bench_function("collect_and_sort", |b| {
+        b.iter(|| collect_and_sort.run((), &world, &resources));
+    });
+
+    let mut iter_sorted_by_key = iter_sorted_by_key_system.system();
+    iter_sorted_by_key.initialize(&mut world, &mut resources);
+    iter_sorted_by_key.update(&world);
+    group.bench_function("iter_sorted_by_key", |b| {
+        b.iter(|| iter_sorted_by_key.run((), &world, &resources));
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_query_iter,
+    bench_query_for_each,
+    bench_query_sorted
+);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+use bevy::ecs::{
+    AlwaysRunExt, BoxedSystem, IntoSystem, ParallelExecutor, Query, Resources, Schedule, World,
+};
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+struct Health(f32);
+struct Burning;
+struct Particle;
+struct Marker<const N: usize>;
+
+fn burn(mut query: Query<(&mut Health, &Burning)>) {
+    for (mut health, _) in query.iter_mut() {
+        health.0 -= 1.0;
+    }
+}
+
+fn cleanup_particles(query: Query<&Particle>) {
+    for _particle in query.iter() {}
+}
+
+/// 10 archetypes that the systems don't match, with 100 entities each
+fn populated_world() -> World {
+    macro_rules! spawn_archetypes {
+        ($($n:literal),*) => {{
+            let mut world = World::new();
+            $(world.spawn_batch((0..100).map(|_| (Health(100.0), Marker::<$n>)));)*
+            world
+        }};
+    }
+    spawn_archetypes!(0, 1, 2, 3, 4, 5, 6, 7, 8, 9)
+}
+
+fn bench_empty_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("empty_queries");
+    for always_run in [false, true].iter() {
+        let mut world = populated_world();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        for i in 0..500 {
+            let system: BoxedSystem = if i % 2 == 0 {
+                burn.system()
+            } else {
+                cleanup_particles.system()
+            };
+            let system = if *always_run {
+                system.always_run()
+            } else {
+                system
+            };
+            schedule.add_system_to_stage("update", system);
+        }
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+        executor.initialize(&mut resources);
+
+        let name = if *always_run { "always_run" } else { "skipped" };
+        group.bench_function(name, |b| {
+            b.iter(|| executor.run(&mut schedule, &mut world, &mut resources));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_empty_queries);
+criterion_main!(benches);
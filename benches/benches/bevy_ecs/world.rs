@@ -0,0 +1,91 @@
+use bevy::ecs::{Entity, StorageType, World};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const ENTITY_COUNT: u32 = 100_000;
+
+struct Position(f32);
+struct Velocity(f32);
+#[derive(Clone)]
+struct Chunk(u32);
+struct Selected;
+
+fn bench_spawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("world_spawn");
+    group.bench_function("spawn", |b| {
+        b.iter(|| {
+            let mut world = World::new();
+            for i in 0..ENTITY_COUNT {
+                world.spawn((Position(i as f32), Velocity(1.0)));
+            }
+        });
+    });
+    group.bench_function("spawn_batch", |b| {
+        b.iter(|| {
+            let mut world = World::new();
+            world.spawn_batch((0..ENTITY_COUNT).map(|i| (Position(i as f32), Velocity(1.0))));
+        });
+    });
+    group.finish();
+}
+
+fn bench_insert(c: &mut Criterion) {
+    fn setup() -> (World, Vec<Entity>) {
+        let mut world = World::new();
+        let entities = world
+            .spawn_batch((0..ENTITY_COUNT).map(|i| (Position(i as f32), Velocity(1.0))))
+            .collect();
+        (world, entities)
+    }
+
+    let mut group = c.benchmark_group("world_insert");
+    group.bench_function("insert", |b| {
+        b.iter_with_setup(setup, |(mut world, entities)| {
+            for entity in entities {
+                world.insert_one(entity, Chunk(0)).unwrap();
+            }
+        });
+    });
+    group.bench_function("insert_batch", |b| {
+        b.iter_with_setup(setup, |(mut world, entities)| {
+            world.insert_batch(entities, (Chunk(0),)).unwrap();
+        });
+    });
+    group.finish();
+}
+
+fn bench_add_remove(c: &mut Criterion) {
+    fn setup(storage_type: StorageType) -> (World, Vec<Entity>) {
+        let mut world = World::new();
+        world.register_component::<Selected>(storage_type);
+        let entities = world
+            .spawn_batch((0..ENTITY_COUNT).map(|i| (Position(i as f32), Velocity(1.0))))
+            .collect();
+        (world, entities)
+    }
+
+    let mut group = c.benchmark_group("world_add_remove");
+    for (name, storage_type) in [
+        ("table", StorageType::Table),
+        ("sparse_set", StorageType::SparseSet),
+    ]
+    .iter()
+    {
+        group.bench_function(*name, |b| {
+            b.iter_with_setup(
+                || setup(*storage_type),
+                |(mut world, entities)| {
+                    for entity in entities.iter() {
+                        world.insert_one(*entity, Selected).unwrap();
+                    }
+                    for entity in entities.iter() {
+                        world.remove_one::<Selected>(*entity).unwrap();
+                    }
+                },
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_spawn, bench_insert, bench_add_remove);
+criterion_main!(benches);
@@ -1,7 +1,14 @@
-use crate::app_builder::AppBuilder;
+use crate::{
+    app_builder::AppBuilder,
+    event::{Events, ManualEventReader},
+};
 use bevy_ecs::{ParallelExecutor, Resources, Schedule, World};
 #[cfg(feature = "trace")]
 use bevy_utils::tracing::info_span;
+use std::{
+    num::NonZeroU8,
+    process::{ExitCode, Termination},
+};
 
 #[allow(clippy::needless_doctest_main)]
 /// Containers of app logic and data
@@ -28,7 +35,7 @@ use bevy_utils::tracing::info_span;
 pub struct App {
     pub world: World,
     pub resources: Resources,
-    pub runner: Box<dyn Fn(App)>,
+    pub runner: Box<dyn FnOnce(App) -> AppExit>,
     pub schedule: Schedule,
     pub executor: ParallelExecutor,
     pub startup_schedule: Schedule,
@@ -49,9 +56,13 @@ impl Default for App {
     }
 }
 
-fn run_once(mut app: App) {
+fn run_once(mut app: App) -> AppExit {
     app.initialize();
     app.update();
+    app.resources
+        .get::<Events<AppExit>>()
+        .and_then(|exits| AppExit::from_events(ManualEventReader::default().iter(&exits)))
+        .unwrap_or_default()
 }
 
 impl App {
@@ -59,6 +70,8 @@ impl App {
         AppBuilder::default()
     }
 
+    /// Runs one pass of the app's schedule, initializing systems added since the last pass first. Runners call this
+    /// once per frame, and tests and external loops can call it to step an app frame by frame.
     pub fn update(&mut self) {
         self.schedule
             .initialize(&mut self.world, &mut self.resources);
@@ -66,6 +79,8 @@ impl App {
             .run(&mut self.schedule, &mut self.world, &mut self.resources);
     }
 
+    /// Runs the startup schedule. Runners call this once before the first [App::update], and commands issued by
+    /// startup systems are applied before it returns.
     pub fn initialize(&mut self) {
         #[cfg(feature = "trace")]
         let startup_schedule_span = info_span!("startup_schedule");
@@ -81,7 +96,8 @@ impl App {
         );
     }
 
-    pub fn run(mut self) {
+    /// Hands the app to its runner, which returns once the app exits. The World and Resources are dropped by then.
+    pub fn run(mut self) -> AppExit {
         #[cfg(feature = "trace")]
         let bevy_app_run_span = info_span!("bevy_app_run");
         #[cfg(feature = "trace")]
@@ -90,10 +106,225 @@ impl App {
         self.executor.initialize(&mut self.resources);
 
         let runner = std::mem::replace(&mut self.runner, Box::new(run_once));
-        (runner)(self);
+        (runner)(self)
     }
 }
 
-/// An event that indicates the app should exit. This will fully exit the app process.
-#[derive(Debug, Clone)]
-pub struct AppExit;
+/// An event that indicates the app should exit. The runner stops at the end of the frame it was sent in, drops the
+/// [App] so `Drop` impls of resources and components run, and returns the exit from [App::run].
+///
+/// `AppExit` implements [Termination], so returning it from `main` makes [AppExit::error] the exit status of the
+/// process:
+/// ```no_run
+///use bevy_app::prelude::*;
+///
+///fn main() -> AppExit {
+///    App::build().run()
+///}
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AppExit {
+    /// The app finished without errors
+    #[default]
+    Success,
+    /// The app failed, with the given process exit code
+    Error(NonZeroU8),
+}
+
+impl AppExit {
+    /// Creates an exit that ends the process with the given exit code.
+    ///
+    /// # Panics
+    /// Panics if `code` is 0, which is reserved for [AppExit::Success].
+    pub fn error(code: u8) -> Self {
+        AppExit::Error(
+            NonZeroU8::new(code).expect("an AppExit error code can't be 0, use AppExit::Success"),
+        )
+    }
+
+    /// The process exit code of this exit
+    pub fn code(&self) -> u8 {
+        match self {
+            AppExit::Success => 0,
+            AppExit::Error(code) => code.get(),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, AppExit::Success)
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, AppExit::Error(_))
+    }
+
+    /// Combines the exits sent during one frame. The first error wins, so a failure isn't hidden by a system that
+    /// asked for a clean exit in the same frame. Returns `None` if no exit was sent.
+    pub fn from_events<'a>(exits: impl IntoIterator<Item = &'a AppExit>) -> Option<AppExit> {
+        let mut result = None;
+        for exit in exits {
+            if exit.is_error() {
+                return Some(*exit);
+            }
+            result = Some(*exit);
+        }
+        result
+    }
+}
+
+impl Termination for AppExit {
+    fn report(self) -> ExitCode {
+        ExitCode::from(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{App, AppExit};
+    use crate::{stage, AppBuilder, Plugin};
+    use bevy_ecs::{Commands, FromResources, IntoSystem, Query, ResMut, Resources};
+    use std::{cell::Cell, rc::Rc};
+
+    struct Player;
+
+    #[derive(Default)]
+    struct Counts {
+        startup_runs: usize,
+        frames: usize,
+    }
+
+    fn spawn_player(commands: &mut Commands, mut counts: ResMut<Counts>) {
+        counts.startup_runs += 1;
+        commands.spawn((Player,));
+    }
+
+    fn count_players(query: Query<&Player>, mut counts: ResMut<Counts>) {
+        assert_eq!(query.iter().count(), 1);
+        counts.frames += 1;
+    }
+
+    #[test]
+    fn startup_systems_run_once_before_first_frame() {
+        let mut builder = App::build();
+        builder
+            .init_resource::<Counts>()
+            .add_startup_system(spawn_player.system())
+            .add_system(count_players.system());
+        let mut app = builder.app;
+
+        app.initialize();
+        app.update();
+        {
+            let counts = app.resources.get::<Counts>().unwrap();
+            assert_eq!(counts.startup_runs, 1);
+            assert_eq!(counts.frames, 1);
+        }
+
+        app.update();
+        let counts = app.resources.get::<Counts>().unwrap();
+        assert_eq!(counts.startup_runs, 1);
+        assert_eq!(counts.frames, 2);
+    }
+
+    #[test]
+    fn custom_runner_steps_frames() {
+        let frames = Rc::new(Cell::new(0));
+        let runner_frames = frames.clone();
+        let exit = App::build()
+            .init_resource::<Counts>()
+            .add_startup_system(spawn_player.system())
+            .add_system(count_players.system())
+            .set_runner(move |mut app: App| {
+                app.initialize();
+                for _ in 0..5 {
+                    app.update();
+                }
+                runner_frames.set(app.resources.get::<Counts>().unwrap().frames);
+                AppExit::Success
+            })
+            .run();
+
+        assert_eq!(exit, AppExit::Success);
+        assert_eq!(frames.get(), 5);
+    }
+
+    struct AdapterInfo {
+        max_texture_size: u32,
+    }
+
+    struct RenderLimits {
+        max_texture_size: u32,
+    }
+
+    impl FromResources for RenderLimits {
+        fn from_resources(resources: &Resources) -> Self {
+            RenderLimits {
+                max_texture_size: resources.expect::<AdapterInfo>().max_texture_size / 2,
+            }
+        }
+    }
+
+    struct AdapterPlugin;
+
+    impl Plugin for AdapterPlugin {
+        fn build(&self, app: &mut AppBuilder) {
+            app.add_resource(AdapterInfo {
+                max_texture_size: 8192,
+            });
+        }
+    }
+
+    struct RenderLimitsPlugin;
+
+    impl Plugin for RenderLimitsPlugin {
+        fn build(&self, app: &mut AppBuilder) {
+            app.init_resource::<RenderLimits>();
+        }
+    }
+
+    #[test]
+    fn init_resource_from_earlier_plugin() {
+        let mut builder = App::build();
+        builder
+            .add_plugin(AdapterPlugin)
+            .add_plugin(RenderLimitsPlugin);
+        let limits = builder.resources().get::<RenderLimits>().unwrap();
+        assert_eq!(limits.max_texture_size, 4096);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "RenderLimits is created from the resource bevy_app::app::tests::AdapterInfo"
+    )]
+    fn init_resource_before_dependency() {
+        App::build()
+            .add_plugin(RenderLimitsPlugin)
+            .add_plugin(AdapterPlugin);
+    }
+
+    struct PrePropagationPlugin;
+
+    impl Plugin for PrePropagationPlugin {
+        fn build(&self, app: &mut AppBuilder) {
+            app.add_stage_before(stage::POST_UPDATE, "pre_propagation")
+                .add_stage_after("pre_propagation", "pre_propagation_late");
+        }
+    }
+
+    #[test]
+    fn plugin_inserts_stages_between_default_stages() {
+        let mut builder = App::build();
+        builder.add_plugin(PrePropagationPlugin);
+        let stage_names = builder.app.schedule.stage_names().collect::<Vec<_>>();
+        let update = stage_names.iter().position(|name| *name == stage::UPDATE);
+        assert_eq!(
+            stage_names[update.unwrap()..update.unwrap() + 4],
+            [
+                stage::UPDATE,
+                "pre_propagation",
+                "pre_propagation_late",
+                stage::POST_UPDATE
+            ]
+        );
+    }
+}
@@ -1,11 +1,15 @@
 use crate::{
     app::{App, AppExit},
-    event::Events,
+    event::{EventSettings, Events},
     plugin::Plugin,
     stage, startup_stage, PluginGroup, PluginGroupBuilder,
 };
-use bevy_ecs::{FromResources, IntoSystem, Resources, System, World};
+use bevy_ecs::{
+    BoxedSystem, Component, ExecutionMode, FromResources, IntoSystem, Resources, ShouldRun, Stage,
+    SystemDescriptor, World,
+};
 use bevy_utils::tracing::debug;
+use std::hash::Hash;
 
 /// Configure [App]s using the builder pattern
 pub struct AppBuilder {
@@ -39,9 +43,9 @@ impl AppBuilder {
         &mut self.app.resources
     }
 
-    pub fn run(&mut self) {
+    pub fn run(&mut self) -> AppExit {
         let app = std::mem::take(&mut self.app);
-        app.run();
+        app.run()
     }
 
     pub fn set_world(&mut self, world: World) -> &mut Self {
@@ -54,6 +58,12 @@ impl AppBuilder {
         self
     }
 
+    /// Adds a custom [Stage] after every other stage
+    pub fn add_stage_with(&mut self, stage_name: &'static str, stage: impl Stage) -> &mut Self {
+        self.app.schedule.add_stage_with(stage_name, stage);
+        self
+    }
+
     pub fn add_stage_after(&mut self, target: &'static str, stage_name: &'static str) -> &mut Self {
         self.app.schedule.add_stage_after(target, stage_name);
         self
@@ -68,6 +78,19 @@ impl AppBuilder {
         self
     }
 
+    /// Runs the systems in `stage_name` only when `run_criteria` allows it. See [ShouldRun] for how each result is
+    /// handled.
+    pub fn set_stage_run_criteria<Params>(
+        &mut self,
+        stage_name: &'static str,
+        run_criteria: impl IntoSystem<Params, ShouldRun>,
+    ) -> &mut Self {
+        self.app
+            .schedule
+            .set_stage_run_criteria(stage_name, run_criteria);
+        self
+    }
+
     pub fn add_startup_stage(&mut self, stage_name: &'static str) -> &mut Self {
         self.app.startup_schedule.add_stage(stage_name);
         self
@@ -95,34 +118,65 @@ impl AppBuilder {
         self
     }
 
-    pub fn add_system(&mut self, system: Box<dyn System>) -> &mut Self {
+    pub fn add_system(&mut self, system: impl Into<SystemDescriptor>) -> &mut Self {
         self.add_system_to_stage(stage::UPDATE, system)
     }
 
-    pub fn add_systems(&mut self, systems: Vec<Box<dyn System>>) -> &mut Self {
+    pub fn add_systems(&mut self, systems: Vec<BoxedSystem>) -> &mut Self {
         self.add_systems_to_stage(stage::UPDATE, systems)
     }
 
-    pub fn init_system(
-        &mut self,
-        build: impl FnMut(&mut Resources) -> Box<dyn System>,
-    ) -> &mut Self {
+    pub fn init_system(&mut self, build: impl FnMut(&mut Resources) -> BoxedSystem) -> &mut Self {
         self.init_system_to_stage(stage::UPDATE, build)
     }
 
     pub fn init_system_to_stage(
         &mut self,
         stage: &'static str,
-        mut build: impl FnMut(&mut Resources) -> Box<dyn System>,
+        mut build: impl FnMut(&mut Resources) -> BoxedSystem,
     ) -> &mut Self {
         let system = build(&mut self.app.resources);
         self.add_system_to_stage(stage, system)
     }
 
+    /// Runs `system` in `stage` when the [State](bevy_ecs::State) changes to `state`. See
+    /// [Schedule::on_state_enter](bevy_ecs::Schedule::on_state_enter).
+    pub fn on_state_enter<T: Component + Clone + Eq + Hash>(
+        &mut self,
+        stage: &'static str,
+        state: T,
+        system: BoxedSystem,
+    ) -> &mut Self {
+        self.app.schedule.on_state_enter(stage, state, system);
+        self
+    }
+
+    /// Runs `system` in `stage` while the [State](bevy_ecs::State) is `state`
+    pub fn on_state_update<T: Component + Clone + Eq + Hash>(
+        &mut self,
+        stage: &'static str,
+        state: T,
+        system: impl Into<SystemDescriptor>,
+    ) -> &mut Self {
+        self.app.schedule.on_state_update(stage, state, system);
+        self
+    }
+
+    /// Runs `system` in `stage` when the [State](bevy_ecs::State) changes away from `state`
+    pub fn on_state_exit<T: Component + Clone + Eq + Hash>(
+        &mut self,
+        stage: &'static str,
+        state: T,
+        system: BoxedSystem,
+    ) -> &mut Self {
+        self.app.schedule.on_state_exit(stage, state, system);
+        self
+    }
+
     pub fn add_startup_system_to_stage(
         &mut self,
         stage_name: &'static str,
-        system: Box<dyn System>,
+        system: impl Into<SystemDescriptor>,
     ) -> &mut Self {
         self.app
             .startup_schedule
@@ -133,7 +187,7 @@ impl AppBuilder {
     pub fn add_startup_systems_to_stage(
         &mut self,
         stage_name: &'static str,
-        systems: Vec<Box<dyn System>>,
+        systems: Vec<BoxedSystem>,
     ) -> &mut Self {
         for system in systems {
             self.app
@@ -143,20 +197,20 @@ impl AppBuilder {
         self
     }
 
-    pub fn add_startup_system(&mut self, system: Box<dyn System>) -> &mut Self {
+    pub fn add_startup_system(&mut self, system: impl Into<SystemDescriptor>) -> &mut Self {
         self.app
             .startup_schedule
             .add_system_to_stage(startup_stage::STARTUP, system);
         self
     }
 
-    pub fn add_startup_systems(&mut self, systems: Vec<Box<dyn System>>) -> &mut Self {
+    pub fn add_startup_systems(&mut self, systems: Vec<BoxedSystem>) -> &mut Self {
         self.add_startup_systems_to_stage(startup_stage::STARTUP, systems)
     }
 
     pub fn init_startup_system(
         &mut self,
-        build: impl FnMut(&mut Resources) -> Box<dyn System>,
+        build: impl FnMut(&mut Resources) -> BoxedSystem,
     ) -> &mut Self {
         self.init_startup_system_to_stage(startup_stage::STARTUP, build)
     }
@@ -164,7 +218,7 @@ impl AppBuilder {
     pub fn init_startup_system_to_stage(
         &mut self,
         stage: &'static str,
-        mut build: impl FnMut(&mut Resources) -> Box<dyn System>,
+        mut build: impl FnMut(&mut Resources) -> BoxedSystem,
     ) -> &mut Self {
         let system = build(&mut self.app.resources);
         self.add_startup_system_to_stage(stage, system)
@@ -186,7 +240,7 @@ impl AppBuilder {
     pub fn add_system_to_stage(
         &mut self,
         stage_name: &'static str,
-        system: Box<dyn System>,
+        system: impl Into<SystemDescriptor>,
     ) -> &mut Self {
         self.app.schedule.add_system_to_stage(stage_name, system);
         self
@@ -195,7 +249,7 @@ impl AppBuilder {
     pub fn add_system_to_stage_front(
         &mut self,
         stage_name: &'static str,
-        system: Box<dyn System>,
+        system: impl Into<SystemDescriptor>,
     ) -> &mut Self {
         self.app
             .schedule
@@ -206,7 +260,7 @@ impl AppBuilder {
     pub fn add_systems_to_stage(
         &mut self,
         stage_name: &'static str,
-        systems: Vec<Box<dyn System>>,
+        systems: Vec<BoxedSystem>,
     ) -> &mut Self {
         for system in systems {
             self.app.schedule.add_system_to_stage(stage_name, system);
@@ -214,12 +268,26 @@ impl AppBuilder {
         self
     }
 
+    /// Registers the event type `T`. Its [Events] are cleared automatically, so each event is dropped once it is two
+    /// frames old.
     pub fn add_event<T>(&mut self) -> &mut Self
     where
         T: Send + Sync + 'static,
     {
-        self.add_resource(Events::<T>::default())
-            .add_system_to_stage(stage::EVENT, Events::<T>::update_system.system())
+        self.add_event_with_settings::<T>(EventSettings::Automatic)
+    }
+
+    /// Registers the event type `T`. With [EventSettings::Manual], its [Events] are only cleared by explicit calls to
+    /// [Events::drain] or [Events::clear].
+    pub fn add_event_with_settings<T>(&mut self, settings: EventSettings) -> &mut Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.add_resource(Events::<T>::default());
+        if settings == EventSettings::Automatic {
+            self.add_system_to_stage(stage::EVENT, Events::<T>::update_system.system());
+        }
+        self
     }
 
     /// Adds a resource to the current [App] and overwrites any resource previously added of the same type.
@@ -239,11 +307,15 @@ impl AppBuilder {
         self
     }
 
+    /// Creates the resource `R` with [FromResources] and inserts it. Resources are created in the order they are
+    /// initialized in, so `R` can be created from the resources that were inserted before it, like the ones inserted
+    /// by plugins added earlier. Use [Resources::expect](bevy_ecs::Resources::expect) in [FromResources::from_resources]
+    /// to get them, which panics naming both `R` and the missing resource if one wasn't inserted yet.
     pub fn init_resource<R>(&mut self) -> &mut Self
     where
         R: FromResources + Send + Sync + 'static,
     {
-        let resource = R::from_resources(&self.app.resources);
+        let resource = self.app.resources.create::<R>();
         self.app.resources.insert(resource);
 
         self
@@ -253,13 +325,24 @@ impl AppBuilder {
     where
         R: FromResources + 'static,
     {
-        let resource = R::from_resources(&self.app.resources);
+        let resource = self.app.resources.create::<R>();
         self.app.resources.insert_thread_local(resource);
 
         self
     }
 
-    pub fn set_runner(&mut self, run_fn: impl Fn(App) + 'static) -> &mut Self {
+    /// Sets how both the startup schedule and the update schedule run their systems. [ExecutionMode::Serial] makes
+    /// every run of the app deterministic, at the cost of running on a single thread.
+    pub fn set_execution_mode(&mut self, mode: ExecutionMode) -> &mut Self {
+        self.app.executor.set_mode(mode);
+        self.app.startup_executor.set_mode(mode);
+        self
+    }
+
+    /// Sets the function that drives the app once it's built, which is called once by [App::run]. The runner calls
+    /// [App::initialize] to run the startup systems, then [App::update] for every frame, and returns the [AppExit] that
+    /// ended the app. This is how an app is driven from an event loop owned by someone else.
+    pub fn set_runner(&mut self, run_fn: impl FnOnce(App) -> AppExit + 'static) -> &mut Self {
         self.app.runner = Box::new(run_fn);
         self
     }
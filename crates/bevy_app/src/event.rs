@@ -1,5 +1,8 @@
-use bevy_ecs::ResMut;
-use std::marker::PhantomData;
+use bevy_ecs::{
+    Command, Commands, Local, Res, ResMut, Resource, Resources, SystemParam, SystemState, World,
+};
+use parking_lot::Mutex;
+use std::{marker::PhantomData, sync::Arc};
 
 #[derive(Debug)]
 struct EventInstance<T> {
@@ -53,8 +56,8 @@ enum State {
 ///
 /// The buffers in [Events] will grow indefinitely if [Events::update] is never called.
 ///
-/// An alternative call pattern would be to call [Events::update] manually across frames to control when events are cleared. However
-/// this complicates consumption
+/// Events that are registered with [EventSettings::Manual] are never updated automatically. They are kept until they are removed
+/// with [Events::drain] or [Events::clear], so readers that don't run every frame can still see them.
 #[derive(Debug)]
 pub struct Events<T> {
     events_a: Vec<EventInstance<T>>,
@@ -78,17 +81,35 @@ impl<T> Default for Events<T> {
     }
 }
 
+/// How the [Events] of a type are cleared, chosen when the event type is registered with
+/// [AppBuilder::add_event_with_settings](crate::AppBuilder::add_event_with_settings)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSettings {
+    /// [Events::update_system] runs every frame, so events are dropped once they are two frames old
+    Automatic,
+    /// Events are kept until they are removed with [Events::drain] or [Events::clear]. Use this for events that are
+    /// read by systems that don't run every frame, like systems behind a fixed timestep or a state.
+    Manual,
+}
+
+impl Default for EventSettings {
+    fn default() -> Self {
+        EventSettings::Automatic
+    }
+}
+
 fn map_instance_event<T>(event_instance: &EventInstance<T>) -> &T {
     &event_instance.event
 }
 
-/// Reads events of type `T` in order and tracks which events have already been read.
-pub struct EventReader<T> {
+/// Reads events of type `T` in order and tracks which events have already been read. The cursor is owned by the
+/// caller, so this can be used outside of systems. Systems should prefer the [EventReader] system parameter.
+pub struct ManualEventReader<T> {
     last_event_count: usize,
     _marker: PhantomData<T>,
 }
 
-impl<T> Default for EventReader<T> {
+impl<T> Default for ManualEventReader<T> {
     fn default() -> Self {
         Self {
             last_event_count: 0,
@@ -97,8 +118,8 @@ impl<T> Default for EventReader<T> {
     }
 }
 
-impl<T> EventReader<T> {
-    /// Iterates over the events this EventReader has not seen yet. This updates the EventReader's
+impl<T> ManualEventReader<T> {
+    /// Iterates over the events this ManualEventReader has not seen yet. This updates the ManualEventReader's
     /// event counter, which means subsequent event reads will not include events that happened before now.
     pub fn iter<'a>(&mut self, events: &'a Events<T>) -> impl DoubleEndedIterator<Item = &'a T> {
         // if the reader has seen some of the events in a buffer, find the proper index offset.
@@ -146,13 +167,13 @@ impl<T> EventReader<T> {
         }
     }
 
-    /// Retrieves the latest event that this EventReader hasn't seen yet. This updates the EventReader's
+    /// Retrieves the latest event that this ManualEventReader hasn't seen yet. This updates the ManualEventReader's
     /// event counter, which means subsequent event reads will not include events that happened before now.
     pub fn latest<'a>(&mut self, events: &'a Events<T>) -> Option<&'a T> {
         self.iter(events).rev().next()
     }
 
-    /// Retrieves the latest event that matches the given `predicate` that this reader hasn't seen yet. This updates the EventReader's
+    /// Retrieves the latest event that matches the given `predicate` that this reader hasn't seen yet. This updates the ManualEventReader's
     /// event counter, which means subsequent event reads will not include events that happened before now.
     pub fn find_latest<'a>(
         &mut self,
@@ -162,13 +183,130 @@ impl<T> EventReader<T> {
         self.iter(events).rev().find(predicate)
     }
 
-    /// Retrieves the earliest event in `events` that this reader hasn't seen yet. This updates the EventReader's
+    /// Retrieves the earliest event in `events` that this reader hasn't seen yet. This updates the ManualEventReader's
     /// event counter, which means subsequent event reads will not include events that happened before now.
     pub fn earliest<'a>(&mut self, events: &'a Events<T>) -> Option<&'a T> {
         self.iter(events).next()
     }
 }
 
+/// A system parameter that reads events of type `T` in order. Each system has its own cursor, which is stored in
+/// the system's state, so every system sees each event exactly once as long as it runs at least once every two
+/// [Events::update] calls.
+pub struct EventReader<'a, T: Resource> {
+    reader: Local<'a, ManualEventReader<T>>,
+    events: Res<'a, Events<T>>,
+}
+
+impl<'a, T: Resource> EventReader<'a, T> {
+    /// Iterates over the events this system has not seen yet. Subsequent reads will not include these events.
+    pub fn iter(&mut self) -> impl DoubleEndedIterator<Item = &T> {
+        self.reader.iter(&self.events)
+    }
+
+    /// Retrieves the latest event that this system hasn't seen yet. Subsequent reads will not include events that
+    /// happened before now.
+    pub fn latest(&mut self) -> Option<&T> {
+        self.iter().rev().next()
+    }
+
+    /// Retrieves the latest unseen event that matches the given `predicate`. Subsequent reads will not include events
+    /// that happened before now.
+    pub fn find_latest(&mut self, predicate: impl FnMut(&&T) -> bool) -> Option<&T> {
+        self.iter().rev().find(predicate)
+    }
+
+    /// Retrieves the earliest event that this system hasn't seen yet. Subsequent reads will not include events that
+    /// happened before now.
+    pub fn earliest(&mut self) -> Option<&T> {
+        self.iter().next()
+    }
+}
+
+impl<'a, T: Resource> SystemParam for EventReader<'a, T> {
+    fn init(system_state: &mut SystemState, world: &World, resources: &mut Resources) {
+        <(Local<ManualEventReader<T>>, Res<Events<T>>)>::init(system_state, world, resources);
+    }
+
+    #[inline]
+    unsafe fn get_param(
+        system_state: &mut SystemState,
+        world: &World,
+        resources: &Resources,
+    ) -> Option<Self> {
+        <(Local<'a, ManualEventReader<T>>, Res<'a, Events<T>>)>::get_param(
+            system_state,
+            world,
+            resources,
+        )
+        .map(|(reader, events)| EventReader { reader, events })
+    }
+}
+
+/// A system parameter that sends events of type `T`. Unlike `ResMut<Events<T>>`, it doesn't access the [Events]
+/// resource while the system runs, so systems that send the same type of event can run in parallel.
+///
+/// Sent events are buffered and added to [Events] when the system's commands are applied, at the end of its stage.
+/// Events sent by one system keep their order, while events from systems that run in parallel can be interleaved in
+/// any order. The events are dropped if the [Events] resource doesn't exist when they are applied.
+pub struct EventWriter<T: Resource> {
+    events: Vec<T>,
+    commands: Arc<Mutex<Commands>>,
+}
+
+impl<T: Resource> EventWriter<T> {
+    pub fn send(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        self.events.extend(events);
+    }
+}
+
+impl<T: Resource> Drop for EventWriter<T> {
+    fn drop(&mut self) {
+        // one command per system run, so the commands are only locked once
+        if !self.events.is_empty() {
+            self.commands.lock().add_command(SendEvents {
+                events: std::mem::take(&mut self.events),
+            });
+        }
+    }
+}
+
+struct SendEvents<T> {
+    events: Vec<T>,
+}
+
+impl<T: Resource> Command for SendEvents<T> {
+    fn write(self, _world: &mut World, resources: &mut Resources) {
+        if let Some(mut events) = resources.get_mut::<Events<T>>() {
+            events.extend(self.events.into_iter());
+        }
+    }
+}
+
+impl<T: Resource> SystemParam for EventWriter<T> {
+    fn init(system_state: &mut SystemState, world: &World, resources: &mut Resources) {
+        <Arc<Mutex<Commands>>>::init(system_state, world, resources);
+    }
+
+    #[inline]
+    unsafe fn get_param(
+        system_state: &mut SystemState,
+        world: &World,
+        resources: &Resources,
+    ) -> Option<Self> {
+        <Arc<Mutex<Commands>>>::get_param(system_state, world, resources).map(|commands| {
+            EventWriter {
+                events: Vec::new(),
+                commands,
+            }
+        })
+    }
+}
+
 impl<T: bevy_ecs::Resource> Events<T> {
     /// "Sends" an `event` by writing it to the current event buffer. [EventReader]s can then read the event.
     pub fn send(&mut self, event: T) {
@@ -185,17 +323,17 @@ impl<T: bevy_ecs::Resource> Events<T> {
         self.event_count += 1;
     }
 
-    /// Gets a new [EventReader]. This will include all events already in the event buffers.
-    pub fn get_reader(&self) -> EventReader<T> {
-        EventReader {
+    /// Gets a new [ManualEventReader]. This will include all events already in the event buffers.
+    pub fn get_reader(&self) -> ManualEventReader<T> {
+        ManualEventReader {
             last_event_count: 0,
             _marker: PhantomData,
         }
     }
 
-    /// Gets a new [EventReader]. This will ignore all events already in the event buffers. It will read all future events.
-    pub fn get_reader_current(&self) -> EventReader<T> {
-        EventReader {
+    /// Gets a new [ManualEventReader]. This will ignore all events already in the event buffers. It will read all future events.
+    pub fn get_reader_current(&self) -> ManualEventReader<T> {
+        ManualEventReader {
             last_event_count: self.event_count,
             _marker: PhantomData,
         }
@@ -222,14 +360,17 @@ impl<T: bevy_ecs::Resource> Events<T> {
         events.update();
     }
 
-    /// Removes all events.
+    /// Removes all events. Readers keep their place, so they only see events sent after this.
     pub fn clear(&mut self) {
+        self.reset_start_event_count();
         self.events_a.clear();
         self.events_b.clear();
     }
 
-    /// Creates a draining iterator that removes all events.
+    /// Creates a draining iterator that removes all events. Readers keep their place, so they only see events sent
+    /// after this. The buffers keep their capacity, so draining every frame doesn't allocate.
     pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.reset_start_event_count();
         let map = |i: EventInstance<T>| i.event;
         match self.state {
             State::A => self
@@ -245,6 +386,12 @@ impl<T: bevy_ecs::Resource> Events<T> {
         }
     }
 
+    /// Both buffers are emptied, so the next event sent to either of them is the current event count
+    fn reset_start_event_count(&mut self) {
+        self.a_start_event_count = self.event_count;
+        self.b_start_event_count = self.event_count;
+    }
+
     pub fn extend<I>(&mut self, events: I)
     where
         I: Iterator<Item = T>,
@@ -270,6 +417,7 @@ impl<T: bevy_ecs::Resource> Events<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bevy_ecs::{IntoSystem, IntoThreadLocalSystem, ParallelExecutor, Schedule};
 
     #[derive(Copy, Clone, PartialEq, Eq, Debug)]
     struct TestEvent {
@@ -368,8 +516,239 @@ mod tests {
 
     fn get_events(
         events: &Events<TestEvent>,
-        reader: &mut EventReader<TestEvent>,
+        reader: &mut ManualEventReader<TestEvent>,
     ) -> Vec<TestEvent> {
         reader.iter(events).cloned().collect::<Vec<TestEvent>>()
     }
+
+    #[test]
+    fn event_reader_system() {
+        fn read(mut reader: EventReader<TestEvent>, mut seen: ResMut<Vec<TestEvent>>) {
+            seen.extend(reader.iter().cloned());
+        }
+
+        fn take_seen(resources: &Resources) -> Vec<TestEvent> {
+            std::mem::take(&mut *resources.get_mut::<Vec<TestEvent>>().unwrap())
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Events::<TestEvent>::default());
+        resources.insert(Vec::<TestEvent>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("read");
+        schedule.add_system_to_stage("update", Events::<TestEvent>::update_system.system());
+        schedule.add_system_to_stage("read", read.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        resources
+            .get_mut::<Events<TestEvent>>()
+            .unwrap()
+            .send(TestEvent { i: 0 });
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(take_seen(&resources), vec![TestEvent { i: 0 }]);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(
+            take_seen(&resources),
+            vec![],
+            "each event is only read once"
+        );
+
+        // a system added later only sees events from the last two updates
+        resources
+            .get_mut::<Events<TestEvent>>()
+            .unwrap()
+            .send(TestEvent { i: 1 });
+        schedule.run(&mut world, &mut resources);
+        resources
+            .get_mut::<Events<TestEvent>>()
+            .unwrap()
+            .send(TestEvent { i: 2 });
+        take_seen(&resources);
+        schedule.add_system_to_stage("read", read.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(
+            take_seen(&resources),
+            vec![TestEvent { i: 2 }, TestEvent { i: 2 }],
+            "the existing reader sees only the new event and the new reader doesn't see event 1, which is older than two updates"
+        );
+    }
+
+    #[test]
+    fn event_writer_system() {
+        fn send_a(mut writer: EventWriter<TestEvent>) {
+            writer.send(TestEvent { i: 0 });
+            writer.send(TestEvent { i: 1 });
+        }
+
+        fn send_b(mut writer: EventWriter<TestEvent>) {
+            writer.send_batch(vec![TestEvent { i: 2 }, TestEvent { i: 3 }]);
+        }
+
+        fn read(mut reader: EventReader<TestEvent>, mut seen: ResMut<Vec<TestEvent>>) {
+            seen.extend(reader.iter().cloned());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Events::<TestEvent>::default());
+        resources.insert(Vec::<TestEvent>::new());
+
+        let mut senders = vec![send_a.system(), send_b.system()];
+        for sender in senders.iter_mut() {
+            sender.initialize(&mut world, &mut resources);
+        }
+        assert!(
+            senders[0]
+                .resource_access()
+                .is_compatible(senders[1].resource_access()),
+            "event writers don't stop systems from running in parallel"
+        );
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("send");
+        schedule.add_stage("read");
+        for sender in senders {
+            schedule.add_system_to_stage("send", sender);
+        }
+        schedule.add_system_to_stage("read", read.system());
+        schedule.initialize(&mut world, &mut resources);
+        let mut executor = ParallelExecutor::default();
+        executor.initialize(&mut resources);
+        executor.run(&mut schedule, &mut world, &mut resources);
+
+        let seen = resources.get::<Vec<TestEvent>>().unwrap();
+        assert_eq!(seen.len(), 4);
+        let order = |i| seen.iter().position(|event| event.i == i).unwrap();
+        assert!(
+            order(0) < order(1),
+            "events from one system keep their order"
+        );
+        assert!(
+            order(2) < order(3),
+            "events from one system keep their order"
+        );
+    }
+
+    #[test]
+    fn manual_events() {
+        fn read_on_fifth_frame(
+            mut frame: Local<usize>,
+            mut reader: EventReader<TestEvent>,
+            mut seen: ResMut<Vec<TestEvent>>,
+        ) {
+            *frame += 1;
+            if *frame == 5 {
+                seen.extend(reader.iter().cloned());
+            }
+        }
+
+        let mut builder = crate::App::build();
+        builder
+            .add_event_with_settings::<TestEvent>(EventSettings::Manual)
+            .add_resource(Vec::<TestEvent>::new())
+            .add_system(read_on_fifth_frame.system());
+        let mut app = builder.app;
+        app.initialize();
+
+        app.resources
+            .get_mut::<Events<TestEvent>>()
+            .unwrap()
+            .send(TestEvent { i: 0 });
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(
+            *app.resources.get::<Vec<TestEvent>>().unwrap(),
+            vec![TestEvent { i: 0 }]
+        );
+    }
+
+    #[test]
+    fn drain_events() {
+        let mut events = Events::<TestEvent>::default();
+        let mut reader = events.get_reader();
+
+        events.send(TestEvent { i: 0 });
+        events.send(TestEvent { i: 1 });
+        assert_eq!(
+            get_events(&events, &mut reader),
+            vec![TestEvent { i: 0 }, TestEvent { i: 1 }]
+        );
+        assert_eq!(
+            events.drain().collect::<Vec<_>>(),
+            vec![TestEvent { i: 0 }, TestEvent { i: 1 }]
+        );
+        let capacity = events.events_a.capacity();
+
+        // readers keep working after events are drained, and the buffer is reused
+        for i in 2..10 {
+            events.send(TestEvent { i });
+            events.send(TestEvent { i });
+            assert_eq!(get_events(&events, &mut reader), vec![TestEvent { i }; 2]);
+            assert_eq!(events.drain().count(), 2);
+            assert_eq!(events.events_a.capacity(), capacity);
+        }
+
+        events.send(TestEvent { i: 10 });
+        events.clear();
+        events.send(TestEvent { i: 11 });
+        let mut new_reader = events.get_reader();
+        assert_eq!(get_events(&events, &mut reader), vec![TestEvent { i: 11 }]);
+        assert_eq!(
+            get_events(&events, &mut new_reader),
+            vec![TestEvent { i: 11 }]
+        );
+    }
+    #[test]
+    fn independent_manual_readers() {
+        let mut events = Events::<TestEvent>::default();
+        let mut reader_a = ManualEventReader::default();
+        let mut reader_b = ManualEventReader::default();
+
+        events.send(TestEvent { i: 0 });
+        events.send(TestEvent { i: 1 });
+        assert_eq!(reader_a.latest(&events), Some(&TestEvent { i: 1 }));
+        assert_eq!(
+            get_events(&events, &mut reader_b),
+            vec![TestEvent { i: 0 }, TestEvent { i: 1 }],
+            "reading with reader_a doesn't advance reader_b"
+        );
+        assert_eq!(get_events(&events, &mut reader_a), vec![]);
+
+        // an exclusive system can own its reader instead of storing it in its system state
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(events);
+        resources.insert(Vec::<TestEvent>::new());
+        let mut schedule = Schedule::default();
+        schedule.add_stage("read");
+        schedule.add_system_to_stage(
+            "read",
+            (move |_world: &mut World, resources: &mut Resources| {
+                let events = resources.get::<Events<TestEvent>>().unwrap();
+                let mut seen = resources.get_mut::<Vec<TestEvent>>().unwrap();
+                seen.extend(reader_a.iter(&events).cloned());
+            })
+            .thread_local_system(),
+        );
+        schedule.initialize(&mut world, &mut resources);
+
+        resources
+            .get_mut::<Events<TestEvent>>()
+            .unwrap()
+            .send(TestEvent { i: 2 });
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(
+            *resources.get::<Vec<TestEvent>>().unwrap(),
+            vec![TestEvent { i: 2 }]
+        );
+        let events = resources.get::<Events<TestEvent>>().unwrap();
+        assert_eq!(get_events(&events, &mut reader_b), vec![TestEvent { i: 2 }]);
+    }
 }
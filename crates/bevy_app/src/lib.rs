@@ -20,9 +20,9 @@ pub use schedule_runner::*;
 
 pub mod prelude {
     pub use crate::{
-        app::App,
+        app::{App, AppExit},
         app_builder::AppBuilder,
-        event::{EventReader, Events},
+        event::{EventReader, EventSettings, EventWriter, Events, ManualEventReader},
         stage, DynamicPlugin, Plugin, PluginGroup,
     };
 }
@@ -1,7 +1,10 @@
 use crate::{AppBuilder, Plugin};
 use bevy_utils::{tracing::debug, HashMap};
-use std::any::TypeId;
+use std::any::{type_name, TypeId};
 
+/// A set of [Plugin]s that are added to an app together, like `DefaultPlugins`. Use
+/// [AppBuilder::add_plugins_with] to adjust the group before it is built, for example to disable one of its plugins or
+/// to replace one with a differently configured instance.
 pub trait PluginGroup {
     fn build(&mut self, group: &mut PluginGroupBuilder);
 }
@@ -11,6 +14,8 @@ struct PluginEntry {
     enabled: bool,
 }
 
+/// Collects the plugins of a [PluginGroup] in the order they will be built. Plugins are identified by their type, so
+/// a group can contain each plugin type only once.
 #[derive(Default)]
 pub struct PluginGroupBuilder {
     plugins: HashMap<TypeId, PluginEntry>,
@@ -18,78 +23,61 @@ pub struct PluginGroupBuilder {
 }
 
 impl PluginGroupBuilder {
+    /// Adds `plugin` after the plugins added so far.
+    ///
+    /// # Panics
+    /// Panics if the group already contains a plugin of type `T`. Use [PluginGroupBuilder::set] to replace it.
     pub fn add<T: Plugin>(&mut self, plugin: T) -> &mut Self {
-        self.order.push(TypeId::of::<T>());
-        self.plugins.insert(
-            TypeId::of::<T>(),
-            PluginEntry {
-                plugin: Box::new(plugin),
-                enabled: true,
-            },
-        );
-        self
+        self.insert(self.order.len(), plugin)
     }
 
+    /// Adds `plugin` right before the `Target` plugin.
+    ///
+    /// # Panics
+    /// Panics if the group doesn't contain `Target`, or already contains a plugin of type `T`
     pub fn add_before<Target: Plugin, T: Plugin>(&mut self, plugin: T) -> &mut Self {
-        let target_index = self
-            .order
-            .iter()
-            .enumerate()
-            .find(|(_i, ty)| **ty == TypeId::of::<Target>())
-            .map(|(i, _)| i)
-            .unwrap_or_else(|| {
-                panic!("Plugin does not exist: {}", std::any::type_name::<Target>())
-            });
-        self.order.insert(target_index, TypeId::of::<T>());
-        self.plugins.insert(
-            TypeId::of::<T>(),
-            PluginEntry {
-                plugin: Box::new(plugin),
-                enabled: true,
-            },
-        );
-        self
+        let target_index = self.index_of::<Target>();
+        self.insert(target_index, plugin)
     }
 
+    /// Adds `plugin` right after the `Target` plugin.
+    ///
+    /// # Panics
+    /// Panics if the group doesn't contain `Target`, or already contains a plugin of type `T`
     pub fn add_after<Target: Plugin, T: Plugin>(&mut self, plugin: T) -> &mut Self {
-        let target_index = self
-            .order
-            .iter()
-            .enumerate()
-            .find(|(_i, ty)| **ty == TypeId::of::<Target>())
-            .map(|(i, _)| i)
-            .unwrap_or_else(|| {
-                panic!("Plugin does not exist: {}", std::any::type_name::<Target>())
-            });
-        self.order.insert(target_index + 1, TypeId::of::<T>());
-        self.plugins.insert(
-            TypeId::of::<T>(),
-            PluginEntry {
-                plugin: Box::new(plugin),
-                enabled: true,
-            },
-        );
+        let target_index = self.index_of::<Target>();
+        self.insert(target_index + 1, plugin)
+    }
+
+    /// Replaces the plugin of type `T` with `plugin`, which keeps its position and whether it is enabled. This is how
+    /// a plugin of the group is configured differently.
+    ///
+    /// # Panics
+    /// Panics if the group doesn't contain a plugin of type `T`
+    pub fn set<T: Plugin>(&mut self, plugin: T) -> &mut Self {
+        let entry = self.plugins.get_mut(&TypeId::of::<T>()).unwrap_or_else(|| {
+            panic!(
+                "Cannot set {}, the plugin group doesn't contain it",
+                type_name::<T>()
+            )
+        });
+        entry.plugin = Box::new(plugin);
         self
     }
 
     pub fn enable<T: Plugin>(&mut self) -> &mut Self {
-        let mut plugin_entry = self
-            .plugins
-            .get_mut(&TypeId::of::<T>())
-            .expect("Cannot enable a plugin that does not exist");
-        plugin_entry.enabled = true;
+        self.entry_mut::<T>("enable").enabled = true;
         self
     }
 
+    /// Keeps the plugin of type `T` in the group, but skips it when the group is built. Other plugins can still be
+    /// added before or after it.
     pub fn disable<T: Plugin>(&mut self) -> &mut Self {
-        let mut plugin_entry = self
-            .plugins
-            .get_mut(&TypeId::of::<T>())
-            .expect("Cannot disable a plugin that does not exist");
-        plugin_entry.enabled = false;
+        self.entry_mut::<T>("disable").enabled = false;
         self
     }
 
+    /// Builds the enabled plugins in order
     pub fn finish(self, app: &mut AppBuilder) {
         for ty in self.order.iter() {
             if let Some(entry) = self.plugins.get(ty) {
@@ -100,4 +88,136 @@ impl PluginGroupBuilder {
             }
         }
     }
+
+    fn insert<T: Plugin>(&mut self, index: usize, plugin: T) -> &mut Self {
+        if self.plugins.contains_key(&TypeId::of::<T>()) {
+            panic!(
+                "The plugin group already contains {}. Use PluginGroupBuilder::set to replace it",
+                type_name::<T>()
+            );
+        }
+        self.order.insert(index, TypeId::of::<T>());
+        self.plugins.insert(
+            TypeId::of::<T>(),
+            PluginEntry {
+                plugin: Box::new(plugin),
+                enabled: true,
+            },
+        );
+        self
+    }
+
+    fn index_of<Target: Plugin>(&self) -> usize {
+        self.order
+            .iter()
+            .position(|ty| *ty == TypeId::of::<Target>())
+            .unwrap_or_else(|| panic!("Plugin does not exist: {}", type_name::<Target>()))
+    }
+
+    fn entry_mut<T: Plugin>(&mut self, action: &str) -> &mut PluginEntry {
+        self.plugins.get_mut(&TypeId::of::<T>()).unwrap_or_else(|| {
+            panic!(
+                "Cannot {} {}, the plugin group doesn't contain it",
+                action,
+                type_name::<T>()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PluginGroup, PluginGroupBuilder};
+    use crate::{App, AppBuilder, Plugin};
+
+    /// The names of the plugins that were built, in order
+    #[derive(Default)]
+    struct Built(Vec<String>);
+
+    macro_rules! test_plugins {
+        ($($plugin: ident),*) => {
+            $(
+                struct $plugin(&'static str);
+
+                impl Plugin for $plugin {
+                    fn build(&self, app: &mut AppBuilder) {
+                        let name = format!("{}{}", stringify!($plugin), self.0);
+                        app.resources_mut().get_mut::<Built>().unwrap().0.push(name);
+                    }
+                }
+            )*
+        };
+    }
+
+    test_plugins!(Log, Audio, Render, Window);
+
+    struct Defaults;
+
+    impl PluginGroup for Defaults {
+        fn build(&mut self, group: &mut PluginGroupBuilder) {
+            group.add(Log("")).add(Audio("")).add(Window(""));
+        }
+    }
+
+    fn built(build: impl FnOnce(&mut AppBuilder)) -> Vec<String> {
+        let mut builder = App::build();
+        builder.init_resource::<Built>();
+        build(&mut builder);
+        let built = builder.resources().get::<Built>().unwrap();
+        built.0.clone()
+    }
+
+    #[test]
+    fn plugins_build_in_order() {
+        assert_eq!(
+            built(|app| {
+                app.add_plugins(Defaults);
+            }),
+            vec!["Log", "Audio", "Window"]
+        );
+    }
+
+    #[test]
+    fn adjust_group() {
+        let plugins = built(|app| {
+            app.add_plugins_with(Defaults, |group| {
+                group
+                    .disable::<Audio>()
+                    .add_before::<Window, _>(Render(" custom"))
+                    .set(Log(" verbose"))
+            });
+        });
+        assert_eq!(plugins, vec!["Log verbose", "Render custom", "Window"]);
+    }
+
+    #[test]
+    fn add_around_disabled_plugin() {
+        let plugins = built(|app| {
+            app.add_plugins_with(Defaults, |group| {
+                group.disable::<Audio>().add_after::<Audio, _>(Render(""))
+            });
+        });
+        assert_eq!(plugins, vec!["Log", "Render", "Window"]);
+
+        let plugins = built(|app| {
+            app.add_plugins_with(Defaults, |group| group.disable::<Audio>().enable::<Audio>());
+        });
+        assert_eq!(plugins, vec!["Log", "Audio", "Window"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "already contains")]
+    fn duplicate_plugin() {
+        built(|app| {
+            app.add_plugins_with(Defaults, |group| group.add(Audio(" again")));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot disable")]
+    fn disable_missing_plugin() {
+        built(|app| {
+            app.add_plugins_with(Defaults, |group| group.disable::<Render>());
+        });
+    }
 }
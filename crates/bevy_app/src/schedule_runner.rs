@@ -1,7 +1,7 @@
 use super::{App, AppBuilder};
 use crate::{
     app::AppExit,
-    event::{EventReader, Events},
+    event::{Events, ManualEventReader},
     plugin::Plugin,
 };
 use std::time::Duration;
@@ -63,10 +63,16 @@ impl Plugin for ScheduleRunnerPlugin {
         app.set_runner(move |mut app: App| {
             app.initialize();
 
-            let mut app_exit_event_reader = EventReader::<AppExit>::default();
+            let mut app_exit_event_reader = ManualEventReader::<AppExit>::default();
             match settings.run_mode {
                 RunMode::Once => {
                     app.update();
+                    app.resources
+                        .get::<Events<AppExit>>()
+                        .and_then(|app_exit_events| {
+                            AppExit::from_events(app_exit_event_reader.iter(&app_exit_events))
+                        })
+                        .unwrap_or_default()
                 }
                 RunMode::Loop { wait } => {
                     let mut tick = move |app: &mut App,
@@ -74,17 +80,21 @@ impl Plugin for ScheduleRunnerPlugin {
                           -> Result<Option<Duration>, AppExit> {
                         let start_time = Instant::now();
 
-                        if let Some(app_exit_events) = app.resources.get_mut::<Events<AppExit>>() {
-                            if let Some(exit) = app_exit_event_reader.latest(&app_exit_events) {
-                                return Err(exit.clone());
+                        if let Some(app_exit_events) = app.resources.get::<Events<AppExit>>() {
+                            if let Some(exit) =
+                                AppExit::from_events(app_exit_event_reader.iter(&app_exit_events))
+                            {
+                                return Err(exit);
                             }
                         }
 
                         app.update();
 
-                        if let Some(app_exit_events) = app.resources.get_mut::<Events<AppExit>>() {
-                            if let Some(exit) = app_exit_event_reader.latest(&app_exit_events) {
-                                return Err(exit.clone());
+                        if let Some(app_exit_events) = app.resources.get::<Events<AppExit>>() {
+                            if let Some(exit) =
+                                AppExit::from_events(app_exit_event_reader.iter(&app_exit_events))
+                            {
+                                return Err(exit);
                             }
                         }
 
@@ -102,9 +112,11 @@ impl Plugin for ScheduleRunnerPlugin {
 
                     #[cfg(not(target_arch = "wasm32"))]
                     {
-                        while let Ok(delay) = tick(&mut app, wait) {
-                            if let Some(delay) = delay {
-                                thread::sleep(delay);
+                        loop {
+                            match tick(&mut app, wait) {
+                                Ok(Some(delay)) => thread::sleep(delay),
+                                Ok(None) => {}
+                                Err(exit) => return exit,
                             }
                         }
                     }
@@ -138,9 +150,76 @@ impl Plugin for ScheduleRunnerPlugin {
                         };
                         *g.borrow_mut() = Some(Closure::wrap(Box::new(c) as Box<dyn FnMut()>));
                         set_timeout(g.borrow().as_ref().unwrap(), asap);
-                    };
+
+                        // the loop keeps running on the browser's timers after the runner returns
+                        AppExit::Success
+                    }
                 }
             }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ScheduleRunnerPlugin;
+    use crate::{app::AppExit, event::Events, App};
+    use bevy_ecs::{IntoSystem, Local, ResMut};
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    };
+
+    struct Frames(Arc<AtomicUsize>);
+
+    struct SaveFile(Arc<AtomicBool>);
+
+    impl Drop for SaveFile {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn exit_on_frame_3(
+        mut frame: Local<usize>,
+        frames: ResMut<Frames>,
+        mut app_exit_events: ResMut<Events<AppExit>>,
+    ) {
+        *frame += 1;
+        frames.0.fetch_add(1, Ordering::SeqCst);
+        if *frame == 3 {
+            app_exit_events.send(AppExit::error(3));
+        }
+    }
+
+    #[test]
+    fn runner_returns_after_app_exit() {
+        let frames = Arc::new(AtomicUsize::new(0));
+        let saved = Arc::new(AtomicBool::new(false));
+        let exit = App::build()
+            .add_resource(Frames(frames.clone()))
+            .add_resource(SaveFile(saved.clone()))
+            .add_plugin(ScheduleRunnerPlugin::default())
+            .add_system(exit_on_frame_3.system())
+            .run();
+
+        assert_eq!(exit, AppExit::error(3));
+        assert_eq!(exit.code(), 3);
+        assert_eq!(frames.load(Ordering::SeqCst), 3);
+        assert!(
+            saved.load(Ordering::SeqCst),
+            "resources should be dropped before the runner returns"
+        );
+    }
+
+    #[test]
+    fn errors_win_over_success() {
+        let exits = [AppExit::Success, AppExit::error(2), AppExit::error(5)];
+        assert_eq!(AppExit::from_events(&exits), Some(AppExit::error(2)));
+        assert_eq!(
+            AppExit::from_events(&[AppExit::Success]),
+            Some(AppExit::Success)
+        );
+        assert_eq!(AppExit::from_events(&[]), None);
+    }
+}
@@ -11,7 +11,7 @@ pub use task_pool_options::DefaultTaskPoolOptions;
 pub use time::*;
 
 pub mod prelude {
-    pub use crate::{DefaultTaskPoolOptions, EntityLabels, Labels, Time, Timer};
+    pub use crate::{DefaultTaskPoolOptions, EntityLabels, FixedTimestep, Labels, Time, Timer};
 }
 
 use bevy_app::prelude::*;
@@ -32,6 +32,7 @@ impl Plugin for CorePlugin {
             .create_default_pools(app.resources_mut());
 
         app.init_resource::<Time>()
+            .init_resource::<FixedTimesteps>()
             .init_resource::<EntityLabels>()
             .register_component::<Timer>()
             .register_property::<Vec2>()
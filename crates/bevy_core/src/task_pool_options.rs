@@ -90,7 +90,23 @@ impl DefaultTaskPoolOptions {
         }
     }
 
-    /// Inserts the default thread pools into the given resource map based on the configured values
+    /// Create a configuration that gives the compute pool exactly `thread_count` threads. The IO and async compute
+    /// pools are sized as usual, but from the cores that remain, so that for example two cores can be kept free
+    /// for audio and IO on a device with few of them.
+    pub fn with_compute_threads(thread_count: usize) -> Self {
+        DefaultTaskPoolOptions {
+            compute: TaskPoolThreadAssignmentPolicy {
+                min_threads: thread_count,
+                max_threads: thread_count,
+                percent: 1.0,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Inserts the default thread pools into the given resource map based on the configured values. Pools that are
+    /// already in the resource map are kept, so calling this again after startup, for example with changed options,
+    /// does nothing.
     pub fn create_default_pools(&self, resources: &mut Resources) {
         let total_threads = bevy_math::clamp(
             bevy_tasks::logical_core_count(),
@@ -152,3 +168,48 @@ impl DefaultTaskPoolOptions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::DefaultTaskPoolOptions;
+    use bevy_ecs::Resources;
+    use bevy_tasks::{AsyncComputeTaskPool, ComputeTaskPool, IoTaskPool};
+
+    fn thread_nums(options: &DefaultTaskPoolOptions) -> (usize, usize, usize) {
+        let mut resources = Resources::default();
+        options.create_default_pools(&mut resources);
+        let io = resources.get::<IoTaskPool>().unwrap().thread_num();
+        let async_compute = resources
+            .get::<AsyncComputeTaskPool>()
+            .unwrap()
+            .thread_num();
+        let compute = resources.get::<ComputeTaskPool>().unwrap().thread_num();
+        (io, async_compute, compute)
+    }
+
+    #[test]
+    fn thread_counts() {
+        // a quarter of the cores each for IO and async compute, and the rest for compute
+        assert_eq!(
+            thread_nums(&DefaultTaskPoolOptions::with_num_threads(8)),
+            (2, 2, 4)
+        );
+        assert_eq!(
+            thread_nums(&DefaultTaskPoolOptions::with_num_threads(2)),
+            (1, 1, 1)
+        );
+
+        let mut options = DefaultTaskPoolOptions::with_compute_threads(3);
+        options.min_total_threads = 16;
+        options.max_total_threads = 16;
+        assert_eq!(thread_nums(&options), (4, 4, 3));
+    }
+
+    #[test]
+    fn existing_pools_are_kept() {
+        let mut resources = Resources::default();
+        DefaultTaskPoolOptions::with_num_threads(4).create_default_pools(&mut resources);
+        DefaultTaskPoolOptions::with_num_threads(8).create_default_pools(&mut resources);
+        assert_eq!(resources.get::<ComputeTaskPool>().unwrap().thread_num(), 2);
+    }
+}
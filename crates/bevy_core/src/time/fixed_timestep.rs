@@ -0,0 +1,300 @@
+use crate::time::Time;
+use bevy_ecs::{BoxedSystem, IntoSystem, Res, ResMut, ShouldRun};
+use bevy_utils::HashMap;
+
+/// The number of steps a [FixedTimestep] runs in one frame before it drops the rest of the accumulated time
+pub const DEFAULT_MAX_STEPS: u32 = 10;
+
+/// The state of a labeled [FixedTimestep], readable through the [FixedTimesteps] resource
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestepState {
+    step: f64,
+    accumulator: f64,
+}
+
+impl FixedTimestepState {
+    /// The delta of each step, in seconds
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// The time that has accumulated but isn't large enough for another step yet, in seconds
+    pub fn accumulator(&self) -> f64 {
+        self.accumulator
+    }
+
+    /// How far the accumulated time is into the next step, from 0.0 up to (but not including) 1.0. Useful for
+    /// interpolating between the last two steps when rendering.
+    pub fn overstep_percentage(&self) -> f64 {
+        self.accumulator / self.step
+    }
+}
+
+/// The state of every labeled [FixedTimestep], updated each time one of them is evaluated
+#[derive(Debug, Default)]
+pub struct FixedTimesteps {
+    fixed_timesteps: HashMap<&'static str, FixedTimestepState>,
+}
+
+impl FixedTimesteps {
+    pub fn get(&self, label: &str) -> Option<&FixedTimestepState> {
+        self.fixed_timesteps.get(label)
+    }
+}
+
+/// Run criteria that runs a stage or system once for every `step` seconds that have passed according to [Time], which
+/// can be zero or more times per frame.
+///
+/// Time that doesn't add up to a full step is kept for the next frame. After a long frame at most `max_steps`
+/// steps are run, and the rest of the accumulated time is dropped so the stage can't fall further and further behind.
+/// Every `FixedTimestep` has its own accumulator, so two systems with their own timesteps never share time.
+#[derive(Debug)]
+pub struct FixedTimestep {
+    step: f64,
+    max_steps: u32,
+    label: Option<&'static str>,
+    accumulator: f64,
+    steps: u32,
+    looping: bool,
+}
+
+impl FixedTimestep {
+    /// Runs once every `step` seconds
+    ///
+    /// # Panics
+    /// Panics if `step` isn't a positive, finite number of seconds.
+    pub fn step(step: f64) -> Self {
+        assert!(
+            step > 0.0 && step.is_finite(),
+            "FixedTimestep step must be a positive, finite number of seconds, got {}",
+            step
+        );
+        Self {
+            step,
+            max_steps: DEFAULT_MAX_STEPS,
+            label: None,
+            accumulator: 0.0,
+            steps: 0,
+            looping: false,
+        }
+    }
+
+    /// Runs `rate` times per second
+    ///
+    /// # Panics
+    /// Panics if `rate` isn't a positive, finite number of steps per second.
+    pub fn steps_per_second(rate: f64) -> Self {
+        assert!(
+            rate > 0.0 && rate.is_finite(),
+            "FixedTimestep rate must be a positive, finite number of steps per second, got {}",
+            rate
+        );
+        Self::step(1.0 / rate)
+    }
+
+    /// Publishes this timestep's [FixedTimestepState] in the [FixedTimesteps] resource under `label`
+    pub fn with_label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Limits the number of steps run in a single frame. Defaults to [DEFAULT_MAX_STEPS].
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    fn update(&mut self, delta_seconds: f64) -> ShouldRun {
+        // the run criteria is evaluated again after every step, but only the first evaluation in a frame adds time
+        if !self.looping {
+            self.accumulator += delta_seconds;
+            self.steps = 0;
+        }
+
+        if self.accumulator >= self.step && self.steps < self.max_steps {
+            self.accumulator -= self.step;
+            self.steps += 1;
+            self.looping = true;
+            ShouldRun::YesAndCheckAgain
+        } else {
+            if self.accumulator >= self.step {
+                self.accumulator %= self.step;
+            }
+            self.looping = false;
+            ShouldRun::No
+        }
+    }
+
+    fn state(&self) -> FixedTimestepState {
+        FixedTimestepState {
+            step: self.step,
+            accumulator: self.accumulator,
+        }
+    }
+}
+
+impl IntoSystem<(), ShouldRun> for FixedTimestep {
+    fn system(mut self) -> BoxedSystem<(), ShouldRun> {
+        (move |time: Res<Time>, fixed_timesteps: Option<ResMut<FixedTimesteps>>| {
+            let should_run = self.update(time.delta_seconds_f64);
+            if let (Some(label), Some(mut fixed_timesteps)) = (self.label, fixed_timesteps) {
+                fixed_timesteps.fixed_timesteps.insert(label, self.state());
+            }
+            should_run
+        })
+        .system()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FixedTimestep, FixedTimesteps};
+    use crate::time::Time;
+    use bevy_ecs::{IntoSystem, ResMut, Resources, RunCriteriaExt, Schedule, ShouldRun, World};
+
+    fn run_frames(timestep: FixedTimestep, deltas: &[f64]) -> (Vec<usize>, Resources) {
+        fn count(mut steps: ResMut<usize>) {
+            *steps += 1;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Time::default());
+        resources.insert(FixedTimesteps::default());
+        resources.insert(0usize);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("fixed_update");
+        schedule.add_system_to_stage("fixed_update", count.system());
+        schedule.set_stage_run_criteria("fixed_update", timestep);
+        schedule.initialize(&mut world, &mut resources);
+
+        let mut steps = Vec::new();
+        for delta in deltas {
+            resources.get_mut::<Time>().unwrap().delta_seconds_f64 = *delta;
+            schedule.run(&mut world, &mut resources);
+            steps.push(std::mem::take(&mut *resources.get_mut::<usize>().unwrap()));
+        }
+        (steps, resources)
+    }
+
+    #[test]
+    fn accumulates_between_frames() {
+        let (steps, resources) = run_frames(
+            FixedTimestep::step(0.5).with_label("physics"),
+            &[0.2, 0.2, 0.2, 1.1, 0.0],
+        );
+        assert_eq!(steps, vec![0, 0, 1, 2, 0]);
+
+        let fixed_timesteps = resources.get::<FixedTimesteps>().unwrap();
+        let state = fixed_timesteps.get("physics").unwrap();
+        assert_eq!(state.step(), 0.5);
+        assert!((state.accumulator() - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn caps_steps_per_frame() {
+        let (steps, resources) = run_frames(
+            FixedTimestep::step(0.1)
+                .with_max_steps(3)
+                .with_label("physics"),
+            &[10.05, 0.0, 0.1],
+        );
+        assert_eq!(steps, vec![3, 0, 1]);
+
+        let fixed_timesteps = resources.get::<FixedTimesteps>().unwrap();
+        assert!(fixed_timesteps.get("physics").unwrap().accumulator() < 0.1);
+    }
+
+    #[test]
+    fn system_timesteps() {
+        #[derive(Default)]
+        struct Steps {
+            physics: usize,
+            ai: usize,
+        }
+
+        fn physics(mut steps: ResMut<Steps>) {
+            steps.physics += 1;
+        }
+
+        fn ai(mut steps: ResMut<Steps>) {
+            steps.ai += 1;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Time::default());
+        resources.insert(FixedTimesteps::default());
+        resources.insert(Steps::default());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage(
+            "update",
+            physics.system().run_if(
+                FixedTimestep::steps_per_second(60.0)
+                    .with_max_steps(20)
+                    .with_label("physics"),
+            ),
+        );
+        schedule.add_system_to_stage(
+            "update",
+            ai.system()
+                .run_if(FixedTimestep::step(0.05).with_max_steps(2)),
+        );
+        schedule.initialize(&mut world, &mut resources);
+
+        let mut counts = Vec::new();
+        for delta in &[0.01, 0.03, 0.2, 0.0] {
+            resources.get_mut::<Time>().unwrap().delta_seconds_f64 = *delta;
+            schedule.run(&mut world, &mut resources);
+            let steps = std::mem::take(&mut *resources.get_mut::<Steps>().unwrap());
+            counts.push((steps.physics, steps.ai));
+        }
+        // 0.01s is less than one physics step, 0.04s adds up to 2 physics steps, and the 0.2s hitch is 12 physics
+        // steps but only 2 of the 4 ai steps because of the ai cap
+        assert_eq!(counts, vec![(0, 0), (2, 0), (12, 2), (0, 0)]);
+
+        let fixed_timesteps = resources.get::<FixedTimesteps>().unwrap();
+        let overstep = fixed_timesteps
+            .get("physics")
+            .unwrap()
+            .overstep_percentage();
+        // 0.24s is 14.4 steps of 1/60s
+        assert!((overstep - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unlabeled_timestep() {
+        let mut timestep = FixedTimestep::steps_per_second(4.0);
+        assert_eq!(timestep.update(0.6), ShouldRun::YesAndCheckAgain);
+        assert_eq!(timestep.update(0.6), ShouldRun::YesAndCheckAgain);
+        assert_eq!(timestep.update(0.6), ShouldRun::No);
+        assert_eq!(timestep.update(0.0), ShouldRun::No);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "FixedTimestep step must be a positive, finite number of seconds, got 0"
+    )]
+    fn zero_step() {
+        FixedTimestep::step(0.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "FixedTimestep step must be a positive, finite number of seconds, got NaN"
+    )]
+    fn nan_step() {
+        FixedTimestep::step(f64::NAN);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "FixedTimestep rate must be a positive, finite number of steps per second, got -60"
+    )]
+    fn negative_rate() {
+        FixedTimestep::steps_per_second(-60.0);
+    }
+}
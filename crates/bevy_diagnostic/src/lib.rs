@@ -1,9 +1,11 @@
 mod diagnostic;
 mod frame_time_diagnostics_plugin;
 mod print_diagnostics_plugin;
+mod system_profile_plugin;
 pub use diagnostic::*;
 pub use frame_time_diagnostics_plugin::FrameTimeDiagnosticsPlugin;
 pub use print_diagnostics_plugin::PrintDiagnosticsPlugin;
+pub use system_profile_plugin::SystemProfilePlugin;
 
 use bevy_app::prelude::*;
 
@@ -0,0 +1,69 @@
+use bevy_app::prelude::*;
+use bevy_core::{Time, Timer};
+use bevy_ecs::{IntoSystem, Res, ResMut, SystemProfile, DEFAULT_PROFILE_FRAMES};
+use std::time::Duration;
+
+/// An App Plugin that profiles the systems of the app's schedule and regularly prints the slowest ones to the console
+pub struct SystemProfilePlugin {
+    /// The number of systems printed, sorted by their average run time
+    pub count: usize,
+    pub wait_duration: Duration,
+    /// The number of frames the averages are taken over
+    pub frames: usize,
+}
+
+/// State used by the [SystemProfilePlugin]
+pub struct SystemProfileState {
+    timer: Timer,
+    count: usize,
+}
+
+impl Default for SystemProfilePlugin {
+    fn default() -> Self {
+        SystemProfilePlugin {
+            count: 5,
+            wait_duration: Duration::from_secs(1),
+            frames: DEFAULT_PROFILE_FRAMES,
+        }
+    }
+}
+
+impl Plugin for SystemProfilePlugin {
+    fn build(&self, app: &mut bevy_app::AppBuilder) {
+        app.app.schedule.enable_timing();
+        app.add_resource(SystemProfile::new(self.frames))
+            .add_resource(SystemProfileState {
+                timer: Timer::new(self.wait_duration, true),
+                count: self.count,
+            })
+            .add_system_to_stage(stage::LAST, Self::print_system_profile_system.system());
+    }
+}
+
+impl SystemProfilePlugin {
+    pub fn print_system_profile_system(
+        mut state: ResMut<SystemProfileState>,
+        time: Res<Time>,
+        profile: Res<SystemProfile>,
+    ) {
+        if state.timer.tick(time.delta_seconds).finished {
+            let mut systems = profile
+                .systems()
+                .map(|(_, system)| system)
+                .collect::<Vec<_>>();
+            systems.sort_by_key(|system| std::cmp::Reverse(system.average()));
+
+            println!("Slowest systems:");
+            println!("{}", "-".repeat(93));
+            for system in systems.iter().take(state.count) {
+                println!(
+                    "{:<65}: {:>10.3?}  (max {:.3?}, thread local {:.3?})",
+                    system.name(),
+                    system.average(),
+                    system.max(),
+                    system.thread_local_average()
+                );
+            }
+        }
+    }
+}
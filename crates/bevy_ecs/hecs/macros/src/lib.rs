@@ -31,7 +31,11 @@ use syn::{
 ///
 /// Using derived `Bundle` impls improves spawn performance and can be convenient when combined with
 /// other derives like `serde::Deserialize`.
-#[proc_macro_derive(Bundle)]
+///
+/// Fields marked with `#[bundle]` are bundles themselves, whose components are added as if they
+/// were fields of the outer struct. Like any bundle, the flattened bundle must not contain the
+/// same component type twice.
+#[proc_macro_derive(Bundle, attributes(bundle))]
 pub fn derive_bundle(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     match derive_bundle_(input) {
@@ -54,6 +58,11 @@ fn derive_bundle_(input: DeriveInput) -> Result<TokenStream2> {
         }
     };
     let (tys, field_members) = struct_fields(&data.fields);
+    let nested_bundles = data
+        .fields
+        .iter()
+        .map(|field| field.attrs.iter().any(|attr| attr.path.is_ident("bundle")))
+        .collect::<Vec<_>>();
     let manifest = Manifest::new().unwrap();
     let path_str = if let Some(package) = manifest.find(|name| name == "bevy") {
         format!("{}::ecs", package.name)
@@ -66,6 +75,18 @@ fn derive_bundle_(input: DeriveInput) -> Result<TokenStream2> {
     let field_idents = member_as_idents(&field_members);
     let generics = add_additional_bounds_to_generic_params(&crate_path, input.generics);
 
+    if nested_bundles.contains(&true) {
+        return Ok(gen_nested_bundle_impl(
+            &crate_path,
+            &ident,
+            &generics,
+            &field_members,
+            &field_idents,
+            &tys,
+            &nested_bundles,
+        ));
+    }
+
     let dyn_bundle_code =
         gen_dynamic_bundle_impl(&crate_path, &ident, &generics, &field_members, &tys);
     let bundle_code = if tys.is_empty() {
@@ -183,6 +204,109 @@ fn gen_bundle_impl(
     }
 }
 
+/// Implements `DynamicBundle` and `Bundle` for a struct with `#[bundle]` fields, whose components
+/// are only known through the nested bundles' impls
+fn gen_nested_bundle_impl(
+    crate_path: &syn::Path,
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    field_members: &[syn::Member],
+    field_idents: &[Cow<syn::Ident>],
+    tys: &[&syn::Type],
+    nested_bundles: &[bool],
+) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let select = |nested: bool| {
+        let (mut members, mut idents, mut types) = (Vec::new(), Vec::new(), Vec::new());
+        for (i, is_bundle) in nested_bundles.iter().enumerate() {
+            if *is_bundle == nested {
+                members.push(&field_members[i]);
+                idents.push(&field_idents[i]);
+                types.push(tys[i]);
+            }
+        }
+        (members, idents, types)
+    };
+    let (component_members, component_idents, component_tys) = select(false);
+    let (bundle_members, bundle_idents, bundle_tys) = select(true);
+
+    let with_static_ids_body = if generics.params.is_empty() {
+        quote! {
+            ::#crate_path::lazy_static::lazy_static! {
+                static ref ELEMENTS: ::std::vec::Vec<::std::any::TypeId> =
+                    <#ident as ::#crate_path::Bundle>::static_type_info().iter().map(|info| info.id()).collect();
+            }
+            f(&*ELEMENTS)
+        }
+    } else {
+        quote! {
+            f(&<Self as ::#crate_path::Bundle>::static_type_info().iter().map(|info| info.id()).collect::<::std::vec::Vec<_>>())
+        }
+    };
+    quote! {
+        impl #impl_generics ::#crate_path::DynamicBundle for #ident #ty_generics #where_clause {
+            fn with_ids<__hecs__T>(&self, f: impl ::std::ops::FnOnce(&[::std::any::TypeId]) -> __hecs__T) -> __hecs__T {
+                <Self as ::#crate_path::Bundle>::with_static_ids(f)
+            }
+
+            fn type_info(&self) -> ::std::vec::Vec<::#crate_path::TypeInfo> {
+                <Self as ::#crate_path::Bundle>::static_type_info()
+            }
+
+            #[allow(clippy::forget_copy)]
+            unsafe fn put(mut self, mut f: impl ::std::ops::FnMut(*mut u8, ::std::any::TypeId, usize) -> bool) {
+                #(
+                    if f((&mut self.#component_members as *mut #component_tys).cast::<u8>(), ::std::any::TypeId::of::<#component_tys>(), ::std::mem::size_of::<#component_tys>()) {
+                        #[allow(clippy::forget_copy)]
+                        ::std::mem::forget(self.#component_members);
+                    }
+                )*
+                #(
+                    <#bundle_tys as ::#crate_path::DynamicBundle>::put(self.#bundle_members, &mut f);
+                )*
+            }
+        }
+
+        impl #impl_generics ::#crate_path::Bundle for #ident #ty_generics #where_clause {
+            #[allow(non_camel_case_types)]
+            fn with_static_ids<__hecs__T>(f: impl ::std::ops::FnOnce(&[::std::any::TypeId]) -> __hecs__T) -> __hecs__T {
+                #with_static_ids_body
+            }
+
+            fn static_type_info() -> ::std::vec::Vec<::#crate_path::TypeInfo> {
+                let mut info = ::std::vec![#(::#crate_path::TypeInfo::of::<#component_tys>()),*];
+                #(
+                    info.extend(<#bundle_tys as ::#crate_path::Bundle>::static_type_info());
+                )*
+                info.sort_unstable();
+                info
+            }
+
+            unsafe fn get(
+                mut f: impl ::std::ops::FnMut(::std::any::TypeId, usize) -> ::std::option::Option<::std::ptr::NonNull<u8>>,
+            ) -> ::std::result::Result<Self, ::#crate_path::MissingComponent> {
+                #(
+                    let #component_idents = f(::std::any::TypeId::of::<#component_tys>(), ::std::mem::size_of::<#component_tys>())
+                            .ok_or_else(::#crate_path::MissingComponent::new::<#component_tys>)?
+                            .cast::<#component_tys>()
+                            .as_ptr();
+                )*
+                // nested bundles read their components right away, so they must not be dropped if a
+                // later bundle is missing a component
+                #(
+                    let #bundle_idents = ::std::mem::ManuallyDrop::new(
+                        <#bundle_tys as ::#crate_path::Bundle>::get(&mut f)?
+                    );
+                )*
+                ::std::result::Result::Ok(Self {
+                    #( #component_members: #component_idents.read(), )*
+                    #( #bundle_members: ::std::mem::ManuallyDrop::into_inner(#bundle_idents), )*
+                })
+            }
+        }
+    }
+}
+
 // no reason to generate a static for unit structs
 fn gen_unit_struct_bundle_impl(
     crate_path: &syn::Path,
@@ -279,7 +403,7 @@ fn get_lifetimes(fmt_string: fn(usize) -> String, count: usize) -> Vec<Lifetime>
 #[proc_macro]
 pub fn impl_query_set(_input: TokenStream) -> TokenStream {
     let mut tokens = TokenStream::new();
-    let max_queries = 4;
+    let max_queries = 8;
     let queries = get_idents(|i| format!("Q{}", i), max_queries);
     let filters = get_idents(|i| format!("F{}", i), max_queries);
     let lifetimes = get_lifetimes(|i| format!("'q{}", i), max_queries);
@@ -312,12 +436,13 @@ pub fn impl_query_set(_input: TokenStream) -> TokenStream {
         let query_fn_mut = &query_fn_muts[0..query_count];
         tokens.extend(TokenStream::from(quote! {
             impl<#(#lifetime,)* #(#query: HecsQuery,)* #(#filter: QueryFilter,)*> QueryTuple for (#(Query<#lifetime, #query, #filter>,)*) {
-                unsafe fn new(world: &World, component_access: &TypeAccess<ArchetypeComponent>) -> Self {
+                unsafe fn new(world: &World, component_access: &TypeAccess<ArchetypeComponent>, ticks: ChangeTicks) -> Self {
                     (
                         #(
                             Query::<#query, #filter>::new(
                                 std::mem::transmute(world),
                                 std::mem::transmute(component_access),
+                                ticks,
                             ),
                         )*
                     )
@@ -413,7 +538,9 @@ pub fn derive_system_param(input: TokenStream) -> TokenStream {
     TokenStream::from(quote! {
         impl #impl_generics #path::SystemParam for #struct_name#ty_generics {
             fn init(system_state: &mut #path::SystemState, world: &#path::World, resources: &mut #path::Resources) {
+                system_state.push_param_struct(std::any::type_name::<Self>());
                 #(<#field_types>::init(system_state, world, resources);)*
+                system_state.pop_param_struct();
             }
 
             unsafe fn get_param(
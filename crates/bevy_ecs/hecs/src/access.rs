@@ -2,7 +2,7 @@ use core::{any::TypeId, hash::Hash};
 use std::{boxed::Box, vec::Vec};
 
 use crate::{Archetype, World};
-use bevy_utils::HashSet;
+use bevy_utils::{HashMap, HashSet};
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum Access {
@@ -114,8 +114,36 @@ impl QueryAccess {
         }
     }
 
+    /// Adds every component this [QueryAccess] reads or writes to `type_access`, along with their type names,
+    /// regardless of which archetypes have them
+    pub fn get_type_access(&self, type_access: &mut TypeAccess<TypeId>) {
+        match self {
+            QueryAccess::None => {}
+            QueryAccess::Read(ty, name) => {
+                type_access.add_read(*ty);
+                type_access.set_type_name(*ty, name);
+            }
+            QueryAccess::Write(ty, name) => {
+                type_access.add_write(*ty);
+                type_access.set_type_name(*ty, name);
+            }
+            QueryAccess::Optional(query_access)
+            | QueryAccess::With(_, query_access)
+            | QueryAccess::Without(_, query_access) => query_access.get_type_access(type_access),
+            QueryAccess::Union(query_accesses) => {
+                for query_access in query_accesses {
+                    query_access.get_type_access(type_access);
+                }
+            }
+        }
+    }
+
     /// Returns how this [QueryAccess] accesses the given `archetype`.
-    /// If `type_access` is set, it will populate type access with the types this query reads/writes
+    /// If `type_access` is set, it will populate type access with the types this query reads/writes, along with their
+    /// type names
+    ///
+    /// Components stored in sparse sets aren't part of any archetype, but any entity of the archetype may have one, so
+    /// reading or writing them counts as accessing them in every archetype.
     pub fn get_access(
         &self,
         archetype: &Archetype,
@@ -124,20 +152,24 @@ impl QueryAccess {
     ) -> Option<Access> {
         match self {
             QueryAccess::None => Some(Access::None),
-            QueryAccess::Read(ty, _) => {
-                if archetype.has_type(*ty) {
+            QueryAccess::Read(ty, name) => {
+                if archetype.has_type(*ty) || archetype.has_sparse_type(*ty) {
                     if let Some(type_access) = type_access {
-                        type_access.add_read(ArchetypeComponent::new_ty(archetype_index, *ty));
+                        let archetype_component = ArchetypeComponent::new_ty(archetype_index, *ty);
+                        type_access.add_read(archetype_component);
+                        type_access.set_type_name(archetype_component, name);
                     }
                     Some(Access::Read)
                 } else {
                     None
                 }
             }
-            QueryAccess::Write(ty, _) => {
-                if archetype.has_type(*ty) {
+            QueryAccess::Write(ty, name) => {
+                if archetype.has_type(*ty) || archetype.has_sparse_type(*ty) {
                     if let Some(type_access) = type_access {
-                        type_access.add_write(ArchetypeComponent::new_ty(archetype_index, *ty));
+                        let archetype_component = ArchetypeComponent::new_ty(archetype_index, *ty);
+                        type_access.add_write(archetype_component);
+                        type_access.set_type_name(archetype_component, name);
                     }
                     Some(Access::Write)
                 } else {
@@ -157,7 +189,7 @@ impl QueryAccess {
                 }
             }
             QueryAccess::With(ty, query_access) => {
-                if archetype.has_type(*ty) {
+                if archetype.has_type(*ty) || archetype.has_sparse_type(*ty) {
                     query_access.get_access(archetype, archetype_index, type_access)
                 } else {
                     None
@@ -197,19 +229,32 @@ impl QueryAccess {
 }
 
 /// Provides information about the types a [System] reads and writes
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub struct TypeAccess<T: Hash + Eq + PartialEq> {
     reads_and_writes: HashSet<T>,
     writes: HashSet<T>,
     reads: HashSet<T>,
+    type_names: HashMap<T, &'static str>,
+}
+
+// type names only describe the accessed types, so they don't take part in comparisons
+impl<T: Hash + Eq + PartialEq> PartialEq for TypeAccess<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.reads_and_writes == other.reads_and_writes
+            && self.writes == other.writes
+            && self.reads == other.reads
+    }
 }
 
+impl<T: Hash + Eq + PartialEq> Eq for TypeAccess<T> {}
+
 impl<T: Hash + Eq + PartialEq> Default for TypeAccess<T> {
     fn default() -> Self {
         Self {
             reads_and_writes: Default::default(),
             writes: Default::default(),
             reads: Default::default(),
+            type_names: Default::default(),
         }
     }
 }
@@ -241,10 +286,20 @@ impl<T: Hash + Eq + PartialEq + Copy> TypeAccess<T> {
         self.reads_and_writes.intersection(&other.writes).next()
     }
 
+    /// Like `get_conflict`, but returns the type name of the conflicting type, if either access knows it
+    pub fn get_conflict_name(&self, other: &TypeAccess<T>) -> Option<&'static str> {
+        let conflict = self.get_conflict(other)?;
+        self.type_names
+            .get(conflict)
+            .or_else(|| other.type_names.get(conflict))
+            .copied()
+    }
+
     pub fn union(&mut self, other: &TypeAccess<T>) {
         self.writes.extend(&other.writes);
         self.reads.extend(&other.reads);
         self.reads_and_writes.extend(&other.reads_and_writes);
+        self.type_names.extend(&other.type_names);
     }
 
     pub fn add_read(&mut self, ty: T) {
@@ -257,10 +312,16 @@ impl<T: Hash + Eq + PartialEq + Copy> TypeAccess<T> {
         self.writes.insert(ty);
     }
 
+    /// Remembers the name of `ty`, so conflicts on it can be reported with `get_conflict_name`
+    pub fn set_type_name(&mut self, ty: T, type_name: &'static str) {
+        self.type_names.insert(ty, type_name);
+    }
+
     pub fn clear(&mut self) {
         self.reads_and_writes.clear();
         self.reads.clear();
         self.writes.clear();
+        self.type_names.clear();
     }
 
     pub fn is_read_or_write(&self, ty: &T) -> bool {
@@ -282,7 +343,9 @@ impl<T: Hash + Eq + PartialEq + Copy> TypeAccess<T> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{ArchetypeComponent, Entity, Fetch, Query, QueryAccess, TypeAccess, World};
+    use crate::{
+        ArchetypeComponent, Entity, Fetch, Query, QueryAccess, StorageType, TypeAccess, World,
+    };
     use std::vec;
 
     struct A;
@@ -290,6 +353,61 @@ mod tests {
     struct B;
     struct C;
 
+    #[test]
+    fn type_access_conflict_name() {
+        let mut reads = TypeAccess::default();
+        reads.add_read(0u32);
+        reads.set_type_name(0, "A");
+        reads.add_read(1);
+
+        let mut writes = TypeAccess::default();
+        writes.add_write(2u32);
+        assert_eq!(reads.get_conflict_name(&writes), None);
+
+        writes.add_write(0);
+        assert_eq!(reads.get_conflict_name(&writes), Some("A"));
+        assert_eq!(writes.get_conflict_name(&reads), Some("A"));
+
+        // the conflict is found, but neither access knows its name
+        let mut other_writes = TypeAccess::default();
+        other_writes.add_write(1u32);
+        assert_eq!(reads.get_conflict(&other_writes), Some(&1));
+        assert_eq!(reads.get_conflict_name(&other_writes), None);
+
+        let mut union = TypeAccess::default();
+        union.union(&reads);
+        assert_eq!(union.get_conflict_name(&writes), Some("A"));
+    }
+
+    #[test]
+    fn archetype_component_conflict_name() {
+        let mut world = World::default();
+        world.spawn((A, B));
+        world.spawn((A,));
+
+        let mut reads = TypeAccess::default();
+        <(&A, &B) as Query>::Fetch::access().get_world_archetype_access(&world, Some(&mut reads));
+        let mut writes = TypeAccess::default();
+        <&mut B as Query>::Fetch::access().get_world_archetype_access(&world, Some(&mut writes));
+        assert_eq!(
+            reads.get_conflict_name(&writes),
+            Some(std::any::type_name::<B>())
+        );
+
+        let mut other_writes = TypeAccess::default();
+        <(Entity, Option<&mut B>) as Query>::Fetch::access()
+            .get_world_archetype_access(&world, Some(&mut other_writes));
+        assert_eq!(
+            reads.get_conflict_name(&other_writes),
+            Some(std::any::type_name::<B>())
+        );
+        // the names are only descriptive, so they don't affect equality
+        assert_eq!(
+            reads,
+            TypeAccess::new(reads.reads.iter().copied().collect(), vec![])
+        );
+    }
+
     #[test]
     fn query_type_access() {
         let mut world = World::default();
@@ -361,5 +479,52 @@ mod tests {
             a_with_b_option_c_type_access,
             TypeAccess::new(vec![e2_a, e3_a], vec![e3_c])
         );
+
+        let mut a_without_b_type_access = TypeAccess::default();
+        QueryAccess::without::<B>(<&A as Query>::Fetch::access())
+            .get_world_archetype_access(&world, Some(&mut a_without_b_type_access));
+
+        assert_eq!(a_without_b_type_access, TypeAccess::new(vec![e1_a], vec![]));
+
+        let mut a_without_c_option_bmut_type_access = TypeAccess::default();
+        QueryAccess::without::<C>(<(&A, Option<&mut B>) as Query>::Fetch::access())
+            .get_world_archetype_access(&world, Some(&mut a_without_c_option_bmut_type_access));
+
+        assert_eq!(
+            a_without_c_option_bmut_type_access,
+            TypeAccess::new(vec![e1_a, e2_a], vec![e2_b])
+        );
+    }
+    #[test]
+    fn sparse_query_type_access() {
+        let mut world = World::default();
+        world.register_component::<C>(StorageType::SparseSet);
+        let e1 = world.spawn((A,));
+        let e2 = world.spawn((A, C));
+        let e1_archetype = world.get_entity_location(e1).unwrap().archetype;
+        assert_eq!(
+            world.get_entity_location(e2).unwrap().archetype,
+            e1_archetype
+        );
+
+        // whether an entity has a sparse component isn't known per archetype, so every archetype counts as accessed
+        let mut a_cmut_type_access = TypeAccess::default();
+        <(&A, &mut C) as Query>::Fetch::access()
+            .get_world_archetype_access(&world, Some(&mut a_cmut_type_access));
+        assert_eq!(
+            a_cmut_type_access,
+            TypeAccess::new(
+                vec![ArchetypeComponent::new::<A>(e1_archetype)],
+                vec![ArchetypeComponent::new::<C>(e1_archetype)]
+            )
+        );
+
+        let mut with_c_type_access = TypeAccess::default();
+        QueryAccess::with::<C>(<&A as Query>::Fetch::access())
+            .get_world_archetype_access(&world, Some(&mut with_c_type_access));
+        assert_eq!(
+            with_c_type_access,
+            TypeAccess::new(vec![ArchetypeComponent::new::<A>(e1_archetype)], vec![])
+        );
     }
 }
@@ -17,8 +17,10 @@
 use crate::{
     alloc::{
         alloc::{alloc, dealloc, Layout},
+        sync::Arc,
         vec::Vec,
     },
+    sparse_set::{ComponentSparseSet, SparseSets},
     Entity,
 };
 use bevy_utils::AHasher;
@@ -48,23 +50,18 @@ pub struct Archetype {
     data: UnsafeCell<NonNull<u8>>,
     data_size: usize,
     grow_size: usize,
+    sparse_sets: Option<Arc<SparseSets>>,
 }
 
 impl Archetype {
     fn assert_type_info(types: &[TypeInfo]) {
         types.windows(2).for_each(|x| match x[0].cmp(&x[1]) {
             core::cmp::Ordering::Less => (),
-            #[cfg(debug_assertions)]
             core::cmp::Ordering::Equal => panic!(
                 "attempted to allocate entity with duplicate {} components; \
                  each type must occur at most once!",
                 x[0].type_name
             ),
-            #[cfg(not(debug_assertions))]
-            core::cmp::Ordering::Equal => panic!(
-                "attempted to allocate entity with duplicate components; \
-                 each type must occur at most once!"
-            ),
             core::cmp::Ordering::Greater => panic!("type info is unsorted"),
         });
     }
@@ -89,9 +86,17 @@ impl Archetype {
             data: UnsafeCell::new(NonNull::dangling()),
             data_size: 0,
             grow_size,
+            sparse_sets: None,
         }
     }
 
+    /// An archetype of a world that stores some component types in `sparse_sets`
+    pub(crate) fn with_sparse_sets(types: Vec<TypeInfo>, sparse_sets: Arc<SparseSets>) -> Self {
+        let mut archetype = Self::new(types);
+        archetype.sparse_sets = Some(sparse_sets);
+        archetype
+    }
+
     pub(crate) fn clear(&mut self) {
         for ty in &self.types {
             for index in 0..self.len {
@@ -123,6 +128,50 @@ impl Archetype {
         self.state.contains_key(&id)
     }
 
+    /// The sparse set that components of type `ty` are stored in, if they aren't stored in tables. Only some of the
+    /// archetype's entities may have a component in it.
+    #[inline]
+    pub(crate) fn get_sparse_set(&self, ty: TypeId) -> Option<&ComponentSparseSet> {
+        self.sparse_sets.as_ref()?.get(ty)
+    }
+
+    /// Whether components of type `ty` are stored in a sparse set instead of in tables
+    #[inline]
+    pub fn has_sparse_type(&self, ty: TypeId) -> bool {
+        match &self.sparse_sets {
+            Some(sparse_sets) => sparse_sets.contains(ty),
+            None => false,
+        }
+    }
+
+    /// Finds the component of type `ty` and size `size` of the entity at `index`, whether it is stored in this
+    /// archetype or in a sparse set
+    ///
+    /// # Safety
+    /// `index` must be in bounds if the component is stored in this archetype
+    pub(crate) unsafe fn get_component(
+        &self,
+        ty: TypeId,
+        size: usize,
+        index: usize,
+    ) -> Option<StoredComponent<'_>> {
+        match self.state.get(&ty) {
+            Some(state) => Some(StoredComponent {
+                value: NonNull::new_unchecked(
+                    (*self.data.get()).as_ptr().add(state.offset + size * index),
+                ),
+                mutated: NonNull::new_unchecked(state.mutated().as_ptr().add(index)),
+                borrow: &state.borrow,
+            }),
+            None => {
+                let set = self.get_sparse_set(ty)?;
+                // reserved entities that haven't been flushed don't have an index yet
+                let entity = *self.entities[..self.len].get(index)?;
+                set.get_component(entity)
+            }
+        }
+    }
+
     #[allow(missing_docs)]
     #[inline]
     pub fn get<T: Component>(&self) -> Option<NonNull<T>> {
@@ -268,19 +317,13 @@ impl Archetype {
         self.entities.len()
     }
 
-    #[allow(missing_docs)]
-    pub fn clear_trackers(&mut self) {
-        for type_state in self.state.values_mut() {
-            type_state.clear_trackers();
-        }
-    }
-
     fn grow(&mut self, increment: usize) {
         unsafe {
             let old_count = self.len;
-            let count = old_count + increment;
+            // the new capacity, which is larger than `old_count + increment` when there already was spare room
+            let count = self.entities.len() + increment;
             self.entities.resize(
-                self.entities.len() + increment,
+                count,
                 Entity {
                     id: u32::MAX,
                     generation: u32::MAX,
@@ -288,8 +331,8 @@ impl Archetype {
             );
 
             for type_state in self.state.values_mut() {
-                type_state.mutated_entities.resize_with(count, || false);
-                type_state.added_entities.resize_with(count, || false);
+                type_state.mutated_ticks.resize(count, 0);
+                type_state.added_ticks.resize(count, 0);
             }
 
             let old_data_size = mem::replace(&mut self.data_size, 0);
@@ -346,7 +389,6 @@ impl Archetype {
                 .as_ptr();
             (ty.drop)(removed);
             if index != last {
-                // TODO: copy component tracker state here
                 ptr::copy_nonoverlapping(
                     self.get_dynamic(ty.id, ty.layout.size(), last)
                         .unwrap()
@@ -356,8 +398,8 @@ impl Archetype {
                 );
 
                 let type_state = self.state.get_mut(&ty.id).unwrap();
-                type_state.mutated_entities[index] = type_state.mutated_entities[last];
-                type_state.added_entities[index] = type_state.added_entities[last];
+                type_state.mutated_ticks[index] = type_state.mutated_ticks[last];
+                type_state.added_ticks[index] = type_state.added_ticks[last];
             }
         }
         self.len = last;
@@ -369,11 +411,15 @@ impl Archetype {
         }
     }
 
-    /// Returns the ID of the entity moved into `index`, if any
-    pub(crate) unsafe fn move_to(
+    /// Moves the components at `index` out of this archetype by handing each one to `f`, which takes ownership of
+    /// it along with its added and mutated ticks. Returns the ID of the entity moved into `index`, if any
+    ///
+    /// # Safety
+    /// `index` must be in-bounds and `f` must move or drop every component it is given
+    pub unsafe fn move_to(
         &mut self,
         index: usize,
-        mut f: impl FnMut(*mut u8, TypeId, usize, bool, bool),
+        mut f: impl FnMut(*mut u8, TypeId, usize, u64, u64),
     ) -> Option<Entity> {
         let last = self.len - 1;
         for ty in &self.types {
@@ -382,9 +428,9 @@ impl Archetype {
                 .unwrap()
                 .as_ptr();
             let type_state = self.state.get(&ty.id).unwrap();
-            let is_added = type_state.added_entities[index];
-            let is_mutated = type_state.mutated_entities[index];
-            f(moved, ty.id(), ty.layout().size(), is_added, is_mutated);
+            let added_tick = type_state.added_ticks[index];
+            let mutated_tick = type_state.mutated_ticks[index];
+            f(moved, ty.id(), ty.layout().size(), added_tick, mutated_tick);
             if index != last {
                 ptr::copy_nonoverlapping(
                     self.get_dynamic(ty.id, ty.layout.size(), last)
@@ -394,8 +440,8 @@ impl Archetype {
                     ty.layout.size(),
                 );
                 let type_state = self.state.get_mut(&ty.id).unwrap();
-                type_state.added_entities[index] = type_state.added_entities[last];
-                type_state.mutated_entities[index] = type_state.mutated_entities[last];
+                type_state.added_ticks[index] = type_state.added_ticks[last];
+                type_state.mutated_ticks[index] = type_state.mutated_ticks[last];
             }
         }
         self.len -= 1;
@@ -407,6 +453,9 @@ impl Archetype {
         }
     }
 
+    /// Writes `component` to `index`. Newly added components get an added tick of `change_tick` and no mutated tick,
+    /// while `mutated` components only have their mutated tick set to `change_tick`.
+    ///
     /// # Safety
     ///
     ///  - `component` must point to valid memory
@@ -414,6 +463,7 @@ impl Archetype {
     ///  - `index` must be in-bound
     ///  - `size` must be the size of the component
     ///  - the storage array must be big enough
+    #[allow(clippy::too_many_arguments)]
     pub unsafe fn put_dynamic(
         &mut self,
         component: *mut u8,
@@ -422,13 +472,16 @@ impl Archetype {
         index: usize,
         added: bool,
         mutated: bool,
+        change_tick: u64,
     ) {
         let state = self.state.get_mut(&ty).unwrap();
         if added {
-            state.added_entities[index] = true;
+            // the slot may still hold the ticks of an entity that was removed from it
+            state.added_ticks[index] = change_tick;
+            state.mutated_ticks[index] = 0;
         }
         if mutated {
-            state.mutated_entities[index] = true;
+            state.mutated_ticks[index] = change_tick;
         }
         let ptr = (*self.data.get())
             .as_ptr()
@@ -455,13 +508,20 @@ impl Drop for Archetype {
     }
 }
 
+/// Pointers to an entity's component and its mutated tick, along with the borrow state guarding the component
+pub(crate) struct StoredComponent<'a> {
+    pub(crate) value: NonNull<u8>,
+    pub(crate) mutated: NonNull<u64>,
+    pub(crate) borrow: &'a AtomicBorrow,
+}
+
 /// Metadata about a type stored in an archetype
 #[derive(Debug)]
 pub struct TypeState {
     offset: usize,
     borrow: AtomicBorrow,
-    mutated_entities: Vec<bool>,
-    added_entities: Vec<bool>,
+    mutated_ticks: Vec<u64>,
+    added_ticks: Vec<u64>,
 }
 
 impl TypeState {
@@ -469,31 +529,22 @@ impl TypeState {
         Self {
             offset: 0,
             borrow: AtomicBorrow::new(),
-            mutated_entities: Vec::new(),
-            added_entities: Vec::new(),
+            mutated_ticks: Vec::new(),
+            added_ticks: Vec::new(),
         }
     }
 
-    fn clear_trackers(&mut self) {
-        for mutated in self.mutated_entities.iter_mut() {
-            *mutated = false;
-        }
-
-        for added in self.added_entities.iter_mut() {
-            *added = false;
-        }
-    }
-
-    #[allow(missing_docs)]
+    /// The change tick each entity's component was last mutated at, or 0 if it hasn't been mutated since it was
+    /// added
     #[inline]
-    pub fn mutated(&self) -> NonNull<bool> {
-        unsafe { NonNull::new_unchecked(self.mutated_entities.as_ptr() as *mut bool) }
+    pub fn mutated(&self) -> NonNull<u64> {
+        unsafe { NonNull::new_unchecked(self.mutated_ticks.as_ptr() as *mut u64) }
     }
 
-    #[allow(missing_docs)]
+    /// The change tick each entity's component was added at
     #[inline]
-    pub fn added(&self) -> NonNull<bool> {
-        unsafe { NonNull::new_unchecked(self.added_entities.as_ptr() as *mut bool) }
+    pub fn added(&self) -> NonNull<u64> {
+        unsafe { NonNull::new_unchecked(self.added_ticks.as_ptr() as *mut u64) }
     }
 }
 
@@ -503,7 +554,6 @@ pub struct TypeInfo {
     id: TypeId,
     layout: Layout,
     drop: unsafe fn(*mut u8),
-    #[cfg(debug_assertions)]
     type_name: &'static str,
 }
 
@@ -518,7 +568,6 @@ impl TypeInfo {
             id: TypeId::of::<T>(),
             layout: Layout::new::<T>(),
             drop: drop_ptr::<T>,
-            #[cfg(debug_assertions)]
             type_name: core::any::type_name::<T>(),
         }
     }
@@ -535,6 +584,12 @@ impl TypeInfo {
         self.layout
     }
 
+    /// The name of the type, as given by [core::any::type_name]
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
     pub(crate) unsafe fn drop(&self, data: *mut u8) {
         (self.drop)(data)
     }
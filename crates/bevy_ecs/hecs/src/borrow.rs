@@ -15,7 +15,9 @@
 // modified by Bevy contributors
 
 use core::{
+    any::{type_name, TypeId},
     fmt::Debug,
+    mem,
     ops::{Deref, DerefMut},
     sync::atomic::{AtomicUsize, Ordering},
 };
@@ -73,7 +75,7 @@ const UNIQUE_BIT: usize = !(usize::max_value() >> 1);
 /// Shared borrow of an entity's component
 #[derive(Clone)]
 pub struct Ref<'a, T: Component> {
-    archetype: &'a Archetype,
+    borrow: &'a AtomicBorrow,
     target: &'a T,
 }
 
@@ -84,13 +86,15 @@ impl<'a, T: Component> Ref<'a, T> {
     ///
     /// - the index of the component must be valid
     pub unsafe fn new(archetype: &'a Archetype, index: usize) -> Result<Self, MissingComponent> {
-        let target = archetype
-            .get::<T>()
+        let component = archetype
+            .get_component(TypeId::of::<T>(), mem::size_of::<T>(), index)
             .ok_or_else(MissingComponent::new::<T>)?;
-        archetype.borrow::<T>();
+        if !component.borrow.borrow() {
+            panic!("{} already borrowed uniquely", type_name::<T>());
+        }
         Ok(Self {
-            archetype,
-            target: &*target.as_ptr().add(index as usize),
+            borrow: component.borrow,
+            target: &*component.value.cast::<T>().as_ptr(),
         })
     }
 }
@@ -100,7 +104,7 @@ unsafe impl<T: Component> Sync for Ref<'_, T> {}
 
 impl<'a, T: Component> Drop for Ref<'a, T> {
     fn drop(&mut self) {
-        self.archetype.release::<T>();
+        self.borrow.release();
     }
 }
 
@@ -123,26 +127,35 @@ where
 
 /// Unique borrow of an entity's component
 pub struct RefMut<'a, T: Component> {
-    archetype: &'a Archetype,
+    borrow: &'a AtomicBorrow,
     target: &'a mut T,
-    modified: &'a mut bool,
+    modified: &'a mut u64,
+    change_tick: u64,
 }
 
 impl<'a, T: Component> RefMut<'a, T> {
-    /// Creates a new entity component mutable borrow
+    /// Creates a new entity component mutable borrow. Mutably dereferencing it stamps the component with
+    /// `change_tick`.
     ///
     /// # Safety
     ///
     /// - the index of the component must be valid
-    pub unsafe fn new(archetype: &'a Archetype, index: usize) -> Result<Self, MissingComponent> {
-        let (target, type_state) = archetype
-            .get_with_type_state::<T>()
+    pub unsafe fn new(
+        archetype: &'a Archetype,
+        index: usize,
+        change_tick: u64,
+    ) -> Result<Self, MissingComponent> {
+        let component = archetype
+            .get_component(TypeId::of::<T>(), mem::size_of::<T>(), index)
             .ok_or_else(MissingComponent::new::<T>)?;
-        archetype.borrow_mut::<T>();
+        if !component.borrow.borrow_mut() {
+            panic!("{} already borrowed", type_name::<T>());
+        }
         Ok(Self {
-            archetype,
-            target: &mut *target.as_ptr().add(index),
-            modified: &mut *type_state.mutated().as_ptr().add(index),
+            borrow: component.borrow,
+            target: &mut *component.value.cast::<T>().as_ptr(),
+            modified: &mut *component.mutated.as_ptr(),
+            change_tick,
         })
     }
 }
@@ -152,7 +165,7 @@ unsafe impl<T: Component> Sync for RefMut<'_, T> {}
 
 impl<'a, T: Component> Drop for RefMut<'a, T> {
     fn drop(&mut self) {
-        self.archetype.release_mut::<T>();
+        self.borrow.release_mut();
     }
 }
 
@@ -166,7 +179,7 @@ impl<'a, T: Component> Deref for RefMut<'a, T> {
 
 impl<'a, T: Component> DerefMut for RefMut<'a, T> {
     fn deref_mut(&mut self) -> &mut T {
-        *self.modified = true;
+        *self.modified = self.change_tick;
         self.target
     }
 }
@@ -183,23 +196,17 @@ where
 /// Handle to an entity with any component types
 #[derive(Copy, Clone)]
 pub struct EntityRef<'a> {
-    archetype: Option<&'a Archetype>,
+    archetype: &'a Archetype,
     index: usize,
+    change_tick: u64,
 }
 
 impl<'a> EntityRef<'a> {
-    /// Construct a `Ref` for an entity with no components
-    pub(crate) fn empty() -> Self {
-        Self {
-            archetype: None,
-            index: 0,
-        }
-    }
-
-    pub(crate) unsafe fn new(archetype: &'a Archetype, index: usize) -> Self {
+    pub(crate) unsafe fn new(archetype: &'a Archetype, index: usize, change_tick: u64) -> Self {
         Self {
-            archetype: Some(archetype),
+            archetype,
             index,
+            change_tick,
         }
     }
 
@@ -208,14 +215,14 @@ impl<'a> EntityRef<'a> {
     /// Panics if the component is already uniquely borrowed from another entity with the same
     /// components.
     pub fn get<T: Component>(&self) -> Option<Ref<'a, T>> {
-        Some(unsafe { Ref::new(self.archetype?, self.index).ok()? })
+        unsafe { Ref::new(self.archetype, self.index).ok() }
     }
 
     /// Uniquely borrow the component of type `T`, if it exists
     ///
     /// Panics if the component is already borrowed from another entity with the same components.
     pub fn get_mut<T: Component>(&self) -> Option<RefMut<'a, T>> {
-        Some(unsafe { RefMut::new(self.archetype?, self.index).ok()? })
+        unsafe { RefMut::new(self.archetype, self.index, self.change_tick).ok() }
     }
 }
 
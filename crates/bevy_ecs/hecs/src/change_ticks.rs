@@ -0,0 +1,32 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+// Shared by every world and resource collection, so ticks from any of them can be compared with each other. Ticks
+// are 64 bits wide so they never wrap around in practice.
+static CHANGE_TICK: AtomicU64 = AtomicU64::new(1);
+
+/// Advances the global change tick and returns the tick it held before. Every run of a system claims a tick this way
+/// and stamps its changes with it, so changes made by later runs always have a larger tick.
+pub fn increment_change_tick() -> u64 {
+    CHANGE_TICK.fetch_add(1, Ordering::AcqRel)
+}
+
+/// The current global change tick. Changes made outside of systems are stamped with this.
+pub fn read_change_tick() -> u64 {
+    CHANGE_TICK.load(Ordering::Acquire)
+}
+
+/// The ticks a query uses for change detection: components changed after `last_change_tick` count as changed, and
+/// components mutated through the query are stamped with `change_tick`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ChangeTicks {
+    pub last_change_tick: u64,
+    pub change_tick: u64,
+}
+
+impl ChangeTicks {
+    /// Returns true if `tick` was stamped after `last_change_tick`
+    #[inline]
+    pub fn is_changed(&self, tick: u64) -> bool {
+        tick > self.last_change_tick
+    }
+}
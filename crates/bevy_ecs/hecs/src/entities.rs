@@ -9,7 +9,9 @@ use std::error::Error;
 
 /// Lightweight unique ID of an entity
 ///
-/// Obtained from `World::spawn`. Can be stored to refer to an entity in the future.
+/// Obtained from `World::spawn`. Can be stored to refer to an entity in the future. The ID of a despawned entity is
+/// reused by later spawns, but with a new generation, so a stored `Entity` never refers to a different entity than
+/// the one it was obtained for: once its entity is despawned, every lookup fails with [NoSuchEntity].
 #[derive(Clone, Copy, Hash, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Entity {
     pub(crate) generation: u32,
@@ -199,6 +201,14 @@ impl Entities {
     }
 
     pub fn clear(&mut self) {
+        // every ID is about to be reused, so bump the generations like `free` does to invalidate existing handles
+        for meta in self.meta.iter_mut() {
+            meta.generation += 1;
+            meta.location = Location {
+                archetype: 0,
+                index: usize::max_value(),
+            };
+        }
         // Not racey due to &mut self
         self.free_cursor
             .store(self.meta.len() as u32, Ordering::Relaxed);
@@ -233,12 +243,6 @@ impl Entities {
         if meta.generation != entity.generation {
             return Err(NoSuchEntity);
         }
-        if meta.location.archetype == 0 {
-            return Ok(Location {
-                archetype: 0,
-                index: usize::max_value(),
-            });
-        }
         Ok(meta.location)
     }
 
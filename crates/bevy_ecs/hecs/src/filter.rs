@@ -1,16 +1,25 @@
-use crate::{archetype::Archetype, Component, QueryAccess};
+use crate::{archetype::Archetype, sparse_set::SparseFetch, ChangeTicks, Component, QueryAccess};
 use core::{any::TypeId, marker::PhantomData, ptr::NonNull};
 use std::{boxed::Box, vec};
 
 pub trait QueryFilter: Sized {
     type EntityFilter: EntityFilter;
     fn access() -> QueryAccess;
-    fn get_entity_filter(archetype: &Archetype) -> Option<Self::EntityFilter>;
+    /// Construct the filter for `archetype`, or `None` if none of its entities can match. Change detection filters
+    /// compare against `ticks.last_change_tick`.
+    fn get_entity_filter(archetype: &Archetype, ticks: ChangeTicks) -> Option<Self::EntityFilter>;
 }
 
 pub trait EntityFilter: Sized {
     const DANGLING: Self;
 
+    /// True if the filter matches every entity of the archetype it was constructed for, so whether an entity
+    /// matches can be answered from its archetype alone
+    #[inline]
+    fn is_archetypal(&self) -> bool {
+        false
+    }
+
     /// # Safety
     /// This might access archetype data in an unsafe manner. In general filters should be read-only and they should only access
     /// the data they have claimed in `access()`.
@@ -22,23 +31,67 @@ pub struct AnyEntityFilter;
 impl EntityFilter for AnyEntityFilter {
     const DANGLING: Self = AnyEntityFilter;
 
+    #[inline]
+    fn is_archetypal(&self) -> bool {
+        true
+    }
+
     #[inline]
     unsafe fn matches_entity(&self, _offset: usize) -> bool {
         true
     }
 }
 
+/// Query filter that matches entities matched by any of the filters in the tuple `T`. Filters nest freely: a tuple
+/// matches entities matched by all of its filters, so `Or<(With<A>, (Without<B>, Changed<C>))>` matches entities that
+/// have an `A`, or that have no `B` and a changed `C`.
+///
+/// Branches are checked in order for each entity and stop at the first match. A branch that can't match an archetype
+/// is skipped for all of its entities, and a branch that matches every entity of an archetype spares the others.
+///
+/// The access of every branch that can match an archetype is registered, since any of them may be checked.
 pub struct Or<T>(pub T);
 
-/// Query transformer that retrieves components of type `T` that have been mutated since the start of the frame.
+/// Query transformer that retrieves components of type `T` that have been mutated since the system last ran.
 /// Added components do not count as mutated.
-pub struct Mutated<T>(NonNull<bool>, PhantomData<T>);
-
-/// Query transformer that retrieves components of type `T` that have been added since the start of the frame.
-pub struct Added<T>(NonNull<bool>, PhantomData<T>);
-
-/// Query transformer that retrieves components of type `T` that have either been mutated or added since the start of the frame.
-pub struct Changed<T>(NonNull<bool>, NonNull<bool>, PhantomData<T>);
+///
+/// Queries made directly on a [World](crate::World) consider changes made since the last
+/// [World::clear_trackers](crate::World::clear_trackers) instead.
+pub struct Mutated<T>(
+    NonNull<u64>,
+    Option<SparseFetch>,
+    ChangeTicks,
+    PhantomData<T>,
+);
+
+/// Query transformer that retrieves components of type `T` that have been added since the system last ran.
+///
+/// Queries made directly on a [World](crate::World) consider changes made since the last
+/// [World::clear_trackers](crate::World::clear_trackers) instead.
+pub struct Added<T>(
+    NonNull<u64>,
+    Option<SparseFetch>,
+    ChangeTicks,
+    PhantomData<T>,
+);
+
+/// Query transformer that retrieves components of type `T` that have either been mutated or added since the system
+/// last ran.
+///
+/// Queries made directly on a [World](crate::World) consider changes made since the last
+/// [World::clear_trackers](crate::World::clear_trackers) instead.
+pub struct Changed<T>(
+    NonNull<u64>,
+    NonNull<u64>,
+    Option<SparseFetch>,
+    ChangeTicks,
+    PhantomData<T>,
+);
+
+const DANGLING_TICKS: ChangeTicks = ChangeTicks {
+    last_change_tick: 0,
+    change_tick: 0,
+};
 
 impl QueryFilter for () {
     type EntityFilter = AnyEntityFilter;
@@ -48,7 +101,10 @@ impl QueryFilter for () {
     }
 
     #[inline]
-    fn get_entity_filter(_archetype: &Archetype) -> Option<Self::EntityFilter> {
+    fn get_entity_filter(
+        _archetype: &Archetype,
+        _ticks: ChangeTicks,
+    ) -> Option<Self::EntityFilter> {
         Some(AnyEntityFilter)
     }
 }
@@ -61,19 +117,28 @@ impl<T: Component> QueryFilter for Added<T> {
     }
 
     #[inline]
-    fn get_entity_filter(archetype: &Archetype) -> Option<Self::EntityFilter> {
-        archetype
-            .get_type_state(TypeId::of::<T>())
-            .map(|state| Added(state.added(), Default::default()))
+    fn get_entity_filter(archetype: &Archetype, ticks: ChangeTicks) -> Option<Self::EntityFilter> {
+        match archetype.get_type_state(TypeId::of::<T>()) {
+            Some(state) => Some(Added(state.added(), None, ticks, Default::default())),
+            // SAFE: the filter starts at the first entity of the archetype
+            None => unsafe { SparseFetch::new(archetype, 0, TypeId::of::<T>()) }
+                .map(|sparse| Added(NonNull::dangling(), Some(sparse), ticks, Default::default())),
+        }
     }
 }
 
 impl<T: Component> EntityFilter for Added<T> {
-    const DANGLING: Self = Added(NonNull::dangling(), PhantomData::<T>);
+    const DANGLING: Self = Added(NonNull::dangling(), None, DANGLING_TICKS, PhantomData::<T>);
 
     #[inline]
     unsafe fn matches_entity(&self, offset: usize) -> bool {
-        *self.0.as_ptr().add(offset)
+        match &self.1 {
+            Some(sparse) => match sparse.dense_index(offset) {
+                Some(index) => self.2.is_changed(*sparse.set().added().as_ptr().add(index)),
+                None => false,
+            },
+            None => self.2.is_changed(*self.0.as_ptr().add(offset)),
+        }
     }
 }
 
@@ -85,18 +150,30 @@ impl<T: Component> QueryFilter for Mutated<T> {
     }
 
     #[inline]
-    fn get_entity_filter(archetype: &Archetype) -> Option<Self::EntityFilter> {
-        archetype
-            .get_type_state(TypeId::of::<T>())
-            .map(|state| Mutated(state.mutated(), Default::default()))
+    fn get_entity_filter(archetype: &Archetype, ticks: ChangeTicks) -> Option<Self::EntityFilter> {
+        match archetype.get_type_state(TypeId::of::<T>()) {
+            Some(state) => Some(Mutated(state.mutated(), None, ticks, Default::default())),
+            // SAFE: the filter starts at the first entity of the archetype
+            None => unsafe { SparseFetch::new(archetype, 0, TypeId::of::<T>()) }.map(|sparse| {
+                Mutated(NonNull::dangling(), Some(sparse), ticks, Default::default())
+            }),
+        }
     }
 }
 
 impl<T: Component> EntityFilter for Mutated<T> {
-    const DANGLING: Self = Mutated(NonNull::dangling(), PhantomData::<T>);
+    const DANGLING: Self = Mutated(NonNull::dangling(), None, DANGLING_TICKS, PhantomData::<T>);
 
     unsafe fn matches_entity(&self, offset: usize) -> bool {
-        *self.0.as_ptr().add(offset)
+        match &self.1 {
+            Some(sparse) => match sparse.dense_index(offset) {
+                Some(index) => self
+                    .2
+                    .is_changed(*sparse.set().mutated().as_ptr().add(index)),
+                None => false,
+            },
+            None => self.2.is_changed(*self.0.as_ptr().add(offset)),
+        }
     }
 }
 
@@ -108,56 +185,134 @@ impl<T: Component> QueryFilter for Changed<T> {
     }
 
     #[inline]
-    fn get_entity_filter(archetype: &Archetype) -> Option<Self::EntityFilter> {
-        archetype
-            .get_type_state(TypeId::of::<T>())
-            .map(|state| Changed(state.added(), state.mutated(), Default::default()))
+    fn get_entity_filter(archetype: &Archetype, ticks: ChangeTicks) -> Option<Self::EntityFilter> {
+        match archetype.get_type_state(TypeId::of::<T>()) {
+            Some(state) => Some(Changed(
+                state.added(),
+                state.mutated(),
+                None,
+                ticks,
+                Default::default(),
+            )),
+            // SAFE: the filter starts at the first entity of the archetype
+            None => unsafe { SparseFetch::new(archetype, 0, TypeId::of::<T>()) }.map(|sparse| {
+                Changed(
+                    NonNull::dangling(),
+                    NonNull::dangling(),
+                    Some(sparse),
+                    ticks,
+                    Default::default(),
+                )
+            }),
+        }
     }
 }
 
 impl<T: Component> EntityFilter for Changed<T> {
-    const DANGLING: Self = Changed(NonNull::dangling(), NonNull::dangling(), PhantomData::<T>);
+    const DANGLING: Self = Changed(
+        NonNull::dangling(),
+        NonNull::dangling(),
+        None,
+        DANGLING_TICKS,
+        PhantomData::<T>,
+    );
 
     #[inline]
     unsafe fn matches_entity(&self, offset: usize) -> bool {
-        *self.0.as_ptr().add(offset) || *self.1.as_ptr().add(offset)
+        match &self.2 {
+            Some(sparse) => match sparse.dense_index(offset) {
+                Some(index) => {
+                    let set = sparse.set();
+                    self.3.is_changed(*set.added().as_ptr().add(index))
+                        || self.3.is_changed(*set.mutated().as_ptr().add(index))
+                }
+                None => false,
+            },
+            None => {
+                self.3.is_changed(*self.0.as_ptr().add(offset))
+                    || self.3.is_changed(*self.1.as_ptr().add(offset))
+            }
+        }
     }
 }
 
+/// The entity filter of [With] and [Without]. Components stored in tables are filtered by archetype, while components
+/// stored in sparse sets have to be looked up for each entity.
+pub struct HasComponentFilter {
+    sparse: Option<SparseFetch>,
+    has: bool,
+}
+
+impl EntityFilter for HasComponentFilter {
+    const DANGLING: Self = HasComponentFilter {
+        sparse: None,
+        has: true,
+    };
+
+    #[inline]
+    fn is_archetypal(&self) -> bool {
+        self.sparse.is_none()
+    }
+
+    #[inline]
+    unsafe fn matches_entity(&self, offset: usize) -> bool {
+        match &self.sparse {
+            Some(sparse) => sparse.dense_index(offset).is_some() == self.has,
+            None => true,
+        }
+    }
+}
+
+/// Query transformer that skips entities that have a component of type `T`. The component itself is not accessed.
 pub struct Without<T>(PhantomData<T>);
 
 impl<T: Component> QueryFilter for Without<T> {
-    type EntityFilter = AnyEntityFilter;
+    type EntityFilter = HasComponentFilter;
 
     fn access() -> QueryAccess {
         QueryAccess::without::<T>(QueryAccess::None)
     }
 
     #[inline]
-    fn get_entity_filter(archetype: &Archetype) -> Option<Self::EntityFilter> {
+    fn get_entity_filter(archetype: &Archetype, _ticks: ChangeTicks) -> Option<Self::EntityFilter> {
         if archetype.has_type(TypeId::of::<T>()) {
             None
         } else {
-            Some(AnyEntityFilter)
+            Some(HasComponentFilter {
+                // SAFE: the filter starts at the first entity of the archetype
+                sparse: unsafe { SparseFetch::new(archetype, 0, TypeId::of::<T>()) },
+                has: false,
+            })
         }
     }
 }
 
+/// Query transformer that skips entities that don't have a component of type `T`. The component itself is not
+/// accessed.
 pub struct With<T>(PhantomData<T>);
 
 impl<T: Component> QueryFilter for With<T> {
-    type EntityFilter = AnyEntityFilter;
+    type EntityFilter = HasComponentFilter;
 
     fn access() -> QueryAccess {
         QueryAccess::with::<T>(QueryAccess::None)
     }
 
     #[inline]
-    fn get_entity_filter(archetype: &Archetype) -> Option<Self::EntityFilter> {
+    fn get_entity_filter(archetype: &Archetype, _ticks: ChangeTicks) -> Option<Self::EntityFilter> {
         if archetype.has_type(TypeId::of::<T>()) {
-            Some(AnyEntityFilter)
+            Some(HasComponentFilter {
+                sparse: None,
+                has: true,
+            })
         } else {
-            None
+            // SAFE: the filter starts at the first entity of the archetype
+            unsafe { SparseFetch::new(archetype, 0, TypeId::of::<T>()) }.map(|sparse| {
+                HasComponentFilter {
+                    sparse: Some(sparse),
+                    has: true,
+                }
+            })
         }
     }
 }
@@ -175,8 +330,8 @@ macro_rules! impl_query_filter_tuple {
                 ])
             }
 
-            fn get_entity_filter(archetype: &Archetype) -> Option<Self::EntityFilter> {
-                Some(($($filter::get_entity_filter(archetype)?,)*))
+            fn get_entity_filter(archetype: &Archetype, ticks: ChangeTicks) -> Option<Self::EntityFilter> {
+                Some(($($filter::get_entity_filter(archetype, ticks)?,)*))
             }
 
         }
@@ -185,6 +340,10 @@ macro_rules! impl_query_filter_tuple {
         #[allow(non_snake_case)]
         impl<$($filter: EntityFilter),*> EntityFilter for ($($filter,)*) {
             const DANGLING: Self = ($($filter::DANGLING,)*);
+            fn is_archetypal(&self) -> bool {
+                let ($($filter,)*) = self;
+                true $(&& $filter.is_archetypal())*
+            }
             unsafe fn matches_entity(&self, offset: usize) -> bool {
                 let ($($filter,)*) = self;
                 true $(&& $filter.matches_entity(offset))*
@@ -201,13 +360,23 @@ macro_rules! impl_query_filter_tuple {
                 ])
             }
 
-            fn get_entity_filter(archetype: &Archetype) -> Option<Self::EntityFilter> {
+            fn get_entity_filter(archetype: &Archetype, ticks: ChangeTicks) -> Option<Self::EntityFilter> {
                 let mut matches_something = false;
+                let mut archetypal = false;
                 $(
-                    let $filter = $filter::get_entity_filter(archetype);
+                    let $filter = if archetypal {
+                        None
+                    } else {
+                        $filter::get_entity_filter(archetype, ticks)
+                    };
                     matches_something = matches_something || $filter.is_some();
+                    archetypal = archetypal || matches!(&$filter, Some(filter) if filter.is_archetypal());
                 )*
-                if matches_something {
+                if archetypal {
+                    // a branch matches every entity of the archetype, so the branches before it never need checking
+                    $(let $filter = $filter.filter(|filter| filter.is_archetypal());)*
+                    Some(Or(($($filter,)*)))
+                } else if matches_something {
                     Some(Or(($($filter,)*)))
                 } else {
                     None
@@ -219,6 +388,11 @@ macro_rules! impl_query_filter_tuple {
         #[allow(non_snake_case)]
         impl<$($filter: EntityFilter),*> EntityFilter for Or<($(Option<$filter>,)*)> {
             const DANGLING: Self = Or(($(Some($filter::DANGLING),)*));
+            // an entity matches as soon as one of the filters does, so one filter matching the whole archetype is enough
+            fn is_archetypal(&self) -> bool {
+                let Or(($($filter,)*)) = self;
+                false $(|| $filter.as_ref().map_or(false, |filter| filter.is_archetypal()))*
+            }
             unsafe fn matches_entity(&self, offset: usize) -> bool {
                 let Or(($($filter,)*)) = self;
                 false $(|| $filter.as_ref().map_or(false, |filter|filter.matches_entity(offset)))*
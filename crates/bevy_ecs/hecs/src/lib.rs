@@ -67,23 +67,29 @@ mod access;
 mod archetype;
 mod borrow;
 mod bundle;
+mod change_ticks;
 mod entities;
 mod entity_builder;
 mod filter;
 mod query;
 #[cfg(feature = "serde")]
 mod serde;
+mod sparse_set;
 mod world;
 
 pub use access::{ArchetypeComponent, QueryAccess, TypeAccess};
 pub use archetype::{Archetype, TypeState};
 pub use borrow::{AtomicBorrow, Ref, RefMut};
 pub use bundle::{Bundle, DynamicBundle, MissingComponent};
+pub use change_ticks::{increment_change_tick, read_change_tick, ChangeTicks};
 pub use entities::{Entity, EntityReserver, Location, NoSuchEntity};
 pub use entity_builder::{BuiltEntity, EntityBuilder};
 pub use filter::{Added, Changed, EntityFilter, Mutated, Or, QueryFilter, With, Without};
-pub use query::{Batch, BatchedIter, Mut, Query, QueryIter, ReadOnlyFetch};
-pub use world::{ArchetypesGeneration, Component, ComponentError, SpawnBatchIter, World};
+pub use query::{Batch, BatchedIter, ChangeTrackers, Mut, Query, QueryIter, ReadOnlyFetch};
+pub use sparse_set::StorageType;
+pub use world::{
+    ArchetypeStats, ArchetypesGeneration, Component, ComponentError, SpawnBatchIter, World,
+};
 
 // Unstable implementation details needed by the macros
 #[doc(hidden)]
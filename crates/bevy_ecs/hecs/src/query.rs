@@ -15,11 +15,13 @@
 // modified by Bevy contributors
 
 use crate::{
-    access::QueryAccess, archetype::Archetype, Component, Entity, EntityFilter, MissingComponent,
-    QueryFilter,
+    access::QueryAccess, archetype::Archetype, sparse_set::SparseFetch, ChangeTicks, Component,
+    Entity, EntityFilter, MissingComponent, QueryFilter,
 };
 use core::{
+    any::TypeId,
     marker::PhantomData,
+    mem,
     ops::{Deref, DerefMut},
     ptr::NonNull,
 };
@@ -46,11 +48,29 @@ pub trait Fetch<'a>: Sized {
     /// How this query will access `archetype`, if at all
     fn access() -> QueryAccess;
 
-    /// Construct a `Fetch` for `archetype` if it should be traversed
+    /// Construct a `Fetch` for `archetype` if it should be traversed. Components fetched mutably are stamped with
+    /// `ticks.change_tick` when they are mutated.
     ///
     /// # Safety
     /// `offset` must be in bounds of `archetype`
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self>;
+    unsafe fn get(archetype: &'a Archetype, offset: usize, ticks: ChangeTicks) -> Option<Self>;
+
+    /// True if every entity of the archetype the fetch was constructed for matches it. Fetches of components stored in
+    /// sparse sets only match the entities that have the component.
+    #[inline]
+    fn is_archetypal(&self) -> bool {
+        true
+    }
+
+    /// Whether the `n`th entity has what this fetch accesses, which must be checked before calling `fetch` unless the
+    /// fetch `is_archetypal`
+    ///
+    /// # Safety
+    /// Bounds-checking must be performed externally
+    #[inline]
+    unsafe fn matches_entity(&self, _n: usize) -> bool {
+        true
+    }
 
     /// Access the `n`th item in this archetype without bounds checking
     ///
@@ -76,7 +96,7 @@ impl<'a> Fetch<'a> for EntityFetch {
     const DANGLING: Self = Self(NonNull::dangling());
 
     #[inline]
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
+    unsafe fn get(archetype: &'a Archetype, offset: usize, _ticks: ChangeTicks) -> Option<Self> {
         Some(EntityFetch(NonNull::new_unchecked(
             archetype.entities().as_ptr().add(offset),
         )))
@@ -98,24 +118,45 @@ impl<'a, T: Component> Query for &'a T {
 }
 
 #[doc(hidden)]
-pub struct FetchRead<T>(NonNull<T>);
+pub struct FetchRead<T>(NonNull<T>, Option<SparseFetch>);
 
 unsafe impl<T> ReadOnlyFetch for FetchRead<T> {}
 
 impl<'a, T: Component> Fetch<'a> for FetchRead<T> {
     type Item = &'a T;
 
-    const DANGLING: Self = Self(NonNull::dangling());
+    const DANGLING: Self = Self(NonNull::dangling(), None);
+
+    unsafe fn get(archetype: &'a Archetype, offset: usize, _ticks: ChangeTicks) -> Option<Self> {
+        match archetype.get::<T>() {
+            Some(x) => Some(Self(NonNull::new_unchecked(x.as_ptr().add(offset)), None)),
+            None => SparseFetch::new(archetype, offset, TypeId::of::<T>())
+                .map(|sparse| Self(NonNull::dangling(), Some(sparse))),
+        }
+    }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        archetype
-            .get::<T>()
-            .map(|x| Self(NonNull::new_unchecked(x.as_ptr().add(offset))))
+    #[inline]
+    fn is_archetypal(&self) -> bool {
+        self.1.is_none()
+    }
+
+    #[inline]
+    unsafe fn matches_entity(&self, n: usize) -> bool {
+        match &self.1 {
+            Some(sparse) => sparse.dense_index(n).is_some(),
+            None => true,
+        }
     }
 
     #[inline]
     unsafe fn fetch(&self, n: usize) -> &'a T {
-        &*self.0.as_ptr().add(n)
+        match &self.1 {
+            Some(sparse) => {
+                let index = sparse.dense_index(n).unwrap();
+                &*sparse.set().get_unchecked(index).cast::<T>().as_ptr()
+            }
+            None => &*self.0.as_ptr().add(n),
+        }
     }
 
     #[inline]
@@ -132,10 +173,12 @@ impl<T: Query> Query for Option<T> {
     type Fetch = TryFetch<T::Fetch>;
 }
 
-/// Unique borrow of an entity's component
+/// Unique borrow of an entity's component. Mutably dereferencing it stamps the component with the change tick it
+/// was fetched with.
 pub struct Mut<'a, T: Component> {
     pub(crate) value: &'a mut T,
-    pub(crate) mutated: &'a mut bool,
+    pub(crate) mutated: &'a mut u64,
+    pub(crate) change_tick: u64,
 }
 
 impl<'a, T: Component> Mut<'a, T> {
@@ -143,13 +186,18 @@ impl<'a, T: Component> Mut<'a, T> {
     ///
     /// # Safety
     /// This doesn't check the bounds of index in archetype
-    pub unsafe fn new(archetype: &'a Archetype, index: usize) -> Result<Self, MissingComponent> {
-        let (target, type_state) = archetype
-            .get_with_type_state::<T>()
+    pub unsafe fn new(
+        archetype: &'a Archetype,
+        index: usize,
+        change_tick: u64,
+    ) -> Result<Self, MissingComponent> {
+        let component = archetype
+            .get_component(TypeId::of::<T>(), mem::size_of::<T>(), index)
             .ok_or_else(MissingComponent::new::<T>)?;
         Ok(Self {
-            value: &mut *target.as_ptr().add(index),
-            mutated: &mut *type_state.mutated().as_ptr().add(index),
+            value: &mut *component.value.cast::<T>().as_ptr(),
+            mutated: &mut *component.mutated.as_ptr(),
+            change_tick,
         })
     }
 }
@@ -169,7 +217,7 @@ impl<'a, T: Component> Deref for Mut<'a, T> {
 impl<'a, T: Component> DerefMut for Mut<'a, T> {
     #[inline]
     fn deref_mut(&mut self) -> &mut T {
-        *self.mutated = true;
+        *self.mutated = self.change_tick;
         self.value
     }
 }
@@ -184,29 +232,62 @@ impl<'a, T: Component> Query for Mut<'a, T> {
     type Fetch = FetchMut<T>;
 }
 #[doc(hidden)]
-pub struct FetchMut<T>(NonNull<T>, NonNull<bool>);
+pub struct FetchMut<T>(NonNull<T>, NonNull<u64>, u64, Option<SparseFetch>);
 
 impl<'a, T: Component> Fetch<'a> for FetchMut<T> {
     type Item = Mut<'a, T>;
 
-    const DANGLING: Self = Self(NonNull::dangling(), NonNull::dangling());
-
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        archetype
-            .get_with_type_state::<T>()
-            .map(|(components, type_state)| {
+    const DANGLING: Self = Self(NonNull::dangling(), NonNull::dangling(), 0, None);
+
+    unsafe fn get(archetype: &'a Archetype, offset: usize, ticks: ChangeTicks) -> Option<Self> {
+        match archetype.get_with_type_state::<T>() {
+            Some((components, type_state)) => Some(Self(
+                NonNull::new_unchecked(components.as_ptr().add(offset)),
+                NonNull::new_unchecked(type_state.mutated().as_ptr().add(offset)),
+                ticks.change_tick,
+                None,
+            )),
+            None => SparseFetch::new(archetype, offset, TypeId::of::<T>()).map(|sparse| {
                 Self(
-                    NonNull::new_unchecked(components.as_ptr().add(offset)),
-                    NonNull::new_unchecked(type_state.mutated().as_ptr().add(offset)),
+                    NonNull::dangling(),
+                    NonNull::dangling(),
+                    ticks.change_tick,
+                    Some(sparse),
                 )
-            })
+            }),
+        }
+    }
+
+    #[inline]
+    fn is_archetypal(&self) -> bool {
+        self.3.is_none()
+    }
+
+    #[inline]
+    unsafe fn matches_entity(&self, n: usize) -> bool {
+        match &self.3 {
+            Some(sparse) => sparse.dense_index(n).is_some(),
+            None => true,
+        }
     }
 
     #[inline]
     unsafe fn fetch(&self, n: usize) -> Mut<'a, T> {
-        Mut {
-            value: &mut *self.0.as_ptr().add(n),
-            mutated: &mut *self.1.as_ptr().add(n),
+        match &self.3 {
+            Some(sparse) => {
+                let index = sparse.dense_index(n).unwrap();
+                let set = sparse.set();
+                Mut {
+                    value: &mut *set.get_unchecked(index).cast::<T>().as_ptr(),
+                    mutated: &mut *set.mutated().as_ptr().add(index),
+                    change_tick: self.2,
+                }
+            }
+            None => Mut {
+                value: &mut *self.0.as_ptr().add(n),
+                mutated: &mut *self.1.as_ptr().add(n),
+                change_tick: self.2,
+            },
         }
     }
 
@@ -230,12 +311,147 @@ impl<'a, T: Fetch<'a>> Fetch<'a> for TryFetch<T> {
         QueryAccess::optional(T::access())
     }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        Some(Self(T::get(archetype, offset)))
+    unsafe fn get(archetype: &'a Archetype, offset: usize, ticks: ChangeTicks) -> Option<Self> {
+        Some(Self(T::get(archetype, offset, ticks)))
     }
 
     unsafe fn fetch(&self, n: usize) -> Option<T::Item> {
-        Some(self.0.as_ref()?.fetch(n))
+        match &self.0 {
+            Some(fetch) if fetch.matches_entity(n) => Some(fetch.fetch(n)),
+            _ => None,
+        }
+    }
+}
+
+/// Query item that reports whether an entity's component of type `T` was added or mutated since the system last ran,
+/// without filtering out the entities whose component didn't change. Only entities that have a `T` are matched, but
+/// the component itself isn't fetched.
+///
+/// This reads the same change ticks as the [Added](crate::Added), [Mutated](crate::Mutated) and
+/// [Changed](crate::Changed) filters, so each method answers whether the matching filter would let the entity through.
+pub struct ChangeTrackers<T: Component> {
+    added: u64,
+    mutated: u64,
+    ticks: ChangeTicks,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> ChangeTrackers<T> {
+    /// True if the component was added since the system last ran
+    #[inline]
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_changed(self.added)
+    }
+
+    /// True if the component was mutated since the system last ran. Adding a component doesn't count as mutating it.
+    #[inline]
+    pub fn is_mutated(&self) -> bool {
+        self.ticks.is_changed(self.mutated)
+    }
+
+    /// True if the component was either added or mutated since the system last ran
+    #[inline]
+    pub fn is_changed(&self) -> bool {
+        self.is_added() || self.is_mutated()
+    }
+}
+
+impl<T: Component> core::fmt::Debug for ChangeTrackers<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ChangeTrackers")
+            .field("added", &self.is_added())
+            .field("mutated", &self.is_mutated())
+            .finish()
+    }
+}
+
+impl<T: Component> Query for ChangeTrackers<T> {
+    type Fetch = FetchChangeTrackers<T>;
+}
+
+#[doc(hidden)]
+pub struct FetchChangeTrackers<T>(
+    NonNull<u64>,
+    NonNull<u64>,
+    ChangeTicks,
+    Option<SparseFetch>,
+    PhantomData<T>,
+);
+
+unsafe impl<T> ReadOnlyFetch for FetchChangeTrackers<T> {}
+
+impl<'a, T: Component> Fetch<'a> for FetchChangeTrackers<T> {
+    type Item = ChangeTrackers<T>;
+
+    const DANGLING: Self = Self(
+        NonNull::dangling(),
+        NonNull::dangling(),
+        ChangeTicks {
+            last_change_tick: 0,
+            change_tick: 0,
+        },
+        None,
+        PhantomData,
+    );
+
+    unsafe fn get(archetype: &'a Archetype, offset: usize, ticks: ChangeTicks) -> Option<Self> {
+        match archetype.get_type_state(TypeId::of::<T>()) {
+            Some(state) => Some(Self(
+                NonNull::new_unchecked(state.added().as_ptr().add(offset)),
+                NonNull::new_unchecked(state.mutated().as_ptr().add(offset)),
+                ticks,
+                None,
+                PhantomData,
+            )),
+            None => SparseFetch::new(archetype, offset, TypeId::of::<T>()).map(|sparse| {
+                Self(
+                    NonNull::dangling(),
+                    NonNull::dangling(),
+                    ticks,
+                    Some(sparse),
+                    PhantomData,
+                )
+            }),
+        }
+    }
+
+    #[inline]
+    fn is_archetypal(&self) -> bool {
+        self.3.is_none()
+    }
+
+    #[inline]
+    unsafe fn matches_entity(&self, n: usize) -> bool {
+        match &self.3 {
+            Some(sparse) => sparse.dense_index(n).is_some(),
+            None => true,
+        }
+    }
+
+    #[inline]
+    unsafe fn fetch(&self, n: usize) -> ChangeTrackers<T> {
+        let (added, mutated) = match &self.3 {
+            Some(sparse) => {
+                let index = sparse.dense_index(n).unwrap();
+                let set = sparse.set();
+                (
+                    *set.added().as_ptr().add(index),
+                    *set.mutated().as_ptr().add(index),
+                )
+            }
+            None => (*self.0.as_ptr().add(n), *self.1.as_ptr().add(n)),
+        };
+        ChangeTrackers {
+            added,
+            mutated,
+            ticks: self.2,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn access() -> QueryAccess {
+        QueryAccess::read::<T>()
     }
 }
 
@@ -248,9 +464,11 @@ struct ChunkInfo<Q: Query, F: QueryFilter> {
 /// Iterator over the set of entities with the components in `Q`
 pub struct QueryIter<'w, Q: Query, F: QueryFilter> {
     archetypes: &'w [Archetype],
+    ticks: ChangeTicks,
     archetype_index: usize,
     chunk_info: ChunkInfo<Q, F>,
     chunk_position: usize,
+    matched_archetypes: Option<&'w [bool]>,
 }
 
 impl<'w, Q: Query, F: QueryFilter> QueryIter<'w, Q, F> {
@@ -262,14 +480,35 @@ impl<'w, Q: Query, F: QueryFilter> QueryIter<'w, Q, F> {
 
     /// Creates a new QueryIter
     #[inline]
-    pub(crate) fn new(archetypes: &'w [Archetype]) -> Self {
+    pub(crate) fn new(archetypes: &'w [Archetype], ticks: ChangeTicks) -> Self {
         Self {
             archetypes,
+            ticks,
             archetype_index: 0,
             chunk_info: Self::EMPTY,
             chunk_position: 0,
+            matched_archetypes: None,
         }
     }
+
+    /// Only iterates over the archetypes whose index is set in `matched_archetypes`
+    #[inline]
+    pub fn with_matched_archetypes(mut self, matched_archetypes: &'w [bool]) -> Self {
+        self.matched_archetypes = Some(matched_archetypes);
+        self
+    }
+}
+
+/// Whether the archetype at `archetype_index` is set in `matched_archetypes`, if given
+#[inline]
+fn is_matched(matched_archetypes: Option<&[bool]>, archetype_index: usize) -> bool {
+    match matched_archetypes {
+        Some(matched_archetypes) => matched_archetypes
+            .get(archetype_index)
+            .copied()
+            .unwrap_or(false),
+        None => true,
+    }
 }
 
 impl<'w, Q: Query, F: QueryFilter> Iterator for QueryIter<'w, Q, F> {
@@ -283,12 +522,16 @@ impl<'w, Q: Query, F: QueryFilter> Iterator for QueryIter<'w, Q, F> {
                     let archetype = self.archetypes.get(self.archetype_index)?;
                     self.archetype_index += 1;
                     self.chunk_position = 0;
-                    self.chunk_info = Q::Fetch::get(archetype, 0)
+                    if !is_matched(self.matched_archetypes, self.archetype_index - 1) {
+                        self.chunk_info = Self::EMPTY;
+                        continue;
+                    }
+                    self.chunk_info = Q::Fetch::get(archetype, 0, self.ticks)
                         .and_then(|fetch| {
                             Some(ChunkInfo {
                                 fetch,
                                 len: archetype.len(),
-                                filter: F::get_entity_filter(archetype)?,
+                                filter: F::get_entity_filter(archetype, self.ticks)?,
                             })
                         })
                         .unwrap_or(Self::EMPTY);
@@ -299,6 +542,10 @@ impl<'w, Q: Query, F: QueryFilter> Iterator for QueryIter<'w, Q, F> {
                     .chunk_info
                     .filter
                     .matches_entity(self.chunk_position as usize)
+                    || !self
+                        .chunk_info
+                        .fetch
+                        .matches_entity(self.chunk_position as usize)
                 {
                     self.chunk_position += 1;
                     continue;
@@ -318,15 +565,26 @@ impl<'w, Q: Query> ExactSizeIterator for QueryIter<'w, Q, ()> {
     fn len(&self) -> usize {
         self.archetypes
             .iter()
-            .filter(|&archetype| unsafe { Q::Fetch::get(archetype, 0).is_some() })
-            .map(|x| x.len())
+            .enumerate()
+            .filter(|(archetype_index, _)| is_matched(self.matched_archetypes, *archetype_index))
+            .map(|(_, archetype)| unsafe {
+                match Q::Fetch::get(archetype, 0, self.ticks) {
+                    Some(fetch) if fetch.is_archetypal() => archetype.len(),
+                    Some(fetch) => (0..archetype.len())
+                        .filter(|n| fetch.matches_entity(*n))
+                        .count(),
+                    None => 0,
+                }
+            })
             .sum()
     }
 }
 
 struct ChunkIter<Q: Query, F: QueryFilter> {
     fetch: Q::Fetch,
+    /// constructed for the whole archetype, unlike `fetch` which starts at `offset`
     filter: F::EntityFilter,
+    offset: usize,
     position: usize,
     len: usize,
 }
@@ -338,7 +596,9 @@ impl<Q: Query, F: QueryFilter> ChunkIter<Q, F> {
                 return None;
             }
 
-            if !self.filter.matches_entity(self.position as usize) {
+            if !self.filter.matches_entity(self.offset + self.position)
+                || !self.fetch.matches_entity(self.position)
+            {
                 self.position += 1;
                 continue;
             }
@@ -353,22 +613,33 @@ impl<Q: Query, F: QueryFilter> ChunkIter<Q, F> {
 /// Batched version of `QueryIter`
 pub struct BatchedIter<'w, Q: Query, F: QueryFilter> {
     archetypes: &'w [Archetype],
+    ticks: ChangeTicks,
     archetype_index: usize,
     batch_size: usize,
     batch: usize,
+    matched_archetypes: Option<&'w [bool]>,
     _marker: PhantomData<(Q, F)>,
 }
 
 impl<'w, Q: Query, F: QueryFilter> BatchedIter<'w, Q, F> {
-    pub(crate) fn new(archetypes: &'w [Archetype], batch_size: usize) -> Self {
+    pub(crate) fn new(archetypes: &'w [Archetype], batch_size: usize, ticks: ChangeTicks) -> Self {
         Self {
             archetypes,
+            ticks,
             archetype_index: 0,
             batch_size,
             batch: 0,
+            matched_archetypes: None,
             _marker: Default::default(),
         }
     }
+
+    /// Only iterates over the archetypes whose index is set in `matched_archetypes`
+    #[inline]
+    pub fn with_matched_archetypes(mut self, matched_archetypes: &'w [bool]) -> Self {
+        self.matched_archetypes = Some(matched_archetypes);
+        self
+    }
 }
 
 unsafe impl<'w, Q: Query, F: QueryFilter> Send for BatchedIter<'w, Q, F> {}
@@ -381,20 +652,23 @@ impl<'w, Q: Query, F: QueryFilter> Iterator for BatchedIter<'w, Q, F> {
         loop {
             let archetype = self.archetypes.get(self.archetype_index)?;
             let offset = self.batch_size * self.batch;
-            if offset >= archetype.len() {
+            if offset >= archetype.len()
+                || !is_matched(self.matched_archetypes, self.archetype_index)
+            {
                 self.archetype_index += 1;
                 self.batch = 0;
                 continue;
             }
             if let (Some(fetch), Some(filter)) = (
-                unsafe { Q::Fetch::get(archetype, offset) },
-                F::get_entity_filter(archetype),
+                unsafe { Q::Fetch::get(archetype, offset, self.ticks) },
+                F::get_entity_filter(archetype, self.ticks),
             ) {
                 self.batch += 1;
                 return Some(Batch {
                     _marker: PhantomData,
                     state: ChunkIter {
                         fetch,
+                        offset,
                         position: 0,
                         len: self.batch_size.min(archetype.len() - offset),
                         filter,
@@ -444,8 +718,20 @@ macro_rules! tuple_impl {
             }
 
             #[allow(unused_variables)]
-            unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-                Some(($($name::get(archetype, offset)?,)*))
+            unsafe fn get(archetype: &'a Archetype, offset: usize, ticks: ChangeTicks) -> Option<Self> {
+                Some(($($name::get(archetype, offset, ticks)?,)*))
+            }
+
+            #[allow(non_snake_case)]
+            fn is_archetypal(&self) -> bool {
+                let ($($name,)*) = self;
+                true $(&& $name.is_archetypal())*
+            }
+
+            #[allow(unused_variables, non_snake_case)]
+            unsafe fn matches_entity(&self, n: usize) -> bool {
+                let ($($name,)*) = self;
+                true $(&& $name.matches_entity(n))*
             }
 
             #[allow(unused_variables)]
@@ -0,0 +1,315 @@
+use crate::{
+    alloc::{
+        alloc::{alloc, dealloc, realloc, Layout},
+        vec::Vec,
+    },
+    archetype::{Archetype, StoredComponent, TypeIdMap, TypeInfo},
+    borrow::AtomicBorrow,
+    Entity,
+};
+use core::{
+    any::TypeId,
+    cell::UnsafeCell,
+    ptr::{self, NonNull},
+};
+
+/// How the components of a type are stored, see [World::register_component](crate::World::register_component)
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StorageType {
+    /// In the table of the entity's archetype. Tables are the fastest to iterate over, but adding or removing a
+    /// component moves the entity to another archetype. Components are stored this way unless registered otherwise.
+    Table,
+    /// In a sparse set indexed by entity. Adding or removing a component leaves the entity in its archetype, which
+    /// suits components that come and go often, like markers, at the cost of slower iteration.
+    SparseSet,
+}
+
+/// The components of a single type that are stored in a sparse set, packed in the order of `entities`
+#[derive(Debug)]
+pub(crate) struct ComponentSparseSet {
+    type_info: TypeInfo,
+    data: NonNull<u8>,
+    capacity: usize,
+    entities: Vec<Entity>,
+    /// The index into `entities` of each entity, by entity id
+    sparse: Vec<Option<usize>>,
+    added_ticks: Vec<u64>,
+    mutated_ticks: Vec<u64>,
+    borrow: AtomicBorrow,
+}
+
+impl ComponentSparseSet {
+    pub(crate) fn new(type_info: TypeInfo) -> Self {
+        let layout = type_info.layout();
+        Self {
+            type_info,
+            // zero sized components never need an allocation
+            capacity: if layout.size() == 0 { usize::MAX } else { 0 },
+            data: unsafe { NonNull::new_unchecked(layout.align() as *mut u8) },
+            entities: Vec::new(),
+            sparse: Vec::new(),
+            added_ticks: Vec::new(),
+            mutated_ticks: Vec::new(),
+            borrow: AtomicBorrow::new(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn type_info(&self) -> &TypeInfo {
+        &self.type_info
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// The index of `entity`'s component, if it has one
+    #[inline]
+    pub(crate) fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let index = (*self.sparse.get(entity.id as usize)?)?;
+        // a despawned entity's id may have been reused
+        if self.entities[index] == entity {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn contains(&self, entity: Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    /// # Safety
+    /// `index` must be in bounds
+    #[inline]
+    pub(crate) unsafe fn get_unchecked(&self, index: usize) -> NonNull<u8> {
+        NonNull::new_unchecked(
+            self.data
+                .as_ptr()
+                .add(self.type_info.layout().size() * index),
+        )
+    }
+
+    /// The change tick each component was added at
+    #[inline]
+    pub(crate) fn added(&self) -> NonNull<u64> {
+        unsafe { NonNull::new_unchecked(self.added_ticks.as_ptr() as *mut u64) }
+    }
+
+    /// The change tick each component was last mutated at, or 0 if it hasn't been mutated since it was added
+    #[inline]
+    pub(crate) fn mutated(&self) -> NonNull<u64> {
+        unsafe { NonNull::new_unchecked(self.mutated_ticks.as_ptr() as *mut u64) }
+    }
+
+    pub(crate) fn get_component(&self, entity: Entity) -> Option<StoredComponent<'_>> {
+        let index = self.dense_index(entity)?;
+        unsafe {
+            Some(StoredComponent {
+                value: self.get_unchecked(index),
+                mutated: NonNull::new_unchecked(self.mutated().as_ptr().add(index)),
+                borrow: &self.borrow,
+            })
+        }
+    }
+
+    /// Moves `component` into the set. If `entity` already has a component, it is dropped and replaced, which counts as
+    /// mutating it rather than adding it.
+    ///
+    /// # Safety
+    /// `component` must point to a valid value of the set's type, which the set takes ownership of
+    pub(crate) unsafe fn insert(&mut self, entity: Entity, component: *mut u8, change_tick: u64) {
+        let size = self.type_info.layout().size();
+        if let Some(index) = self.dense_index(entity) {
+            let replaced = self.get_unchecked(index).as_ptr();
+            self.type_info.drop(replaced);
+            ptr::copy_nonoverlapping(component, replaced, size);
+            self.mutated_ticks[index] = change_tick;
+            return;
+        }
+
+        if self.entities.len() == self.capacity {
+            self.grow();
+        }
+        let index = self.entities.len();
+        ptr::copy_nonoverlapping(component, self.get_unchecked(index).as_ptr(), size);
+        self.entities.push(entity);
+        self.added_ticks.push(change_tick);
+        self.mutated_ticks.push(0);
+        let id = entity.id as usize;
+        if id >= self.sparse.len() {
+            self.sparse.resize(id + 1, None);
+        }
+        self.sparse[id] = Some(index);
+    }
+
+    /// Takes `entity`'s component out of the set by handing it to `f`. Returns false if the entity doesn't have one.
+    ///
+    /// # Safety
+    /// `f` must move or drop the component it is given
+    pub(crate) unsafe fn remove_with(&mut self, entity: Entity, f: impl FnOnce(*mut u8)) -> bool {
+        let index = match self.dense_index(entity) {
+            Some(index) => index,
+            None => return false,
+        };
+        let last = self.entities.len() - 1;
+        let removed = self.get_unchecked(index).as_ptr();
+        f(removed);
+        if index != last {
+            ptr::copy_nonoverlapping(
+                self.get_unchecked(last).as_ptr(),
+                removed,
+                self.type_info.layout().size(),
+            );
+        }
+        self.entities.swap_remove(index);
+        self.added_ticks.swap_remove(index);
+        self.mutated_ticks.swap_remove(index);
+        self.sparse[entity.id as usize] = None;
+        if index != last {
+            self.sparse[self.entities[index].id as usize] = Some(index);
+        }
+        true
+    }
+
+    /// Removes and drops `entity`'s component. Returns false if the entity doesn't have one.
+    pub(crate) fn remove_and_drop(&mut self, entity: Entity) -> bool {
+        let type_info = self.type_info;
+        unsafe { self.remove_with(entity, |component| type_info.drop(component)) }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        for index in 0..self.entities.len() {
+            unsafe { self.type_info.drop(self.get_unchecked(index).as_ptr()) };
+        }
+        self.entities.clear();
+        self.sparse.clear();
+        self.added_ticks.clear();
+        self.mutated_ticks.clear();
+    }
+
+    fn grow(&mut self) {
+        let layout = self.type_info.layout();
+        let capacity = (self.capacity * 2).max(4);
+        let new_layout = Layout::from_size_align(layout.size() * capacity, layout.align()).unwrap();
+        unsafe {
+            let data = if self.capacity == 0 {
+                alloc(new_layout)
+            } else {
+                realloc(
+                    self.data.as_ptr(),
+                    Layout::from_size_align_unchecked(
+                        layout.size() * self.capacity,
+                        layout.align(),
+                    ),
+                    new_layout.size(),
+                )
+            };
+            self.data = NonNull::new(data).unwrap();
+        }
+        self.capacity = capacity;
+    }
+}
+
+impl Drop for ComponentSparseSet {
+    fn drop(&mut self) {
+        self.clear();
+        let layout = self.type_info.layout();
+        if layout.size() != 0 && self.capacity != 0 {
+            unsafe {
+                dealloc(
+                    self.data.as_ptr(),
+                    Layout::from_size_align_unchecked(
+                        layout.size() * self.capacity,
+                        layout.align(),
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// The sparse sets of a world, by component type. They are shared with the world's archetypes, so that queries can
+/// find the components of an archetype's entities that don't live in its table.
+#[derive(Debug, Default)]
+pub(crate) struct SparseSets(UnsafeCell<TypeIdMap<ComponentSparseSet>>);
+
+// components are Send + Sync, and the sets are only changed through `get_mut`
+unsafe impl Send for SparseSets {}
+unsafe impl Sync for SparseSets {}
+
+impl SparseSets {
+    #[inline]
+    pub(crate) fn get(&self, ty: TypeId) -> Option<&ComponentSparseSet> {
+        unsafe { (*self.0.get()).get(&ty) }
+    }
+
+    #[inline]
+    pub(crate) fn contains(&self, ty: TypeId) -> bool {
+        unsafe { (*self.0.get()).contains_key(&ty) }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &ComponentSparseSet> {
+        unsafe { (*self.0.get()).values() }
+    }
+
+    /// # Safety
+    /// Nothing else may access the sets while the returned reference is live, which a mutable borrow of the world
+    /// that owns them ensures
+    #[allow(clippy::mut_from_ref)]
+    #[inline]
+    pub(crate) unsafe fn get_mut(&self) -> &mut TypeIdMap<ComponentSparseSet> {
+        &mut *self.0.get()
+    }
+}
+
+/// Finds the sparse set components of an archetype's entities, for the fetches and filters of components stored in
+/// sparse sets
+#[derive(Copy, Clone)]
+pub(crate) struct SparseFetch {
+    entities: NonNull<Entity>,
+    set: NonNull<ComponentSparseSet>,
+}
+
+impl SparseFetch {
+    /// Returns `None` if `ty` isn't stored in a sparse set, or if no entity has a component of that type
+    ///
+    /// # Safety
+    /// `offset` must be in bounds of `archetype`
+    #[inline]
+    pub(crate) unsafe fn new(archetype: &Archetype, offset: usize, ty: TypeId) -> Option<Self> {
+        let set = archetype.get_sparse_set(ty)?;
+        if set.is_empty() {
+            return None;
+        }
+        Some(Self {
+            entities: NonNull::new_unchecked(archetype.entities().as_ptr().add(offset)),
+            set: NonNull::from(set),
+        })
+    }
+
+    /// The index in the set of the `n`th entity's component, if it has one
+    ///
+    /// # Safety
+    /// `n` must be in bounds of the archetype, and the set must not have changed since the fetch was created
+    #[inline]
+    pub(crate) unsafe fn dense_index(&self, n: usize) -> Option<usize> {
+        self.set
+            .as_ref()
+            .dense_index(*self.entities.as_ptr().add(n))
+    }
+
+    /// # Safety
+    /// The set must not have changed since the fetch was created
+    #[inline]
+    pub(crate) unsafe fn set<'a>(&self) -> &'a ComponentSparseSet {
+        &*self.set.as_ptr()
+    }
+}
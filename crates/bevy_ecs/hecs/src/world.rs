@@ -15,17 +15,29 @@
 // modified by Bevy contributors
 
 use crate::{
-    alloc::vec::Vec, borrow::EntityRef, filter::EntityFilter, query::ReadOnlyFetch, BatchedIter,
-    EntityReserver, Fetch, Mut, QueryFilter, QueryIter, RefMut,
+    alloc::{format, string::String, sync::Arc, vec::Vec},
+    archetype::StoredComponent,
+    borrow::EntityRef,
+    filter::EntityFilter,
+    increment_change_tick,
+    query::ReadOnlyFetch,
+    read_change_tick,
+    sparse_set::{ComponentSparseSet, SparseSets},
+    BatchedIter, ChangeTicks, EntityReserver, Fetch, Mut, QueryFilter, QueryIter, RefMut,
+    StorageType,
 };
 use bevy_utils::{HashMap, HashSet};
-use core::{any::TypeId, fmt, mem, ptr};
+use core::{
+    any::{type_name, TypeId},
+    fmt, mem,
+    ptr::{self, NonNull},
+};
 
 #[cfg(feature = "std")]
 use std::error::Error;
 
 use crate::{
-    archetype::Archetype,
+    archetype::{Archetype, TypeInfo},
     entities::{Entities, Location},
     Bundle, DynamicBundle, Entity, MissingComponent, NoSuchEntity, Query, Ref,
 };
@@ -45,14 +57,17 @@ pub struct World {
     #[allow(missing_docs)]
     pub archetypes: Vec<Archetype>,
     archetype_generation: u64,
+    last_change_tick: u64,
+    sparse_sets: Arc<SparseSets>,
 }
 
 impl World {
     /// Create an empty world
     pub fn new() -> Self {
         // `flush` assumes archetype 0 always exists, representing entities with no components.
+        let sparse_sets = Arc::new(SparseSets::default());
         let mut archetypes = Vec::new();
-        archetypes.push(Archetype::new(Vec::new()));
+        archetypes.push(Archetype::with_sparse_sets(Vec::new(), sparse_sets.clone()));
         let mut index = HashMap::default();
         index.insert(Vec::new(), 0);
         Self {
@@ -61,7 +76,63 @@ impl World {
             archetypes,
             archetype_generation: 0,
             removed_components: HashMap::default(),
+            last_change_tick: 0,
+            sparse_sets,
+        }
+    }
+
+    /// Chooses how the components of type `T` are stored. Components are stored in the tables of their entities'
+    /// archetypes unless registered otherwise.
+    ///
+    /// Components stored in a [StorageType::SparseSet] can be added and removed without moving their entity to
+    /// another archetype, which suits marker components that come and go often. Queries and filters work the same for
+    /// both storage types.
+    ///
+    /// Panics if entities already have `T` components stored the other way.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// struct Selected;
+    ///
+    /// let mut world = World::new();
+    /// world.register_component::<Selected>(StorageType::SparseSet);
+    /// let a = world.spawn((123,));
+    /// let archetypes = world.archetypes().count();
+    /// world.insert_one(a, Selected).unwrap();
+    /// assert_eq!(world.archetypes().count(), archetypes);
+    /// assert_eq!(world.query::<(&i32, &Selected)>().count(), 1);
+    /// ```
+    pub fn register_component<T: Component>(&mut self, storage_type: StorageType) {
+        let ty = TypeId::of::<T>();
+        // SAFE: borrowing the world mutably keeps the sets from being accessed elsewhere
+        let sparse_sets = unsafe { self.sparse_sets.get_mut() };
+        match storage_type {
+            StorageType::Table => match sparse_sets.get(&ty) {
+                Some(set) if !set.is_empty() => panic!(
+                    "cannot store {} components in tables, some entities already have one in a sparse set",
+                    type_name::<T>()
+                ),
+                Some(_) => {
+                    sparse_sets.remove(&ty);
+                }
+                None => return,
+            },
+            StorageType::SparseSet => {
+                if sparse_sets.contains_key(&ty) {
+                    return;
+                }
+                if self.archetypes.iter().any(|archetype| archetype.has_type(ty)) {
+                    panic!(
+                        "cannot store {} components in a sparse set, some entities already have one in a table",
+                        type_name::<T>()
+                    );
+                }
+                sparse_sets.insert(ty, ComponentSparseSet::new(TypeInfo::of::<T>()));
+            }
         }
+        // queries have to look for the components elsewhere now
+        self.archetype_generation += 1;
     }
 
     /// Create an entity with certain components
@@ -88,21 +159,18 @@ impl World {
         self.flush();
 
         let entity = self.entities.alloc();
-        let archetype_id = components.with_ids(|ids| {
-            self.index.get(ids).copied().unwrap_or_else(|| {
-                let x = self.archetypes.len() as u32;
-                self.archetypes.push(Archetype::new(components.type_info()));
-                self.index.insert(ids.to_vec(), x);
-                self.archetype_generation += 1;
-                x
-            })
-        });
+        let archetype_id =
+            components.with_ids(|ids| self.archetype_for(ids, || components.type_info()));
 
         let archetype = &mut self.archetypes[archetype_id as usize];
+        let sparse_sets = &self.sparse_sets;
+        let change_tick = read_change_tick();
         unsafe {
             let index = archetype.allocate(entity);
             components.put(|ptr, ty, size| {
-                archetype.put_dynamic(ptr, ty, size, index, true, false);
+                if !put_sparse(sparse_sets, entity, ptr, ty, change_tick) {
+                    archetype.put_dynamic(ptr, ty, size, index, true, false, change_tick);
+                }
                 true
             });
             self.entities.meta[entity.id as usize].location = Location {
@@ -145,6 +213,8 @@ impl World {
             entities: &mut self.entities,
             archetype_id,
             archetype: &mut self.archetypes[archetype_id as usize],
+            sparse_sets: &self.sparse_sets,
+            change_tick: read_change_tick(),
         }
     }
 
@@ -164,6 +234,15 @@ impl World {
                 .or_insert_with(Vec::new);
             removed_entities.push(entity);
         }
+        // SAFE: borrowing the world mutably keeps the sets from being accessed elsewhere
+        for (ty, set) in unsafe { self.sparse_sets.get_mut() }.iter_mut() {
+            if set.remove_and_drop(entity) {
+                self.removed_components
+                    .entry(*ty)
+                    .or_insert_with(Vec::new)
+                    .push(entity);
+            }
+        }
         Ok(())
     }
 
@@ -181,15 +260,7 @@ impl World {
         self.flush();
         self.entities.reserve(additional);
 
-        let archetype_id = T::with_static_ids(|ids| {
-            self.index.get(ids).copied().unwrap_or_else(|| {
-                let x = self.archetypes.len() as u32;
-                self.archetypes.push(Archetype::new(T::static_type_info()));
-                self.index.insert(ids.to_vec(), x);
-                self.archetype_generation += 1;
-                x
-            })
-        });
+        let archetype_id = T::with_static_ids(|ids| self.archetype_for(ids, T::static_type_info));
 
         self.archetypes[archetype_id as usize].reserve(additional as usize);
         archetype_id
@@ -209,9 +280,51 @@ impl World {
             }
             archetype.clear();
         }
+        // SAFE: borrowing the world mutably keeps the sets from being accessed elsewhere
+        for (ty, set) in unsafe { self.sparse_sets.get_mut() }.iter_mut() {
+            self.removed_components
+                .entry(*ty)
+                .or_insert_with(Vec::new)
+                .extend(set.entities().iter().copied());
+            set.clear();
+        }
         self.entities.clear();
     }
 
+    /// Finds or creates the archetype of entities with the component types `ids`, which leaves out the types stored
+    /// in sparse sets. `type_info` must return the `TypeInfo` of each type in `ids`, and is only called when the
+    /// archetype can't be found by `ids` alone.
+    fn archetype_for(&mut self, ids: &[TypeId], type_info: impl FnOnce() -> Vec<TypeInfo>) -> u32 {
+        if !ids.iter().any(|ty| self.sparse_sets.contains(*ty)) {
+            if let Some(archetype_id) = self.index.get(ids) {
+                return *archetype_id;
+            }
+        }
+        let info = type_info()
+            .into_iter()
+            .filter(|ty| !self.sparse_sets.contains(ty.id()))
+            .collect();
+        self.get_or_create_archetype(info)
+    }
+
+    /// Finds or creates the archetype with exactly the component types in `info`, which must be sorted
+    fn get_or_create_archetype(&mut self, info: Vec<TypeInfo>) -> u32 {
+        use std::collections::hash_map::Entry;
+
+        let elements = info.iter().map(|x| x.id()).collect::<Vec<_>>();
+        match self.index.entry(elements) {
+            Entry::Occupied(x) => *x.get(),
+            Entry::Vacant(x) => {
+                let index = self.archetypes.len() as u32;
+                self.archetypes
+                    .push(Archetype::with_sparse_sets(info, self.sparse_sets.clone()));
+                x.insert(index);
+                self.archetype_generation += 1;
+                index
+            }
+        }
+    }
+
     /// Whether `entity` still exists
     pub fn contains(&self, entity: Entity) -> bool {
         self.entities.contains(entity)
@@ -219,10 +332,14 @@ impl World {
 
     /// Returns true if the given entity has a component with the given type id.
     pub fn has_component_type(&self, entity: Entity, ty: TypeId) -> bool {
-        self.get_entity_location(entity)
-            .map(|location| &self.archetypes[location.archetype as usize])
-            .map(|archetype| archetype.has_type(ty))
-            .unwrap_or(false)
+        match self.sparse_sets.get(ty) {
+            Some(set) => set.contains(entity),
+            None => self
+                .get_entity_location(entity)
+                .map(|location| &self.archetypes[location.archetype as usize])
+                .map(|archetype| archetype.has_type(ty))
+                .unwrap_or(false),
+        }
     }
 
     /// Efficiently iterate over all entities that have certain components
@@ -255,16 +372,19 @@ impl World {
         Q::Fetch: ReadOnlyFetch,
     {
         // SAFE: read-only access to world and read only query prevents mutable access
-        unsafe { self.query_unchecked() }
+        unsafe { self.query_unchecked(self.change_ticks()) }
     }
 
+    /// Like `query`, but only yields entities that match the filter `F`. This matches entities exactly like a system
+    /// `Query<Q, F>` does, so it is handy for inspecting the world outside of systems. Change detection filters see
+    /// the changes made since the last `clear_trackers`.
     #[inline]
     pub fn query_filtered<Q: Query, F: QueryFilter>(&self) -> QueryIter<'_, Q, F>
     where
         Q::Fetch: ReadOnlyFetch,
     {
         // SAFE: read-only access to world and read only query prevents mutable access
-        unsafe { self.query_unchecked() }
+        unsafe { self.query_unchecked(self.change_ticks()) }
     }
 
     /// Efficiently iterate over all entities that have certain components
@@ -294,13 +414,14 @@ impl World {
     #[inline]
     pub fn query_mut<Q: Query>(&mut self) -> QueryIter<'_, Q, ()> {
         // SAFE: unique mutable access
-        unsafe { self.query_unchecked() }
+        unsafe { self.query_unchecked(self.change_ticks()) }
     }
 
+    /// Like `query_mut`, but only yields entities that match the filter `F`
     #[inline]
     pub fn query_filtered_mut<Q: Query, F: QueryFilter>(&mut self) -> QueryIter<'_, Q, F> {
         // SAFE: unique mutable access
-        unsafe { self.query_unchecked() }
+        unsafe { self.query_unchecked(self.change_ticks()) }
     }
 
     /// Like `query`, but instead of returning a single iterator it returns a "batched iterator",
@@ -311,7 +432,7 @@ impl World {
         Q::Fetch: ReadOnlyFetch,
     {
         // SAFE: read-only access to world and read only query prevents mutable access
-        unsafe { self.query_batched_unchecked(batch_size) }
+        unsafe { self.query_batched_unchecked(batch_size, self.change_ticks()) }
     }
 
     #[inline]
@@ -323,7 +444,7 @@ impl World {
         Q::Fetch: ReadOnlyFetch,
     {
         // SAFE: read-only access to world and read only query prevents mutable access
-        unsafe { self.query_batched_unchecked(batch_size) }
+        unsafe { self.query_batched_unchecked(batch_size, self.change_ticks()) }
     }
 
     /// Like `query`, but instead of returning a single iterator it returns a "batched iterator",
@@ -331,7 +452,7 @@ impl World {
     #[inline]
     pub fn query_batched_mut<Q: Query>(&mut self, batch_size: usize) -> BatchedIter<'_, Q, ()> {
         // SAFE: unique mutable access
-        unsafe { self.query_batched_unchecked(batch_size) }
+        unsafe { self.query_batched_unchecked(batch_size, self.change_ticks()) }
     }
 
     #[inline]
@@ -340,7 +461,7 @@ impl World {
         batch_size: usize,
     ) -> BatchedIter<'_, Q, F> {
         // SAFE: unique mutable access
-        unsafe { self.query_batched_unchecked(batch_size) }
+        unsafe { self.query_batched_unchecked(batch_size, self.change_ticks()) }
     }
 
     /// Efficiently iterate over all entities that have certain components
@@ -353,12 +474,17 @@ impl World {
     ///
     /// Entities are yielded in arbitrary order.
     ///
+    /// Change detection filters compare component ticks against `ticks`, see `World::change_ticks`.
+    ///
     /// # Safety
     /// This does not check for mutable query correctness. To be safe, make sure mutable queries
     /// have unique access to the components they query.
     #[inline]
-    pub unsafe fn query_unchecked<Q: Query, F: QueryFilter>(&self) -> QueryIter<'_, Q, F> {
-        QueryIter::new(&self.archetypes)
+    pub unsafe fn query_unchecked<Q: Query, F: QueryFilter>(
+        &self,
+        ticks: ChangeTicks,
+    ) -> QueryIter<'_, Q, F> {
+        QueryIter::new(&self.archetypes, ticks)
     }
 
     /// Like `query`, but instead of returning a single iterator it returns a "batched iterator",
@@ -371,8 +497,9 @@ impl World {
     pub unsafe fn query_batched_unchecked<Q: Query, F: QueryFilter>(
         &self,
         batch_size: usize,
+        ticks: ChangeTicks,
     ) -> BatchedIter<'_, Q, F> {
-        BatchedIter::new(&self.archetypes, batch_size)
+        BatchedIter::new(&self.archetypes, batch_size, ticks)
     }
 
     /// Prepare a read only query against a single entity
@@ -397,7 +524,7 @@ impl World {
         Q::Fetch: ReadOnlyFetch,
     {
         // SAFE: read-only access to world and read only query prevents mutable access
-        unsafe { self.query_one_unchecked::<Q, ()>(entity) }
+        unsafe { self.query_one_unchecked::<Q, ()>(entity, self.change_ticks()) }
     }
 
     #[inline]
@@ -409,7 +536,7 @@ impl World {
         Q::Fetch: ReadOnlyFetch,
     {
         // SAFE: read-only access to world and read only query prevents mutable access
-        unsafe { self.query_one_unchecked::<Q, F>(entity) }
+        unsafe { self.query_one_unchecked::<Q, F>(entity, self.change_ticks()) }
     }
 
     /// Prepare a query against a single entity
@@ -432,7 +559,7 @@ impl World {
         entity: Entity,
     ) -> Result<<Q::Fetch as Fetch>::Item, NoSuchEntity> {
         // SAFE: unique mutable access to world
-        unsafe { self.query_one_unchecked::<Q, ()>(entity) }
+        unsafe { self.query_one_unchecked::<Q, ()>(entity, self.change_ticks()) }
     }
 
     #[inline]
@@ -441,12 +568,13 @@ impl World {
         entity: Entity,
     ) -> Result<<Q::Fetch as Fetch>::Item, NoSuchEntity> {
         // SAFE: unique mutable access to world
-        unsafe { self.query_one_unchecked::<Q, F>(entity) }
+        unsafe { self.query_one_unchecked::<Q, F>(entity, self.change_ticks()) }
     }
 
     /// Prepare a query against a single entity, without checking the safety of mutable queries
     ///
-    /// Handy for accessing multiple components simultaneously.
+    /// Handy for accessing multiple components simultaneously. Reserved entities that haven't been flushed yet never
+    /// match.
     ///
     /// # Safety
     /// This does not check for mutable query correctness. To be safe, make sure mutable queries
@@ -455,18 +583,23 @@ impl World {
     pub unsafe fn query_one_unchecked<Q: Query, F: QueryFilter>(
         &self,
         entity: Entity,
+        ticks: ChangeTicks,
     ) -> Result<<Q::Fetch as Fetch>::Item, NoSuchEntity> {
         let loc = self.entities.get(entity)?;
         let archetype = &self.archetypes[loc.archetype as usize];
-        let matches_filter = F::get_entity_filter(archetype)
+        if loc.index >= archetype.len() {
+            // reserved entities that haven't been flushed yet have no index to fetch from
+            return Err(NoSuchEntity);
+        }
+        let matches_filter = F::get_entity_filter(archetype, ticks)
             .map(|entity_filter| entity_filter.matches_entity(loc.index))
             .unwrap_or(false);
-        if matches_filter {
-            <Q::Fetch as Fetch>::get(archetype, 0)
-                .map(|fetch| fetch.fetch(loc.index))
-                .ok_or(NoSuchEntity)
-        } else {
-            Err(NoSuchEntity)
+        if !matches_filter {
+            return Err(NoSuchEntity);
+        }
+        match <Q::Fetch as Fetch>::get(archetype, 0, ticks) {
+            Some(fetch) if fetch.matches_entity(loc.index) => Ok(fetch.fetch(loc.index)),
+            _ => Err(NoSuchEntity),
         }
     }
 
@@ -475,14 +608,7 @@ impl World {
     pub fn get<T: Component>(&self, entity: Entity) -> Result<&'_ T, ComponentError> {
         unsafe {
             let loc = self.entities.get(entity)?;
-            if loc.archetype == 0 {
-                return Err(MissingComponent::new::<T>().into());
-            }
-            Ok(&*self.archetypes[loc.archetype as usize]
-                .get::<T>()
-                .ok_or_else(MissingComponent::new::<T>)?
-                .as_ptr()
-                .add(loc.index as usize))
+            Ok(&*self.get_component::<T>(loc)?.value.cast::<T>().as_ptr())
         }
     }
 
@@ -490,20 +616,25 @@ impl World {
     #[inline]
     pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Result<Mut<'_, T>, ComponentError> {
         // SAFE: uniquely borrows world
-        unsafe { self.get_mut_unchecked(entity) }
+        unsafe { self.get_mut_unchecked(entity, read_change_tick()) }
     }
 
     /// Access an entity regardless of its component types
     ///
     /// Does not immediately borrow any component.
     pub fn entity(&mut self, entity: Entity) -> Result<EntityRef<'_>, NoSuchEntity> {
-        Ok(match self.entities.get(entity)? {
-            Location { archetype: 0, .. } => EntityRef::empty(),
-            loc => unsafe { EntityRef::new(&self.archetypes[loc.archetype as usize], loc.index) },
+        let loc = self.entities.get(entity)?;
+        Ok(unsafe {
+            EntityRef::new(
+                &self.archetypes[loc.archetype as usize],
+                loc.index,
+                read_change_tick(),
+            )
         })
     }
 
-    /// Borrow the `T` component of `entity` without checking if it can be mutated
+    /// Borrow the `T` component of `entity` without checking if it can be mutated. Mutations are
+    /// stamped with `change_tick`.
     ///
     /// # Safety
     /// This does not check for mutable access correctness. To be safe, make sure this is the only
@@ -512,14 +643,13 @@ impl World {
     pub unsafe fn get_mut_unchecked<T: Component>(
         &self,
         entity: Entity,
+        change_tick: u64,
     ) -> Result<Mut<'_, T>, ComponentError> {
         let loc = self.entities.get(entity)?;
-        if loc.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
         Ok(Mut::new(
             &self.archetypes[loc.archetype as usize],
             loc.index,
+            change_tick,
         )?)
     }
 
@@ -540,7 +670,7 @@ impl World {
     /// assert!(ids.contains(&b));
     /// ```
     pub fn iter(&mut self) -> Iter<'_> {
-        Iter::new(&self.archetypes, &self.entities)
+        Iter::new(&self.archetypes, &self.entities, read_change_tick())
     }
 
     #[allow(missing_docs)]
@@ -571,41 +701,31 @@ impl World {
         entity: Entity,
         components: impl DynamicBundle,
     ) -> Result<(), NoSuchEntity> {
-        use std::collections::hash_map::Entry;
-
         self.flush();
-        let loc = self.entities.get_mut(entity)?;
+        let source = *self.entities.get_mut(entity)?;
         unsafe {
-            // Assemble Vec<TypeInfo> for the final entity
-            let arch = &mut self.archetypes[loc.archetype as usize];
-            let mut info = arch.types().to_vec();
-            for ty in components.type_info() {
-                if let Some(ptr) = arch.get_dynamic(ty.id(), ty.layout().size(), loc.index) {
+            // Drop the components that are about to be replaced
+            let type_info = components.type_info();
+            let arch = &mut self.archetypes[source.archetype as usize];
+            for ty in type_info.iter() {
+                if let Some(ptr) = arch.get_dynamic(ty.id(), ty.layout().size(), source.index) {
                     ty.drop(ptr.as_ptr());
-                } else {
-                    info.push(ty);
                 }
             }
-            info.sort();
 
             // Find the archetype it'll live in
-            let elements = info.iter().map(|x| x.id()).collect::<Vec<_>>();
-            let target = match self.index.entry(elements) {
-                Entry::Occupied(x) => *x.get(),
-                Entry::Vacant(x) => {
-                    let index = self.archetypes.len() as u32;
-                    self.archetypes.push(Archetype::new(info));
-                    x.insert(index);
-                    self.archetype_generation += 1;
-                    index
-                }
-            };
+            let target = self.insert_target(source.archetype, &type_info);
+            let loc = self.entities.get_mut(entity).unwrap();
 
+            let change_tick = read_change_tick();
+            let sparse_sets = &self.sparse_sets;
             if target == loc.archetype {
                 // Update components in the current archetype
                 let arch = &mut self.archetypes[loc.archetype as usize];
                 components.put(|ptr, ty, size| {
-                    arch.put_dynamic(ptr, ty, size, loc.index, false, true);
+                    if !put_sparse(sparse_sets, entity, ptr, ty, change_tick) {
+                        arch.put_dynamic(ptr, ty, size, loc.index, false, true, change_tick);
+                    }
                     true
                 });
                 return Ok(());
@@ -621,25 +741,97 @@ impl World {
             loc.archetype = target;
             let old_index = mem::replace(&mut loc.index, target_index);
             if let Some(moved) =
-                source_arch.move_to(old_index, |ptr, ty, size, is_added, is_mutated| {
-                    target_arch.put_dynamic(ptr, ty, size, target_index, false, false);
+                source_arch.move_to(old_index, |ptr, ty, size, added_tick, mutated_tick| {
+                    target_arch.put_dynamic(ptr, ty, size, target_index, false, false, change_tick);
                     let type_state = target_arch.get_type_state_mut(ty).unwrap();
-                    *type_state.added().as_ptr().add(target_index) = is_added;
-                    *type_state.mutated().as_ptr().add(target_index) = is_mutated;
+                    *type_state.added().as_ptr().add(target_index) = added_tick;
+                    *type_state.mutated().as_ptr().add(target_index) = mutated_tick;
                 })
             {
                 self.entities.get_mut(moved).unwrap().index = old_index;
             }
 
             components.put(|ptr, ty, size| {
+                if put_sparse(sparse_sets, entity, ptr, ty, change_tick) {
+                    return true;
+                }
                 let had_component = source_arch.has_dynamic(ty);
-                target_arch.put_dynamic(ptr, ty, size, target_index, !had_component, had_component);
+                target_arch.put_dynamic(
+                    ptr,
+                    ty,
+                    size,
+                    target_index,
+                    !had_component,
+                    had_component,
+                    change_tick,
+                );
                 true
             });
         }
         Ok(())
     }
 
+    /// Add a clone of `components` to every entity in `entities`
+    ///
+    /// Equivalent to calling `insert` for each entity, but the archetypes the entities move into
+    /// are grown once up front instead of once per entity. Entities that don't exist are skipped,
+    /// and an error is returned once the others have been updated.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// let mut world = World::new();
+    /// let entities = world.spawn_batch((0..1_000).map(|i| (i,))).collect::<Vec<_>>();
+    /// world.insert_batch(entities.iter().copied(), ("abc", true)).unwrap();
+    /// assert_eq!(*world.get::<&str>(entities[999]).unwrap(), "abc");
+    /// ```
+    pub fn insert_batch<T: DynamicBundle + Clone>(
+        &mut self,
+        entities: impl IntoIterator<Item = Entity>,
+        components: T,
+    ) -> Result<(), NoSuchEntity> {
+        self.flush();
+        let entities = entities.into_iter().collect::<Vec<_>>();
+
+        // Count the entities that will move out of each archetype, then reserve room for them in
+        // the archetypes they move into
+        let mut moving = HashMap::<u32, usize>::default();
+        for entity in entities.iter() {
+            if let Ok(loc) = self.entities.get_mut(*entity) {
+                *moving.entry(loc.archetype).or_default() += 1;
+            }
+        }
+        let type_info = components.type_info();
+        for (source, count) in moving {
+            let target = self.insert_target(source, &type_info);
+            if target != source {
+                self.archetypes[target as usize].reserve(count);
+            }
+        }
+
+        let mut result = Ok(());
+        for entity in entities {
+            if let Err(err) = self.insert(entity, components.clone()) {
+                result = Err(err);
+            }
+        }
+        result
+    }
+
+    /// Finds or creates the archetype that entities in the archetype `source` move into when
+    /// components of the given `types` are added to them
+    fn insert_target(&mut self, source: u32, types: &[TypeInfo]) -> u32 {
+        let arch = &self.archetypes[source as usize];
+        let mut info = arch.types().to_vec();
+        for ty in types {
+            if !arch.has_dynamic(ty.id()) && !self.sparse_sets.contains(ty.id()) {
+                info.push(*ty);
+            }
+        }
+        info.sort();
+        self.get_or_create_archetype(info)
+    }
+
     /// Add `component` to `entity`
     ///
     /// See `insert`.
@@ -671,32 +863,31 @@ impl World {
     /// assert_eq!(*world.get::<bool>(e).unwrap(), true);
     /// ```
     pub fn remove<T: Bundle>(&mut self, entity: Entity) -> Result<T, ComponentError> {
-        use std::collections::hash_map::Entry;
-
         self.flush();
+        let source = self.entities.get(entity)?.archetype;
+        let removed = T::with_static_ids(|ids| ids.iter().copied().collect::<HashSet<_>>());
+        let target = self.remove_target(source, &removed);
         let loc = self.entities.get_mut(entity)?;
         unsafe {
-            let removed = T::with_static_ids(|ids| ids.iter().copied().collect::<HashSet<_>>());
-            let info = self.archetypes[loc.archetype as usize]
-                .types()
-                .iter()
-                .cloned()
-                .filter(|x| !removed.contains(&x.id()))
-                .collect::<Vec<_>>();
-            let elements = info.iter().map(|x| x.id()).collect::<Vec<_>>();
-            let target = match self.index.entry(elements) {
-                Entry::Occupied(x) => *x.get(),
-                Entry::Vacant(x) => {
-                    self.archetypes.push(Archetype::new(info));
-                    let index = (self.archetypes.len() - 1) as u32;
-                    x.insert(index);
-                    self.archetype_generation += 1;
-                    index
-                }
-            };
             let old_index = loc.index;
             let source_arch = &self.archetypes[loc.archetype as usize];
-            let bundle = T::get(|ty, size| source_arch.get_dynamic(ty, size, old_index))?;
+            let bundle = T::get(|ty, size| {
+                source_arch
+                    .get_component(ty, size, old_index)
+                    .map(|component| component.value)
+            })?;
+            // the bundle owns the components now, so they are taken out of their sparse sets without being dropped
+            for (ty, set) in self.sparse_sets.get_mut().iter_mut() {
+                if removed.contains(ty) && set.remove_with(entity, |_| {}) {
+                    self.removed_components
+                        .entry(*ty)
+                        .or_insert_with(Vec::new)
+                        .push(entity);
+                }
+            }
+            if target == loc.archetype {
+                return Ok(bundle);
+            }
             let (source_arch, target_arch) = index2(
                 &mut self.archetypes,
                 loc.archetype as usize,
@@ -707,13 +898,13 @@ impl World {
             loc.index = target_index;
             let removed_components = &mut self.removed_components;
             if let Some(moved) =
-                source_arch.move_to(old_index, |src, ty, size, is_added, is_mutated| {
+                source_arch.move_to(old_index, |src, ty, size, added_tick, mutated_tick| {
                     // Only move the components present in the target archetype, i.e. the non-removed ones.
                     if let Some(dst) = target_arch.get_dynamic(ty, size, target_index) {
                         ptr::copy_nonoverlapping(src, dst.as_ptr(), size);
                         let state = target_arch.get_type_state_mut(ty).unwrap();
-                        *state.added().as_ptr().add(target_index) = is_added;
-                        *state.mutated().as_ptr().add(target_index) = is_mutated;
+                        *state.added().as_ptr().add(target_index) = added_tick;
+                        *state.mutated().as_ptr().add(target_index) = mutated_tick;
                     } else {
                         let removed_entities =
                             removed_components.entry(ty).or_insert_with(Vec::new);
@@ -734,6 +925,99 @@ impl World {
         self.remove::<(T,)>(entity).map(|(x,)| x)
     }
 
+    /// Remove and drop the components of `T` that `entity` has, ignoring the ones it doesn't have
+    ///
+    /// Unlike `remove`, this never fails because of a missing component, and the entity only moves
+    /// to a new archetype once however many components are removed. Nothing happens if the entity
+    /// has none of the components.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// let mut world = World::new();
+    /// let e = world.spawn((123, "abc"));
+    /// world.remove_intersection::<(i32, bool)>(e).unwrap();
+    /// assert!(world.get::<i32>(e).is_err());
+    /// assert_eq!(*world.get::<&str>(e).unwrap(), "abc");
+    /// ```
+    pub fn remove_intersection<T: Bundle>(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
+        self.flush();
+        let loc = self.entities.get(entity)?;
+        let removed = T::with_static_ids(|ids| ids.iter().copied().collect::<HashSet<_>>());
+        // SAFE: borrowing the world mutably keeps the sets from being accessed elsewhere
+        for (ty, set) in unsafe { self.sparse_sets.get_mut() }.iter_mut() {
+            if removed.contains(ty) && set.remove_and_drop(entity) {
+                self.removed_components
+                    .entry(*ty)
+                    .or_insert_with(Vec::new)
+                    .push(entity);
+            }
+        }
+        let dropped = self.archetypes[loc.archetype as usize]
+            .types()
+            .iter()
+            .filter(|ty| removed.contains(&ty.id()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if dropped.is_empty() {
+            return Ok(());
+        }
+
+        let target = self.remove_target(loc.archetype, &removed);
+        let (source_arch, target_arch) = index2(
+            &mut self.archetypes,
+            loc.archetype as usize,
+            target as usize,
+        );
+        unsafe {
+            let target_index = target_arch.allocate(entity);
+            let removed_components = &mut self.removed_components;
+            let moved =
+                source_arch.move_to(loc.index, |src, ty, size, added_tick, mutated_tick| {
+                    if let Some(dst) = target_arch.get_dynamic(ty, size, target_index) {
+                        ptr::copy_nonoverlapping(src, dst.as_ptr(), size);
+                        let state = target_arch.get_type_state_mut(ty).unwrap();
+                        *state.added().as_ptr().add(target_index) = added_tick;
+                        *state.mutated().as_ptr().add(target_index) = mutated_tick;
+                    } else {
+                        let info = dropped.iter().find(|info| info.id() == ty).unwrap();
+                        info.drop(src);
+                        removed_components.entry(ty).or_default().push(entity);
+                    }
+                });
+            if let Some(moved) = moved {
+                self.entities.get_mut(moved).unwrap().index = loc.index;
+            }
+            let location = self.entities.get_mut(entity).unwrap();
+            location.archetype = target;
+            location.index = target_index;
+        }
+        Ok(())
+    }
+
+    /// Finds or creates the archetype an entity of the `source` archetype moves to when the `removed` component
+    /// types are taken off of it
+    fn remove_target(&mut self, source: u32, removed: &HashSet<TypeId>) -> u32 {
+        let info = self.archetypes[source as usize]
+            .types()
+            .iter()
+            .cloned()
+            .filter(|x| !removed.contains(&x.id()))
+            .collect::<Vec<_>>();
+        self.get_or_create_archetype(info)
+    }
+
+    /// # Safety
+    /// The location must be current
+    unsafe fn get_component<T: Component>(
+        &self,
+        location: Location,
+    ) -> Result<StoredComponent<'_>, MissingComponent> {
+        self.archetypes[location.archetype as usize]
+            .get_component(TypeId::of::<T>(), mem::size_of::<T>(), location.index)
+            .ok_or_else(MissingComponent::new::<T>)
+    }
+
     /// Borrow the `T` component at the given location, without safety checks
     ///
     /// # Safety
@@ -742,9 +1026,6 @@ impl World {
         &self,
         location: Location,
     ) -> Result<Ref<T>, ComponentError> {
-        if location.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
         Ok(Ref::new(
             &self.archetypes[location.archetype as usize],
             location.index,
@@ -760,13 +1041,12 @@ impl World {
     pub unsafe fn get_ref_mut_at_location_unchecked<T: Component>(
         &self,
         location: Location,
+        change_tick: u64,
     ) -> Result<RefMut<T>, ComponentError> {
-        if location.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
         Ok(RefMut::new(
             &self.archetypes[location.archetype as usize],
             location.index,
+            change_tick,
         )?)
     }
 
@@ -777,14 +1057,11 @@ impl World {
         &self,
         location: Location,
     ) -> Result<&T, ComponentError> {
-        if location.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
-        Ok(&*self.archetypes[location.archetype as usize]
-            .get::<T>()
-            .ok_or_else(MissingComponent::new::<T>)?
-            .as_ptr()
-            .add(location.index as usize))
+        Ok(&*self
+            .get_component::<T>(location)?
+            .value
+            .cast::<T>()
+            .as_ptr())
     }
 
     /// Borrow the `T` component at the given location, without safety checks
@@ -795,16 +1072,123 @@ impl World {
     pub unsafe fn get_mut_at_location_unchecked<T: Component>(
         &self,
         location: Location,
+        change_tick: u64,
     ) -> Result<Mut<T>, ComponentError> {
-        if location.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
         Ok(Mut::new(
             &self.archetypes[location.archetype as usize],
             location.index,
+            change_tick,
         )?)
     }
 
+    /// Iterate over the types of `entity`'s components, along with their names. Empty if the entity
+    /// doesn't exist.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true));
+    /// let names = world
+    ///     .entity_component_types(a)
+    ///     .map(|(_, name)| name)
+    ///     .collect::<Vec<_>>();
+    /// assert!(names.contains(&"i32"));
+    /// assert!(names.contains(&"bool"));
+    /// ```
+    pub fn entity_component_types(
+        &self,
+        entity: Entity,
+    ) -> impl Iterator<Item = (TypeId, &'static str)> + '_ {
+        let sparse_types = self
+            .sparse_sets
+            .iter()
+            .filter(move |set| set.contains(entity))
+            .map(|set| set.type_info());
+        self.entities
+            .get(entity)
+            .ok()
+            .into_iter()
+            .flat_map(move |location| self.archetypes[location.archetype as usize].types())
+            .chain(sparse_types)
+            .map(|type_info| (type_info.id(), type_info.type_name()))
+    }
+
+    /// Describe every archetype by its number of entities and the names of its component types
+    pub fn archetypes_stats(&self) -> impl Iterator<Item = ArchetypeStats> + '_ {
+        self.archetypes.iter().map(|archetype| ArchetypeStats {
+            entity_count: archetype.len(),
+            component_names: archetype
+                .types()
+                .iter()
+                .map(|type_info| type_info.type_name())
+                .collect(),
+        })
+    }
+
+    /// Format `entity` along with the names of its components, like `0v0 [i32, bool]`, for debug output
+    pub fn debug_entity(&self, entity: Entity) -> String {
+        if !self.contains(entity) {
+            return format!("{:?} (despawned)", entity);
+        }
+        let names = self
+            .entity_component_types(entity)
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>();
+        format!("{:?} [{}]", entity, names.join(", "))
+    }
+
+    /// Returns a pointer to `entity`'s component of type `ty`, or `None` if the entity doesn't exist
+    /// or doesn't have such a component
+    ///
+    /// The pointer is valid until the world is next changed. Reading through it requires casting it
+    /// to the component's actual type, and is only sound while nothing is writing the component.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// # use std::any::TypeId;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, "abc"));
+    /// let number = world.get_dynamic(a, TypeId::of::<i32>()).unwrap();
+    /// assert_eq!(unsafe { *number.cast::<i32>().as_ref() }, 123);
+    /// assert!(world.get_dynamic(a, TypeId::of::<bool>()).is_none());
+    /// ```
+    pub fn get_dynamic(&self, entity: Entity, ty: TypeId) -> Option<NonNull<u8>> {
+        let location = self.entities.get(entity).ok()?;
+        // SAFE: the location is current, and no component is accessed through the returned pointer here
+        unsafe { self.get_dynamic_at_location_unchecked(location, ty, None) }
+    }
+
+    /// Returns a pointer to the component of type `ty` at the given location, or `None` if the entity doesn't have one.
+    /// If `mutated_tick` is set, the component is marked as mutated at that tick.
+    ///
+    /// This is meant for code that only knows components by their [TypeId], like scripting integrations.
+    /// # Safety
+    /// The location must be valid. The pointer must be cast to the actual type of the component, and it can only be
+    /// written through if nothing else is accessing this entity's `ty` component.
+    pub unsafe fn get_dynamic_at_location_unchecked(
+        &self,
+        location: Location,
+        ty: TypeId,
+        mutated_tick: Option<u64>,
+    ) -> Option<NonNull<u8>> {
+        let archetype = &self.archetypes[location.archetype as usize];
+        let type_info = match archetype
+            .types()
+            .iter()
+            .find(|type_info| type_info.id() == ty)
+        {
+            Some(type_info) => type_info,
+            None => self.sparse_sets.get(ty)?.type_info(),
+        };
+        let component = archetype.get_component(ty, type_info.layout().size(), location.index)?;
+        if let Some(mutated_tick) = mutated_tick {
+            *component.mutated.as_ptr() = mutated_tick;
+        }
+        Some(component.value)
+    }
+
     /// Uniquely borrow the `T` component of `entity` without safety checks
     ///
     /// Should only be used as a building block for safe abstractions.
@@ -813,19 +1197,13 @@ impl World {
     ///
     /// `entity` must have been previously obtained from this `World`, and no borrow of the same
     /// component of `entity` may be live simultaneous to the returned reference.
+    #[allow(clippy::mut_from_ref)]
     pub unsafe fn get_unchecked_mut<T: Component>(
         &self,
         entity: Entity,
     ) -> Result<&mut T, ComponentError> {
         let loc = self.entities.get(entity)?;
-        if loc.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
-        Ok(&mut *self.archetypes[loc.archetype as usize]
-            .get::<T>()
-            .ok_or_else(MissingComponent::new::<T>)?
-            .as_ptr()
-            .add(loc.index as usize))
+        Ok(&mut *self.get_component::<T>(loc)?.value.cast::<T>().as_ptr())
     }
 
     /// Convert all reserved entities into empty entities that can be iterated and accessed
@@ -855,8 +1233,10 @@ impl World {
 
     /// Inspect the archetypes that entities are organized into
     ///
-    /// Useful for dynamically scheduling concurrent queries by checking borrows in advance. Does
-    /// not provide access to entities.
+    /// Useful for dynamically scheduling concurrent queries by checking borrows in advance. Each
+    /// archetype lists its component types with [Archetype::types] and its entities with
+    /// [Archetype::iter_entities], which together with [World::get_dynamic] allows inspecting
+    /// components whose types aren't known at compile time.
     pub fn archetypes(&self) -> impl ExactSizeIterator<Item = &'_ Archetype> + '_ {
         self.archetypes.iter()
     }
@@ -888,15 +1268,22 @@ impl World {
         self.entities.get(entity).ok()
     }
 
-    /// Clears each entity's tracker state. For example, each entity's component "mutated" state will be reset to `false`.
+    /// Clears the removed components and starts a new change detection window for queries made on the
+    /// world directly: from now on only components added or mutated after this call match `Added`,
+    /// `Mutated` and `Changed`. Systems keep their own change ticks and are not affected by this.
     pub fn clear_trackers(&mut self) {
-        for archetype in self.archetypes.iter_mut() {
-            archetype.clear_trackers();
-        }
-
+        self.last_change_tick = increment_change_tick();
         self.removed_components.clear();
     }
 
+    /// The change ticks used by queries made on the world directly
+    pub fn change_ticks(&self) -> ChangeTicks {
+        ChangeTicks {
+            last_change_tick: self.last_change_tick,
+            change_tick: read_change_tick(),
+        }
+    }
+
     /// Gets an entity reserver, which can be used to reserve entity ids in a multi-threaded context.
     pub fn get_entity_reserver(&self) -> EntityReserver {
         self.entities.get_reserver()
@@ -921,6 +1308,27 @@ impl<'a> IntoIterator for &'a mut World {
     }
 }
 
+/// Moves a component into its sparse set, if components of type `ty` are stored in one
+///
+/// # Safety
+/// `component` must point to a valid component of type `ty`, which is moved if this returns true. The world that owns
+/// the sets must be borrowed mutably.
+unsafe fn put_sparse(
+    sparse_sets: &SparseSets,
+    entity: Entity,
+    component: *mut u8,
+    ty: TypeId,
+    change_tick: u64,
+) -> bool {
+    match sparse_sets.get_mut().get_mut(&ty) {
+        Some(set) => {
+            set.insert(entity, component, change_tick);
+            true
+        }
+        None => false,
+    }
+}
+
 fn index2<T>(x: &mut [T], i: usize, j: usize) -> (&mut T, &mut T) {
     assert!(i != j);
     assert!(i < x.len());
@@ -976,15 +1384,17 @@ pub struct Iter<'a> {
     entities: &'a Entities,
     current: Option<&'a Archetype>,
     index: usize,
+    change_tick: u64,
 }
 
 impl<'a> Iter<'a> {
-    fn new(archetypes: &'a [Archetype], entities: &'a Entities) -> Self {
+    fn new(archetypes: &'a [Archetype], entities: &'a Entities, change_tick: u64) -> Self {
         Self {
             archetypes: archetypes.iter(),
             entities,
             current: None,
             index: 0,
+            change_tick,
         }
     }
 }
@@ -1010,7 +1420,9 @@ impl<'a> Iterator for Iter<'a> {
                     let index = self.index;
                     self.index += 1;
                     let id = current.get_entity(index);
-                    return Some((id, unsafe { EntityRef::new(current, index) }));
+                    return Some((id, unsafe {
+                        EntityRef::new(current, index, self.change_tick)
+                    }));
                 }
             }
         }
@@ -1044,6 +1456,15 @@ impl<A: DynamicBundle> core::iter::FromIterator<A> for World {
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct ArchetypesGeneration(pub u64);
 
+/// The size and component types of an archetype, returned by `World::archetypes_stats`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArchetypeStats {
+    /// The number of entities in the archetype
+    pub entity_count: usize,
+    /// The names of the archetype's component types
+    pub component_names: Vec<&'static str>,
+}
+
 /// Entity IDs created by `World::spawn_batch`
 pub struct SpawnBatchIter<'a, I>
 where
@@ -1054,6 +1475,8 @@ where
     entities: &'a mut Entities,
     archetype_id: u32,
     archetype: &'a mut Archetype,
+    sparse_sets: &'a SparseSets,
+    change_tick: u64,
 }
 
 impl<I> Drop for SpawnBatchIter<'_, I>
@@ -1078,9 +1501,12 @@ where
         let entity = self.entities.alloc();
         unsafe {
             let index = self.archetype.allocate(entity);
+            let (archetype, sparse_sets, change_tick) =
+                (&mut self.archetype, self.sparse_sets, self.change_tick);
             components.put(|ptr, ty, size| {
-                self.archetype
-                    .put_dynamic(ptr, ty, size, index, true, false);
+                if !put_sparse(sparse_sets, entity, ptr, ty, change_tick) {
+                    archetype.put_dynamic(ptr, ty, size, index, true, false, change_tick);
+                }
                 true
             });
             self.entities.meta[entity.id as usize].location = Location {
@@ -15,6 +15,7 @@
 // modified by Bevy contributors
 
 use bevy_hecs::*;
+use std::any::TypeId;
 
 #[test]
 fn random_access() {
@@ -111,6 +112,71 @@ fn query_optional_component() {
     assert!(ents.contains(&(f, Some(true), 456)));
 }
 
+#[test]
+fn query_with_without_filters() {
+    let mut world = World::new();
+    let e = world.spawn(("abc", 123));
+    let f = world.spawn(("def", 456, true));
+    let g = world.spawn(("ghi", true));
+
+    let ents = world
+        .query_filtered::<Entity, With<bool>>()
+        .collect::<Vec<_>>();
+    assert_eq!(ents.len(), 2);
+    assert!(ents.contains(&f));
+    assert!(ents.contains(&g));
+
+    let ents = world
+        .query_filtered::<Entity, Without<bool>>()
+        .collect::<Vec<_>>();
+    assert_eq!(ents, &[e]);
+
+    let ents = world
+        .query_filtered::<Entity, (With<i32>, Without<bool>)>()
+        .collect::<Vec<_>>();
+    assert_eq!(ents, &[e]);
+
+    let ents = world
+        .query_filtered::<Entity, Or<(Without<i32>, Without<bool>)>>()
+        .collect::<Vec<_>>();
+    assert_eq!(ents.len(), 2);
+    assert!(ents.contains(&e));
+    assert!(ents.contains(&g));
+}
+
+#[test]
+fn nested_filters() {
+    let mut world = World::new();
+    let with_u8 = world.spawn((0u8, true));
+    let with_bool = world.spawn((true, 0i32));
+    let with_i32 = world.spawn((0i32,));
+    let mutated = world.spawn(('a',));
+    let unchanged = world.spawn(('b',));
+    let mutated_with_bool = world.spawn(('c', true));
+    world.clear_trackers();
+    *world.get_mut::<char>(mutated).unwrap() = 'd';
+    *world.get_mut::<char>(mutated_with_bool).unwrap() = 'e';
+
+    // entities with a u8, or entities without a bool that have an i32 or a mutated char
+    let mut ents = world
+        .query_filtered::<Entity, Or<(With<u8>, (Without<bool>, Or<(With<i32>, Mutated<char>)>))>>()
+        .collect::<Vec<_>>();
+    ents.sort();
+    let mut expected = vec![with_u8, with_i32, mutated];
+    expected.sort();
+    assert_eq!(ents, expected);
+    assert!(!ents.contains(&with_bool));
+    assert!(!ents.contains(&unchanged));
+    assert!(!ents.contains(&mutated_with_bool));
+
+    // the same tree with the branches swapped at every level
+    let mut ents = world
+        .query_filtered::<Entity, Or<((Or<(Mutated<char>, With<i32>)>, Without<bool>), With<u8>)>>()
+        .collect::<Vec<_>>();
+    ents.sort();
+    assert_eq!(ents, expected);
+}
+
 #[test]
 fn build_entity() {
     let mut world = World::new();
@@ -183,17 +249,94 @@ fn derived_bundle() {
 
 #[test]
 #[cfg(feature = "macros")]
-#[cfg_attr(
-    debug_assertions,
-    should_panic(
-        expected = "attempted to allocate entity with duplicate i32 components; each type must occur at most once!"
-    )
+fn nested_bundle() {
+    #[derive(Debug, Clone, PartialEq)]
+    struct Sprite(&'static str);
+    #[derive(Debug, Clone, PartialEq)]
+    struct Transform(f32);
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(u32);
+
+    #[derive(Bundle, Clone)]
+    struct SpriteBundle {
+        sprite: Sprite,
+        transform: Transform,
+    }
+
+    #[derive(Bundle, Clone)]
+    struct PlayerBundle {
+        health: Health,
+        #[bundle]
+        sprite: SpriteBundle,
+    }
+
+    #[derive(Bundle)]
+    struct Level(u8, #[bundle] PlayerBundle);
+
+    let player = PlayerBundle {
+        health: Health(10),
+        sprite: SpriteBundle {
+            sprite: Sprite("player.png"),
+            transform: Transform(1.0),
+        },
+    };
+    let mut world = World::new();
+    let e = world.spawn(player.clone());
+    let f = world.spawn((Sprite("enemy.png"), Transform(2.0), Health(5)));
+    assert_eq!(*world.get::<Health>(e).unwrap(), Health(10));
+    assert_eq!(*world.get::<Sprite>(e).unwrap(), Sprite("player.png"));
+    assert_eq!(*world.get::<Transform>(e).unwrap(), Transform(1.0));
+    assert_eq!(
+        world.get_entity_location(e).unwrap().archetype,
+        world.get_entity_location(f).unwrap().archetype,
+        "a nested bundle matches the tuple of its components"
+    );
+
+    let g = world.spawn(Level(3, player));
+    assert_eq!(*world.get::<u8>(g).unwrap(), 3);
+    assert_eq!(*world.get::<Sprite>(g).unwrap(), Sprite("player.png"));
+
+    let removed = world.remove::<PlayerBundle>(e).unwrap();
+    assert_eq!(removed.health, Health(10));
+    assert_eq!(removed.sprite.sprite, Sprite("player.png"));
+    assert!(world.get::<Sprite>(e).is_err());
+
+    world.remove_one::<Transform>(f).unwrap();
+    assert!(world.remove::<PlayerBundle>(f).is_err());
+    assert_eq!(*world.get::<Sprite>(f).unwrap(), Sprite("enemy.png"));
+    assert_eq!(*world.get::<Health>(f).unwrap(), Health(5));
+}
+
+#[test]
+#[cfg(feature = "macros")]
+#[should_panic(
+    expected = "attempted to allocate entity with duplicate i32 components; each type must occur at most once!"
 )]
-#[cfg_attr(
-    not(debug_assertions),
-    should_panic(
-        expected = "attempted to allocate entity with duplicate components; each type must occur at most once!"
-    )
+fn bad_nested_bundle_derive() {
+    #[derive(Bundle)]
+    struct Inner {
+        x: i32,
+        y: f64,
+    }
+
+    #[derive(Bundle)]
+    struct Outer {
+        x: i32,
+        #[bundle]
+        inner: Inner,
+    }
+
+    let mut world = World::new();
+    world.spawn(Outer {
+        x: 1,
+        inner: Inner { x: 2, y: 3.0 },
+    });
+}
+
+#[test]
+#[cfg(feature = "macros")]
+#[should_panic(
+    expected = "attempted to allocate entity with duplicate i32 components; each type must occur at most once!"
 )]
 fn bad_bundle_derive() {
     #[derive(Bundle)]
@@ -226,6 +369,47 @@ fn clear() {
     assert_eq!(world.iter().count(), 0);
 }
 
+#[test]
+fn clear_invalidates_entities() {
+    let mut world = World::new();
+    let e = world.spawn(("abc", 123));
+    world.clear();
+    let f = world.spawn(("def", 456));
+    assert!(!world.contains(e));
+    assert!(world.get::<&str>(e).is_err());
+    assert_eq!(*world.get::<&str>(f).unwrap(), "def");
+}
+
+#[test]
+fn stale_entity() {
+    let mut world = World::new();
+    let e = world.spawn(("abc", 123));
+    world.despawn(e).unwrap();
+    let f = world.spawn(("def", 456));
+    assert_eq!(e.id(), f.id(), "the ID of a despawned entity is reused");
+    assert_ne!(e, f);
+    assert_eq!(Entity::from_bits(e.to_bits()), e);
+    assert_eq!(Entity::from_bits(f.to_bits()), f);
+    assert_ne!(e.to_bits(), f.to_bits());
+
+    assert!(!world.contains(e));
+    assert!(matches!(
+        world.get::<i32>(e),
+        Err(ComponentError::NoSuchEntity)
+    ));
+    assert!(matches!(
+        world.get_mut::<i32>(e),
+        Err(ComponentError::NoSuchEntity)
+    ));
+    assert!(world.query_one::<&i32>(e).is_err());
+    assert!(world.get_entity_location(e).is_none());
+    assert_eq!(world.despawn(e), Err(NoSuchEntity));
+    assert_eq!(world.insert_one(e, true), Err(NoSuchEntity));
+    assert!(world.remove_one::<i32>(e).is_err());
+    assert_eq!(*world.get::<i32>(f).unwrap(), 456);
+    assert!(world.get::<bool>(f).is_err());
+}
+
 #[test]
 fn remove_missing() {
     let mut world = World::new();
@@ -233,6 +417,35 @@ fn remove_missing() {
     assert!(world.remove_one::<bool>(e).is_err());
 }
 
+#[test]
+fn remove_intersection() {
+    let mut world = World::new();
+    let dropped = std::sync::Arc::new(());
+    let a = world.spawn(("abc", 123, dropped.clone()));
+    let b = world.spawn(("def", 456, dropped.clone()));
+    let archetypes = world.archetypes().count();
+
+    world
+        .remove_intersection::<(i32, std::sync::Arc<()>, bool)>(a)
+        .unwrap();
+    assert_eq!(std::sync::Arc::strong_count(&dropped), 2);
+    assert!(world.get::<i32>(a).is_err());
+    assert_eq!(*world.get::<&str>(a).unwrap(), "abc");
+    assert_eq!(world.removed::<i32>(), &[a]);
+    assert!(world.removed::<bool>().is_empty());
+    // the other entity of the archetype took the place of the removed one
+    assert_eq!(*world.get::<&str>(b).unwrap(), "def");
+    assert_eq!(*world.get::<i32>(b).unwrap(), 456);
+    assert_eq!(world.archetypes().count(), archetypes + 1);
+
+    // nothing to remove
+    world.remove_intersection::<(i32, bool)>(a).unwrap();
+    assert_eq!(*world.get::<&str>(a).unwrap(), "abc");
+
+    world.despawn(a).unwrap();
+    assert!(world.remove_intersection::<(i32,)>(a).is_err());
+}
+
 #[test]
 fn query_batched() {
     let mut world = World::new();
@@ -265,6 +478,100 @@ fn spawn_batch() {
     assert_eq!(entities.len(), 100);
 }
 
+#[test]
+fn spawn_batch_dropped_early() {
+    let mut world = World::new();
+    let first = world
+        .spawn_batch((0..100).map(|x| (x, "abc")))
+        .take(10)
+        .collect::<Vec<_>>();
+    assert_eq!(first.len(), 10);
+    assert_eq!(world.query::<&i32>().count(), 100);
+    assert_eq!(*world.get::<i32>(first[9]).unwrap(), 9);
+}
+
+#[test]
+fn spawn_batch_partially_filled_archetype() {
+    let mut world = World::new();
+    let first = world.spawn((0, "abc"));
+    let entities = world
+        .spawn_batch((1..200).map(|x| (x, "abc")))
+        .collect::<Vec<_>>();
+    assert_eq!(*world.get::<i32>(first).unwrap(), 0);
+    for (i, entity) in entities.iter().enumerate() {
+        assert_eq!(*world.get::<i32>(*entity).unwrap(), i as i32 + 1);
+        assert_eq!(*world.get::<&str>(*entity).unwrap(), "abc");
+    }
+}
+
+#[test]
+fn insert_batch() {
+    let mut world = World::new();
+    let mut entities = world.spawn_batch((0..10).map(|x| (x,))).collect::<Vec<_>>();
+    entities.extend(world.spawn_batch((10..15).map(|x| (x, false))));
+    let despawned = world.spawn((15,));
+    world.despawn(despawned).unwrap();
+    entities.insert(3, despawned);
+
+    assert!(world
+        .insert_batch(entities.iter().copied(), ("abc", true))
+        .is_err());
+    entities.remove(3);
+    for (i, entity) in entities.iter().enumerate() {
+        assert_eq!(*world.get::<i32>(*entity).unwrap(), i as i32);
+        assert_eq!(*world.get::<&str>(*entity).unwrap(), "abc");
+        assert_eq!(*world.get::<bool>(*entity).unwrap(), true);
+    }
+    assert_eq!(world.query::<(&i32, &&str, &bool)>().count(), 15);
+}
+
+#[test]
+fn reserved_entities() {
+    let mut world = World::new();
+    let despawned = world.spawn((1,));
+    world.despawn(despawned).unwrap();
+
+    // one id comes from the free list and the other is new
+    let reused = world.reserve_entity();
+    let fresh = world.reserve_entity();
+    assert_eq!(reused.id(), despawned.id());
+    assert_ne!(fresh.id(), despawned.id());
+
+    for &entity in &[reused, fresh] {
+        assert!(world.contains(entity));
+        assert!(matches!(
+            world.get::<i32>(entity),
+            Err(ComponentError::MissingComponent(_))
+        ));
+        assert!(world.query_one::<Entity>(entity).is_err());
+        assert!(world.entity(entity).unwrap().get::<i32>().is_none());
+    }
+    assert_eq!(
+        world.get::<i32>(despawned).unwrap_err(),
+        ComponentError::NoSuchEntity
+    );
+
+    world.insert_one(fresh, 2).unwrap();
+    assert_eq!(*world.get::<i32>(fresh).unwrap(), 2);
+    assert!(matches!(
+        world.get::<i32>(reused),
+        Err(ComponentError::MissingComponent(_))
+    ));
+}
+
+#[test]
+fn query_one_empty_entity() {
+    let mut world = World::new();
+    let empty = world.spawn(());
+    let reserved = world.reserve_entity();
+    assert!(world.query_one::<Entity>(reserved).is_err());
+
+    world.flush();
+    assert_eq!(world.query_one::<Entity>(empty), Ok(empty));
+    assert_eq!(world.query_one::<Entity>(reserved), Ok(reserved));
+    assert!(world.query_one::<&i32>(reserved).is_err());
+}
+
 #[test]
 fn query_one() {
     let mut world = World::new();
@@ -372,6 +679,138 @@ fn added_tracking() {
     assert!(world.query_one_filtered_mut::<(), Added<i32>>(a).is_err());
 }
 
+#[test]
+fn change_trackers() {
+    let mut world = World::new();
+    let a = world.spawn((1, 1.0));
+    world.clear_trackers();
+    let b = world.spawn((2,));
+    let c = world.spawn((3,));
+    *world.get_mut::<i32>(a).unwrap() += 1;
+    *world.get_mut::<i32>(b).unwrap() += 1;
+
+    let mut trackers = world
+        .query::<(Entity, ChangeTrackers<i32>)>()
+        .map(|(entity, trackers)| {
+            (
+                entity,
+                trackers.is_added(),
+                trackers.is_mutated(),
+                trackers.is_changed(),
+            )
+        })
+        .collect::<Vec<_>>();
+    trackers.sort_by_key(|(entity, ..)| entity.id());
+    assert_eq!(
+        trackers,
+        vec![
+            (a, false, true, true),
+            (b, true, true, true),
+            (c, true, false, true)
+        ]
+    );
+    assert!(world
+        .query::<ChangeTrackers<f64>>()
+        .all(|t| !t.is_changed()));
+
+    world.clear_trackers();
+    assert_eq!(world.query::<ChangeTrackers<i32>>().count(), 3);
+    assert!(world
+        .query::<ChangeTrackers<i32>>()
+        .all(|t| !t.is_changed()));
+}
+
+#[test]
+fn archetype_reflection() {
+    let mut world = World::new();
+    let a = world.spawn((123, "abc"));
+    let b = world.spawn((456, "def"));
+    let c = world.spawn((true,));
+
+    let mut archetypes = world
+        .archetypes()
+        .filter(|archetype| !archetype.is_empty())
+        .map(|archetype| {
+            let mut types = archetype
+                .types()
+                .iter()
+                .map(|type_info| type_info.id())
+                .collect::<Vec<_>>();
+            types.sort();
+            (
+                types,
+                archetype.iter_entities().copied().collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+    archetypes.sort_by_key(|(_, entities)| entities.len());
+    let mut number_and_name = vec![TypeId::of::<i32>(), TypeId::of::<&str>()];
+    number_and_name.sort();
+    assert_eq!(
+        archetypes,
+        vec![
+            (vec![TypeId::of::<bool>()], vec![c]),
+            (number_and_name, vec![a, b]),
+        ]
+    );
+
+    let number = world.get_dynamic(b, TypeId::of::<i32>()).unwrap();
+    assert_eq!(unsafe { *number.cast::<i32>().as_ref() }, 456);
+    let flag = world.get_dynamic(c, TypeId::of::<bool>()).unwrap();
+    assert!(unsafe { *flag.cast::<bool>().as_ref() });
+    assert!(world.get_dynamic(c, TypeId::of::<i32>()).is_none());
+
+    world.despawn(c).unwrap();
+    assert!(world.get_dynamic(c, TypeId::of::<bool>()).is_none());
+}
+
+#[test]
+fn entity_component_names() {
+    struct A;
+    struct B;
+    struct C;
+
+    let mut world = World::new();
+    let e = world.spawn((A, B, C));
+    world.spawn((A, B));
+    world.spawn((A, B));
+
+    let mut names = world
+        .entity_component_types(e)
+        .map(|(_, name)| name)
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        vec![
+            std::any::type_name::<A>(),
+            std::any::type_name::<B>(),
+            std::any::type_name::<C>()
+        ]
+    );
+    assert!(world
+        .entity_component_types(e)
+        .any(|(ty, _)| ty == TypeId::of::<C>()));
+
+    let debug = world.debug_entity(e);
+    assert!(debug.starts_with(&format!("{:?} [", e)));
+    for name in names.iter() {
+        assert!(debug.contains(name));
+    }
+
+    let mut stats = world
+        .archetypes_stats()
+        .filter(|stats| stats.entity_count > 0)
+        .map(|stats| (stats.entity_count, stats.component_names.len()))
+        .collect::<Vec<_>>();
+    stats.sort_unstable();
+    assert_eq!(stats, vec![(1, 3), (2, 2)]);
+
+    world.despawn(e).unwrap();
+    assert_eq!(world.entity_component_types(e).count(), 0);
+    assert_eq!(world.debug_entity(e), format!("{:?} (despawned)", e));
+}
+
 #[test]
 #[cfg_attr(
     debug_assertions,
@@ -389,3 +828,109 @@ fn duplicate_components_panic() {
     let mut world = World::new();
     world.reserve::<(f32, i64, f32)>(1);
 }
+
+#[test]
+fn sparse_set_components() {
+    struct Marker(u32);
+
+    let mut world = World::new();
+    world.register_component::<Marker>(StorageType::SparseSet);
+    let a = world.spawn((123, "abc"));
+    let b = world.spawn((456, "def", Marker(1)));
+    let c = world.spawn((Marker(2),));
+    let archetypes = world.archetypes().count();
+
+    world.insert_one(a, Marker(0)).unwrap();
+    assert_eq!(world.archetypes().count(), archetypes);
+    assert_eq!(world.get::<Marker>(a).unwrap().0, 0);
+    world.get_mut::<Marker>(b).unwrap().0 += 10;
+    assert_eq!(world.get::<Marker>(b).unwrap().0, 11);
+    assert!(world.has_component_type(c, TypeId::of::<Marker>()));
+    assert_eq!(world.query_one::<&Marker>(c).unwrap().0, 2);
+
+    let mut ents = world
+        .query::<(Entity, &i32, &Marker)>()
+        .map(|(e, &i, m)| (e, i, m.0))
+        .collect::<Vec<_>>();
+    ents.sort_by_key(|(e, ..)| e.id());
+    assert_eq!(ents, &[(a, 123, 0), (b, 456, 11)]);
+
+    assert_eq!(world.remove_one::<Marker>(a).unwrap().0, 0);
+    assert_eq!(world.archetypes().count(), archetypes);
+    assert!(world.get::<Marker>(a).is_err());
+    assert_eq!(world.query::<&Marker>().count(), 2);
+    assert_eq!(
+        world
+            .query::<(Entity, Option<&Marker>)>()
+            .map(|(e, m)| (e, m.map(|m| m.0)))
+            .filter(|(e, _)| *e == a || *e == b)
+            .count(),
+        2
+    );
+    assert_eq!(
+        world
+            .query_filtered::<Entity, With<Marker>>()
+            .filter(|e| *e == a)
+            .count(),
+        0
+    );
+    assert_eq!(
+        world
+            .query_filtered::<Entity, (With<i32>, Without<Marker>)>()
+            .collect::<Vec<_>>(),
+        &[a]
+    );
+
+    world.despawn(b).unwrap();
+    assert_eq!(world.removed::<Marker>(), &[a, b]);
+    assert_eq!(
+        world
+            .query::<(Entity, &Marker)>()
+            .map(|(e, _)| e)
+            .collect::<Vec<_>>(),
+        &[c]
+    );
+}
+
+#[test]
+fn sparse_set_change_tracking() {
+    struct Marker(u32);
+
+    let mut world = World::new();
+    world.register_component::<Marker>(StorageType::SparseSet);
+    let a = world.spawn((1, Marker(0)));
+    let b = world.spawn((2,));
+    world.clear_trackers();
+
+    world.insert_one(b, Marker(0)).unwrap();
+    assert_eq!(
+        world
+            .query_filtered::<Entity, Added<Marker>>()
+            .collect::<Vec<_>>(),
+        &[b]
+    );
+    assert_eq!(world.query_filtered::<Entity, Mutated<Marker>>().count(), 0);
+
+    world.clear_trackers();
+    world.get_mut::<Marker>(a).unwrap().0 += 1;
+    assert_eq!(world.query_filtered::<Entity, Added<Marker>>().count(), 0);
+    assert_eq!(
+        world
+            .query_filtered::<Entity, Changed<Marker>>()
+            .collect::<Vec<_>>(),
+        &[a]
+    );
+    assert!(world
+        .query_one_filtered::<&Marker, Mutated<Marker>>(b)
+        .is_err());
+    let trackers = world.query_one::<ChangeTrackers<Marker>>(a).unwrap();
+    assert!(!trackers.is_added() && trackers.is_mutated());
+}
+
+#[test]
+#[should_panic(expected = "some entities already have one in a table")]
+fn register_stored_component_panic() {
+    let mut world = World::new();
+    world.spawn((123,));
+    world.register_component::<i32>(StorageType::SparseSet);
+}
@@ -1,4 +1,6 @@
 pub use bevy_hecs::{Query as HecsQuery, *};
+// lets derives like `SystemParam` refer to `bevy_ecs` from inside this crate
+extern crate self as bevy_ecs;
 mod resource;
 mod schedule;
 mod system;
@@ -11,10 +13,16 @@ pub use world::*;
 
 pub mod prelude {
     pub use crate::{
-        resource::{ChangedRes, FromResources, Local, Res, ResMut, Resource, Resources},
-        system::{Commands, IntoSystem, IntoThreadLocalSystem, Query, System},
+        resource::{
+            ChangedRes, FromResources, Local, NonSend, NonSendMut, Res, ResMut, Resource, Resources,
+        },
+        schedule::{State, SystemDescriptorCoercion},
+        system::{
+            AlwaysRunExt, ChainSystemExt, Commands, In, IntoSystem, IntoThreadLocalSystem, Query,
+            RemovedComponents, RunCriteriaExt, ShouldRun, System, SystemNameExt,
+        },
         world::WorldBuilderSource,
-        Added, Bundle, Changed, Component, Entity, Mut, Mutated, Or, QuerySet, Ref, RefMut, With,
-        Without, World,
+        Added, Bundle, ChangeTrackers, Changed, Component, Entity, Mut, Mutated, Or, QuerySet, Ref,
+        RefMut, With, Without, World,
     };
 }
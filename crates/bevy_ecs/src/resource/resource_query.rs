@@ -1,5 +1,6 @@
 use super::FromResources;
-use crate::{Resource, ResourceIndex, Resources, SystemId};
+use crate::Resource;
+use bevy_hecs::ChangeTicks;
 use core::{
     ops::{Deref, DerefMut},
     ptr::NonNull,
@@ -9,7 +10,8 @@ use std::marker::PhantomData;
 // TODO: align TypeAccess api with Query::Fetch
 
 /// A shared borrow of a Resource
-/// that will only return in a query if the Resource has been changed
+/// that will only return in a query if the Resource has been added or mutated since the system last ran.
+/// Systems that take a `ChangedRes` are skipped while the Resource doesn't exist, and run on the frame it is inserted.
 #[derive(Debug)]
 pub struct ChangedRes<'a, T: Resource> {
     value: &'a T,
@@ -39,18 +41,26 @@ impl<'a, T: Resource> Deref for ChangedRes<'a, T> {
 #[derive(Debug)]
 pub struct Res<'a, T: Resource> {
     value: &'a T,
+    changed: bool,
 }
 
 impl<'a, T: Resource> Res<'a, T> {
-    /// Creates a reference cell to a Resource from a pointer
+    /// Creates a reference cell to a Resource from a pointer. `changed` is what [Res::is_changed] returns.
     ///
     /// # Safety
     /// The pointer must have correct lifetime / storage
-    pub unsafe fn new(value: NonNull<T>) -> Self {
+    pub unsafe fn new(value: NonNull<T>, changed: bool) -> Self {
         Self {
             value: &*value.as_ptr(),
+            changed,
         }
     }
+
+    /// Returns true if the Resource has been added or mutated since the system last ran, which is when a
+    /// [ChangedRes] would let the system run
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
 }
 
 impl<'a, T: Resource> Deref for Res<'a, T> {
@@ -66,21 +76,38 @@ impl<'a, T: Resource> Deref for Res<'a, T> {
 pub struct ResMut<'a, T: Resource> {
     _marker: PhantomData<&'a T>,
     value: *mut T,
-    mutated: *mut bool,
+    added: *const u64,
+    mutated: *mut u64,
+    ticks: ChangeTicks,
 }
 
 impl<'a, T: Resource> ResMut<'a, T> {
-    /// Creates a mutable reference cell to a Resource from a pointer
+    /// Creates a mutable reference cell to a Resource from a pointer. Mutations through it stamp `mutated` with
+    /// `ticks.change_tick`, and [ResMut::is_changed] compares `added` and `mutated` against `ticks.last_change_tick`.
     ///
     /// # Safety
     /// The pointer must have correct lifetime / storage / ownership
-    pub unsafe fn new(value: NonNull<T>, mutated: NonNull<bool>) -> Self {
+    pub unsafe fn new(
+        value: NonNull<T>,
+        added: NonNull<u64>,
+        mutated: NonNull<u64>,
+        ticks: ChangeTicks,
+    ) -> Self {
         Self {
             value: value.as_ptr(),
+            added: added.as_ptr(),
             mutated: mutated.as_ptr(),
+            ticks,
             _marker: Default::default(),
         }
     }
+
+    /// Returns true if the Resource has been added or mutated since the system last ran, including mutations through
+    /// this [ResMut]
+    pub fn is_changed(&self) -> bool {
+        // SAFE: the pointers are valid while the resource is borrowed
+        unsafe { self.ticks.is_changed(*self.added) || self.ticks.is_changed(*self.mutated) }
+    }
 }
 
 impl<'a, T: Resource> Deref for ResMut<'a, T> {
@@ -94,14 +121,75 @@ impl<'a, T: Resource> Deref for ResMut<'a, T> {
 impl<'a, T: Resource> DerefMut for ResMut<'a, T> {
     fn deref_mut(&mut self) -> &mut T {
         unsafe {
-            *self.mutated = true;
+            *self.mutated = self.ticks.change_tick;
             &mut *self.value
         }
     }
 }
 
+/// Shared borrow of a thread local Resource, which doesn't need to be `Send` or `Sync`. Thread local resources are
+/// inserted with [Resources::insert_thread_local](crate::Resources::insert_thread_local). Systems that use a
+/// `NonSend` run on the main thread, like thread local systems do.
+#[derive(Debug)]
+pub struct NonSend<'a, T: 'static> {
+    value: &'a T,
+}
+
+impl<'a, T: 'static> NonSend<'a, T> {
+    /// Creates a reference cell to a thread local Resource from a pointer
+    ///
+    /// # Safety
+    /// The pointer must have correct lifetime / storage, and must only be used on the main thread
+    pub unsafe fn new(value: NonNull<T>) -> Self {
+        Self {
+            value: &*value.as_ptr(),
+        }
+    }
+}
+
+impl<'a, T: 'static> Deref for NonSend<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+/// Unique borrow of a thread local Resource. See [NonSend].
+#[derive(Debug)]
+pub struct NonSendMut<'a, T: 'static> {
+    value: &'a mut T,
+}
+
+impl<'a, T: 'static> NonSendMut<'a, T> {
+    /// Creates a mutable reference cell to a thread local Resource from a pointer
+    ///
+    /// # Safety
+    /// The pointer must have correct lifetime / storage / ownership, and must only be used on the main thread
+    pub unsafe fn new(value: NonNull<T>) -> Self {
+        Self {
+            value: &mut *value.as_ptr(),
+        }
+    }
+}
+
+impl<'a, T: 'static> Deref for NonSendMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T: 'static> DerefMut for NonSendMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
 /// Local<T> resources are unique per-system. Two instances of the same system will each have their own resource.
-/// Local resources are automatically initialized using the FromResources trait.
+/// Local resources are automatically initialized using the FromResources trait and are stored in the system's
+/// [SystemState](crate::SystemState), so they never show up in [Resources](crate::Resources).
 #[derive(Debug)]
 pub struct Local<'a, T: Resource + FromResources> {
     value: *mut T,
@@ -109,11 +197,9 @@ pub struct Local<'a, T: Resource + FromResources> {
 }
 
 impl<'a, T: Resource + FromResources> Local<'a, T> {
-    pub(crate) unsafe fn new(resources: &Resources, id: SystemId) -> Self {
+    pub(crate) fn new(value: &mut T) -> Self {
         Local {
-            value: resources
-                .get_unsafe_ref::<T>(ResourceIndex::System(id))
-                .as_ptr(),
+            value,
             _marker: Default::default(),
         }
     }
@@ -1,10 +1,14 @@
 use crate::system::SystemId;
-use bevy_hecs::{Archetype, AtomicBorrow, Entity, Ref, RefMut, TypeInfo, TypeState};
+use bevy_hecs::{
+    read_change_tick, Archetype, AtomicBorrow, Entity, Ref, RefMut, TypeInfo, TypeState,
+};
 use bevy_utils::HashMap;
 use core::any::TypeId;
 use downcast_rs::{impl_downcast, Downcast};
 use std::{
     fmt::Debug,
+    marker::PhantomData,
+    mem::MaybeUninit,
     ops::{Deref, DerefMut},
     ptr::NonNull,
     thread::ThreadId,
@@ -84,6 +88,8 @@ pub struct Resources {
     pub(crate) resource_data: HashMap<TypeId, ResourceData>,
     thread_local_data: HashMap<TypeId, Box<dyn ResourceStorage>>,
     main_thread_id: ThreadId,
+    /// The resources [Resources::create] is creating, innermost last
+    creating: Vec<&'static str>,
 }
 
 impl Default for Resources {
@@ -92,6 +98,7 @@ impl Default for Resources {
             resource_data: Default::default(),
             thread_local_data: Default::default(),
             main_thread_id: std::thread::current().id(),
+            creating: Vec::new(),
         }
     }
 }
@@ -121,6 +128,57 @@ impl Resources {
         }
     }
 
+    /// Returns a pointer to the thread local resource of type `T`. Panics if it doesn't exist or if this isn't
+    /// called on the main thread.
+    ///
+    /// # Safety
+    /// This doesn't borrow the resource, so the caller has to make sure it isn't aliased mutably
+    #[inline]
+    pub unsafe fn get_thread_local_unsafe_ref<T: 'static>(&self) -> NonNull<T> {
+        self.check_thread_local();
+        self.thread_local_data
+            .get(&TypeId::of::<T>())
+            .and_then(|storage| {
+                let resources = storage.downcast_ref::<VecResourceStorage<T>>().unwrap();
+                resources.stored.first()
+            })
+            .map(|stored| NonNull::new_unchecked(stored.value.get()))
+            .unwrap_or_else(|| {
+                panic!(
+                    "Thread local resource does not exist {}",
+                    std::any::type_name::<T>()
+                )
+            })
+    }
+
+    /// Removes the global resource of type `T` and returns it, if it exists
+    pub fn remove<T: Resource>(&mut self) -> Option<T> {
+        let data = self.resource_data.get_mut(&TypeId::of::<T>())?;
+        let index = data.default_index.take()?;
+        let last = data.archetype.len() - 1;
+        let mut resource = MaybeUninit::<T>::uninit();
+        // SAFE: the index belongs to the global resource and the only component in the archetype is `T`, which is
+        // moved into `resource`
+        unsafe {
+            data.archetype
+                .move_to(index, |ptr, _ty, size, _added, _mutated| {
+                    std::ptr::copy_nonoverlapping(ptr, resource.as_mut_ptr().cast::<u8>(), size);
+                });
+        }
+
+        // the last resource in the archetype was swapped into the removed resource's slot
+        if index != last {
+            for archetype_index in data.system_id_to_archetype_index.values_mut() {
+                if *archetype_index == last {
+                    *archetype_index = index;
+                }
+            }
+        }
+
+        // SAFE: the resource was copied out of the archetype above
+        Some(unsafe { resource.assume_init() })
+    }
+
     pub fn contains<T: Resource>(&self) -> bool {
         self.get_resource::<T>(ResourceIndex::Global).is_some()
     }
@@ -153,16 +211,54 @@ impl Resources {
             })
     }
 
+    /// Borrows the global resource of type `T`, first inserting `value` if it doesn't exist
+    pub fn get_or_insert<T: Resource>(&mut self, value: T) -> RefMut<'_, T> {
+        self.entry().or_insert(value)
+    }
+
+    /// Borrows the global resource of type `T`, first inserting the result of `get_resource` if it doesn't exist.
+    /// `get_resource` only runs when the resource is missing, and an insert is marked as added, so
+    /// [ChangedRes](crate::ChangedRes) sees it.
     pub fn get_or_insert_with<T: Resource>(
         &mut self,
         get_resource: impl FnOnce() -> T,
     ) -> RefMut<'_, T> {
-        // NOTE: this double-get is really weird. why cant we use an if-let here?
-        if self.get::<T>().is_some() {
-            return self.get_mut::<T>().unwrap();
+        self.entry().or_insert_with(get_resource)
+    }
+
+    /// Gets the global resource of type `T` for in-place manipulation, like
+    /// [HashMap::entry](std::collections::HashMap::entry). Resources inserted through the entry are marked as added,
+    /// just like with [Resources::insert].
+    pub fn entry<T: Resource>(&mut self) -> ResourceEntry<'_, T> {
+        ResourceEntry {
+            resources: self,
+            marker: PhantomData,
+        }
+    }
+
+    /// Creates `R` with [FromResources]. While `R` is created, [Resources::expect] names `R` when it panics, which
+    /// tells which resource was initialized before the resources it is created from.
+    pub fn create<R: FromResources>(&mut self) -> R {
+        self.creating.push(std::any::type_name::<R>());
+        let resource = R::from_resources(self);
+        self.creating.pop();
+        resource
+    }
+
+    /// Gets the resource `T`, for [FromResources] implementations that can't create their resource without it.
+    ///
+    /// # Panics
+    /// Panics if `T` doesn't exist. Under [Resources::create], the message also names the resource being created.
+    pub fn expect<T: Resource>(&self) -> Ref<'_, T> {
+        match (self.get::<T>(), self.creating.last()) {
+            (Some(resource), _) => resource,
+            (None, Some(creating)) => panic!(
+                "{} is created from the resource {}, which does not exist. Insert it before initializing {0}",
+                creating,
+                std::any::type_name::<T>()
+            ),
+            (None, None) => panic!("Resource does not exist {}", std::any::type_name::<T>()),
         }
-        self.insert(get_resource());
-        self.get_mut().unwrap()
     }
 
     /// Returns a clone of the underlying resource, this is helpful when borrowing something
@@ -231,6 +327,7 @@ impl Resources {
                 index,
                 added,
                 !added,
+                read_change_tick(),
             );
             std::mem::forget(resource);
         }
@@ -259,7 +356,7 @@ impl Resources {
                     ResourceIndex::Global => data.default_index?,
                     ResourceIndex::System(id) => *data.system_id_to_archetype_index.get(&id.0)?,
                 };
-                RefMut::new(&data.archetype, index).ok()
+                RefMut::new(&data.archetype, index, read_change_tick()).ok()
             })
     }
 
@@ -300,7 +397,7 @@ impl Resources {
     pub unsafe fn get_unsafe_added_and_mutated<T: Resource>(
         &self,
         resource_index: ResourceIndex,
-    ) -> (NonNull<bool>, NonNull<bool>) {
+    ) -> (NonNull<u64>, NonNull<u64>) {
         self.get_resource_data_index::<T>(resource_index)
             .and_then(|(data, index)| {
                 let type_state = data.archetype.get_type_state(TypeId::of::<T>())?;
@@ -312,6 +409,15 @@ impl Resources {
             .unwrap_or_else(|| panic!("Resource does not exist {}", std::any::type_name::<T>()))
     }
 
+    /// Checks whether the resource exists without borrowing it, so this is safe to call while a system holds it
+    #[inline]
+    pub(crate) fn contains_without_borrow<T: Resource>(
+        &self,
+        resource_index: ResourceIndex,
+    ) -> bool {
+        self.get_resource_data_index::<T>(resource_index).is_some()
+    }
+
     #[inline]
     fn get_resource_data_index<T: Resource>(
         &self,
@@ -351,14 +457,6 @@ impl Resources {
             data.archetype.release_mut::<T>();
         }
     }
-
-    /// Clears each resource's tracker state.
-    /// For example, each resource's component "mutated" state will be reset to `false`.
-    pub fn clear_trackers(&mut self) {
-        for (_, resource_data) in self.resource_data.iter_mut() {
-            resource_data.archetype.clear_trackers();
-        }
-    }
 }
 
 unsafe impl Send for Resources {}
@@ -379,6 +477,43 @@ where
     }
 }
 
+/// A global resource that may or may not exist yet, returned by [Resources::entry]
+pub struct ResourceEntry<'a, T: Resource> {
+    resources: &'a mut Resources,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: Resource> ResourceEntry<'a, T> {
+    /// Inserts `default` if the resource doesn't exist, then borrows the resource
+    pub fn or_insert(self, default: T) -> RefMut<'a, T> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `default` if the resource doesn't exist, then borrows the resource. `default` isn't
+    /// called if the resource exists.
+    pub fn or_insert_with(self, default: impl FnOnce() -> T) -> RefMut<'a, T> {
+        if !self.resources.contains::<T>() {
+            self.resources.insert(default());
+        }
+        self.resources.get_mut::<T>().unwrap()
+    }
+
+    /// Calls `modify` on the resource if it exists. This marks the resource as mutated.
+    pub fn and_modify(self, modify: impl FnOnce(&mut T)) -> Self {
+        if let Some(mut resource) = self.resources.get_mut::<T>() {
+            modify(&mut *resource);
+        }
+        self
+    }
+}
+
+impl<'a, T: Resource + Default> ResourceEntry<'a, T> {
+    /// Inserts the default value if the resource doesn't exist, then borrows the resource
+    pub fn or_default(self) -> RefMut<'a, T> {
+        self.or_insert_with(T::default)
+    }
+}
+
 /// Shared borrow of an entity's component
 #[derive(Clone)]
 pub struct ResourceRef<'a, T: 'static> {
@@ -498,8 +633,9 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::Resources;
+    use super::{ResourceIndex, Resources};
     use crate::system::SystemId;
+    use bevy_hecs::read_change_tick;
 
     #[test]
     fn resource() {
@@ -542,6 +678,28 @@ mod tests {
         assert_eq!(*resources.get::<i32>().expect("resource exists"), 123);
     }
 
+    #[test]
+    fn remove_resource() {
+        let mut resources = Resources::default();
+        assert_eq!(resources.remove::<String>(), None);
+
+        let system_id = SystemId(1);
+        resources.insert("global".to_string());
+        resources.insert_local(system_id, "local".to_string());
+        assert_eq!(resources.remove::<String>(), Some("global".to_string()));
+        assert!(!resources.contains::<String>());
+        assert_eq!(resources.remove::<String>(), None);
+        assert_eq!(
+            *resources.get_local::<String>(system_id).unwrap(),
+            "local",
+            "local resources should still be reachable after the global resource is removed"
+        );
+
+        resources.insert("global again".to_string());
+        assert_eq!(*resources.get::<String>().unwrap(), "global again");
+        assert_eq!(*resources.get_local::<String>(system_id).unwrap(), "local");
+    }
+
     #[test]
     #[should_panic(expected = "i32 already borrowed")]
     fn resource_double_mut_panic() {
@@ -551,6 +709,71 @@ mod tests {
         let _y = resources.get_mut::<i32>();
     }
 
+    #[test]
+    fn resource_entry() {
+        fn ticks(resources: &Resources) -> (u64, u64) {
+            unsafe {
+                let (added, mutated) =
+                    resources.get_unsafe_added_and_mutated::<i32>(ResourceIndex::Global);
+                (*added.as_ptr(), *mutated.as_ptr())
+            }
+        }
+
+        let mut resources = Resources::default();
+        let before_insert = read_change_tick();
+        assert_eq!(*resources.entry::<i32>().or_insert(1), 1);
+        let (added, mutated) = ticks(&resources);
+        assert!(added >= before_insert);
+        assert_eq!(mutated, 0);
+
+        // the resource exists, so neither the value nor its ticks change
+        assert_eq!(*resources.entry::<i32>().or_insert(2), 1);
+        assert_eq!(
+            *resources
+                .entry::<i32>()
+                .or_insert_with(|| panic!("resource exists")),
+            1
+        );
+        assert_eq!(*resources.entry::<i32>().or_default(), 1);
+        assert_eq!(ticks(&resources), (added, 0));
+
+        let before_modify = read_change_tick();
+        let value = resources
+            .entry::<i32>()
+            .and_modify(|value| *value += 1)
+            .or_insert(5);
+        assert_eq!(*value, 2);
+        drop(value);
+        assert!(ticks(&resources).1 >= before_modify);
+
+        assert_eq!(
+            *resources
+                .entry::<u64>()
+                .and_modify(|_| panic!("resource doesn't exist"))
+                .or_default(),
+            0
+        );
+        assert_eq!(*resources.get_or_insert_with(|| 7u8), 7);
+        assert_eq!(*resources.get_or_insert_with(|| 8u8), 7);
+        assert_eq!(*resources.get_or_insert(9u8), 7);
+        assert_eq!(*resources.get_or_insert(10u16), 10);
+    }
+
+    #[test]
+    fn get_or_insert_with_runs_once() {
+        let mut resources = Resources::default();
+        let mut calls = 0;
+        for i in 0..3 {
+            let mut value = resources.get_or_insert_with(|| {
+                calls += 1;
+                vec![i]
+            });
+            value.push(i);
+        }
+        assert_eq!(calls, 1);
+        assert_eq!(*resources.get::<Vec<i32>>().unwrap(), vec![0, 0, 1, 2]);
+    }
+
     #[test]
     fn thread_local_resource() {
         let mut resources = Resources::default();
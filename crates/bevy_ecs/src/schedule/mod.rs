@@ -1,6 +1,18 @@
+mod panic_policy;
 mod parallel_executor;
 #[allow(clippy::module_inception)]
 mod schedule;
+mod stage;
+mod state;
+mod system_descriptor;
+mod system_diagnostics;
 
+pub use panic_policy::PanicPolicy;
 pub use parallel_executor::*;
 pub use schedule::*;
+pub use stage::{LoopStage, Stage, SystemStage, DEFAULT_MAX_LOOP_ITERATIONS};
+pub use state::{State, StateError};
+pub use system_descriptor::*;
+pub use system_diagnostics::{
+    ProfiledSystem, SystemDiagnostics, SystemProfile, SystemTiming, DEFAULT_PROFILE_FRAMES,
+};
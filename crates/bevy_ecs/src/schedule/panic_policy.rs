@@ -0,0 +1,52 @@
+use crate::system::System;
+use bevy_utils::tracing::error;
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+};
+
+/// What a [Schedule](crate::Schedule) does when one of its systems panics, set with
+/// [Schedule::set_panic_policy](crate::Schedule::set_panic_policy)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PanicPolicy {
+    /// Unwinds through the schedule, which usually brings down the app. This is the default.
+    Propagate,
+    /// Logs the name of the system and the panic message, then carries on with the rest of the stage as if the
+    /// system had returned. The system runs again the next time its stage runs.
+    ///
+    /// Whatever the system did before it panicked is kept, including the [Commands](crate::Commands) it queued,
+    /// which are still applied at the end of the stage.
+    Continue,
+}
+
+/// Calls `run` with `system`. When `catch_panics` is set, a panic in `run` is logged instead of unwinding.
+pub(crate) fn run_guarded(
+    catch_panics: bool,
+    system: &mut dyn System<In = (), Out = ()>,
+    run: impl FnOnce(&mut dyn System<In = (), Out = ()>),
+) {
+    if !catch_panics {
+        run(system);
+        return;
+    }
+
+    // resources are only borrowed at runtime through guards that are released as the panic unwinds, so the world and
+    // resources are left usable
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| run(&mut *system))) {
+        error!(
+            "System {} panicked: {}",
+            system.name(),
+            panic_message(&*payload)
+        );
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<Any>"
+    }
+}
@@ -1,15 +1,25 @@
-use super::Schedule;
+use super::{
+    panic_policy::run_guarded,
+    schedule::run_stage_criteria,
+    state::run_state_drivers,
+    system_diagnostics::{record_run_time, run_thread_local_timed},
+    Schedule, SystemStage,
+};
 use crate::{
     resource::Resources,
-    system::{System, ThreadLocalExecution},
+    system::{BoxedSystem, CommandErrors, ShouldRun, SystemId, ThreadLocalExecution},
 };
 use bevy_hecs::{ArchetypesGeneration, TypeAccess, World};
-use bevy_tasks::{ComputeTaskPool, CountdownEvent, TaskPool};
+use bevy_tasks::{ComputeTaskPool, CountdownEvent, Scope, TaskPool};
 #[cfg(feature = "trace")]
 use bevy_utils::tracing::info_span;
-use bevy_utils::tracing::trace;
+use bevy_utils::tracing::{debug, trace};
+use bevy_utils::HashSet;
 use fixedbitset::FixedBitSet;
-use std::ops::Range;
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
 
 /// Executes each schedule stage in parallel by analyzing system dependencies.
 /// System execution order is undefined except under the following conditions:
@@ -18,12 +28,27 @@ use std::ops::Range;
 /// * in a given stage, systems the read [archetype+component] X cannot run before systems registered before them that write [archetype+component] X
 /// * in a given stage, systems that mutate resource Y cannot run before systems registered before them that read/write resource Y
 /// * in a given stage, systems the read resource Y cannot run before systems registered before them that write resource Y
-
+///
+/// Use [ExecutionMode::Serial] to run every system in a fixed order instead, or
+/// [ParallelExecutor::set_stage_parallelism] to let adjacent stages that don't conflict run at the same time.
 #[derive(Debug)]
 pub struct ParallelExecutor {
     stages: Vec<ExecutorStage>,
     last_schedule_generation: usize,
     clear_trackers: bool,
+    mode: ExecutionMode,
+    stage_parallelism: bool,
+}
+
+/// How a [ParallelExecutor] runs the systems of a stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// Systems run on the [ComputeTaskPool] as soon as the systems they depend on have finished
+    Parallel,
+    /// Systems run one at a time on the calling thread, in the order of their stage after sorting by labels. Every
+    /// run of the schedule mutates the world in the same sequence, which makes runs reproducible, for example to diff
+    /// two runs or to keep tests from depending on thread timing.
+    Serial,
 }
 
 impl Default for ParallelExecutor {
@@ -32,6 +57,8 @@ impl Default for ParallelExecutor {
             stages: Default::default(),
             last_schedule_generation: usize::MAX, // MAX forces prepare to run the first time
             clear_trackers: true,
+            mode: ExecutionMode::Parallel,
+            stage_parallelism: false,
         }
     }
 }
@@ -44,6 +71,31 @@ impl ParallelExecutor {
         }
     }
 
+    pub fn mode(&self) -> ExecutionMode {
+        self.mode
+    }
+
+    /// Changes how systems are run from the next run on
+    pub fn set_mode(&mut self, mode: ExecutionMode) {
+        self.mode = mode;
+    }
+
+    pub fn stage_parallelism(&self) -> bool {
+        self.stage_parallelism
+    }
+
+    /// Lets a stage run at the same time as the stage after it, from the next run on, when no system of one stage
+    /// conflicts with a system of the other. Stages with run criteria, states or thread local systems that run
+    /// immediately always run on their own, and stages that access the same [archetype+component] or resource, with
+    /// at least one of them writing it, still run one after the other.
+    ///
+    /// The commands of both stages are applied once both have run, so the later stage doesn't see the entities and
+    /// components the earlier one adds or removes until the next run. Off by default, and has no effect in
+    /// [ExecutionMode::Serial].
+    pub fn set_stage_parallelism(&mut self, enabled: bool) {
+        self.stage_parallelism = enabled;
+    }
+
     pub fn initialize(&mut self, resources: &mut Resources) {
         if resources.get::<ComputeTaskPool>().is_none() {
             resources.insert(ComputeTaskPool(TaskPool::default()));
@@ -56,6 +108,19 @@ impl ParallelExecutor {
         #[cfg(feature = "trace")]
         let _schedule_guard = schedule_span.enter();
 
+        if self.clear_trackers {
+            CommandErrors::clear_in(resources);
+        }
+        if self.mode == ExecutionMode::Serial {
+            schedule.run_stages(world, resources);
+            schedule.finish_timing_frame(resources);
+            if self.clear_trackers {
+                world.clear_trackers();
+            }
+            return;
+        }
+
+        schedule.prepare_timing(resources);
         let schedule_generation = schedule.generation();
         let schedule_changed = schedule.generation() != self.last_schedule_generation;
         if schedule_changed {
@@ -63,31 +128,224 @@ impl ParallelExecutor {
             self.stages
                 .resize_with(schedule.stage_order.len(), ExecutorStage::default);
         }
-        for (stage_name, executor_stage) in schedule.stage_order.iter().zip(self.stages.iter_mut())
-        {
+        let mut stage_index = 0;
+        while stage_index < schedule.stage_order.len() {
+            if self.stage_parallelism
+                && self.run_stage_pair(schedule, stage_index, world, resources, schedule_generation)
+            {
+                stage_index += 2;
+                continue;
+            }
+
+            let stage_name = &schedule.stage_order[stage_index];
+            let executor_stage = &mut self.stages[stage_index];
+            stage_index += 1;
             #[cfg(feature = "trace")]
             let stage_span = info_span!("stage", name = stage_name.as_ref());
             #[cfg(feature = "trace")]
             let _stage_guard = stage_span.enter();
-            if let Some(stage_systems) = schedule.stages.get_mut(stage_name) {
-                executor_stage.run(world, resources, stage_systems, schedule_changed);
+            if let Some(stage) = schedule.stages.get_mut(stage_name) {
+                let ordering_dependencies = schedule
+                    .stage_dependencies
+                    .get(stage_name)
+                    .map(|dependencies| dependencies.as_slice())
+                    .unwrap_or(&[]);
+                loop {
+                    let should_run = run_stage_criteria(
+                        schedule.stage_run_criteria.get_mut(stage_name),
+                        world,
+                        resources,
+                    );
+                    if should_run == ShouldRun::No {
+                        break;
+                    }
+
+                    run_state_drivers(schedule.state_drivers.get_mut(stage_name), world, resources);
+
+                    match stage.downcast_mut::<SystemStage>() {
+                        Some(SystemStage { systems }) => {
+                            // a stage that was skipped when the schedule changed still needs to be prepared the next
+                            // time it runs
+                            let stage_changed =
+                                executor_stage.last_schedule_generation != schedule_generation;
+                            executor_stage.run(
+                                world,
+                                resources,
+                                systems,
+                                ordering_dependencies,
+                                stage_changed,
+                                schedule.timing,
+                                schedule.catch_panics,
+                                &schedule.disabled_systems,
+                            );
+                            executor_stage.last_schedule_generation = schedule_generation;
+                        }
+                        None => stage.run(world, resources),
+                    }
+
+                    if should_run == ShouldRun::Yes {
+                        break;
+                    }
+                }
             }
         }
 
+        schedule.finish_timing_frame(resources);
         if self.clear_trackers {
             world.clear_trackers();
-            resources.clear_trackers();
         }
 
         self.last_schedule_generation = schedule_generation;
     }
 
+    /// Runs the stage at `index` and the stage after it at the same time, unless either has to run on its own or
+    /// they conflict. Returns false without running either stage in that case.
+    fn run_stage_pair(
+        &mut self,
+        schedule: &mut Schedule,
+        index: usize,
+        world: &mut World,
+        resources: &mut Resources,
+        schedule_generation: usize,
+    ) -> bool {
+        let names = match schedule.stage_order.get(index..index + 2) {
+            Some(names) => names,
+            None => return false,
+        };
+        // run criteria and states decide whether, and how many times, their stage runs
+        if names.iter().any(|name| {
+            schedule.stage_run_criteria.contains_key(name)
+                || schedule.state_drivers.contains_key(name)
+        }) {
+            return false;
+        }
+
+        let mut stages = [None, None];
+        for (name, stage) in schedule.stages.iter_mut() {
+            if let Some(slot) = names.iter().position(|pair_name| pair_name == name) {
+                stages[slot] = stage.downcast_mut::<SystemStage>();
+            }
+        }
+        let (first, second) = match stages {
+            [Some(first), Some(second)] => (first, second),
+            _ => return false,
+        };
+        let runs_immediately = |stage: &SystemStage| {
+            stage
+                .systems
+                .iter()
+                .any(|system| system.thread_local_execution() == ThreadLocalExecution::Immediate)
+        };
+        if runs_immediately(first) || runs_immediately(second) {
+            return false;
+        }
+
+        #[cfg(feature = "trace")]
+        let stage_span = info_span!(
+            "stage_pair",
+            first = names[0].as_ref(),
+            second = names[1].as_ref()
+        );
+        #[cfg(feature = "trace")]
+        let _stage_guard = stage_span.enter();
+
+        let start_archetypes_generation = world.archetypes_generation();
+        let (earlier_executor_stages, later_executor_stages) = self.stages.split_at_mut(index + 1);
+        let mut pair = [
+            (&mut earlier_executor_stages[index], first),
+            (&mut later_executor_stages[0], second),
+        ];
+        let mut prepared_system_ranges = [0..0, 0..0];
+        let mut archetype_accesses = [TypeAccess::default(), TypeAccess::default()];
+        let mut resource_accesses = [TypeAccess::default(), TypeAccess::default()];
+        for (slot, (executor_stage, stage)) in pair.iter_mut().enumerate() {
+            let stage_changed = executor_stage.last_schedule_generation != schedule_generation;
+            if stage_changed {
+                executor_stage.reset(&stage.systems);
+            }
+            let ordering_dependencies = schedule
+                .stage_dependencies
+                .get(&names[slot])
+                .map(|dependencies| dependencies.as_slice())
+                .unwrap_or(&[]);
+            // without thread local systems that run immediately, this prepares every system of the stage
+            prepared_system_ranges[slot] = executor_stage.prepare_to_next_thread_local(
+                world,
+                &mut stage.systems,
+                ordering_dependencies,
+                stage_changed,
+                0,
+            );
+            for system in stage.systems.iter() {
+                archetype_accesses[slot].union(system.archetype_component_access());
+                resource_accesses[slot].union(system.resource_access());
+            }
+        }
+        if !archetype_accesses[0].is_compatible(&archetype_accesses[1])
+            || !resource_accesses[0].is_compatible(&resource_accesses[1])
+        {
+            // the prepared state is still valid, so running the stages one after the other only resets it
+            return false;
+        }
+
+        let timing = schedule.timing;
+        let catch_panics = schedule.catch_panics;
+        let disabled_systems = &schedule.disabled_systems;
+        let compute_pool = resources.get_cloned::<ComputeTaskPool>().unwrap();
+        {
+            let world = &*world;
+            let resources = &*resources;
+            let prepared_system_ranges = &prepared_system_ranges;
+            let pair = &mut pair;
+            let run_times = compute_pool.scope(|scope| {
+                for (slot, (executor_stage, stage)) in pair.iter_mut().enumerate() {
+                    executor_stage.spawn_systems(
+                        scope,
+                        slot,
+                        world,
+                        resources,
+                        &mut stage.systems,
+                        prepared_system_ranges[slot].clone(),
+                        timing,
+                        catch_panics,
+                        disabled_systems,
+                    );
+                }
+            });
+            for (slot, system_index, run_time) in run_times.into_iter().flatten() {
+                record_run_time(
+                    resources,
+                    pair[slot].1.systems[system_index].as_ref(),
+                    run_time,
+                );
+            }
+        }
+
+        for (executor_stage, stage) in pair.iter_mut() {
+            executor_stage.flush(
+                world,
+                resources,
+                &mut stage.systems,
+                timing,
+                catch_panics,
+                disabled_systems,
+            );
+        }
+        for (executor_stage, _) in pair.iter_mut() {
+            if start_archetypes_generation == world.archetypes_generation() {
+                executor_stage.last_archetypes_generation = world.archetypes_generation();
+            }
+            executor_stage.last_schedule_generation = schedule_generation;
+        }
+        true
+    }
+
     pub fn print_order(&self, schedule: &Schedule) {
         println!("----------------------------");
         for (stage_name, executor_stage) in schedule.stage_order.iter().zip(self.stages.iter()) {
             println!("stage {:?}", stage_name);
-            if let Some(stage_systems) = schedule.stages.get(stage_name) {
-                for (i, system) in stage_systems.iter().enumerate() {
+            if let Some(stage) = schedule.get_stage::<SystemStage>(stage_name) {
+                for (i, system) in stage.systems.iter().enumerate() {
                     println!("  {}-{}", i, system.name());
                     println!(
                         "      dependencies({:?})",
@@ -122,6 +380,9 @@ pub struct ExecutorStage {
     /// When archetypes change a counter is bumped - we cache the state of that counter when it was
     /// last read here so that we can detect when archetypes are changed
     last_archetypes_generation: ArchetypesGeneration,
+    /// The schedule generation this stage was last prepared for. Stages with run criteria can be skipped on the
+    /// frame the schedule changes, so each stage tracks this on its own
+    last_schedule_generation: usize,
 }
 
 impl Default for ExecutorStage {
@@ -134,6 +395,7 @@ impl Default for ExecutorStage {
             system_dependencies: Default::default(),
             thread_local_system_indices: Default::default(),
             last_archetypes_generation: ArchetypesGeneration(u64::MAX), // MAX forces prepare to run the first time
+            last_schedule_generation: usize::MAX,
         }
     }
 }
@@ -147,7 +409,8 @@ impl ExecutorStage {
     pub fn prepare_to_next_thread_local(
         &mut self,
         world: &World,
-        systems: &mut [Box<dyn System>],
+        systems: &mut [BoxedSystem],
+        ordering_dependencies: &[Vec<usize>],
         schedule_changed: bool,
         next_thread_local_index: usize,
     ) -> Range<usize> {
@@ -215,12 +478,44 @@ impl ExecutorStage {
                                 );
 
                                 // if earlier system is incompatible, make the current system dependent
-                                if !earlier_system
-                                    .archetype_component_access()
-                                    .is_compatible(archetype_access)
-                                    || !earlier_system
-                                        .resource_access()
-                                        .is_compatible(resource_access)
+                                let earlier_archetype_access =
+                                    earlier_system.archetype_component_access();
+                                let earlier_resource_access = earlier_system.resource_access();
+                                if !earlier_archetype_access.is_compatible(archetype_access)
+                                    || !earlier_resource_access.is_compatible(resource_access)
+                                {
+                                    if let Some(component) =
+                                        earlier_archetype_access.get_conflict_name(archetype_access)
+                                    {
+                                        debug!(
+                                            "System {} waits for system {} because both access the component {}",
+                                            system.name(),
+                                            earlier_system.name(),
+                                            component
+                                        );
+                                    } else if let Some(resource) =
+                                        earlier_resource_access.get_conflict_name(resource_access)
+                                    {
+                                        debug!(
+                                            "System {} waits for system {} because both access the resource {}",
+                                            system.name(),
+                                            earlier_system.name(),
+                                            resource
+                                        );
+                                    }
+                                    self.system_dependents[earlier_system_index].push(system_index);
+                                    self.system_dependencies[system_index]
+                                        .insert(earlier_system_index);
+                                }
+                            }
+                        }
+
+                        // systems explicitly ordered before this one must finish first, even if they are compatible
+                        if let Some(dependencies) = ordering_dependencies.get(system_index) {
+                            for earlier_system_index in dependencies.iter().cloned() {
+                                if earlier_system_index >= prepare_system_index_range.start
+                                    && !self.system_dependencies[system_index]
+                                        .contains(earlier_system_index)
                                 {
                                     self.system_dependents[earlier_system_index].push(system_index);
                                     self.system_dependencies[system_index]
@@ -324,137 +619,234 @@ impl ExecutorStage {
         }
     }
 
-    /// Runs the non-thread-local systems in the given prepared_system_range range
+    /// Clears executor state / fills it with new defaults after the schedule has changed.
+    /// This is mostly zeroing out a bunch of arrays parallel to the systems array. They will get
+    /// repopulated by prepare_to_next_thread_local() calls
+    fn reset(&mut self, systems: &[BoxedSystem]) {
+        self.system_dependencies.clear();
+        self.system_dependencies
+            .resize_with(systems.len(), || FixedBitSet::with_capacity(systems.len()));
+
+        self.system_dependency_count.clear();
+        self.system_dependency_count.resize(systems.len(), 0);
+
+        self.thread_local_system_indices = Vec::new();
+
+        self.system_dependents.clear();
+        self.system_dependents.resize(systems.len(), Vec::new());
+
+        self.ready_events.resize(systems.len(), None);
+        self.ready_events_of_dependents
+            .resize(systems.len(), Vec::new());
+
+        for (system_index, system) in systems.iter().enumerate() {
+            if system.thread_local_execution() == ThreadLocalExecution::Immediate {
+                #[cfg(feature = "trace")]
+                let system_span = info_span!("system", name = system.name().as_ref());
+                #[cfg(feature = "trace")]
+                let _system_guard = system_span.enter();
+
+                self.thread_local_system_indices.push(system_index);
+            }
+        }
+    }
+
+    /// Runs the thread local part of the systems that run at the next flush, like applying their commands
+    fn flush(
+        &self,
+        world: &mut World,
+        resources: &mut Resources,
+        systems: &mut [BoxedSystem],
+        timing: bool,
+        catch_panics: bool,
+        disabled_systems: &HashSet<SystemId>,
+    ) {
+        for system in systems.iter_mut() {
+            if disabled_systems.contains(&system.id()) {
+                continue;
+            }
+            match system.thread_local_execution() {
+                ThreadLocalExecution::NextFlush => {
+                    #[cfg(feature = "trace")]
+                    let system_span = info_span!("system", name = system.name().as_ref());
+                    #[cfg(feature = "trace")]
+                    let _system_guard = system_span.enter();
+                    run_guarded(catch_panics, system.as_mut(), |system| {
+                        run_thread_local_timed(system, timing, world, resources)
+                    });
+                }
+                ThreadLocalExecution::Immediate => { /* already ran */ }
+            }
+        }
+    }
+
+    /// Runs the non-thread-local systems in the given prepared_system_range range. When `timing` is set, the run time
+    /// of each system is recorded in the [SystemDiagnostics](super::SystemDiagnostics) resource. Systems in
+    /// `disabled_systems` don't run, but still notify their dependents, and so do systems that panic when
+    /// `catch_panics` is set.
+    #[allow(clippy::too_many_arguments)]
     pub fn run_systems(
         &self,
         world: &World,
         resources: &Resources,
-        systems: &mut [Box<dyn System>],
+        systems: &mut [BoxedSystem],
         prepared_system_range: Range<usize>,
         compute_pool: &TaskPool,
+        timing: bool,
+        catch_panics: bool,
+        disabled_systems: &HashSet<SystemId>,
     ) {
         // Generate tasks for systems in the given range and block until they are complete
         trace!("running systems {:?}", prepared_system_range);
-        compute_pool.scope(|scope| {
-            let start_system_index = prepared_system_range.start;
-            let mut system_index = start_system_index;
-            for system in &mut systems[prepared_system_range] {
-                trace!(
-                    "prepare {} {} with {} dependents and {} dependencies",
-                    system_index,
-                    system.name(),
-                    self.system_dependents[system_index].len(),
-                    self.system_dependencies[system_index].count_ones(..)
-                );
+        let run_times = compute_pool.scope(|scope| {
+            self.spawn_systems(
+                scope,
+                0,
+                world,
+                resources,
+                systems,
+                prepared_system_range,
+                timing,
+                catch_panics,
+                disabled_systems,
+            );
+        });
 
-                // This event will be awaited, preventing the task from starting until all
-                // our dependencies finish running
-                let ready_event = &self.ready_events[system_index];
-
-                // Clear any dependencies on systems before this range of systems. We know at this
-                // point everything before start_system_index is finished, and our ready_event did
-                // not exist to be decremented until we started processing this range
-                if start_system_index != 0 {
-                    if let Some(ready_event) = ready_event.as_ref() {
-                        for dependency in self.system_dependencies[system_index].ones() {
-                            if dependency < start_system_index {
-                                ready_event.decrement();
-                            }
+        // recorded once every task is done, so systems that read the diagnostics don't conflict with the recording
+        for (_, system_index, run_time) in run_times.into_iter().flatten() {
+            record_run_time(resources, systems[system_index].as_ref(), run_time);
+        }
+    }
+
+    /// Spawns a task on `scope` for each system in `prepared_system_range` that has to run. Tasks return the run time
+    /// of their system along with `stage_slot` when `timing` is set, which tells apart the systems of stages that run
+    /// in the same scope.
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_systems<'scope>(
+        &'scope self,
+        scope: &mut Scope<'scope, Option<(usize, usize, Duration)>>,
+        stage_slot: usize,
+        world: &'scope World,
+        resources: &'scope Resources,
+        systems: &'scope mut [BoxedSystem],
+        prepared_system_range: Range<usize>,
+        timing: bool,
+        catch_panics: bool,
+        disabled_systems: &HashSet<SystemId>,
+    ) {
+        let start_system_index = prepared_system_range.start;
+        let mut system_index = start_system_index;
+        for system in &mut systems[prepared_system_range] {
+            trace!(
+                "prepare {} {} with {} dependents and {} dependencies",
+                system_index,
+                system.name(),
+                self.system_dependents[system_index].len(),
+                self.system_dependencies[system_index].count_ones(..)
+            );
+
+            // This event will be awaited, preventing the task from starting until all
+            // our dependencies finish running
+            let ready_event = &self.ready_events[system_index];
+
+            // Clear any dependencies on systems before this range of systems. We know at this
+            // point everything before start_system_index is finished, and our ready_event did
+            // not exist to be decremented until we started processing this range
+            if start_system_index != 0 {
+                if let Some(ready_event) = ready_event.as_ref() {
+                    for dependency in self.system_dependencies[system_index].ones() {
+                        if dependency < start_system_index {
+                            ready_event.decrement();
                         }
                     }
                 }
+            }
 
-                let world_ref = &*world;
-                let resources_ref = &*resources;
-
-                let trigger_events = &self.ready_events_of_dependents[system_index];
+            let trigger_events = &self.ready_events_of_dependents[system_index];
+            let enabled = !disabled_systems.contains(&system.id()) && !system.can_skip();
 
-                // Verify that any dependent task has a > 0 count. If a dependent task has > 0
-                // count, then the current system we are starting now isn't blocking it from running
-                // as it should be. Failure here implies the sync primitives are not matching the
-                // intended schedule. This likely compiles out if trace/asserts are disabled but
-                // make it explicitly debug-only anyways
-                #[cfg(debug_assertions)]
+            // Verify that any dependent task has a > 0 count. If a dependent task has > 0
+            // count, then the current system we are starting now isn't blocking it from running
+            // as it should be. Failure here implies the sync primitives are not matching the
+            // intended schedule. This likely compiles out if trace/asserts are disabled but
+            // make it explicitly debug-only anyways
+            #[cfg(debug_assertions)]
+            {
+                let dependent_systems = &self.system_dependents[system_index];
+                debug_assert_eq!(trigger_events.len(), dependent_systems.len());
+                for (trigger_event, dependent_system_index) in
+                    trigger_events.iter().zip(dependent_systems)
                 {
-                    let dependent_systems = &self.system_dependents[system_index];
-                    debug_assert_eq!(trigger_events.len(), dependent_systems.len());
-                    for (trigger_event, dependent_system_index) in
-                        trigger_events.iter().zip(dependent_systems)
-                    {
-                        debug_assert!(
-                            *dependent_system_index < start_system_index || trigger_event.get() > 0
-                        );
-                    }
+                    debug_assert!(
+                        *dependent_system_index < start_system_index || trigger_event.get() > 0
+                    );
                 }
+            }
 
-                // Spawn the task
-                scope.spawn(async move {
-                    // Wait until our dependencies are done
-                    if let Some(ready_event) = ready_event {
-                        ready_event.listen().await;
-                    }
+            // A skipped system whose dependencies are all done can notify its dependents right away, which saves
+            // spawning a task that would do nothing else
+            let dependencies_done = match ready_event {
+                Some(ready_event) => ready_event.get() <= 0,
+                None => true,
+            };
+            if !enabled && dependencies_done {
+                for trigger_event in trigger_events {
+                    trigger_event.decrement();
+                }
+                system_index += 1;
+                continue;
+            }
 
-                    // Execute the system - in a scope to ensure the system lock is dropped before
-                    // triggering dependents
-                    {
-                        #[cfg(feature = "trace")]
-                        let system_span = info_span!("system", name = system.name().as_ref());
-                        #[cfg(feature = "trace")]
-                        let _system_guard = system_span.enter();
+            // Spawn the task
+            scope.spawn(async move {
+                // Wait until our dependencies are done
+                if let Some(ready_event) = ready_event {
+                    ready_event.listen().await;
+                }
 
-                        system.run(world_ref, resources_ref);
-                    }
+                // Execute the system - in a scope to ensure the system lock is dropped before
+                // triggering dependents
+                let mut run_time = None;
+                if enabled {
+                    #[cfg(feature = "trace")]
+                    let system_span = info_span!("system", name = system.name().as_ref());
+                    #[cfg(feature = "trace")]
+                    let _system_guard = system_span.enter();
 
-                    // Notify dependents that this task is done
-                    for trigger_event in trigger_events {
-                        trigger_event.decrement();
-                    }
-                });
-                system_index += 1;
-            }
-        });
+                    run_guarded(catch_panics, system.as_mut(), |system| {
+                        let start = if timing { Some(Instant::now()) } else { None };
+                        system.run((), world, resources);
+                        run_time = start.map(|start| (stage_slot, system_index, start.elapsed()));
+                    });
+                }
+
+                // Notify dependents that this task is done
+                for trigger_event in trigger_events {
+                    trigger_event.decrement();
+                }
+                run_time
+            });
+            system_index += 1;
+        }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn run(
         &mut self,
         world: &mut World,
         resources: &mut Resources,
-        systems: &mut [Box<dyn System>],
+        systems: &mut [BoxedSystem],
+        ordering_dependencies: &[Vec<usize>],
         schedule_changed: bool,
+        timing: bool,
+        catch_panics: bool,
+        disabled_systems: &HashSet<SystemId>,
     ) {
         let start_archetypes_generation = world.archetypes_generation();
         let compute_pool = resources.get_cloned::<ComputeTaskPool>().unwrap();
 
-        // if the schedule has changed, clear executor state / fill it with new defaults
-        // This is mostly zeroing out a bunch of arrays parallel to the systems array. They will get
-        // repopulated by prepare_to_next_thread_local() calls
         if schedule_changed {
-            self.system_dependencies.clear();
-            self.system_dependencies
-                .resize_with(systems.len(), || FixedBitSet::with_capacity(systems.len()));
-
-            self.system_dependency_count.clear();
-            self.system_dependency_count.resize(systems.len(), 0);
-
-            self.thread_local_system_indices = Vec::new();
-
-            self.system_dependents.clear();
-            self.system_dependents.resize(systems.len(), Vec::new());
-
-            self.ready_events.resize(systems.len(), None);
-            self.ready_events_of_dependents
-                .resize(systems.len(), Vec::new());
-
-            for (system_index, system) in systems.iter().enumerate() {
-                if system.thread_local_execution() == ThreadLocalExecution::Immediate {
-                    #[cfg(feature = "trace")]
-                    let system_span = info_span!("system", name = system.name().as_ref());
-                    #[cfg(feature = "trace")]
-                    let _system_guard = system_span.enter();
-
-                    self.thread_local_system_indices.push(system_index);
-                }
-            }
+            self.reset(systems);
         }
 
         // index of next thread local system in thread_local_system_indices. (always incremented by one
@@ -467,6 +859,7 @@ impl ExecutorStage {
             let prepared_system_range = self.prepare_to_next_thread_local(
                 world,
                 systems,
+                ordering_dependencies,
                 schedule_changed,
                 next_thread_local_index,
             );
@@ -477,7 +870,10 @@ impl ExecutorStage {
                 resources,
                 systems,
                 prepared_system_range,
-                &*compute_pool,
+                &compute_pool,
+                timing,
+                catch_panics,
+                disabled_systems,
             );
         }
 
@@ -493,14 +889,24 @@ impl ExecutorStage {
             {
                 // if a thread local system is ready to run, run it exclusively on the main thread
                 let system = systems[thread_local_system_index].as_mut();
+                if !disabled_systems.contains(&system.id()) && !system.can_skip() {
+                    #[cfg(feature = "trace")]
+                    let system_span =
+                        info_span!("thread_local_system", name = system.name().as_ref());
+                    #[cfg(feature = "trace")]
+                    let _system_guard = system_span.enter();
 
-                #[cfg(feature = "trace")]
-                let system_span = info_span!("thread_local_system", name = system.name().as_ref());
-                #[cfg(feature = "trace")]
-                let _system_guard = system_span.enter();
-
-                system.run(world, resources);
-                system.run_thread_local(world, resources);
+                    run_guarded(catch_panics, system, |system| {
+                        let start = if timing { Some(Instant::now()) } else { None };
+                        system.run((), world, resources);
+                        if let Some(start) = start {
+                            record_run_time(resources, system, start.elapsed());
+                        }
+                    });
+                    run_guarded(catch_panics, system, |system| {
+                        run_thread_local_timed(system, timing, world, resources)
+                    });
+                }
             }
 
             // Now that the previous thread local system has run, time to advance to the next one
@@ -511,6 +917,7 @@ impl ExecutorStage {
             let run_ready_system_index_range = self.prepare_to_next_thread_local(
                 world,
                 systems,
+                ordering_dependencies,
                 schedule_changed,
                 next_thread_local_index,
             );
@@ -520,23 +927,21 @@ impl ExecutorStage {
                 resources,
                 systems,
                 run_ready_system_index_range,
-                &*compute_pool,
+                &compute_pool,
+                timing,
+                catch_panics,
+                disabled_systems,
             );
         }
 
-        // "flush"
-        for system in systems.iter_mut() {
-            match system.thread_local_execution() {
-                ThreadLocalExecution::NextFlush => {
-                    #[cfg(feature = "trace")]
-                    let system_span = info_span!("system", name = system.name().as_ref());
-                    #[cfg(feature = "trace")]
-                    let _system_guard = system_span.enter();
-                    system.run_thread_local(world, resources);
-                }
-                ThreadLocalExecution::Immediate => { /* already ran */ }
-            }
-        }
+        self.flush(
+            world,
+            resources,
+            systems,
+            timing,
+            catch_panics,
+            disabled_systems,
+        );
 
         // If world's archetypes_generation is the same as it was before running any systems then
         // we can assume that all systems have correct archetype accesses.
@@ -548,18 +953,25 @@ impl ExecutorStage {
 
 #[cfg(test)]
 mod tests {
-    use super::ParallelExecutor;
+    use super::{ExecutionMode, ParallelExecutor};
     use crate::{
         resource::{Res, ResMut, Resources},
-        schedule::Schedule,
-        system::{IntoSystem, IntoThreadLocalSystem, Query},
+        schedule::{Schedule, SystemDescriptorCoercion},
+        system::{DynamicSystem, IntoSystem, IntoThreadLocalSystem, Query, ShouldRun},
         Commands,
     };
-    use bevy_hecs::{Entity, World};
-    use bevy_tasks::{ComputeTaskPool, TaskPool};
+    use bevy_hecs::{Entity, QueryAccess, World};
+    use bevy_tasks::{ComputeTaskPool, TaskPool, TaskPoolBuilder};
     use fixedbitset::FixedBitSet;
     use parking_lot::Mutex;
-    use std::{collections::HashSet, sync::Arc};
+    use std::{
+        any::TypeId,
+        collections::HashSet,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
 
     #[derive(Default)]
     struct CompletedSystems {
@@ -598,6 +1010,371 @@ mod tests {
         executor.run(&mut schedule, &mut world, &mut resources);
     }
 
+    #[test]
+    fn explicit_ordering_between_compatible_systems() {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+
+        // these systems don't access any data, so only their labels keep them from running in parallel
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let push = |name: &'static str| {
+            let order = order.clone();
+            move || order.lock().push(name)
+        };
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", push("third").system().after("second"));
+        schedule.add_system_to_stage(
+            "update",
+            push("second").system().label("second").after("first"),
+        );
+        schedule.add_system_to_stage("update", push("first").system().label("first"));
+
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+        for _ in 0..10 {
+            executor.run(&mut schedule, &mut world, &mut resources);
+            assert_eq!(
+                order.lock().drain(..).collect::<Vec<_>>(),
+                vec!["first", "second", "third"]
+            );
+        }
+    }
+
+    #[test]
+    fn resource_writers_never_run_in_parallel() {
+        #[derive(Default)]
+        struct Accessing(AtomicUsize);
+
+        fn access(accessing: &Accessing) {
+            assert_eq!(
+                accessing.0.fetch_add(1, Ordering::SeqCst),
+                0,
+                "another system is accessing the i32 resource"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            accessing.0.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        fn writer_a(accessing: Res<Accessing>, mut value: ResMut<i32>) {
+            access(&accessing);
+            *value += 1;
+        }
+
+        fn writer_b(accessing: Res<Accessing>, mut value: ResMut<i32>) {
+            access(&accessing);
+            *value *= 2;
+        }
+
+        fn reader(accessing: Res<Accessing>, _value: Res<i32>) {
+            access(&accessing);
+        }
+
+        fn unrelated(mut value: ResMut<u32>) {
+            *value += 1;
+        }
+
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+        resources.insert(Accessing::default());
+        resources.insert(0i32);
+        resources.insert(0u32);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", writer_a.system());
+        schedule.add_system_to_stage("update", unrelated.system());
+        schedule.add_system_to_stage("update", writer_b.system());
+        schedule.add_system_to_stage("update", reader.system());
+
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+        for _ in 0..10 {
+            executor.run(&mut schedule, &mut world, &mut resources);
+        }
+        assert_eq!(*resources.get::<i32>().unwrap(), 2046);
+        assert_eq!(*resources.get::<u32>().unwrap(), 10);
+
+        let dependencies = executor.stages[0]
+            .system_dependencies
+            .iter()
+            .map(|dependencies| dependencies.ones().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(dependencies, vec![vec![], vec![], vec![0], vec![0, 2]]);
+    }
+
+    #[test]
+    fn dynamic_system_conflicts() {
+        struct A(u32);
+        struct B;
+
+        fn write_a(mut query: Query<&mut A>) {
+            for mut a in query.iter_mut() {
+                a.0 *= 2;
+            }
+        }
+
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+        let entity = world.spawn((A(1), B));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage(
+            "update",
+            DynamicSystem::new("dynamic_write_a")
+                .with_query(QueryAccess::Write(TypeId::of::<A>(), "A"))
+                .build(|world| {
+                    for entity in world.query_entities(0).collect::<Vec<_>>() {
+                        let a = world.get_dynamic_mut(entity, TypeId::of::<A>()).unwrap();
+                        // SAFE: the pointer points to an A component
+                        unsafe { a.cast::<A>().as_mut().0 += 1 };
+                    }
+                }),
+        );
+        schedule.add_system_to_stage("update", write_a.system());
+        schedule.add_system_to_stage(
+            "update",
+            DynamicSystem::new("dynamic_read_b")
+                .with_query(QueryAccess::Read(TypeId::of::<B>(), "B"))
+                .build(|_world| {}),
+        );
+
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+        for _ in 0..3 {
+            executor.run(&mut schedule, &mut world, &mut resources);
+        }
+        // the static system always runs after the dynamic one, so A goes 1 -> 4 -> 10 -> 22
+        assert_eq!(world.get::<A>(entity).unwrap().0, 22);
+
+        let dependencies = executor.stages[0]
+            .system_dependencies
+            .iter()
+            .map(|dependencies| dependencies.ones().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(dependencies, vec![vec![], vec![0], vec![]]);
+    }
+
+    #[test]
+    fn serial_execution() {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        let order = Arc::new(Mutex::new(Vec::<usize>::new()));
+        resources.insert(order.clone());
+
+        // the systems only read resources, so they could all run at the same time in parallel mode
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("late");
+        schedule.add_system_to_stage(
+            "update",
+            (|order: Res<Arc<Mutex<Vec<usize>>>>| order.lock().push(0))
+                .system()
+                .label("first"),
+        );
+        for i in 1..8 {
+            schedule.add_system_to_stage(
+                "update",
+                (move |order: Res<Arc<Mutex<Vec<usize>>>>| order.lock().push(i)).system(),
+            );
+        }
+        schedule.add_system_to_stage(
+            "update",
+            (|order: Res<Arc<Mutex<Vec<usize>>>>| order.lock().push(8))
+                .system()
+                .before("first"),
+        );
+        schedule.add_system_to_stage(
+            "late",
+            (|commands: &mut Commands| {
+                commands.spawn((0u32,));
+            })
+            .system(),
+        );
+        schedule.add_system_to_stage(
+            "late",
+            (|query: Query<&u32>, order: Res<Arc<Mutex<Vec<usize>>>>| {
+                order.lock().push(query.iter().count() + 100)
+            })
+            .system(),
+        );
+        schedule.initialize(&mut world, &mut resources);
+
+        let mut executor = ParallelExecutor::without_tracker_clears();
+        executor.set_mode(ExecutionMode::Serial);
+        assert_eq!(executor.mode(), ExecutionMode::Serial);
+        for run in 0..3 {
+            executor.run(&mut schedule, &mut world, &mut resources);
+            assert_eq!(
+                order.lock().drain(..).collect::<Vec<_>>(),
+                vec![1, 2, 3, 4, 5, 6, 7, 8, 0, 100 + run]
+            );
+        }
+        assert!(
+            resources.get::<ComputeTaskPool>().is_none(),
+            "serial execution doesn't need a task pool"
+        );
+        assert_eq!(world.query::<&u32>().count(), 3);
+    }
+
+    #[test]
+    fn overlapping_stages_run_one_after_another() {
+        struct A(u32);
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let write_a = {
+            let order = order.clone();
+            move |mut query: Query<&mut A>| {
+                // gives the reader every chance to run first if the stages did run at the same time
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                for mut a in query.iter_mut() {
+                    a.0 += 1;
+                }
+                order.lock().push("write");
+            }
+        };
+        let read_a = {
+            let order = order.clone();
+            move |query: Query<&A>| {
+                assert!(query.iter().all(|a| a.0 > 0));
+                order.lock().push("read");
+            }
+        };
+
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(
+            TaskPoolBuilder::default().num_threads(2).build(),
+        ));
+        world.spawn((A(0),));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("write");
+        schedule.add_stage("read");
+        schedule.add_system_to_stage("write", write_a.system());
+        schedule.add_system_to_stage("read", read_a.system());
+
+        let mut executor = ParallelExecutor::default();
+        executor.set_stage_parallelism(true);
+        assert!(executor.stage_parallelism());
+        schedule.initialize(&mut world, &mut resources);
+        for _ in 0..5 {
+            executor.run(&mut schedule, &mut world, &mut resources);
+            assert_eq!(
+                order.lock().drain(..).collect::<Vec<_>>(),
+                vec!["write", "read"]
+            );
+        }
+    }
+
+    #[test]
+    fn disjoint_stages_run_together() {
+        #[derive(Default)]
+        struct Arrived(AtomicUsize);
+
+        // waits for the system of the other stage, which only arrives in time if both stages run at the same time
+        fn meet(arrived: &Arrived) -> bool {
+            arrived.0.fetch_add(1, Ordering::SeqCst);
+            let start = std::time::Instant::now();
+            while start.elapsed() < std::time::Duration::from_secs(5) {
+                if arrived.0.load(Ordering::SeqCst) >= 2 {
+                    return true;
+                }
+                std::thread::yield_now();
+            }
+            false
+        }
+
+        fn first(arrived: Res<Arrived>, mut met: ResMut<u32>, query: Query<&u32>) {
+            assert_eq!(query.iter().count(), 1);
+            if meet(&arrived) {
+                *met += 1;
+            }
+        }
+
+        fn second(arrived: Res<Arrived>, mut met: ResMut<i32>, query: Query<&i32>) {
+            assert_eq!(query.iter().count(), 1);
+            if meet(&arrived) {
+                *met += 1;
+            }
+        }
+
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(
+            TaskPoolBuilder::default().num_threads(2).build(),
+        ));
+        resources.insert(Arrived::default());
+        resources.insert(0u32);
+        resources.insert(0i32);
+        world.spawn((0u32,));
+        world.spawn((0i32,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("first");
+        schedule.add_stage("second");
+        schedule.add_system_to_stage("first", first.system());
+        schedule.add_system_to_stage("second", second.system());
+
+        let mut executor = ParallelExecutor::default();
+        executor.set_stage_parallelism(true);
+        schedule.initialize(&mut world, &mut resources);
+        for _ in 0..3 {
+            resources
+                .get_mut::<Arrived>()
+                .unwrap()
+                .0
+                .store(0, Ordering::SeqCst);
+            executor.run(&mut schedule, &mut world, &mut resources);
+        }
+        assert_eq!(*resources.get::<u32>().unwrap(), 3);
+        assert_eq!(*resources.get::<i32>().unwrap(), 3);
+    }
+
+    #[test]
+    fn stage_run_criteria() {
+        fn substeps(mut remaining: ResMut<u32>) -> ShouldRun {
+            if *remaining > 0 {
+                *remaining -= 1;
+                ShouldRun::YesAndCheckAgain
+            } else {
+                ShouldRun::No
+            }
+        }
+
+        fn count(mut count: ResMut<usize>, query: Query<&u32>) {
+            *count += query.iter().count();
+        }
+
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+        resources.insert(0u32);
+        resources.insert(0usize);
+        world.spawn((1u32,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", count.system());
+        schedule.set_stage_run_criteria("update", substeps);
+
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+        // the stage is skipped on the frame the schedule changed, and has to be prepared when it first runs
+        executor.run(&mut schedule, &mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 0);
+
+        *resources.get_mut::<u32>().unwrap() = 4;
+        executor.run(&mut schedule, &mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 4);
+        assert_eq!(*resources.get::<u32>().unwrap(), 0);
+    }
+
     #[test]
     fn intra_stage_archetype_change_prepare() {
         let mut world = World::new();
@@ -1,37 +1,114 @@
+use super::{
+    stage::run_systems_serially,
+    state::{run_in_state, run_state_drivers, StateDriver, StateSystems},
+    PanicPolicy, Stage, SystemDescriptor, SystemDiagnostics, SystemOrdering, SystemProfile,
+    SystemStage,
+};
 use crate::{
     resource::Resources,
-    system::{System, SystemId, ThreadLocalExecution},
+    system::{
+        BoxedSystem, CommandErrors, IntoSystem, QueryConflictError, ShouldRun, System, SystemId,
+    },
+};
+use bevy_hecs::{Component, World};
+use bevy_utils::{tracing::warn, HashMap, HashSet};
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    fmt::{self, Write},
+    hash::Hash,
 };
-use bevy_hecs::World;
-use bevy_utils::{HashMap, HashSet};
-use std::{borrow::Cow, fmt};
+use thiserror::Error;
 
-/// An ordered collection of stages, which each contain an ordered list of [System]s.
+/// An ordered collection of stages, which usually each contain an ordered list of [System]s.
 /// Schedules are essentially the "execution plan" for an App's systems.
 /// They are run on a given [World] and [Resources] reference.
 #[derive(Default)]
 pub struct Schedule {
-    pub(crate) stages: HashMap<Cow<'static, str>, Vec<Box<dyn System>>>,
+    pub(crate) stages: HashMap<Cow<'static, str>, Box<dyn Stage>>,
     pub(crate) stage_order: Vec<Cow<'static, str>>,
     pub(crate) system_ids: HashSet<SystemId>,
+    /// systems that were disabled with [Schedule::set_system_enabled] or [Schedule::set_label_enabled]
+    pub(crate) disabled_systems: HashSet<SystemId>,
+    system_orderings: HashMap<SystemId, SystemOrdering>,
+    /// for each system in a stage, the indices of the systems it was explicitly ordered after
+    pub(crate) stage_dependencies: HashMap<Cow<'static, str>, Vec<Vec<usize>>>,
+    pub(crate) stage_run_criteria: HashMap<Cow<'static, str>, BoxedSystem<(), ShouldRun>>,
+    pub(crate) state_drivers: HashMap<Cow<'static, str>, Vec<Box<dyn StateDriver>>>,
     generation: usize,
     last_initialize_generation: usize,
+    pub(crate) timing: bool,
+    pub(crate) catch_panics: bool,
 }
 
-impl fmt::Debug for Schedule {
+/// Every query conflict and system ordering cycle found while initializing a [Schedule]
+#[derive(Error, Debug)]
+pub struct ScheduleConflictError {
+    pub conflicts: Vec<QueryConflictError>,
+    pub cycles: Vec<DependencyCycleError>,
+}
+
+impl fmt::Display for ScheduleConflictError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "Schedule {{")?;
+        if !self.conflicts.is_empty() {
+            write!(
+                f,
+                "{} system(s) have conflicting queries:",
+                self.conflicts.len()
+            )?;
+            for conflict in self.conflicts.iter() {
+                write!(f, "\n    {}", conflict)?;
+            }
+        }
+        if !self.cycles.is_empty() {
+            if !self.conflicts.is_empty() {
+                f.write_char('\n')?;
+            }
+            write!(
+                f,
+                "{} stage(s) have system ordering cycles:",
+                self.cycles.len()
+            )?;
+            for cycle in self.cycles.iter() {
+                write!(f, "\n    {}", cycle)?;
+            }
+        }
+        Ok(())
+    }
+}
 
-        let stages = self
-            .stage_order
-            .iter()
-            .map(|s| (s, self.stages[s].iter().map(|s| (s.name(), s.id()))));
+/// An error that occurs when the before/after labels of systems in a stage depend on each other
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Systems in stage {stage} can't be ordered because they depend on each other: {}. Labels in the cycle: {}", .systems.join(", "), .labels.join(", "))]
+pub struct DependencyCycleError {
+    pub stage: Cow<'static, str>,
+    pub systems: Vec<Cow<'static, str>>,
+    pub labels: Vec<&'static str>,
+}
 
-        for (stage, syss) in stages {
-            writeln!(f, "    Stage \"{}\"", stage)?;
+/// An error returned by [Schedule::set_label_enabled] when no system in the schedule has the label
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("No system has the label: {0}")]
+pub struct UnknownLabelError(pub String);
 
-            for (name, id) in syss {
-                writeln!(f, "        System {{ name: \"{}\", id: {:?} }}", name, id)?;
+impl fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Schedule {{")?;
+
+        for stage_name in self.stage_order.iter() {
+            match self.get_stage::<SystemStage>(stage_name) {
+                Some(stage) => {
+                    writeln!(f, "    Stage \"{}\"", stage_name)?;
+                    for system in stage.systems.iter() {
+                        writeln!(
+                            f,
+                            "        System {{ name: \"{}\", id: {:?} }}",
+                            system.name(),
+                            system.id()
+                        )?;
+                    }
+                }
+                None => writeln!(f, "    Custom stage \"{}\"", stage_name)?,
             }
         }
 
@@ -40,96 +117,264 @@ impl fmt::Debug for Schedule {
 }
 
 impl Schedule {
+    /// Adds an empty [SystemStage] named `stage` after every other stage. Other kinds of [Stage] are added with
+    /// [Schedule::add_stage_with].
     pub fn add_stage(&mut self, stage: impl Into<Cow<'static, str>>) {
-        let stage: Cow<str> = stage.into();
-        if self.stages.get(&stage).is_some() {
-            panic!("Stage already exists: {}", stage);
-        } else {
-            self.stages.insert(stage.clone(), Vec::new());
-            self.stage_order.push(stage);
-        }
+        self.add_stage_with(stage, SystemStage::default());
+    }
+
+    /// Adds `stage` after every other stage. Unlike the stages added by [Schedule::add_stage], custom stages control
+    /// how their work runs. See [Stage] for more.
+    pub fn add_stage_with(&mut self, stage_name: impl Into<Cow<'static, str>>, stage: impl Stage) {
+        let index = self.stage_order.len();
+        self.insert_stage(index, stage_name.into(), Box::new(stage));
     }
 
+    /// Adds an empty [SystemStage] named `stage` right after the stage `target`. Panics if `target` doesn't exist or
+    /// if a stage named `stage` already does.
     pub fn add_stage_after(
         &mut self,
         target: impl Into<Cow<'static, str>>,
         stage: impl Into<Cow<'static, str>>,
     ) {
-        let target: Cow<str> = target.into();
-        let stage: Cow<str> = stage.into();
-        if self.stages.get(&stage).is_some() {
-            panic!("Stage already exists: {}", stage);
-        }
-
-        let target_index = self
-            .stage_order
-            .iter()
-            .enumerate()
-            .find(|(_i, stage)| **stage == target)
-            .map(|(i, _)| i)
-            .unwrap_or_else(|| panic!("Target stage does not exist: {}", target));
-
-        self.stages.insert(stage.clone(), Vec::new());
-        self.stage_order.insert(target_index + 1, stage);
+        let target_index = self.stage_index(target.into());
+        self.insert_stage(
+            target_index + 1,
+            stage.into(),
+            Box::new(SystemStage::default()),
+        );
     }
 
+    /// Adds an empty [SystemStage] named `stage` right before the stage `target`. Panics if `target` doesn't exist or
+    /// if a stage named `stage` already does.
     pub fn add_stage_before(
         &mut self,
         target: impl Into<Cow<'static, str>>,
         stage: impl Into<Cow<'static, str>>,
     ) {
-        let target: Cow<str> = target.into();
-        let stage: Cow<str> = stage.into();
-        if self.stages.get(&stage).is_some() {
-            panic!("Stage already exists: {}", stage);
+        let target_index = self.stage_index(target.into());
+        self.insert_stage(target_index, stage.into(), Box::new(SystemStage::default()));
+    }
+
+    fn stage_index(&self, target: Cow<'static, str>) -> usize {
+        self.stage_order
+            .iter()
+            .position(|stage| *stage == target)
+            .unwrap_or_else(|| panic!("Target stage does not exist: {}", target))
+    }
+
+    fn insert_stage(
+        &mut self,
+        index: usize,
+        stage_name: Cow<'static, str>,
+        mut stage: Box<dyn Stage>,
+    ) {
+        if self.stages.contains_key(&stage_name) {
+            panic!("Stage already exists: {}", stage_name);
         }
+        if let Some(system_stage) = stage.downcast_mut::<SystemStage>() {
+            for system in system_stage.systems.iter() {
+                self.register_system_id(system);
+            }
+        }
+        self.stages.insert(stage_name.clone(), stage);
+        self.stage_order.insert(index, stage_name);
+
+        self.generation += 1;
+    }
 
-        let target_index = self
-            .stage_order
+    /// Iterates over the names of the stages in the order they run in
+    pub fn stage_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.stage_order
             .iter()
-            .enumerate()
-            .find(|(_i, stage)| **stage == target)
-            .map(|(i, _)| i)
-            .unwrap_or_else(|| panic!("Target stage does not exist: {}", target));
+            .map(|stage_name| stage_name.as_ref())
+    }
+
+    /// Gets the stage named `stage_name` if it is a `T`
+    pub fn get_stage<T: Stage>(&self, stage_name: &str) -> Option<&T> {
+        self.stages
+            .get(stage_name)
+            .and_then(|stage| stage.downcast_ref())
+    }
 
-        self.stages.insert(stage.clone(), Vec::new());
-        self.stage_order.insert(target_index, stage);
+    /// Gets the stage named `stage_name` if it is a `T`. Systems added directly to a [SystemStage] this way aren't
+    /// initialized or sorted by the schedule, so prefer [Schedule::add_system_to_stage] for those.
+    pub fn get_stage_mut<T: Stage>(&mut self, stage_name: &str) -> Option<&mut T> {
+        self.stages
+            .get_mut(stage_name)
+            .and_then(|stage| stage.downcast_mut())
+    }
+
+    /// Registers `system` and returns the [SystemStage] it should be added to. Panics if the stage doesn't exist, or
+    /// if it is a custom stage.
+    fn prepare_system(
+        &mut self,
+        stage_name: Cow<'static, str>,
+        system: &BoxedSystem,
+        ordering: SystemOrdering,
+    ) -> &mut SystemStage {
+        if !self.stages.contains_key(&stage_name) {
+            panic!("Stage does not exist: {}", stage_name);
+        }
+        if self.get_stage::<SystemStage>(&stage_name).is_none() {
+            panic!(
+                "Stage {} is not a SystemStage, so systems can't be added to it",
+                stage_name
+            );
+        }
+        self.register_system_id(system);
+        if !ordering.is_empty() {
+            self.system_orderings.insert(system.id(), ordering);
+        }
+        self.generation += 1;
+        self.get_stage_mut(&stage_name).unwrap()
     }
 
     pub fn add_system_to_stage(
         &mut self,
         stage_name: impl Into<Cow<'static, str>>,
-        system: Box<dyn System>,
+        system: impl Into<SystemDescriptor>,
+    ) -> &mut Self {
+        let SystemDescriptor { system, ordering } = system.into();
+        self.prepare_system(stage_name.into(), &system, ordering)
+            .systems
+            .push(system);
+        self
+    }
+
+    pub fn add_system_to_stage_front(
+        &mut self,
+        stage_name: impl Into<Cow<'static, str>>,
+        system: impl Into<SystemDescriptor>,
+    ) -> &mut Self {
+        let SystemDescriptor { system, ordering } = system.into();
+        self.prepare_system(stage_name.into(), &system, ordering)
+            .systems
+            .insert(0, system);
+        self
+    }
+
+    /// Makes `stage` consult `run_criteria` every time the schedule runs. The stage is skipped when it returns
+    /// [ShouldRun::No], and runs again after [ShouldRun::YesAndCheckAgain] until the criteria returns something else.
+    pub fn set_stage_run_criteria<Params>(
+        &mut self,
+        stage_name: impl Into<Cow<'static, str>>,
+        run_criteria: impl IntoSystem<Params, ShouldRun>,
     ) -> &mut Self {
         let stage_name = stage_name.into();
-        let systems = self
-            .stages
-            .get_mut(&stage_name)
-            .unwrap_or_else(|| panic!("Stage does not exist: {}", stage_name));
-        if self.system_ids.contains(&system.id()) {
-            panic!(
-                "System with id {:?} ({}) already exists",
-                system.id(),
-                system.name()
-            );
+        if !self.stages.contains_key(&stage_name) {
+            panic!("Stage does not exist: {}", stage_name);
         }
-        self.system_ids.insert(system.id());
-        systems.push(system);
+        self.stage_run_criteria
+            .insert(stage_name, run_criteria.system());
 
         self.generation += 1;
         self
     }
 
-    pub fn add_system_to_stage_front(
+    /// Runs `system` at the start of `stage` whenever the [State](crate::State) changes to `state`, and the first
+    /// time the stage runs if `state` is the initial state
+    pub fn on_state_enter<T: Component + Clone + Eq + Hash>(
         &mut self,
         stage_name: impl Into<Cow<'static, str>>,
-        system: Box<dyn System>,
+        state: T,
+        system: BoxedSystem,
+    ) -> &mut Self {
+        self.register_system_id(&system);
+        self.state_systems_mut::<T>(stage_name.into())
+            .enter
+            .entry(state)
+            .or_default()
+            .push(system);
+
+        self.generation += 1;
+        self
+    }
+
+    /// Runs `system` in `stage` every time the stage runs while the [State](crate::State) is `state`
+    pub fn on_state_update<T: Component + Clone + Eq + Hash>(
+        &mut self,
+        stage_name: impl Into<Cow<'static, str>>,
+        state: T,
+        system: impl Into<SystemDescriptor>,
     ) -> &mut Self {
         let stage_name = stage_name.into();
-        let systems = self
-            .stages
-            .get_mut(&stage_name)
-            .unwrap_or_else(|| panic!("Stage does not exist: {}", stage_name));
+        // the stage's driver applies pending transitions, so it needs one even without enter or exit systems
+        self.state_systems_mut::<T>(stage_name.clone());
+        let SystemDescriptor { system, ordering } = system.into();
+        self.add_system_to_stage(
+            stage_name,
+            SystemDescriptor {
+                system: run_in_state(system, state),
+                ordering,
+            },
+        )
+    }
+
+    /// Runs `system` at the start of `stage` whenever the [State](crate::State) changes away from `state`
+    pub fn on_state_exit<T: Component + Clone + Eq + Hash>(
+        &mut self,
+        stage_name: impl Into<Cow<'static, str>>,
+        state: T,
+        system: BoxedSystem,
+    ) -> &mut Self {
+        self.register_system_id(&system);
+        self.state_systems_mut::<T>(stage_name.into())
+            .exit
+            .entry(state)
+            .or_default()
+            .push(system);
+
+        self.generation += 1;
+        self
+    }
+
+    /// Enables or disables the system with the id `system_id` without rebuilding the schedule. Panics if the system
+    /// isn't part of the schedule.
+    ///
+    /// A disabled system is skipped entirely: it isn't run, and its thread local work isn't applied. Because it
+    /// doesn't run, its [Commands](crate::Commands) don't accumulate while it is disabled. Systems stay enabled until
+    /// they are disabled, and the enter and exit systems of a [State](crate::State) always run.
+    pub fn set_system_enabled(&mut self, system_id: SystemId, enabled: bool) -> &mut Self {
+        if !self.system_ids.contains(&system_id) {
+            panic!("System does not exist: {:?}", system_id);
+        }
+        if enabled {
+            self.disabled_systems.remove(&system_id);
+        } else {
+            self.disabled_systems.insert(system_id);
+        }
+        self
+    }
+
+    /// Enables or disables every system with the label `label`. See [Schedule::set_system_enabled] for what disabling
+    /// a system does. Labels often come from user input, like a debug console, so an unknown label is returned as an
+    /// error instead of panicking, and leaves every system as it was.
+    pub fn set_label_enabled(
+        &mut self,
+        label: &str,
+        enabled: bool,
+    ) -> Result<(), UnknownLabelError> {
+        let system_ids = self
+            .system_orderings
+            .iter()
+            .filter(|(_, ordering)| ordering.labels.contains(&label))
+            .map(|(system_id, _)| *system_id)
+            .collect::<Vec<_>>();
+        if system_ids.is_empty() {
+            return Err(UnknownLabelError(label.to_string()));
+        }
+        for system_id in system_ids {
+            self.set_system_enabled(system_id, enabled);
+        }
+        Ok(())
+    }
+
+    pub fn is_system_enabled(&self, system_id: SystemId) -> bool {
+        !self.disabled_systems.contains(&system_id)
+    }
+
+    fn register_system_id(&mut self, system: &BoxedSystem) {
         if self.system_ids.contains(&system.id()) {
             panic!(
                 "System with id {:?} ({}) already exists",
@@ -138,70 +383,909 @@ impl Schedule {
             );
         }
         self.system_ids.insert(system.id());
-        systems.insert(0, system);
+    }
 
-        self.generation += 1;
-        self
+    fn state_systems_mut<T: Component + Clone + Eq + Hash>(
+        &mut self,
+        stage_name: Cow<'static, str>,
+    ) -> &mut StateSystems<T> {
+        if !self.stages.contains_key(&stage_name) {
+            panic!("Stage does not exist: {}", stage_name);
+        }
+        let drivers = self.state_drivers.entry(stage_name).or_default();
+        let index = match drivers
+            .iter_mut()
+            .position(|driver| driver.as_any_mut().is::<StateSystems<T>>())
+        {
+            Some(index) => index,
+            None => {
+                drivers.push(Box::new(StateSystems::<T>::default()));
+                drivers.len() - 1
+            }
+        };
+        drivers[index].as_any_mut().downcast_mut().unwrap()
     }
 
+    /// Runs every stage on the calling thread, one system at a time, in the order the systems are in after sorting
+    /// by labels. Trackers are cleared afterwards.
     pub fn run(&mut self, world: &mut World, resources: &mut Resources) {
+        CommandErrors::clear_in(resources);
+        self.run_stages(world, resources);
+        self.finish_timing_frame(resources);
+        world.clear_trackers();
+    }
+
+    /// Initializes any newly added systems and runs every stage once, like [Schedule::run], but leaves the trackers
+    /// alone. This is meant for running a schedule from inside another one, where clearing the trackers would hide
+    /// changes from the systems of the outer schedule that haven't run yet.
+    ///
+    /// Commands of the inner systems are applied at the end of each inner stage. A schedule can be stored as a
+    /// resource and run from an exclusive system by taking it out of the resources for the duration of the run:
+    /// ```
+    /// # use bevy_ecs::{Resources, Schedule, World};
+    /// fn resolve_turns(world: &mut World, resources: &mut Resources) {
+    ///     let mut turn_schedule = resources.remove::<Schedule>().unwrap();
+    ///     turn_schedule.run_once(world, resources);
+    ///     resources.insert(turn_schedule);
+    /// }
+    /// ```
+    pub fn run_once(&mut self, world: &mut World, resources: &mut Resources) {
+        self.initialize(world, resources);
+        self.run_stages(world, resources);
+    }
+
+    pub(crate) fn run_stages(&mut self, world: &mut World, resources: &mut Resources) {
+        self.prepare_timing(resources);
         for stage_name in self.stage_order.iter() {
-            if let Some(stage_systems) = self.stages.get_mut(stage_name) {
-                for system in stage_systems.iter_mut() {
-                    system.update(world);
-                    match system.thread_local_execution() {
-                        ThreadLocalExecution::NextFlush => system.run(world, resources),
-                        ThreadLocalExecution::Immediate => {
-                            system.run(world, resources);
-                            // NOTE: when this is made parallel a full sync is required here
-                            system.run_thread_local(world, resources);
-                        }
+            if let Some(stage) = self.stages.get_mut(stage_name) {
+                loop {
+                    let should_run = run_stage_criteria(
+                        self.stage_run_criteria.get_mut(stage_name),
+                        world,
+                        resources,
+                    );
+                    if should_run == ShouldRun::No {
+                        break;
                     }
-                }
 
-                // "flush"
-                // NOTE: when this is made parallel a full sync is required here
-                for system in stage_systems.iter_mut() {
-                    match system.thread_local_execution() {
-                        ThreadLocalExecution::NextFlush => {
-                            system.run_thread_local(world, resources)
-                        }
-                        ThreadLocalExecution::Immediate => { /* already ran immediate */ }
+                    run_state_drivers(self.state_drivers.get_mut(stage_name), world, resources);
+
+                    match stage.downcast_mut::<SystemStage>() {
+                        Some(stage) => run_systems_serially(
+                            &mut stage.systems,
+                            &self.disabled_systems,
+                            self.timing,
+                            self.catch_panics,
+                            world,
+                            resources,
+                        ),
+                        None => stage.run(world, resources),
+                    }
+
+                    if should_run == ShouldRun::Yes {
+                        break;
                     }
                 }
             }
         }
+    }
 
-        world.clear_trackers();
-        resources.clear_trackers();
+    /// Initializes any newly added systems and sorts each stage by its systems' labels. Panics if any system has
+    /// conflicting queries or if the systems in a stage are ordered in a cycle.
+    pub fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        if let Err(err) = self.try_initialize(world, resources) {
+            panic!("{}", err);
+        }
     }
 
+    /// Initializes any newly added systems and validates every system in the schedule, returning all of the
+    /// query conflicts and ordering cycles that were found
     // TODO: move this code to ParallelExecutor
-    pub fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+    pub fn try_initialize(
+        &mut self,
+        world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<(), ScheduleConflictError> {
         if self.last_initialize_generation == self.generation {
-            return;
+            return Ok(());
         }
 
         for stage in self.stages.values_mut() {
-            for system in stage.iter_mut() {
-                system.initialize(world, resources);
+            stage.initialize(world, resources);
+        }
+        for run_criteria in self.stage_run_criteria.values_mut() {
+            run_criteria.initialize(world, resources);
+        }
+        for driver in self.state_drivers.values_mut().flatten() {
+            driver.initialize(world, resources);
+        }
+
+        let mut cycles = Vec::new();
+        for stage_name in self.stage_order.iter() {
+            let systems = match self.stages.get_mut(stage_name).unwrap().downcast_mut() {
+                Some(SystemStage { systems }) => systems,
+                None => continue,
+            };
+            match sort_systems(stage_name.clone(), systems, &self.system_orderings) {
+                Ok(dependencies) => {
+                    self.stage_dependencies
+                        .insert(stage_name.clone(), dependencies);
+                }
+                Err(cycle) => {
+                    self.stage_dependencies.remove(stage_name);
+                    cycles.push(cycle);
+                }
             }
         }
 
-        self.last_initialize_generation = self.generation;
+        let mut conflicts = Vec::new();
+        self.run_on_systems(|system| {
+            if let Err(conflict) = system.validate(world) {
+                conflicts.push(conflict);
+            }
+        });
+        for run_criteria in self.stage_run_criteria.values() {
+            if let Err(conflict) = run_criteria.validate(world) {
+                conflicts.push(conflict);
+            }
+        }
+        for driver in self.state_drivers.values().flatten() {
+            driver.validate(world, &mut conflicts);
+        }
+
+        if conflicts.is_empty() && cycles.is_empty() {
+            // only skip validation next time once it has passed, so errors are reported until they're fixed
+            self.last_initialize_generation = self.generation;
+            Ok(())
+        } else {
+            Err(ScheduleConflictError { conflicts, cycles })
+        }
+    }
+
+    /// Measures how long each system of the schedule takes to run from now on, and records it in the
+    /// [SystemDiagnostics] resource, which is inserted the next time the schedule runs if it doesn't exist yet. Each
+    /// run of a system is measured with a single pair of [Instant::now] calls, so timing is cheap enough to leave on.
+    /// The [SystemProfile] resource is recorded too if it was inserted.
+    pub fn enable_timing(&mut self) -> &mut Self {
+        self.timing = true;
+        self
+    }
+
+    pub fn disable_timing(&mut self) -> &mut Self {
+        self.timing = false;
+        self
+    }
+
+    /// Sets what happens when a system of this schedule panics while it runs, or while its thread local work like
+    /// [Commands](crate::Commands) is applied. Defaults to [PanicPolicy::Propagate].
+    ///
+    /// [PanicPolicy::Continue] is meant for tools like editors that should survive a faulty system. A system can panic
+    /// halfway through its work, so the world may be left in a state the other systems don't expect.
+    pub fn set_panic_policy(&mut self, policy: PanicPolicy) -> &mut Self {
+        self.catch_panics = policy == PanicPolicy::Continue;
+        self
+    }
+
+    pub fn panic_policy(&self) -> PanicPolicy {
+        if self.catch_panics {
+            PanicPolicy::Continue
+        } else {
+            PanicPolicy::Propagate
+        }
+    }
+
+    /// Inserts the [SystemDiagnostics] resource if timing is enabled and it is missing
+    pub(crate) fn prepare_timing(&self, resources: &mut Resources) {
+        if self.timing && !resources.contains::<SystemDiagnostics>() {
+            resources.insert(SystemDiagnostics::default());
+        }
+    }
+
+    /// Ends the frame of the [SystemProfile] resource, if timing is enabled and it exists
+    pub(crate) fn finish_timing_frame(&self, resources: &Resources) {
+        if self.timing {
+            if let Some(mut profile) = resources.get_mut::<SystemProfile>() {
+                profile.finish_frame();
+            }
+        }
     }
 
     pub fn generation(&self) -> usize {
         self.generation
     }
 
-    pub fn run_on_systems(&mut self, mut func: impl FnMut(&mut dyn System)) {
+    /// Iterates over the id and name of every system in the schedule's [SystemStage]s, in stage order. Within a stage,
+    /// systems are in the order they were added in until the schedule is initialized, and in the order they run in
+    /// afterwards.
+    pub fn systems(&self) -> impl Iterator<Item = (SystemId, Cow<'static, str>)> + '_ {
+        self.stage_order
+            .iter()
+            .filter_map(move |stage_name| self.get_stage::<SystemStage>(stage_name))
+            .flat_map(|stage| stage.systems.iter())
+            .map(|system| (system.id(), system.name()))
+    }
+
+    /// Finds the id of the system named `name`, which is the type name of its function unless it was renamed with
+    /// [SystemNameExt::with_name](crate::SystemNameExt::with_name). Systems made from the same function share a name,
+    /// in which case the first one in [Schedule::systems] is returned.
+    ///
+    /// The id of a system never changes once it is added, so it can be kept to refer to the system later, for example
+    /// in [Schedule::set_system_enabled].
+    pub fn get_system_id(&self, name: &str) -> Option<SystemId> {
+        self.systems()
+            .find(|(_, system_name)| system_name == name)
+            .map(|(system_id, _)| system_id)
+    }
+
+    pub fn run_on_systems(&mut self, mut func: impl FnMut(&mut dyn System<In = (), Out = ()>)) {
         for stage_name in self.stage_order.iter() {
-            if let Some(stage_systems) = self.stages.get_mut(stage_name) {
-                for system in stage_systems.iter_mut() {
+            if let Some(stage) = self.stages.get_mut(stage_name).unwrap().downcast_mut() {
+                let SystemStage { systems } = stage;
+                for system in systems.iter_mut() {
                     func(&mut **system);
                 }
             }
         }
     }
 }
+
+/// Runs a stage's run criteria to completion, including its thread local work. Stages without run criteria always
+/// run once.
+pub(crate) fn run_stage_criteria(
+    run_criteria: Option<&mut BoxedSystem<(), ShouldRun>>,
+    world: &mut World,
+    resources: &mut Resources,
+) -> ShouldRun {
+    match run_criteria {
+        Some(run_criteria) => {
+            run_criteria.update(world);
+            let should_run = run_criteria
+                .run((), world, resources)
+                .unwrap_or(ShouldRun::No);
+            run_criteria.run_thread_local(world, resources);
+            should_run
+        }
+        None => ShouldRun::Yes,
+    }
+}
+
+/// Sorts the systems in a stage so that every system comes after the systems it is ordered after. Systems that
+/// aren't ordered relative to each other keep the order they were added in. Returns the indices of each system's
+/// explicit dependencies in the sorted list.
+fn sort_systems(
+    stage_name: Cow<'static, str>,
+    systems: &mut Vec<BoxedSystem>,
+    orderings: &HashMap<SystemId, SystemOrdering>,
+) -> Result<Vec<Vec<usize>>, DependencyCycleError> {
+    let no_ordering = SystemOrdering::default();
+    let system_orderings = systems
+        .iter()
+        .map(|system| orderings.get(&system.id()).unwrap_or(&no_ordering))
+        .collect::<Vec<_>>();
+
+    let mut label_indices = HashMap::<&'static str, Vec<usize>>::default();
+    for (index, ordering) in system_orderings.iter().enumerate() {
+        for label in ordering.labels.iter() {
+            label_indices
+                .entry(label)
+                .or_insert_with(Vec::new)
+                .push(index);
+        }
+    }
+
+    let mut dependencies = vec![Vec::new(); systems.len()];
+    for (index, ordering) in system_orderings.iter().enumerate() {
+        for label in ordering.after.iter() {
+            match label_indices.get(label) {
+                Some(after) => dependencies[index].extend(after.iter().filter(|i| **i != index)),
+                None => warn!(
+                    "System {} is ordered after label {}, which no system in stage {} has",
+                    systems[index].name(),
+                    label,
+                    stage_name
+                ),
+            }
+        }
+        for label in ordering.before.iter() {
+            match label_indices.get(label) {
+                Some(before) => {
+                    for before_index in before.iter().filter(|i| **i != index) {
+                        dependencies[*before_index].push(index);
+                    }
+                }
+                None => warn!(
+                    "System {} is ordered before label {}, which no system in stage {} has",
+                    systems[index].name(),
+                    label,
+                    stage_name
+                ),
+            }
+        }
+    }
+
+    let mut dependents = vec![Vec::new(); systems.len()];
+    let mut dependency_counts = Vec::with_capacity(systems.len());
+    for (index, system_dependencies) in dependencies.iter_mut().enumerate() {
+        system_dependencies.sort_unstable();
+        system_dependencies.dedup();
+        for dependency in system_dependencies.iter() {
+            dependents[*dependency].push(index);
+        }
+        dependency_counts.push(system_dependencies.len());
+    }
+
+    // always pick the earliest added system that is ready so unordered systems keep their order
+    let mut ready = (0..systems.len())
+        .filter(|index| dependency_counts[*index] == 0)
+        .collect::<BTreeSet<_>>();
+    let mut sorted = Vec::with_capacity(systems.len());
+    while let Some(index) = ready.iter().next().cloned() {
+        ready.remove(&index);
+        sorted.push(index);
+        for dependent in dependents[index].iter() {
+            dependency_counts[*dependent] -= 1;
+            if dependency_counts[*dependent] == 0 {
+                ready.insert(*dependent);
+            }
+        }
+    }
+
+    if sorted.len() < systems.len() {
+        // systems that are only ordered after a cycle can't be sorted either, but they aren't part of it
+        let cycle = (0..systems.len())
+            .filter(|index| dependency_counts[*index] > 0 && is_in_cycle(*index, &dependents))
+            .collect::<Vec<_>>();
+        let mut labels = Vec::new();
+        for index in cycle.iter() {
+            for label in system_orderings[*index].labels.iter() {
+                if !labels.contains(label) {
+                    labels.push(*label);
+                }
+            }
+        }
+        return Err(DependencyCycleError {
+            stage: stage_name,
+            systems: cycle.iter().map(|index| systems[*index].name()).collect(),
+            labels,
+        });
+    }
+
+    let mut sorted_indices = vec![0; systems.len()];
+    for (sorted_index, index) in sorted.iter().enumerate() {
+        sorted_indices[*index] = sorted_index;
+    }
+
+    let mut unsorted_systems = systems.drain(..).map(Some).collect::<Vec<_>>();
+    let mut sorted_dependencies = Vec::with_capacity(sorted.len());
+    for index in sorted {
+        systems.push(unsorted_systems[index].take().unwrap());
+        sorted_dependencies.push(
+            dependencies[index]
+                .iter()
+                .map(|dependency| sorted_indices[*dependency])
+                .collect(),
+        );
+    }
+
+    Ok(sorted_dependencies)
+}
+
+/// Returns whether the system at `index` is ordered after itself through `dependents`
+fn is_in_cycle(index: usize, dependents: &[Vec<usize>]) -> bool {
+    let mut visited = vec![false; dependents.len()];
+    let mut stack = dependents[index].clone();
+    while let Some(dependent) = stack.pop() {
+        if dependent == index {
+            return true;
+        }
+        if !visited[dependent] {
+            visited[dependent] = true;
+            stack.extend(dependents[dependent].iter());
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PanicPolicy, Schedule, UnknownLabelError};
+    use crate::{
+        resource::{Res, ResMut, Resources},
+        schedule::ParallelExecutor,
+        schedule::SystemDescriptorCoercion,
+        system::{
+            Commands, IntoSystem, IntoThreadLocalSystem, Query, ShouldRun, SystemId, SystemNameExt,
+        },
+    };
+    use bevy_hecs::World;
+    use bevy_tasks::{ComputeTaskPool, TaskPool};
+
+    fn first(mut order: ResMut<Vec<&'static str>>) {
+        order.push("first");
+    }
+
+    fn second(mut order: ResMut<Vec<&'static str>>) {
+        order.push("second");
+    }
+
+    fn third(mut order: ResMut<Vec<&'static str>>) {
+        order.push("third");
+    }
+
+    #[test]
+    fn system_ordering() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<&'static str>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", third.system().after("second"));
+        schedule.add_system_to_stage("update", second.system().label("second").after("first"));
+        schedule.add_system_to_stage("update", first.system().label("first"));
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(
+            *resources.get::<Vec<&'static str>>().unwrap(),
+            vec!["first", "second", "third", "first", "second", "third"]
+        );
+    }
+
+    #[test]
+    fn system_ordering_before() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<&'static str>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", second.system().label("second"));
+        schedule.add_system_to_stage("update", first.system().before("second"));
+        schedule.add_system_to_stage("update", third.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(
+            *resources.get::<Vec<&'static str>>().unwrap(),
+            vec!["first", "second", "third"]
+        );
+    }
+
+    #[test]
+    fn system_ordering_cycle() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<&'static str>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", first.system().label("first"));
+        schedule.add_system_to_stage("update", second.system().label("second").after("third"));
+        schedule.add_system_to_stage("update", third.system().label("third").after("second"));
+        schedule.add_system_to_stage("update", first.system().label("last").after("third"));
+
+        // the cycle keeps being reported, instead of the stage running with its ordering dropped
+        for _ in 0..2 {
+            let err = schedule
+                .try_initialize(&mut world, &mut resources)
+                .unwrap_err();
+            assert!(err.conflicts.is_empty());
+            assert_eq!(err.cycles.len(), 1);
+            assert_eq!(err.cycles[0].stage, "update");
+            let systems = &err.cycles[0].systems;
+            assert_eq!(systems.len(), 2);
+            assert!(systems[0].ends_with("::second"));
+            assert!(systems[1].ends_with("::third"));
+            assert_eq!(err.cycles[0].labels, vec!["second", "third"]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Labels in the cycle: input, movement")]
+    fn system_ordering_cycle_panics() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<&'static str>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", first.system().label("input").before("movement"));
+        schedule.add_system_to_stage("update", second.system().label("movement").before("input"));
+        schedule.initialize(&mut world, &mut resources);
+    }
+
+    #[test]
+    fn stage_insertion() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<&'static str>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("post_update");
+        schedule.add_stage_after("update", "physics");
+        schedule.add_stage_before("update", "pre_update");
+        schedule.add_stage_before("post_update", "late_physics");
+        schedule.add_stage_after("post_update", "last");
+        assert_eq!(
+            schedule.stage_names().collect::<Vec<_>>(),
+            vec![
+                "pre_update",
+                "update",
+                "physics",
+                "late_physics",
+                "post_update",
+                "last"
+            ]
+        );
+
+        // systems can be added to the inserted stages right away, and run in stage order
+        schedule.add_system_to_stage("last", third.system());
+        schedule.add_system_to_stage("physics", second.system());
+        schedule.add_system_to_stage("pre_update", first.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(
+            *resources.get::<Vec<&'static str>>().unwrap(),
+            vec!["first", "second", "third"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Target stage does not exist: missing")]
+    fn add_stage_after_missing_target() {
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage_after("missing", "physics");
+    }
+
+    #[test]
+    #[should_panic(expected = "Target stage does not exist: missing")]
+    fn add_stage_before_missing_target() {
+        let mut schedule = Schedule::default();
+        schedule.add_stage_before("missing", "physics");
+    }
+
+    #[test]
+    #[should_panic(expected = "Stage already exists: update")]
+    fn add_duplicate_stage() {
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("physics");
+        schedule.add_stage_after("physics", "update");
+    }
+
+    #[test]
+    fn stage_run_criteria() {
+        fn not_paused(paused: Res<bool>) -> ShouldRun {
+            if *paused {
+                ShouldRun::No
+            } else {
+                ShouldRun::Yes
+            }
+        }
+
+        fn substeps(mut remaining: ResMut<u32>) -> ShouldRun {
+            if *remaining > 0 {
+                *remaining -= 1;
+                ShouldRun::YesAndCheckAgain
+            } else {
+                ShouldRun::No
+            }
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<&'static str>::new());
+        resources.insert(false);
+        resources.insert(0u32);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("first");
+        schedule.add_stage("second");
+        schedule.add_stage("third");
+        schedule.add_system_to_stage("first", first.system());
+        schedule.add_system_to_stage("second", second.system());
+        schedule.add_system_to_stage("third", third.system());
+        schedule.set_stage_run_criteria("first", not_paused);
+        schedule.set_stage_run_criteria("second", substeps);
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(
+            resources
+                .get_mut::<Vec<&'static str>>()
+                .unwrap()
+                .drain(..)
+                .collect::<Vec<_>>(),
+            vec!["first", "third"]
+        );
+
+        *resources.get_mut::<bool>().unwrap() = true;
+        *resources.get_mut::<u32>().unwrap() = 3;
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(
+            resources
+                .get_mut::<Vec<&'static str>>()
+                .unwrap()
+                .drain(..)
+                .collect::<Vec<_>>(),
+            vec!["second", "second", "second", "third"]
+        );
+        assert_eq!(*resources.get::<u32>().unwrap(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Stage does not exist: update")]
+    fn stage_run_criteria_missing_stage() {
+        let mut schedule = Schedule::default();
+        schedule.set_stage_run_criteria("update", || ShouldRun::Yes);
+    }
+
+    fn toggled_schedule() -> (Schedule, SystemId) {
+        fn third_local(_world: &mut World, resources: &mut Resources) {
+            resources
+                .get_mut::<Vec<&'static str>>()
+                .unwrap()
+                .push("third");
+        }
+
+        fn spawn(commands: &mut Commands) {
+            commands.spawn((0u32,));
+        }
+
+        let second = second.system();
+        let second_id = second.id();
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", first.system().label("first"));
+        schedule.add_system_to_stage("update", second);
+        schedule.add_system_to_stage("update", spawn.system().label("spawn"));
+        schedule.add_system_to_stage("update", third_local.thread_local_system());
+        (schedule, second_id)
+    }
+
+    #[test]
+    fn disabled_systems() {
+        let (mut schedule, second_id) = toggled_schedule();
+        let mut executor = ParallelExecutor::default();
+        for parallel in [false, true].iter() {
+            let mut world = World::default();
+            let mut resources = Resources::default();
+            resources.insert(Vec::<&'static str>::new());
+            resources.insert(ComputeTaskPool(TaskPool::default()));
+            schedule.initialize(&mut world, &mut resources);
+            executor.initialize(&mut resources);
+            let mut run = |schedule: &mut Schedule| {
+                if *parallel {
+                    executor.run(schedule, &mut world, &mut resources);
+                } else {
+                    schedule.run(&mut world, &mut resources);
+                }
+                let mut order = resources.get_mut::<Vec<&'static str>>().unwrap();
+                (
+                    order.drain(..).collect::<Vec<_>>(),
+                    world.query::<&u32>().count(),
+                )
+            };
+
+            assert_eq!(run(&mut schedule), (vec!["first", "second", "third"], 1));
+
+            schedule.set_system_enabled(second_id, false);
+            schedule.set_label_enabled("spawn", false).unwrap();
+            assert!(!schedule.is_system_enabled(second_id));
+            assert_eq!(run(&mut schedule), (vec!["first", "third"], 1));
+            assert_eq!(run(&mut schedule), (vec!["first", "third"], 1));
+
+            // the commands of the spawn system didn't pile up while it was disabled
+            schedule.set_system_enabled(second_id, true);
+            schedule.set_label_enabled("spawn", true).unwrap();
+            assert_eq!(run(&mut schedule), (vec!["first", "second", "third"], 2));
+        }
+    }
+
+    #[test]
+    fn disable_missing_label() {
+        let (mut schedule, _) = toggled_schedule();
+        // labels typed into a debug console are only borrowed
+        let label = String::from("missing");
+        let err = schedule.set_label_enabled(&label, false).unwrap_err();
+        assert_eq!(err, UnknownLabelError("missing".to_string()));
+        assert_eq!(err.to_string(), "No system has the label: missing");
+    }
+
+    #[test]
+    fn nested_schedule() {
+        struct TurnSchedule(Schedule);
+
+        #[derive(Default)]
+        struct Turns {
+            started: usize,
+            resolved: usize,
+        }
+
+        fn start_turn(mut turns: ResMut<Turns>) {
+            turns.started += 1;
+        }
+
+        fn spawn_unit(commands: &mut Commands) {
+            commands.spawn((0u32,));
+        }
+
+        fn resolve_turn(mut turns: ResMut<Turns>, units: Query<&u32>) {
+            // commands of the inner schedule were applied at the end of its first stage
+            turns.resolved += units.iter().count();
+        }
+
+        fn run_turns(world: &mut World, resources: &mut Resources) {
+            let mut turn_schedule = resources.remove::<TurnSchedule>().unwrap();
+            for _ in 0..2 {
+                turn_schedule.0.run_once(world, resources);
+            }
+            resources.insert(turn_schedule);
+        }
+
+        fn count_units(units: Query<&u32>, mut counts: ResMut<Vec<usize>>) {
+            counts.push(units.iter().count());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Turns::default());
+        resources.insert(Vec::<usize>::new());
+
+        let mut turn_schedule = Schedule::default();
+        turn_schedule.add_stage("turn");
+        turn_schedule.add_stage("resolve");
+        turn_schedule.add_system_to_stage("turn", start_turn.system());
+        turn_schedule.add_system_to_stage("turn", spawn_unit.system());
+        turn_schedule.add_system_to_stage("resolve", resolve_turn.system());
+        // the inner schedule is initialized the first time it runs
+        resources.insert(TurnSchedule(turn_schedule));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", run_turns.system());
+        schedule.add_system_to_stage("update", count_units.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        let turns = resources.get::<Turns>().unwrap();
+        assert_eq!(turns.started, 2);
+        assert_eq!(turns.resolved, 1 + 2);
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn toggle_label_between_frames() {
+        fn ai(mut ticks: ResMut<u32>) {
+            *ticks += 1;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(0u32);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", ai.system().label("ai"));
+        schedule.initialize(&mut world, &mut resources);
+
+        let mut ticks = Vec::new();
+        for frame in 1..=3 {
+            schedule.set_label_enabled("ai", frame != 2).unwrap();
+            schedule.run(&mut world, &mut resources);
+            ticks.push(*resources.get::<u32>().unwrap());
+        }
+        assert_eq!(ticks, vec![1, 1, 2]);
+    }
+
+    fn faulty(mut frames: ResMut<u32>, commands: &mut Commands) {
+        *frames += 1;
+        commands.spawn((*frames,));
+        panic!("faulty system");
+    }
+
+    fn count(mut counted: ResMut<usize>) {
+        *counted += 1;
+    }
+
+    fn panicking_schedule() -> Schedule {
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", faulty.system());
+        schedule.add_system_to_stage("update", count.system());
+        schedule
+    }
+
+    #[test]
+    fn continue_after_panic() {
+        let mut schedule = panicking_schedule();
+        assert_eq!(schedule.panic_policy(), PanicPolicy::Propagate);
+        schedule.set_panic_policy(PanicPolicy::Continue);
+        assert_eq!(schedule.panic_policy(), PanicPolicy::Continue);
+
+        let mut executor = ParallelExecutor::default();
+        for parallel in [false, true].iter() {
+            let mut world = World::default();
+            let mut resources = Resources::default();
+            resources.insert(0u32);
+            resources.insert(0usize);
+            schedule.initialize(&mut world, &mut resources);
+            executor.initialize(&mut resources);
+            for _ in 0..2 {
+                if *parallel {
+                    executor.run(&mut schedule, &mut world, &mut resources);
+                } else {
+                    schedule.run(&mut world, &mut resources);
+                }
+            }
+
+            // the faulty system ran every frame, the resources it borrowed were released, and the other system ran too
+            assert_eq!(*resources.get::<u32>().unwrap(), 2);
+            assert_eq!(*resources.get::<usize>().unwrap(), 2);
+            // commands queued before the panic were applied
+            let mut spawned = world.query::<&u32>().copied().collect::<Vec<_>>();
+            spawned.sort_unstable();
+            assert_eq!(spawned, vec![1, 2]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "faulty system")]
+    fn propagate_panic() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(0u32);
+        resources.insert(0usize);
+        let mut schedule = panicking_schedule();
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+    }
+
+    #[test]
+    fn system_ids_by_name() {
+        fn physics(mut steps: ResMut<u32>) {
+            *steps += 1;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(0u32);
+        resources.insert(Vec::<&'static str>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("late");
+        schedule.add_system_to_stage("late", first.system().with_name("late_first"));
+        schedule.add_system_to_stage("update", physics.system());
+        schedule.add_system_to_stage("update", first.system());
+
+        let physics_name = "bevy_ecs::schedule::schedule::tests::system_ids_by_name::physics";
+        let physics_id = schedule.get_system_id(physics_name).unwrap();
+        let late_id = schedule.get_system_id("late_first").unwrap();
+        assert_ne!(physics_id, late_id);
+        assert_eq!(schedule.get_system_id("missing"), None);
+        let names = schedule.systems().map(|(_, name)| name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                physics_name,
+                "bevy_ecs::schedule::schedule::tests::first",
+                "late_first"
+            ]
+        );
+
+        // ids stay the same once the schedule is initialized and run
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(schedule.get_system_id(physics_name), Some(physics_id));
+        assert_eq!(schedule.get_system_id("late_first"), Some(late_id));
+
+        schedule.set_system_enabled(physics_id, false);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<u32>().unwrap(), 1);
+    }
+}
@@ -0,0 +1,321 @@
+use super::{
+    panic_policy::run_guarded,
+    system_diagnostics::{record_run_time, run_thread_local_timed},
+};
+use crate::{
+    resource::Resources,
+    system::{BoxedSystem, SystemId, ThreadLocalExecution},
+};
+use bevy_hecs::World;
+use bevy_utils::{tracing::warn, HashSet};
+use std::{any::Any, time::Instant};
+
+/// The number of times a [LoopStage] runs its stage in one run before it gives up on its condition
+pub const DEFAULT_MAX_LOOP_ITERATIONS: usize = 100;
+
+/// A step of a [Schedule](crate::Schedule). Stages run one after another, in the order they were added in.
+///
+/// Most stages are [SystemStage]s, which are what [Schedule::add_stage](crate::Schedule::add_stage) creates. Custom
+/// stages can be added with [Schedule::add_stage_with](crate::Schedule::add_stage_with) to control how and how often
+/// their work runs. Run criteria and [State](crate::State) systems set for a custom stage run around it just like
+/// they do for a [SystemStage].
+///
+/// The schedule only knows about the systems it runs itself. Systems inside a custom stage, including a [SystemStage]
+/// nested in a [LoopStage], can't be disabled with
+/// [Schedule::set_system_enabled](crate::Schedule::set_system_enabled), aren't timed, and always propagate their
+/// panics, whatever the schedule's [PanicPolicy](crate::PanicPolicy) is.
+pub trait Stage: AsAnyStage + Send + Sync {
+    /// Called by [Schedule::initialize](crate::Schedule::initialize) before the stage runs, and again every time
+    /// the schedule changes
+    fn initialize(&mut self, _world: &mut World, _resources: &mut Resources) {}
+
+    fn run(&mut self, world: &mut World, resources: &mut Resources);
+}
+
+/// Gives access to a [Stage] as [Any], so that it can be downcast to its concrete type. This is implemented for every
+/// type, so stages never have to implement it themselves.
+#[doc(hidden)]
+pub trait AsAnyStage: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAnyStage for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl dyn Stage {
+    pub fn downcast_ref<T: Stage>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    pub fn downcast_mut<T: Stage>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+}
+
+/// A stage that holds a list of systems. Within a [Schedule](crate::Schedule), its systems are sorted by their labels
+/// and run by the schedule's executor. Run on its own, for example inside a [LoopStage], it runs its systems one at a
+/// time in the order they were added in, with none of the schedule's per-system settings (see [Stage]). In both cases
+/// the commands of its systems are applied at the end of the stage.
+#[derive(Default)]
+pub struct SystemStage {
+    pub(crate) systems: Vec<BoxedSystem>,
+}
+
+impl SystemStage {
+    pub fn new(systems: Vec<BoxedSystem>) -> Self {
+        Self { systems }
+    }
+
+    /// Adds `system` to the end of the stage. Use
+    /// [Schedule::add_system_to_stage](crate::Schedule::add_system_to_stage) for stages that are already part of a
+    /// schedule.
+    pub fn add_system(&mut self, system: BoxedSystem) -> &mut Self {
+        self.systems.push(system);
+        self
+    }
+
+    pub fn systems(&self) -> &[BoxedSystem] {
+        &self.systems
+    }
+}
+
+impl Stage for SystemStage {
+    fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        for system in self.systems.iter_mut() {
+            system.initialize(world, resources);
+        }
+    }
+
+    fn run(&mut self, world: &mut World, resources: &mut Resources) {
+        run_systems_serially(
+            &mut self.systems,
+            &HashSet::default(),
+            false,
+            false,
+            world,
+            resources,
+        );
+    }
+}
+
+/// Runs `systems` one at a time on the calling thread, skipping the disabled ones, then applies their thread local
+/// work like commands. With `catch_panics`, a panicking system is logged and the others still run.
+pub(crate) fn run_systems_serially(
+    systems: &mut [BoxedSystem],
+    disabled_systems: &HashSet<SystemId>,
+    timing: bool,
+    catch_panics: bool,
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    for system in systems.iter_mut() {
+        if disabled_systems.contains(&system.id()) {
+            continue;
+        }
+        system.update(world);
+        if system.can_skip() {
+            continue;
+        }
+        run_guarded(catch_panics, system.as_mut(), |system| {
+            let start = if timing { Some(Instant::now()) } else { None };
+            system.run((), world, resources);
+            if let Some(start) = start {
+                record_run_time(resources, system, start.elapsed());
+            }
+        });
+        if system.thread_local_execution() == ThreadLocalExecution::Immediate {
+            // NOTE: when this is made parallel a full sync is required here
+            run_guarded(catch_panics, system.as_mut(), |system| {
+                run_thread_local_timed(system, timing, world, resources)
+            });
+        }
+    }
+
+    // "flush"
+    // NOTE: when this is made parallel a full sync is required here
+    for system in systems.iter_mut() {
+        if disabled_systems.contains(&system.id()) {
+            continue;
+        }
+        match system.thread_local_execution() {
+            ThreadLocalExecution::NextFlush => {
+                run_guarded(catch_panics, system.as_mut(), |system| {
+                    run_thread_local_timed(system, timing, world, resources)
+                });
+            }
+            ThreadLocalExecution::Immediate => { /* already ran immediate */ }
+        }
+    }
+}
+
+/// A stage that runs another stage repeatedly, for work that has to converge within a frame, like relaxing
+/// constraints. After every run of the inner stage, `condition` decides whether it runs again.
+///
+/// The inner stage runs at most `max_iterations` times per run of the loop stage, which defaults to
+/// [DEFAULT_MAX_LOOP_ITERATIONS]. Reaching the cap logs a warning, and the loop starts over the next time it runs.
+pub struct LoopStage {
+    stage: Box<dyn Stage>,
+    condition: Box<dyn FnMut(&Resources) -> bool + Send + Sync>,
+    max_iterations: usize,
+}
+
+impl LoopStage {
+    /// Runs `stage` again as long as `condition` returns true after a run
+    pub fn new(
+        stage: impl Stage,
+        condition: impl FnMut(&Resources) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            stage: Box::new(stage),
+            condition: Box::new(condition),
+            max_iterations: DEFAULT_MAX_LOOP_ITERATIONS,
+        }
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+}
+
+impl Stage for LoopStage {
+    fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        self.stage.initialize(world, resources);
+    }
+
+    fn run(&mut self, world: &mut World, resources: &mut Resources) {
+        for _ in 0..self.max_iterations {
+            self.stage.run(world, resources);
+            if !(self.condition)(resources) {
+                return;
+            }
+        }
+        warn!(
+            "Loop stage stopped after {} iterations without its condition being met",
+            self.max_iterations
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoopStage, Stage, SystemStage};
+    use crate::{
+        resource::{ResMut, Resources},
+        schedule::{ParallelExecutor, Schedule},
+        system::{IntoSystem, ShouldRun},
+        Commands,
+    };
+    use bevy_hecs::World;
+
+    /// Halves the error until it is small enough, without any systems
+    struct Relax;
+
+    impl Stage for Relax {
+        fn run(&mut self, _world: &mut World, resources: &mut Resources) {
+            let mut error = resources.get_mut::<f32>().unwrap();
+            while *error > 1.0 {
+                *error /= 2.0;
+            }
+        }
+    }
+
+    fn log(name: &'static str) -> impl FnMut(ResMut<Vec<&'static str>>) + Send + Sync + 'static {
+        move |mut log| log.push(name)
+    }
+
+    #[test]
+    fn custom_stage() {
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage_with("relax", Relax);
+        schedule.add_stage("late");
+        schedule.add_system_to_stage("update", log("update").system());
+        schedule.add_system_to_stage(
+            "late",
+            (|error: ResMut<f32>, mut log: ResMut<Vec<&'static str>>| {
+                log.push(if *error <= 1.0 { "relaxed" } else { "tense" })
+            })
+            .system(),
+        );
+        schedule.set_stage_run_criteria("relax", || ShouldRun::Yes);
+        assert!(schedule.get_stage::<Relax>("relax").is_some());
+        assert!(schedule.get_stage::<SystemStage>("relax").is_none());
+
+        let mut executor = ParallelExecutor::default();
+        for parallel in [false, true].iter() {
+            let mut world = World::default();
+            let mut resources = Resources::default();
+            resources.insert(Vec::<&'static str>::new());
+            resources.insert(100.0f32);
+            schedule.initialize(&mut world, &mut resources);
+            executor.initialize(&mut resources);
+            if *parallel {
+                executor.run(&mut schedule, &mut world, &mut resources);
+            } else {
+                schedule.run(&mut world, &mut resources);
+            }
+            assert_eq!(
+                *resources.get::<Vec<&'static str>>().unwrap(),
+                vec!["update", "relaxed"]
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Stage relax is not a SystemStage, so systems can't be added to it")]
+    fn add_system_to_custom_stage() {
+        let mut schedule = Schedule::default();
+        schedule.add_stage_with("relax", Relax);
+        schedule.add_system_to_stage("relax", log("relax").system());
+    }
+
+    #[test]
+    fn loop_stage() {
+        fn step(mut remaining: ResMut<u32>, mut steps: ResMut<usize>, commands: &mut Commands) {
+            *remaining = remaining.saturating_sub(1);
+            *steps += 1;
+            commands.spawn((0u8,));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(3u32);
+        resources.insert(0usize);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage_with(
+            "solve",
+            LoopStage::new(SystemStage::new(vec![step.system()]), |resources| {
+                *resources.get::<u32>().unwrap() > 0
+            }),
+        );
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        // the loop stopped once the condition was false, and the commands of every iteration were applied
+        assert_eq!(*resources.get::<usize>().unwrap(), 3);
+        assert_eq!(world.query::<&u8>().count(), 3);
+
+        // the condition is only checked after a run, so the stage runs at least once
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 4);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage_with(
+            "capped",
+            LoopStage::new(SystemStage::new(vec![step.system()]), |_| true).with_max_iterations(5),
+        );
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 9);
+    }
+}
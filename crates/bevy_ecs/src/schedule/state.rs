@@ -0,0 +1,478 @@
+use crate::{
+    resource::{Res, Resources},
+    system::{BoxedSystem, IntoSystem, QueryConflictError, RunCriteriaSystem, ShouldRun},
+};
+use bevy_hecs::{Component, World};
+use bevy_utils::HashMap;
+use std::{any::Any, hash::Hash};
+use thiserror::Error;
+
+/// A resource that holds the current value of a state machine, like the screen a game is on.
+///
+/// Systems added with [Schedule::on_state_enter](crate::Schedule::on_state_enter),
+/// [Schedule::on_state_update](crate::Schedule::on_state_update) and
+/// [Schedule::on_state_exit](crate::Schedule::on_state_exit) run depending on its value. Transitions requested with
+/// [State::set], [State::push] and [State::pop] are queued, and applied in order at the start of the next stage that
+/// has enter or exit systems for `T`. That stage then runs the exit systems of the old state followed by the enter
+/// systems of the new one for every transition, before any of its other systems run. Other stages with enter or exit
+/// systems for `T` only run theirs for the state they last saw and the current state, the next time they run, which
+/// is later in the same frame for the stages after it.
+///
+/// The states form a stack, and only the state on top of it is current. Pushing a state, like a pause menu, exits the
+/// state below it, which is entered again when the pushed state is popped.
+#[derive(Debug)]
+pub struct State<T> {
+    stack: Vec<T>,
+    previous: Option<T>,
+    pending: Vec<StateTransition<T>>,
+    /// the length of the stack once the pending transitions are applied
+    pending_len: usize,
+}
+
+#[derive(Debug)]
+enum StateTransition<T> {
+    Set(T),
+    Push(T),
+    Pop,
+}
+
+/// An error returned when requesting a [State] transition
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    #[error("The requested state is already the current state")]
+    AlreadyInState,
+    #[error("Another state transition is already pending")]
+    TransitionPending,
+    #[error("The last state on the stack can't be popped")]
+    CannotPopLast,
+}
+
+impl<T: Component + Clone + Eq> State<T> {
+    /// Starts the state machine in `initial`. The enter systems of `initial` run the first time their stage runs.
+    pub fn new(initial: T) -> Self {
+        Self {
+            stack: vec![initial],
+            previous: None,
+            pending: Vec::new(),
+            pending_len: 1,
+        }
+    }
+
+    pub fn current(&self) -> &T {
+        self.stack.last().unwrap()
+    }
+
+    /// The state before the last transition, if there has been one
+    pub fn previous(&self) -> Option<&T> {
+        self.previous.as_ref()
+    }
+
+    /// The state that the first pending transition will move to
+    pub fn next(&self) -> Option<&T> {
+        match self.pending.first()? {
+            StateTransition::Set(state) | StateTransition::Push(state) => Some(state),
+            StateTransition::Pop => self.stack.iter().rev().nth(1),
+        }
+    }
+
+    /// Every state on the stack, starting with the bottom one. The last state is the current one.
+    pub fn stack(&self) -> &[T] {
+        &self.stack
+    }
+
+    /// Requests a transition to `state`, unless another transition is already pending. This fails with
+    /// [StateError::TransitionPending] if a transition was already requested and hasn't been applied yet, and with
+    /// [StateError::AlreadyInState] if `state` is the current state. Use [State::set] to queue transitions instead.
+    pub fn set_next(&mut self, state: T) -> Result<(), StateError> {
+        if !self.pending.is_empty() {
+            return Err(StateError::TransitionPending);
+        }
+        if *self.current() == state {
+            return Err(StateError::AlreadyInState);
+        }
+        self.set(state);
+        Ok(())
+    }
+
+    /// Queues a transition that replaces the state on top of the stack with `state`
+    pub fn set(&mut self, state: T) {
+        self.pending.push(StateTransition::Set(state));
+    }
+
+    /// Queues a transition that puts `state` on top of the stack
+    pub fn push(&mut self, state: T) {
+        self.pending.push(StateTransition::Push(state));
+        self.pending_len += 1;
+    }
+
+    /// Queues a transition that removes the state on top of the stack, returning to the state below it. Fails with
+    /// [StateError::CannotPopLast] if the queued transitions would leave the stack empty.
+    pub fn pop(&mut self) -> Result<(), StateError> {
+        if self.pending_len == 1 {
+            return Err(StateError::CannotPopLast);
+        }
+        self.pending.push(StateTransition::Pop);
+        self.pending_len -= 1;
+        Ok(())
+    }
+
+    /// Applies every pending transition in order, and returns the states they moved to. Transitions to the state
+    /// that is already current are skipped.
+    fn apply_pending(&mut self) -> Vec<T> {
+        let mut entered = Vec::new();
+        for transition in std::mem::take(&mut self.pending) {
+            let previous = self.current().clone();
+            match transition {
+                StateTransition::Set(state) => *self.stack.last_mut().unwrap() = state,
+                StateTransition::Push(state) => self.stack.push(state),
+                StateTransition::Pop => {
+                    self.stack.pop();
+                }
+            }
+            if *self.current() != previous {
+                self.previous = Some(previous);
+                entered.push(self.current().clone());
+            }
+        }
+        entered
+    }
+}
+
+/// Runs the enter and exit systems for one state type at the start of a stage
+pub(crate) trait StateDriver: Send + Sync {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn initialize(&mut self, world: &mut World, resources: &mut Resources);
+    fn validate(&self, world: &World, conflicts: &mut Vec<QueryConflictError>);
+    fn run(&mut self, world: &mut World, resources: &mut Resources);
+}
+
+pub(crate) struct StateSystems<T> {
+    pub(crate) enter: HashMap<T, Vec<BoxedSystem>>,
+    pub(crate) exit: HashMap<T, Vec<BoxedSystem>>,
+    /// the state this stage last ran enter systems for
+    last_state: Option<T>,
+}
+
+impl<T> Default for StateSystems<T> {
+    fn default() -> Self {
+        Self {
+            enter: Default::default(),
+            exit: Default::default(),
+            last_state: None,
+        }
+    }
+}
+
+impl<T> StateSystems<T> {
+    fn systems(&self) -> impl Iterator<Item = &BoxedSystem> {
+        self.enter.values().chain(self.exit.values()).flatten()
+    }
+}
+
+impl<T: Component + Clone + Eq + Hash> StateDriver for StateSystems<T> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        for system in self
+            .enter
+            .values_mut()
+            .chain(self.exit.values_mut())
+            .flatten()
+        {
+            system.initialize(world, resources);
+        }
+    }
+
+    fn validate(&self, world: &World, conflicts: &mut Vec<QueryConflictError>) {
+        for system in self.systems() {
+            if let Err(conflict) = system.validate(world) {
+                conflicts.push(conflict);
+            }
+        }
+    }
+
+    fn run(&mut self, world: &mut World, resources: &mut Resources) {
+        let (entered, current) = match resources.get_mut::<State<T>>() {
+            Some(mut state) => (state.apply_pending(), state.current().clone()),
+            None => return,
+        };
+
+        for state in entered.into_iter().chain(std::iter::once(current)) {
+            if self.last_state.as_ref() == Some(&state) {
+                continue;
+            }
+            if let Some(systems) = self
+                .last_state
+                .take()
+                .and_then(|last_state| self.exit.get_mut(&last_state))
+            {
+                run_systems(systems, world, resources);
+            }
+            if let Some(systems) = self.enter.get_mut(&state) {
+                run_systems(systems, world, resources);
+            }
+            self.last_state = Some(state);
+        }
+    }
+}
+
+/// Runs systems one after another, applying each system's thread local work before the next one runs
+fn run_systems(systems: &mut [BoxedSystem], world: &mut World, resources: &mut Resources) {
+    for system in systems.iter_mut() {
+        system.update(world);
+        system.run((), world, resources);
+        system.run_thread_local(world, resources);
+    }
+}
+
+/// Applies pending transitions and runs the enter and exit systems of a stage
+pub(crate) fn run_state_drivers(
+    drivers: Option<&mut Vec<Box<dyn StateDriver>>>,
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    if let Some(drivers) = drivers {
+        for driver in drivers.iter_mut() {
+            driver.run(world, resources);
+        }
+    }
+}
+
+/// Skips `system` unless the [State] is `state`
+pub(crate) fn run_in_state<T: Component + Clone + Eq>(
+    system: BoxedSystem,
+    state: T,
+) -> BoxedSystem {
+    let run_criteria = (move |current: Option<Res<State<T>>>| match current {
+        Some(current) if *current.current() == state => ShouldRun::Yes,
+        _ => ShouldRun::No,
+    })
+    .system();
+    Box::new(RunCriteriaSystem::new(system, run_criteria))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{State, StateError};
+    use crate::{
+        resource::{Local, ResMut, Resources},
+        schedule::{ParallelExecutor, Schedule},
+        system::IntoSystem,
+    };
+    use bevy_hecs::World;
+
+    #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+    enum GameState {
+        Menu,
+        Loading,
+        InGame,
+    }
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    fn log(message: &'static str) -> impl FnMut(ResMut<Log>) + Send + Sync + 'static {
+        move |mut log: ResMut<Log>| log.0.push(message)
+    }
+
+    fn advance(mut state: ResMut<State<GameState>>, mut log: ResMut<Log>) {
+        let next = match state.current() {
+            GameState::Menu => GameState::Loading,
+            GameState::Loading => GameState::InGame,
+            GameState::InGame => return,
+        };
+        log.0.push("advance");
+        state.set_next(next).unwrap();
+    }
+
+    fn state_schedule() -> Schedule {
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("late");
+        schedule.on_state_enter("update", GameState::Menu, log("enter menu").system());
+        schedule.on_state_exit("update", GameState::Menu, log("exit menu").system());
+        schedule.on_state_enter("update", GameState::Loading, log("enter loading").system());
+        schedule.on_state_update("update", GameState::Loading, log("loading").system());
+        schedule.on_state_exit("update", GameState::Loading, log("exit loading").system());
+        schedule.on_state_enter("update", GameState::InGame, log("enter in game").system());
+        schedule.add_system_to_stage("late", advance.system());
+        schedule
+    }
+
+    fn run_frames(frames: usize, mut run: impl FnMut(&mut World, &mut Resources)) -> Resources {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(State::new(GameState::Menu));
+        resources.insert(Log::default());
+        for _ in 0..frames {
+            run(&mut world, &mut resources);
+            resources.get_mut::<Log>().unwrap().0.push("frame");
+        }
+        resources
+    }
+
+    #[test]
+    fn state_transitions() {
+        let expected = vec![
+            "enter menu",
+            "advance",
+            "frame",
+            "exit menu",
+            "enter loading",
+            "loading",
+            "advance",
+            "frame",
+            "exit loading",
+            "enter in game",
+            "frame",
+            "frame",
+        ];
+
+        let mut schedule = state_schedule();
+        let resources = run_frames(4, |world, resources| {
+            schedule.initialize(world, resources);
+            schedule.run(world, resources);
+        });
+        assert_eq!(resources.get::<Log>().unwrap().0, expected);
+        let state = resources.get::<State<GameState>>().unwrap();
+        assert_eq!(*state.current(), GameState::InGame);
+        assert_eq!(state.previous(), Some(&GameState::Loading));
+
+        let mut schedule = state_schedule();
+        let mut executor = ParallelExecutor::default();
+        let resources = run_frames(4, |world, resources| {
+            schedule.initialize(world, resources);
+            executor.initialize(resources);
+            executor.run(&mut schedule, world, resources);
+        });
+        assert_eq!(resources.get::<Log>().unwrap().0, expected);
+    }
+
+    #[test]
+    fn update_only_stage() {
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("late");
+        schedule.on_state_update("update", GameState::Menu, log("menu").system());
+        schedule.on_state_update("update", GameState::Loading, log("loading").system());
+        schedule.add_system_to_stage("late", advance.system());
+
+        let resources = run_frames(2, |world, resources| {
+            schedule.initialize(world, resources);
+            schedule.run(world, resources);
+        });
+        assert_eq!(
+            resources.get::<Log>().unwrap().0,
+            vec!["menu", "advance", "frame", "loading", "advance", "frame"]
+        );
+        assert_eq!(
+            *resources.get::<State<GameState>>().unwrap().current(),
+            GameState::Loading
+        );
+    }
+
+    #[test]
+    fn set_next() {
+        let mut state = State::new(GameState::Menu);
+        assert_eq!(
+            state.set_next(GameState::Menu),
+            Err(StateError::AlreadyInState)
+        );
+        assert_eq!(state.set_next(GameState::Loading), Ok(()));
+        assert_eq!(
+            state.set_next(GameState::InGame),
+            Err(StateError::TransitionPending)
+        );
+        assert_eq!(state.next(), Some(&GameState::Loading));
+
+        assert_eq!(state.apply_pending(), vec![GameState::Loading]);
+        assert_eq!(*state.current(), GameState::Loading);
+        assert_eq!(state.previous(), Some(&GameState::Menu));
+        assert_eq!(state.next(), None);
+        assert_eq!(state.set_next(GameState::InGame), Ok(()));
+    }
+
+    #[test]
+    fn state_stack() {
+        let mut state = State::new(GameState::InGame);
+        assert_eq!(state.pop(), Err(StateError::CannotPopLast));
+        state.push(GameState::Menu);
+        assert_eq!(state.next(), Some(&GameState::Menu));
+        state.set(GameState::Loading);
+        assert_eq!(state.pop(), Ok(()));
+        assert_eq!(state.pop(), Err(StateError::CannotPopLast));
+        assert_eq!(*state.current(), GameState::InGame);
+
+        assert_eq!(
+            state.apply_pending(),
+            vec![GameState::Menu, GameState::Loading, GameState::InGame]
+        );
+        assert_eq!(state.stack(), &[GameState::InGame]);
+        assert_eq!(state.previous(), Some(&GameState::Loading));
+
+        state.push(GameState::Menu);
+        state.apply_pending();
+        assert_eq!(state.stack(), &[GameState::InGame, GameState::Menu]);
+        assert_eq!(state.next(), None);
+        assert_eq!(state.pop(), Ok(()));
+        assert_eq!(state.next(), Some(&GameState::InGame));
+    }
+
+    #[test]
+    fn queued_transitions() {
+        fn pause(mut state: ResMut<State<GameState>>, mut frame: Local<usize>) {
+            *frame += 1;
+            match *frame {
+                // several transitions in one frame run every exit and enter system in order
+                1 => {
+                    state.set(GameState::Loading);
+                    state.set(GameState::InGame);
+                    state.push(GameState::Menu);
+                }
+                2 => state.pop().unwrap(),
+                _ => {}
+            }
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("late");
+        for (state, enter, exit) in [
+            (GameState::Menu, "enter menu", "exit menu"),
+            (GameState::Loading, "enter loading", "exit loading"),
+            (GameState::InGame, "enter in game", "exit in game"),
+        ]
+        .iter()
+        {
+            schedule.on_state_enter("update", *state, log(enter).system());
+            schedule.on_state_exit("update", *state, log(exit).system());
+        }
+        schedule.on_state_update("update", GameState::InGame, log("in game").system());
+        schedule.add_system_to_stage("late", pause.system());
+
+        let resources = run_frames(3, |world, resources| {
+            schedule.initialize(world, resources);
+            schedule.run(world, resources);
+        });
+        assert_eq!(
+            resources.get::<Log>().unwrap().0,
+            vec![
+                "enter menu",
+                "frame",
+                "exit menu",
+                "enter loading",
+                "exit loading",
+                "enter in game",
+                "exit in game",
+                "enter menu",
+                "frame",
+                "exit menu",
+                "enter in game",
+                "in game",
+                "frame",
+            ]
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use crate::system::BoxedSystem;
+
+/// A [System](crate::System) along with the labels that order it relative to the other systems in its stage.
+/// Boxed systems convert into descriptors, so they can be added to a [Schedule](crate::Schedule) directly.
+pub struct SystemDescriptor {
+    pub(crate) system: BoxedSystem,
+    pub(crate) ordering: SystemOrdering,
+}
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SystemOrdering {
+    pub(crate) labels: Vec<&'static str>,
+    pub(crate) before: Vec<&'static str>,
+    pub(crate) after: Vec<&'static str>,
+}
+
+impl SystemOrdering {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.before.is_empty() && self.after.is_empty()
+    }
+}
+
+impl From<BoxedSystem> for SystemDescriptor {
+    fn from(system: BoxedSystem) -> Self {
+        SystemDescriptor {
+            system,
+            ordering: SystemOrdering::default(),
+        }
+    }
+}
+
+/// Labels systems and orders them relative to other labeled systems in the same stage
+pub trait SystemDescriptorCoercion {
+    /// Adds a label that other systems can refer to in [before](Self::before) and [after](Self::after)
+    fn label(self, label: &'static str) -> SystemDescriptor;
+
+    /// Runs this system before every system with the given label
+    fn before(self, label: &'static str) -> SystemDescriptor;
+
+    /// Runs this system after every system with the given label
+    fn after(self, label: &'static str) -> SystemDescriptor;
+}
+
+impl SystemDescriptorCoercion for SystemDescriptor {
+    fn label(mut self, label: &'static str) -> SystemDescriptor {
+        self.ordering.labels.push(label);
+        self
+    }
+
+    fn before(mut self, label: &'static str) -> SystemDescriptor {
+        self.ordering.before.push(label);
+        self
+    }
+
+    fn after(mut self, label: &'static str) -> SystemDescriptor {
+        self.ordering.after.push(label);
+        self
+    }
+}
+
+impl SystemDescriptorCoercion for BoxedSystem {
+    fn label(self, label: &'static str) -> SystemDescriptor {
+        SystemDescriptor::from(self).label(label)
+    }
+
+    fn before(self, label: &'static str) -> SystemDescriptor {
+        SystemDescriptor::from(self).before(label)
+    }
+
+    fn after(self, label: &'static str) -> SystemDescriptor {
+        SystemDescriptor::from(self).after(label)
+    }
+}
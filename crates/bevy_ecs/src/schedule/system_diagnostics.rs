@@ -0,0 +1,415 @@
+use crate::{
+    resource::Resources,
+    system::{System, SystemId},
+};
+use bevy_hecs::World;
+use bevy_utils::HashMap;
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The number of frames a [SystemProfile] keeps by default
+pub const DEFAULT_PROFILE_FRAMES: usize = 60;
+
+/// How long one system took to run, across every run since timing was enabled
+#[derive(Debug, Clone)]
+pub struct SystemTiming {
+    pub name: Cow<'static, str>,
+    pub runs: u32,
+    pub total: Duration,
+    pub min: Duration,
+    pub max: Duration,
+    pub last: Duration,
+}
+
+impl SystemTiming {
+    fn new(name: Cow<'static, str>, duration: Duration) -> Self {
+        Self {
+            name,
+            runs: 1,
+            total: duration,
+            min: duration,
+            max: duration,
+            last: duration,
+        }
+    }
+
+    pub fn average(&self) -> Duration {
+        self.total / self.runs
+    }
+
+    fn add(&mut self, duration: Duration) {
+        self.runs += 1;
+        self.total += duration;
+        self.min = self.min.min(duration);
+        self.max = self.max.max(duration);
+        self.last = duration;
+    }
+}
+
+/// A resource holding the run times of the systems of every [Schedule](crate::Schedule) that has timing enabled with
+/// [Schedule::enable_timing](crate::Schedule::enable_timing).
+///
+/// Only [System::run] is timed, which leaves out the thread local work of a system, like applying its
+/// [Commands](crate::Commands). Run criteria and state transition systems aren't timed.
+#[derive(Debug, Default)]
+pub struct SystemDiagnostics {
+    timings: HashMap<SystemId, SystemTiming>,
+}
+
+impl SystemDiagnostics {
+    pub fn get(&self, id: SystemId) -> Option<&SystemTiming> {
+        self.timings.get(&id)
+    }
+
+    /// Gets the timing of the first system named `name`. Systems made from the same function share a name, so prefer
+    /// [SystemDiagnostics::get] when a function is added more than once.
+    pub fn get_by_name(&self, name: &str) -> Option<&SystemTiming> {
+        self.timings.values().find(|timing| timing.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SystemId, &SystemTiming)> {
+        self.timings.iter().map(|(id, timing)| (*id, timing))
+    }
+
+    /// Forgets every recorded run, for example to only measure the runs after a loading screen
+    pub fn clear(&mut self) {
+        self.timings.clear();
+    }
+
+    pub fn record(&mut self, system: &dyn System<In = (), Out = ()>, duration: Duration) {
+        match self.timings.get_mut(&system.id()) {
+            Some(timing) => timing.add(duration),
+            None => {
+                self.timings
+                    .insert(system.id(), SystemTiming::new(system.name(), duration));
+            }
+        }
+    }
+}
+
+/// The time one system spent in its last frames, kept by a [SystemProfile]. The time spent in [System::run] and the
+/// time spent in the system's thread local work, like applying its [Commands](crate::Commands), are kept separately.
+#[derive(Debug, Clone)]
+pub struct ProfiledSystem {
+    name: Cow<'static, str>,
+    /// the run time and thread local time of every finished frame the system ran in, oldest first
+    frames: VecDeque<(Duration, Duration)>,
+    /// the time spent in the current frame, if the system ran in it
+    current: Option<(Duration, Duration)>,
+}
+
+impl ProfiledSystem {
+    fn new(name: Cow<'static, str>) -> Self {
+        Self {
+            name,
+            frames: VecDeque::new(),
+            current: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of frames the averages are taken over
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The average time per frame spent in [System::run]
+    pub fn average(&self) -> Duration {
+        average(self.frames.iter().map(|(run, _)| *run))
+    }
+
+    pub fn max(&self) -> Duration {
+        self.frames
+            .iter()
+            .map(|(run, _)| *run)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The average time per frame spent in the system's thread local work
+    pub fn thread_local_average(&self) -> Duration {
+        average(self.frames.iter().map(|(_, thread_local)| *thread_local))
+    }
+
+    pub fn thread_local_max(&self) -> Duration {
+        self.frames
+            .iter()
+            .map(|(_, thread_local)| *thread_local)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+fn average(durations: impl ExactSizeIterator<Item = Duration>) -> Duration {
+    match durations.len() {
+        0 => Duration::default(),
+        frames => durations.sum::<Duration>() / frames as u32,
+    }
+}
+
+/// A resource holding the time each system spent per frame over the last few frames, for finding the systems behind
+/// frame spikes. Like [SystemDiagnostics], it is only recorded by schedules that have timing enabled with
+/// [Schedule::enable_timing](crate::Schedule::enable_timing), but it isn't inserted automatically: insert it to
+/// start profiling.
+///
+/// A frame ends every time [Schedule::run](crate::Schedule::run) or
+/// [ParallelExecutor::run](crate::ParallelExecutor::run) finishes. A system that runs several times in a frame, for
+/// example in a stage with a fixed timestep, has the time of all of those runs added up.
+#[derive(Debug)]
+pub struct SystemProfile {
+    max_frames: usize,
+    systems: HashMap<SystemId, ProfiledSystem>,
+}
+
+impl Default for SystemProfile {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROFILE_FRAMES)
+    }
+}
+
+impl SystemProfile {
+    /// Keeps the times of the last `max_frames` frames that each system ran in
+    pub fn new(max_frames: usize) -> Self {
+        Self {
+            max_frames,
+            systems: HashMap::default(),
+        }
+    }
+
+    pub fn get(&self, id: SystemId) -> Option<&ProfiledSystem> {
+        self.systems.get(&id)
+    }
+
+    /// Iterates over the name, average run time and maximum run time of every profiled system, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Duration, Duration)> {
+        self.systems
+            .values()
+            .map(|system| (system.name(), system.average(), system.max()))
+    }
+
+    pub fn systems(&self) -> impl Iterator<Item = (SystemId, &ProfiledSystem)> {
+        self.systems.iter().map(|(id, system)| (*id, system))
+    }
+
+    pub fn clear(&mut self) {
+        self.systems.clear();
+    }
+
+    fn current_frame(
+        &mut self,
+        system: &dyn System<In = (), Out = ()>,
+    ) -> &mut (Duration, Duration) {
+        self.systems
+            .entry(system.id())
+            .or_insert_with(|| ProfiledSystem::new(system.name()))
+            .current
+            .get_or_insert_with(Default::default)
+    }
+
+    pub fn record_run(&mut self, system: &dyn System<In = (), Out = ()>, duration: Duration) {
+        self.current_frame(system).0 += duration;
+    }
+
+    pub fn record_thread_local(
+        &mut self,
+        system: &dyn System<In = (), Out = ()>,
+        duration: Duration,
+    ) {
+        self.current_frame(system).1 += duration;
+    }
+
+    /// Ends the current frame, dropping the oldest frame of a system once it has more than the maximum
+    pub fn finish_frame(&mut self) {
+        for system in self.systems.values_mut() {
+            if let Some(frame) = system.current.take() {
+                system.frames.push_back(frame);
+                if system.frames.len() > self.max_frames {
+                    system.frames.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// Records a run of `system` in the [SystemDiagnostics] and [SystemProfile] resources, if they exist
+pub(crate) fn record_run_time(
+    resources: &Resources,
+    system: &dyn System<In = (), Out = ()>,
+    duration: Duration,
+) {
+    if let Some(mut diagnostics) = resources.get_mut::<SystemDiagnostics>() {
+        diagnostics.record(system, duration);
+    }
+    if let Some(mut profile) = resources.get_mut::<SystemProfile>() {
+        profile.record_run(system, duration);
+    }
+}
+
+/// Runs the thread local work of `system`. When `timing` is set, its duration is recorded in the [SystemProfile]
+/// resource, if it exists.
+pub(crate) fn run_thread_local_timed(
+    system: &mut dyn System<In = (), Out = ()>,
+    timing: bool,
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    let start = if timing { Some(Instant::now()) } else { None };
+    system.run_thread_local(world, resources);
+    if let Some(start) = start {
+        if let Some(mut profile) = resources.get_mut::<SystemProfile>() {
+            profile.record_thread_local(system, start.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SystemDiagnostics, SystemProfile};
+    use crate::{
+        resource::Resources,
+        schedule::{ParallelExecutor, Schedule},
+        system::{Commands, IntoSystem},
+    };
+    use bevy_hecs::World;
+    use std::time::Duration;
+
+    fn sleep() {
+        std::thread::sleep(Duration::from_millis(2));
+    }
+
+    fn empty() {}
+
+    fn timed_schedule() -> Schedule {
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", sleep.system());
+        schedule.add_system_to_stage("update", empty.system());
+        schedule
+    }
+
+    fn check_timings(resources: &Resources, runs: u32) {
+        let diagnostics = resources.get::<SystemDiagnostics>().unwrap();
+        assert_eq!(diagnostics.iter().count(), 2);
+        for (id, timing) in diagnostics.iter() {
+            assert_eq!(timing.runs, runs);
+            assert!(timing.min <= timing.average() && timing.average() <= timing.max);
+            assert!(timing.min <= timing.last && timing.last <= timing.max);
+            assert_eq!(diagnostics.get(id).unwrap().name, timing.name);
+        }
+
+        let sleep_timing = diagnostics
+            .get_by_name("bevy_ecs::schedule::system_diagnostics::tests::sleep")
+            .unwrap();
+        assert!(sleep_timing.min >= Duration::from_millis(2));
+        assert!(sleep_timing.total >= Duration::from_millis(2) * runs);
+    }
+
+    #[test]
+    fn schedule_timing() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut schedule = timed_schedule();
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert!(
+            !resources.contains::<SystemDiagnostics>(),
+            "timing is disabled by default"
+        );
+
+        schedule.enable_timing();
+        for _ in 0..3 {
+            schedule.run(&mut world, &mut resources);
+        }
+        check_timings(&resources, 3);
+
+        schedule.disable_timing();
+        schedule.run(&mut world, &mut resources);
+        check_timings(&resources, 3);
+    }
+
+    #[test]
+    fn parallel_executor_timing() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut schedule = timed_schedule();
+        schedule.enable_timing();
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+        executor.initialize(&mut resources);
+        for _ in 0..3 {
+            executor.run(&mut schedule, &mut world, &mut resources);
+        }
+        check_timings(&resources, 3);
+
+        resources.get_mut::<SystemDiagnostics>().unwrap().clear();
+        executor.run(&mut schedule, &mut world, &mut resources);
+        check_timings(&resources, 1);
+    }
+
+    #[test]
+    fn system_profile() {
+        fn slow() {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        fn spawn(commands: &mut Commands) {
+            commands.spawn((0u32,));
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", slow.system());
+        schedule.add_system_to_stage("update", empty.system());
+        schedule.add_system_to_stage("update", spawn.system());
+        schedule.enable_timing();
+
+        let mut executor = ParallelExecutor::default();
+        for parallel in [false, true].iter() {
+            let mut world = World::default();
+            let mut resources = Resources::default();
+            resources.insert(SystemProfile::new(2));
+            schedule.initialize(&mut world, &mut resources);
+            executor.initialize(&mut resources);
+            for _ in 0..3 {
+                if *parallel {
+                    executor.run(&mut schedule, &mut world, &mut resources);
+                } else {
+                    schedule.run(&mut world, &mut resources);
+                }
+            }
+
+            let profile = resources.get::<SystemProfile>().unwrap();
+            let (_, slow_average, slow_max) = profile
+                .iter()
+                .find(|(name, _, _)| name.ends_with("::slow"))
+                .unwrap();
+            let (_, empty_average, empty_max) = profile
+                .iter()
+                .find(|(name, _, _)| name.ends_with("::empty"))
+                .unwrap();
+            assert!(slow_average >= Duration::from_millis(5));
+            assert!(slow_average > empty_average);
+            assert!(slow_max > empty_max);
+
+            for (_, system) in profile.systems() {
+                // only the last 2 frames are kept
+                assert_eq!(system.frames(), 2);
+                assert!(system.average() <= system.max());
+                assert!(system.thread_local_average() <= system.thread_local_max());
+            }
+            // applying commands is attributed to the thread local time of the system that queued them
+            let spawn = profile
+                .systems()
+                .find(|(_, system)| system.name().ends_with("::spawn"))
+                .unwrap()
+                .1;
+            assert!(spawn.thread_local_max() > Duration::default());
+        }
+    }
+}
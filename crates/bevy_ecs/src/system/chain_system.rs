@@ -0,0 +1,190 @@
+use crate::{
+    resource::Resources,
+    system::{BoxedSystem, QueryConflictError, System, SystemId, ThreadLocalExecution},
+};
+use bevy_hecs::{ArchetypeComponent, TypeAccess, World};
+use std::{any::TypeId, borrow::Cow};
+
+/// A [System] that runs `system_a` and passes its output into `system_b`.
+///
+/// `system_b` is skipped whenever `system_a` is skipped. The chain's accesses are the union of both systems' accesses.
+pub struct ChainSystem<In, Middle, Out> {
+    system_a: BoxedSystem<In, Middle>,
+    system_b: BoxedSystem<Middle, Out>,
+    name: Cow<'static, str>,
+    id: SystemId,
+    archetype_component_access: TypeAccess<ArchetypeComponent>,
+    resource_access: TypeAccess<TypeId>,
+}
+
+impl<In, Middle, Out> ChainSystem<In, Middle, Out> {
+    pub fn new(system_a: BoxedSystem<In, Middle>, system_b: BoxedSystem<Middle, Out>) -> Self {
+        Self {
+            name: format!("Chain({}, {})", system_a.name(), system_b.name()).into(),
+            id: SystemId::new(),
+            system_a,
+            system_b,
+            archetype_component_access: Default::default(),
+            resource_access: Default::default(),
+        }
+    }
+
+    fn update_access(&mut self) {
+        self.archetype_component_access.clear();
+        self.archetype_component_access
+            .union(self.system_a.archetype_component_access());
+        self.archetype_component_access
+            .union(self.system_b.archetype_component_access());
+        self.resource_access.clear();
+        self.resource_access.union(self.system_a.resource_access());
+        self.resource_access.union(self.system_b.resource_access());
+    }
+}
+
+impl<In: 'static, Middle: 'static, Out: 'static> System for ChainSystem<In, Middle, Out> {
+    type In = In;
+    type Out = Out;
+
+    fn name(&self) -> Cow<'static, str> {
+        self.name.clone()
+    }
+
+    fn set_name(&mut self, name: Cow<'static, str>) {
+        self.name = name;
+    }
+
+    fn id(&self) -> SystemId {
+        self.id
+    }
+
+    fn is_initialized(&self) -> bool {
+        self.system_a.is_initialized() && self.system_b.is_initialized()
+    }
+
+    fn update(&mut self, world: &World) {
+        self.system_a.update(world);
+        self.system_b.update(world);
+        self.update_access();
+    }
+
+    fn validate(&self, world: &World) -> Result<(), QueryConflictError> {
+        self.system_a.validate(world)?;
+        self.system_b.validate(world)
+    }
+
+    fn archetype_component_access(&self) -> &TypeAccess<ArchetypeComponent> {
+        &self.archetype_component_access
+    }
+
+    fn resource_access(&self) -> &TypeAccess<TypeId> {
+        &self.resource_access
+    }
+
+    fn thread_local_execution(&self) -> ThreadLocalExecution {
+        if self.system_a.thread_local_execution() == ThreadLocalExecution::Immediate
+            || self.system_b.thread_local_execution() == ThreadLocalExecution::Immediate
+        {
+            ThreadLocalExecution::Immediate
+        } else {
+            ThreadLocalExecution::NextFlush
+        }
+    }
+
+    fn run(&mut self, input: In, world: &World, resources: &Resources) -> Option<Out> {
+        let middle = self.system_a.run(input, world, resources)?;
+        self.system_b.run(middle, world, resources)
+    }
+
+    fn run_thread_local(&mut self, world: &mut World, resources: &mut Resources) {
+        self.system_a.run_thread_local(world, resources);
+        self.system_b.run_thread_local(world, resources);
+    }
+
+    fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        self.system_a.initialize(world, resources);
+        self.system_b.initialize(world, resources);
+        self.update_access();
+    }
+}
+
+/// Chains boxed [System]s together
+pub trait ChainSystemExt<In, Middle> {
+    /// Passes the output of this system into `system`, which takes it as an [In](crate::In) parameter
+    fn chain<Out: 'static>(self, system: BoxedSystem<Middle, Out>) -> BoxedSystem<In, Out>;
+}
+
+impl<In: 'static, Middle: 'static> ChainSystemExt<In, Middle> for BoxedSystem<In, Middle> {
+    fn chain<Out: 'static>(self, system: BoxedSystem<Middle, Out>) -> BoxedSystem<In, Out> {
+        Box::new(ChainSystem::new(self, system))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChainSystemExt;
+    use crate::{
+        resource::{Res, ResMut, Resources},
+        schedule::Schedule,
+        In, IntoSystem,
+    };
+    use bevy_hecs::World;
+    use std::num::ParseIntError;
+
+    fn parse(input: Res<&'static str>) -> Result<usize, ParseIntError> {
+        input.parse()
+    }
+
+    fn log_errors(
+        In(result): In<Result<usize, ParseIntError>>,
+        mut log: ResMut<Vec<String>>,
+        mut total: ResMut<usize>,
+    ) {
+        match result {
+            Ok(value) => *total += value,
+            Err(err) => log.push(err.to_string()),
+        }
+    }
+
+    #[test]
+    fn chain_system() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert("5");
+        resources.insert(Vec::<String>::new());
+        resources.insert(0usize);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", parse.system().chain(log_errors.system()));
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 5);
+        assert!(resources.get::<Vec<String>>().unwrap().is_empty());
+
+        *resources.get_mut::<&'static str>().unwrap() = "five";
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 5);
+        assert_eq!(
+            *resources.get::<Vec<String>>().unwrap(),
+            vec!["invalid digit found in string".to_string()]
+        );
+    }
+
+    #[test]
+    fn chain_system_access() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert("5");
+        resources.insert(Vec::<String>::new());
+        resources.insert(0usize);
+
+        let mut system = parse.system().chain(log_errors.system());
+        system.initialize(&mut world, &mut resources);
+
+        let resource_access = system.resource_access();
+        assert!(resource_access.is_read_or_write(&std::any::TypeId::of::<&'static str>()));
+        assert!(resource_access.is_write(&std::any::TypeId::of::<Vec<String>>()));
+        assert!(resource_access.is_write(&std::any::TypeId::of::<usize>()));
+    }
+}
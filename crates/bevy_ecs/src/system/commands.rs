@@ -1,12 +1,153 @@
 use super::SystemId;
 use crate::resource::{Resource, Resources};
-use bevy_hecs::{Bundle, Component, DynamicBundle, Entity, EntityReserver, World};
-use bevy_utils::tracing::debug;
-use std::marker::PhantomData;
+use bevy_hecs::{Bundle, Component, ComponentError, DynamicBundle, Entity, EntityReserver, World};
+use bevy_utils::tracing::{info, warn};
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+use thiserror::Error;
 
-/// A [World] mutation
-pub trait Command: Send + Sync {
-    fn write(self: Box<Self>, world: &mut World, resources: &mut Resources);
+/// A [World] mutation. Commands are queued by value and consumed when they are applied. A `Box<dyn Command>` is a
+/// command too, which applies the boxed one.
+pub trait Command: BoxedCommand + Send + Sync {
+    fn write(self, world: &mut World, resources: &mut Resources)
+    where
+        Self: Sized;
+
+    /// Applies the command like [Command::write], but returns an error if the command couldn't be applied, which
+    /// [Commands::apply] reports according to the [OnCommandError] policy. Commands that can fail should implement
+    /// this, and have [Command::write] ignore the error.
+    fn try_write(self, world: &mut World, resources: &mut Resources) -> Result<(), CommandError>
+    where
+        Self: Sized,
+    {
+        self.write(world, resources);
+        Ok(())
+    }
+}
+
+/// Applies a [Command] that was moved into a [Box], since [Command::try_write] can't be called on a `dyn Command`.
+/// This is implemented for every [Command].
+#[doc(hidden)]
+pub trait BoxedCommand {
+    fn try_write_boxed(
+        self: Box<Self>,
+        world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<(), CommandError>;
+}
+
+impl<C: Command> BoxedCommand for C {
+    fn try_write_boxed(
+        self: Box<Self>,
+        world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<(), CommandError> {
+        (*self).try_write(world, resources)
+    }
+}
+
+impl Command for Box<dyn Command> {
+    fn write(self, world: &mut World, resources: &mut Resources) {
+        self.try_write(world, resources).ok();
+    }
+
+    fn try_write(self, world: &mut World, resources: &mut Resources) -> Result<(), CommandError> {
+        self.try_write_boxed(world, resources)
+    }
+}
+
+/// A command that couldn't be applied to its target entity
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{command} command for entity {entity:?} failed: {error}")]
+pub struct CommandError {
+    /// The name of the system that queued the command, or None if the commands weren't applied by a system
+    pub system_name: Option<Cow<'static, str>>,
+    /// The kind of command, like `"insert"` or `"despawn"`
+    pub command: &'static str,
+    pub entity: Entity,
+    pub error: ComponentError,
+}
+
+impl CommandError {
+    pub fn new(command: &'static str, entity: Entity, error: ComponentError) -> Self {
+        Self {
+            system_name: None,
+            command,
+            entity,
+            error,
+        }
+    }
+}
+
+/// What [Commands::apply] does with a command that fails. This is set for every system by inserting it as a resource,
+/// and defaults to [OnCommandError::Warn] when there is none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OnCommandError {
+    /// Carries on as if the command had succeeded
+    Ignore,
+    /// Logs a warning and records the error in the [CommandErrors] resource
+    Warn,
+    /// Panics with the error
+    Panic,
+}
+
+/// The commands that failed under the [OnCommandError::Warn] policy. They are cleared whenever the app's
+/// [Schedule](crate::Schedule) starts a run, so after a run they hold the errors of that run.
+#[derive(Debug, Default)]
+pub struct CommandErrors {
+    errors: Vec<CommandError>,
+}
+
+impl CommandErrors {
+    pub fn iter(&self) -> impl Iterator<Item = &CommandError> {
+        self.errors.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = CommandError> + '_ {
+        self.errors.drain(..)
+    }
+
+    pub fn clear(&mut self) {
+        self.errors.clear();
+    }
+
+    pub(crate) fn clear_in(resources: &Resources) {
+        if let Some(mut errors) = resources.get_mut::<CommandErrors>() {
+            errors.clear();
+        }
+    }
+
+    fn report(resources: &mut Resources, error: CommandError) {
+        let policy = resources
+            .get::<OnCommandError>()
+            .map_or(OnCommandError::Warn, |policy| *policy);
+        match policy {
+            OnCommandError::Ignore => {}
+            OnCommandError::Warn => {
+                warn!("{}", error);
+                if resources.get::<CommandErrors>().is_none() {
+                    resources.insert(CommandErrors::default());
+                }
+                resources
+                    .get_mut::<CommandErrors>()
+                    .unwrap()
+                    .errors
+                    .push(error);
+            }
+            OnCommandError::Panic => panic!("{}", error),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -21,7 +162,7 @@ impl<T> Command for Spawn<T>
 where
     T: DynamicBundle + Send + Sync + 'static,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
+    fn write(self, world: &mut World, _resources: &mut Resources) {
         world.spawn(self.components);
     }
 }
@@ -39,7 +180,7 @@ where
     I: IntoIterator + Send + Sync,
     I::Item: Bundle,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
+    fn write(self, world: &mut World, _resources: &mut Resources) {
         world.spawn_batch(self.components_iter);
     }
 }
@@ -50,10 +191,14 @@ pub(crate) struct Despawn {
 }
 
 impl Command for Despawn {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        if let Err(e) = world.despawn(self.entity) {
-            debug!("Failed to despawn entity {:?}: {}", self.entity, e);
-        }
+    fn write(self, world: &mut World, resources: &mut Resources) {
+        self.try_write(world, resources).ok();
+    }
+
+    fn try_write(self, world: &mut World, _resources: &mut Resources) -> Result<(), CommandError> {
+        world
+            .despawn(self.entity)
+            .map_err(|err| CommandError::new("despawn", self.entity, err.into()))
     }
 }
 
@@ -69,8 +214,15 @@ impl<T> Command for Insert<T>
 where
     T: DynamicBundle + Send + Sync + 'static,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        world.insert(self.entity, self.components).unwrap();
+    fn write(self, world: &mut World, resources: &mut Resources) {
+        self.try_write(world, resources).ok();
+    }
+
+    fn try_write(self, world: &mut World, _resources: &mut Resources) -> Result<(), CommandError> {
+        let entity = self.entity;
+        world
+            .insert(entity, self.components)
+            .map_err(|err| CommandError::new("insert", entity, err.into()))
     }
 }
 
@@ -87,8 +239,15 @@ impl<T> Command for InsertOne<T>
 where
     T: Component,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        world.insert(self.entity, (self.component,)).unwrap();
+    fn write(self, world: &mut World, resources: &mut Resources) {
+        self.try_write(world, resources).ok();
+    }
+
+    fn try_write(self, world: &mut World, _resources: &mut Resources) -> Result<(), CommandError> {
+        let entity = self.entity;
+        world
+            .insert(entity, (self.component,))
+            .map_err(|err| CommandError::new("insert_one", entity, err.into()))
     }
 }
 
@@ -105,9 +264,15 @@ impl<T> Command for RemoveOne<T>
 where
     T: Component,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        if world.get::<T>(self.entity).is_ok() {
-            world.remove_one::<T>(self.entity).unwrap();
+    fn write(self, world: &mut World, resources: &mut Resources) {
+        self.try_write(world, resources).ok();
+    }
+
+    fn try_write(self, world: &mut World, _resources: &mut Resources) -> Result<(), CommandError> {
+        // a missing component is fine, there is nothing to remove
+        match world.remove_one::<T>(self.entity) {
+            Ok(_) | Err(ComponentError::MissingComponent(_)) => Ok(()),
+            Err(err) => Err(CommandError::new("remove_one", self.entity, err)),
         }
     }
 }
@@ -125,13 +290,55 @@ impl<T> Command for Remove<T>
 where
     T: Bundle + Send + Sync + 'static,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        world.remove::<T>(self.entity).unwrap();
+    fn write(self, world: &mut World, resources: &mut Resources) {
+        self.try_write(world, resources).ok();
+    }
+
+    fn try_write(self, world: &mut World, _resources: &mut Resources) -> Result<(), CommandError> {
+        world
+            .remove::<T>(self.entity)
+            .map(|_| ())
+            .map_err(|err| CommandError::new("remove", self.entity, err))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct RemoveBundle<T>
+where
+    T: Bundle + Send + Sync + 'static,
+{
+    entity: Entity,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Command for RemoveBundle<T>
+where
+    T: Bundle + Send + Sync + 'static,
+{
+    fn write(self, world: &mut World, resources: &mut Resources) {
+        self.try_write(world, resources).ok();
+    }
+
+    fn try_write(self, world: &mut World, _resources: &mut Resources) -> Result<(), CommandError> {
+        world
+            .remove_intersection::<T>(self.entity)
+            .map(|_| ())
+            .map_err(|err| CommandError::new("remove_bundle", self.entity, err.into()))
+    }
+}
+
+pub(crate) struct DebugEntity {
+    entity: Entity,
+}
+
+impl Command for DebugEntity {
+    fn write(self, world: &mut World, _resources: &mut Resources) {
+        info!("{}", world.debug_entity(self.entity));
     }
 }
 
 pub trait ResourcesWriter: Send + Sync {
-    fn write(self: Box<Self>, resources: &mut Resources);
+    fn write(self, resources: &mut Resources);
 }
 
 pub struct InsertResource<T: Resource> {
@@ -139,11 +346,22 @@ pub struct InsertResource<T: Resource> {
 }
 
 impl<T: Resource> Command for InsertResource<T> {
-    fn write(self: Box<Self>, _world: &mut World, resources: &mut Resources) {
+    fn write(self, _world: &mut World, resources: &mut Resources) {
         resources.insert(self.resource);
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct RemoveResource<T: Resource> {
+    phantom: PhantomData<T>,
+}
+
+impl<T: Resource> Command for RemoveResource<T> {
+    fn write(self, _world: &mut World, resources: &mut Resources) {
+        resources.remove::<T>();
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct InsertLocalResource<T: Resource> {
     resource: T,
@@ -151,30 +369,141 @@ pub(crate) struct InsertLocalResource<T: Resource> {
 }
 
 impl<T: Resource> Command for InsertLocalResource<T> {
-    fn write(self: Box<Self>, _world: &mut World, resources: &mut Resources) {
+    fn write(self, _world: &mut World, resources: &mut Resources) {
         resources.insert_local(self.system_id, self.resource);
     }
 }
 
+/// A queue of [World] and [Resources] mutations, which a system records and the schedule applies at the end of its
+/// stage. Commands are applied in the order they were queued, so a command can build on the effects of any command
+/// queued before it.
+///
+/// An entity spawned by a command is reserved right away, and its [Entity] can be passed to any later command in the
+/// same queue, for example to insert a component that refers to it. Its components only exist once the queue has been
+/// applied though, so it can't be queried or looked up in the [World] before that.
 #[derive(Default)]
 pub struct Commands {
-    commands: Vec<Box<dyn Command>>,
+    queue: CommandQueue,
     current_entity: Option<Entity>,
     entity_reserver: Option<EntityReserver>,
 }
 
+/// Where a queued command is stored in a [CommandQueue], and how to consume it
+#[derive(Clone, Copy)]
+struct CommandMeta {
+    offset: usize,
+    apply: unsafe fn(*const u8, &mut World, &mut Resources) -> Result<(), CommandError>,
+    drop: unsafe fn(*const u8),
+}
+
+/// Stores commands back to back in one byte buffer instead of boxing each of them. Applying the queue empties it but
+/// keeps its allocations, so once it has grown to fit a frame's commands, queueing them doesn't allocate.
+#[derive(Default)]
+struct CommandQueue {
+    /// the commands, unaligned. the bytes of a command are only valid until it is applied or dropped
+    bytes: Vec<u8>,
+    metas: Vec<CommandMeta>,
+}
+
+impl CommandQueue {
+    fn push<C: Command + 'static>(&mut self, command: C) {
+        /// # Safety
+        /// `command` must point to a `C` that is read exactly once
+        unsafe fn apply_command<C: Command>(
+            command: *const u8,
+            world: &mut World,
+            resources: &mut Resources,
+        ) -> Result<(), CommandError> {
+            (command as *const C)
+                .read_unaligned()
+                .try_write(world, resources)
+        }
+
+        /// # Safety
+        /// `command` must point to a `C` that is read exactly once
+        unsafe fn drop_command<C>(command: *const u8) {
+            (command as *const C).read_unaligned();
+        }
+
+        let size = std::mem::size_of::<C>();
+        let offset = self.bytes.len();
+        self.bytes.reserve(size);
+        // SAFE: the buffer has room for `size` more bytes, which are initialized by writing the command
+        unsafe {
+            (self.bytes.as_mut_ptr().add(offset) as *mut C).write_unaligned(command);
+            self.bytes.set_len(offset + size);
+        }
+        self.metas.push(CommandMeta {
+            offset,
+            apply: apply_command::<C>,
+            drop: drop_command::<C>,
+        });
+    }
+
+    fn reserve<C: Command>(&mut self, additional: usize) {
+        self.metas.reserve(additional);
+        self.bytes.reserve(additional * std::mem::size_of::<C>());
+    }
+
+    fn len(&self) -> usize {
+        self.metas.len()
+    }
+
+    /// Applies the commands in the order they were queued. `on_error` is called for every command that fails.
+    fn apply(
+        &mut self,
+        world: &mut World,
+        resources: &mut Resources,
+        mut on_error: impl FnMut(&mut Resources, CommandError),
+    ) {
+        let bytes = self.bytes.as_ptr();
+        // the commands are moved out of the buffer below. it forgets them first, so if one of them panics the rest
+        // are leaked instead of being dropped twice
+        // SAFE: u8 doesn't need to be dropped
+        unsafe { self.bytes.set_len(0) };
+        for meta in self.metas.drain(..) {
+            // SAFE: every command is read once, and the buffer isn't written to until all of them have been read
+            if let Err(error) = unsafe { (meta.apply)(bytes.add(meta.offset), world, resources) } {
+                on_error(resources, error);
+            }
+        }
+    }
+}
+
+impl Drop for CommandQueue {
+    fn drop(&mut self) {
+        let bytes = self.bytes.as_ptr();
+        for meta in self.metas.drain(..) {
+            // SAFE: commands that weren't applied are still in the buffer, and each of them is read once
+            unsafe { (meta.drop)(bytes.add(meta.offset)) };
+        }
+    }
+}
+
 impl Commands {
-    pub fn spawn(&mut self, components: impl DynamicBundle + Send + Sync + 'static) -> &mut Self {
+    /// Reserves an [Entity] and queues a command that inserts `components` into it. The reserved entity is returned
+    /// right away through [EntityCommands::id], so it can be referred to by other commands before it exists in the
+    /// [World]. It also becomes the "current entity" used by [Commands::with] and [Commands::with_bundle].
+    pub fn spawn(
+        &mut self,
+        components: impl DynamicBundle + Send + Sync + 'static,
+    ) -> EntityCommands<'_> {
         let entity = self
             .entity_reserver
             .as_ref()
             .expect("entity reserver has not been set")
             .reserve_entity();
         self.current_entity = Some(entity);
-        self.commands.push(Box::new(Insert { entity, components }));
-        self
+        self.queue.push(Insert { entity, components });
+        EntityCommands {
+            entity,
+            commands: self,
+        }
     }
 
+    /// Spawns an entity for every bundle in `components_iter`. This records a single command, and the bundles are
+    /// all written into their archetype in one pass when the commands are applied, which is much faster than calling
+    /// [Commands::spawn] in a loop.
     pub fn spawn_batch<I>(&mut self, components_iter: I) -> &mut Self
     where
         I: IntoIterator + Send + Sync + 'static,
@@ -183,11 +512,15 @@ impl Commands {
         self.add_command(SpawnBatch { components_iter })
     }
 
-    /// Despawns only the specified entity, ignoring any other consideration.
+    /// Despawns only the specified entity, ignoring any other consideration. Despawning an entity that doesn't exist
+    /// anymore fails like any other command, see [OnCommandError].
     pub fn despawn(&mut self, entity: Entity) -> &mut Self {
         self.add_command(Despawn { entity })
     }
 
+    /// Queues a command that inserts `components` into `entity`, replacing any components of the same types. Like the
+    /// other commands that target an entity, it fails if the entity was despawned before the commands are applied,
+    /// which is handled according to the [OnCommandError] policy.
     pub fn insert(
         &mut self,
         entity: Entity,
@@ -200,10 +533,20 @@ impl Commands {
         self.add_command(InsertOne { entity, component })
     }
 
+    /// Queues a command that inserts `resource`, replacing the current `T` if there is one. The resource is inserted
+    /// when the commands are applied at the end of the stage, so other systems see it from the next stage on.
     pub fn insert_resource<T: Resource>(&mut self, resource: T) -> &mut Self {
         self.add_command(InsertResource { resource })
     }
 
+    /// Queues a command that removes the `T` resource, if there is one. Commands of a stage are applied in the order
+    /// of its systems, so an insert queued by one system and a removal queued by a later one leave no `T` behind.
+    pub fn remove_resource<T: Resource>(&mut self) -> &mut Self {
+        self.add_command(RemoveResource::<T> {
+            phantom: PhantomData,
+        })
+    }
+
     pub fn insert_local_resource<T: Resource>(
         &mut self,
         system_id: SystemId,
@@ -225,6 +568,8 @@ impl Commands {
         })
     }
 
+    /// Queues a command that removes the bundle `T` from `entity`. It fails if the entity doesn't exist or is missing
+    /// one of the components when the commands are applied, see [OnCommandError].
     pub fn remove<T>(&mut self, entity: Entity) -> &mut Self
     where
         T: Bundle + Send + Sync + 'static,
@@ -235,41 +580,102 @@ impl Commands {
         })
     }
 
+    /// Queues a command that removes every component of the bundle `T` that `entity` has when the commands are
+    /// applied, moving the entity to its new archetype only once. Unlike [Commands::remove], components of `T` that
+    /// the entity doesn't have are ignored.
+    pub fn remove_bundle<T>(&mut self, entity: Entity) -> &mut Self
+    where
+        T: Bundle + Send + Sync + 'static,
+    {
+        self.add_command(RemoveBundle::<T> {
+            entity,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Queues a command that logs `entity` with the names of its components, as they are when the commands are
+    /// applied. See [World::debug_entity].
+    pub fn debug_entity(&mut self, entity: Entity) -> &mut Self {
+        self.add_command(DebugEntity { entity })
+    }
+
     pub fn with_bundle(
         &mut self,
         components: impl DynamicBundle + Send + Sync + 'static,
     ) -> &mut Self {
         let current_entity =  self.current_entity.expect("Cannot add components because the 'current entity' is not set. You should spawn an entity first.");
-        self.commands.push(Box::new(Insert {
+        self.queue.push(Insert {
             entity: current_entity,
             components,
-        }));
+        });
         self
     }
 
     pub fn with(&mut self, component: impl Component) -> &mut Self {
         let current_entity =  self.current_entity.expect("Cannot add component because the 'current entity' is not set. You should spawn an entity first.");
-        self.commands.push(Box::new(InsertOne {
+        self.queue.push(InsertOne {
             entity: current_entity,
             component,
-        }));
+        });
         self
     }
 
     pub fn add_command<C: Command + 'static>(&mut self, command: C) -> &mut Self {
-        self.commands.push(Box::new(command));
+        self.queue.push(command);
         self
     }
 
+    /// Queues a boxed command. It is stored like any other command, so only the [Box] itself is an extra allocation.
     pub fn add_command_boxed(&mut self, command: Box<dyn Command>) -> &mut Self {
-        self.commands.push(command);
+        self.queue.push(command);
+        self
+    }
+
+    /// Reserves room for at least `additional` more commands of type `C`, so that queueing them doesn't allocate.
+    ///
+    /// The queue keeps its capacity after being applied, so this only matters the first time a system queues many
+    /// commands.
+    pub fn reserve<C: Command>(&mut self, additional: usize) -> &mut Self {
+        self.queue.reserve::<C>(additional);
+        self
+    }
+
+    /// Reserves room for at least `additional` more [Commands::spawn] calls with bundles of type `T`, like
+    /// [Commands::reserve].
+    pub fn reserve_spawn<T>(&mut self, additional: usize) -> &mut Self
+    where
+        T: DynamicBundle + Send + Sync + 'static,
+    {
+        self.queue.reserve::<Insert<T>>(additional);
         self
     }
 
+    /// The number of queued commands
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.len() == 0
+    }
+
+    /// Applies the queued commands in the order they were queued, and empties the queue. Commands that fail are
+    /// handled according to the [OnCommandError] resource.
     pub fn apply(&mut self, world: &mut World, resources: &mut Resources) {
-        for command in self.commands.drain(..) {
-            command.write(world, resources);
-        }
+        self.apply_for_system(world, resources, None);
+    }
+
+    /// Applies the queued commands like [Commands::apply], naming `system_name` as the origin of the failed ones
+    pub(crate) fn apply_for_system(
+        &mut self,
+        world: &mut World,
+        resources: &mut Resources,
+        system_name: Option<&Cow<'static, str>>,
+    ) {
+        self.queue.apply(world, resources, |resources, mut error| {
+            error.system_name = system_name.cloned();
+            CommandErrors::report(resources, error);
+        });
     }
 
     pub fn current_entity(&self) -> Option<Entity> {
@@ -297,11 +703,57 @@ impl Commands {
     }
 }
 
+/// The [Commands] returned by [Commands::spawn], along with the [Entity] that was reserved for the spawned bundle.
+/// Derefs to [Commands], so calls can keep being chained onto it.
+pub struct EntityCommands<'a> {
+    entity: Entity,
+    commands: &'a mut Commands,
+}
+
+impl<'a> EntityCommands<'a> {
+    /// The reserved [Entity]. It is valid for use in other commands immediately, and exists in the [World] once the
+    /// commands have been applied.
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+}
+
+impl<'a> Deref for EntityCommands<'a> {
+    type Target = Commands;
+
+    fn deref(&self) -> &Commands {
+        self.commands
+    }
+}
+
+impl<'a> DerefMut for EntityCommands<'a> {
+    fn deref_mut(&mut self) -> &mut Commands {
+        self.commands
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Commands;
-    use crate::resource::Resources;
-    use bevy_hecs::World;
+    use super::{Command, CommandErrors, Commands, OnCommandError};
+    use crate::{
+        resource::{ChangedRes, Local, Res, ResMut, Resources},
+        schedule::Schedule,
+        system::IntoSystem,
+    };
+    use bevy_hecs::{ComponentError, Entity, World};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    struct Position(f32);
+    struct Velocity(f32);
+
+    struct CountPositions;
+
+    impl Command for CountPositions {
+        fn write(self, world: &mut World, resources: &mut Resources) {
+            resources.insert(world.query::<&Position>().count());
+        }
+    }
 
     #[test]
     fn command_buffer() {
@@ -329,4 +781,465 @@ mod tests {
             .collect::<Vec<_>>();
         assert_eq!(results2, vec![]);
     }
+
+    #[test]
+    fn spawn_returns_reserved_entity() {
+        struct Target(Entity);
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut command_buffer = Commands::default();
+        command_buffer.set_entity_reserver(world.get_entity_reserver());
+        let a = command_buffer.spawn((1u32,)).id();
+        assert_eq!(command_buffer.current_entity(), Some(a));
+        let b = command_buffer.spawn((2u32,)).id();
+        command_buffer.with(Target(a));
+        assert_ne!(a, b);
+        command_buffer.insert_one(a, Target(b));
+        assert!(world.get::<u32>(a).is_err());
+        command_buffer.apply(&mut world, &mut resources);
+
+        assert_eq!(*world.get::<u32>(a).unwrap(), 1);
+        assert_eq!(*world.get::<u32>(b).unwrap(), 2);
+        assert_eq!(world.get::<Target>(a).unwrap().0, b);
+        assert_eq!(world.get::<Target>(b).unwrap().0, a);
+    }
+
+    #[test]
+    fn commands_apply_in_order() {
+        struct Emitter;
+        struct Particle(Entity);
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut command_buffer = Commands::default();
+        command_buffer.set_entity_reserver(world.get_entity_reserver());
+        let emitter = command_buffer.spawn((Emitter,)).id();
+        let particles = (0..3)
+            .map(|_| command_buffer.spawn((Particle(emitter),)).id())
+            .collect::<Vec<_>>();
+        // queued after the spawn, so it replaces the spawned component
+        command_buffer.insert_one(particles[0], Particle(particles[1]));
+        command_buffer.despawn(particles[2]);
+        command_buffer.apply(&mut world, &mut resources);
+
+        assert!(world.get::<Emitter>(emitter).is_ok());
+        assert_eq!(world.get::<Particle>(particles[0]).unwrap().0, particles[1]);
+        assert_eq!(world.get::<Particle>(particles[1]).unwrap().0, emitter);
+        assert!(!world.contains(particles[2]));
+    }
+
+    #[test]
+    fn insert_and_remove_existing_entity() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let entity = world.spawn((1u32,));
+        let mut command_buffer = Commands::default();
+        command_buffer.set_entity_reserver(world.get_entity_reserver());
+
+        // commands are applied in the order they were queued
+        let spawned = command_buffer.spawn((2u32,)).id();
+        command_buffer
+            .insert(spawned, (3u64,))
+            .remove::<(u32,)>(entity)
+            .insert(entity, (4u64, 5u32))
+            .insert_one(entity, 6u32)
+            .remove_one::<u64>(entity);
+        command_buffer.apply(&mut world, &mut resources);
+
+        assert_eq!(*world.get::<u32>(spawned).unwrap(), 2);
+        assert_eq!(*world.get::<u64>(spawned).unwrap(), 3);
+        assert_eq!(*world.get::<u32>(entity).unwrap(), 6);
+        assert!(world.get::<u64>(entity).is_err());
+
+        command_buffer
+            .insert(entity, (7u64,))
+            .remove::<(u32, u64)>(entity);
+        command_buffer.apply(&mut world, &mut resources);
+        assert!(world.get::<u32>(entity).is_err());
+        assert!(world.get::<u64>(entity).is_err());
+    }
+
+    #[test]
+    fn remove_bundle() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let entity = world.spawn((1u32, 2u64, 3.0f32));
+        let despawned = world.spawn((4u32,));
+        world.despawn(despawned).unwrap();
+        let mut command_buffer = Commands::default();
+        command_buffer.set_entity_reserver(world.get_entity_reserver());
+
+        // the entity has no bool, which doesn't keep the other components from being removed
+        command_buffer
+            .remove_bundle::<(u32, u64, bool)>(entity)
+            .remove_bundle::<(u32,)>(despawned);
+        command_buffer.apply(&mut world, &mut resources);
+        assert!(world.get::<u32>(entity).is_err());
+        assert!(world.get::<u64>(entity).is_err());
+        assert_eq!(*world.get::<f32>(entity).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn commands_on_despawned_entity() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let entity = world.spawn((1u32,));
+        let mut command_buffer = Commands::default();
+        command_buffer
+            .insert(entity, (2u64,))
+            .insert_one(entity, 3u32)
+            .remove::<(u32,)>(entity)
+            .remove_one::<u32>(entity);
+        world.despawn(entity).unwrap();
+        command_buffer.apply(&mut world, &mut resources);
+        assert!(!world.contains(entity));
+        let errors = resources
+            .get_mut::<CommandErrors>()
+            .unwrap()
+            .drain()
+            .map(|error| (error.command, error.entity, error.error))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                ("insert", entity, ComponentError::NoSuchEntity),
+                ("insert_one", entity, ComponentError::NoSuchEntity),
+                ("remove", entity, ComponentError::NoSuchEntity),
+                ("remove_one", entity, ComponentError::NoSuchEntity),
+            ]
+        );
+
+        // removing a component the entity doesn't have fails too, and leaves the entity as it was
+        let entity = world.spawn((1u32,));
+        command_buffer.remove::<(u32, u64)>(entity);
+        command_buffer.apply(&mut world, &mut resources);
+        assert_eq!(*world.get::<u32>(entity).unwrap(), 1);
+        assert_eq!(resources.get::<CommandErrors>().unwrap().len(), 1);
+
+        resources.insert(OnCommandError::Ignore);
+        world.despawn(entity).unwrap();
+        command_buffer.despawn(entity);
+        command_buffer.apply(&mut world, &mut resources);
+        assert_eq!(resources.get::<CommandErrors>().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn command_errors_name_system() {
+        fn insert_into_despawned(commands: &mut Commands, target: Res<Entity>) {
+            commands.insert_one(*target, 1u32);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let entity = world.spawn((0u32,));
+        world.despawn(entity).unwrap();
+        resources.insert(entity);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", insert_into_despawned.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        // the errors of earlier runs are cleared
+        let errors = resources.get::<CommandErrors>().unwrap();
+        assert_eq!(errors.len(), 1);
+        let error = errors.iter().next().unwrap();
+        assert!(error
+            .system_name
+            .as_ref()
+            .unwrap()
+            .ends_with("insert_into_despawned"));
+        assert_eq!(error.command, "insert_one");
+        assert_eq!(error.entity, entity);
+    }
+
+    #[test]
+    #[should_panic(expected = "despawn command for entity")]
+    fn command_error_panic() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(OnCommandError::Panic);
+        let entity = world.spawn((1u32,));
+        let mut command_buffer = Commands::default();
+        command_buffer.despawn(entity).despawn(entity);
+        command_buffer.apply(&mut world, &mut resources);
+    }
+
+    #[test]
+    fn remove_resource() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut command_buffer = Commands::default();
+        command_buffer.insert_resource(1u32);
+        command_buffer.remove_resource::<u32>();
+        command_buffer.remove_resource::<u64>(); // removing a missing resource shouldn't panic
+        command_buffer.apply(&mut world, &mut resources);
+        assert!(!resources.contains::<u32>());
+
+        command_buffer.remove_resource::<u32>();
+        command_buffer.insert_resource(2u32);
+        command_buffer.apply(&mut world, &mut resources);
+        assert_eq!(*resources.get::<u32>().unwrap(), 2);
+    }
+
+    #[test]
+    fn insert_resource_system() {
+        fn insert(commands: &mut Commands) {
+            commands.insert_resource(42usize);
+        }
+        fn read(value: Res<usize>, mut seen: ResMut<Vec<usize>>) {
+            seen.push(*value);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<usize>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("insert");
+        schedule.add_stage("read");
+        schedule.add_system_to_stage("insert", insert.system());
+        schedule.add_system_to_stage("read", read.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![42]);
+    }
+
+    #[test]
+    fn resource_commands_apply_in_system_order() {
+        fn insert(commands: &mut Commands) {
+            commands.insert_resource(1u32);
+        }
+        fn remove(commands: &mut Commands) {
+            commands.remove_resource::<u32>();
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", insert.system());
+        schedule.add_system_to_stage("update", remove.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert!(!resources.contains::<u32>());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", remove.system());
+        schedule.add_system_to_stage("update", insert.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<u32>().unwrap(), 1);
+    }
+
+    #[test]
+    fn marker_resource_system() {
+        struct Paused;
+
+        fn pause(commands: &mut Commands, mut paused: Local<bool>) {
+            if !*paused {
+                commands.insert_resource(Paused);
+                *paused = true;
+            }
+        }
+        fn watch(paused: Option<Res<Paused>>, mut log: ResMut<Vec<&'static str>>) {
+            log.push(if paused.is_some() {
+                "paused"
+            } else {
+                "running"
+            });
+        }
+        fn on_pause(_paused: ChangedRes<Paused>, mut log: ResMut<Vec<&'static str>>) {
+            log.push("pause changed");
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<&'static str>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", pause.system());
+        schedule.add_system_to_stage("update", watch.system());
+        schedule.add_system_to_stage("update", on_pause.system());
+        schedule.initialize(&mut world, &mut resources);
+        for _ in 0..3 {
+            schedule.run(&mut world, &mut resources);
+        }
+
+        assert_eq!(
+            *resources.get::<Vec<&'static str>>().unwrap(),
+            vec!["running", "paused", "pause changed", "paused"]
+        );
+    }
+
+    #[test]
+    fn spawn_batch() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut command_buffer = Commands::default();
+        command_buffer.set_entity_reserver(world.get_entity_reserver());
+        command_buffer.spawn_batch((0..100u32).map(|i| (i, i as u64 * 2)));
+        command_buffer.spawn((1000u32,));
+        command_buffer.apply(&mut world, &mut resources);
+
+        let mut results = world
+            .query::<(&u32, &u64)>()
+            .map(|(a, b)| (*a, *b))
+            .collect::<Vec<_>>();
+        results.sort_unstable();
+        assert_eq!(
+            results,
+            (0..100u32).map(|i| (i, i as u64 * 2)).collect::<Vec<_>>()
+        );
+        assert_eq!(world.query::<&u32>().count(), 101);
+    }
+
+    #[test]
+    fn spawn_batch_system() {
+        fn spawn(commands: &mut Commands) {
+            commands
+                .spawn_batch((0..10_000).map(|i| (Position(i as f32), Velocity(1.0))))
+                .add_command(CountPositions);
+        }
+        fn spawn_arc(commands: Arc<Mutex<Commands>>) {
+            commands
+                .lock()
+                .spawn_batch((0..10_000).map(|i| (Position(i as f32), Velocity(1.0))));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", spawn.system());
+        schedule.add_system_to_stage("update", spawn_arc.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(
+            *resources.get::<usize>().unwrap(),
+            10_000,
+            "the batch should be spawned before the next command runs"
+        );
+        let (count, total_velocity, max_position) = world
+            .query::<(&Position, &Velocity)>()
+            .fold((0, 0.0, 0.0), |(count, velocity, position), (p, v)| {
+                (count + 1, velocity + v.0, p.0.max(position))
+            });
+        assert_eq!(count, 20_000);
+        assert_eq!(total_velocity, 20_000.0);
+        assert_eq!(max_position, 9_999.0);
+    }
+
+    #[test]
+    fn queue_keeps_capacity_after_apply() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let entities = world
+            .spawn_batch((0..100).map(|i| (Position(i as f32),)))
+            .collect::<Vec<_>>();
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+
+        let queue_frame = |commands: &mut Commands| {
+            for (i, entity) in entities.iter().enumerate() {
+                commands.insert_one(*entity, Position(i as f32 * 2.0));
+            }
+            commands.add_command(CountPositions);
+        };
+
+        queue_frame(&mut commands);
+        assert_eq!(commands.len(), 101);
+        commands.apply(&mut world, &mut resources);
+        assert!(commands.is_empty());
+        let bytes = (
+            commands.queue.bytes.as_ptr(),
+            commands.queue.bytes.capacity(),
+        );
+        let metas = (
+            commands.queue.metas.as_ptr(),
+            commands.queue.metas.capacity(),
+        );
+
+        queue_frame(&mut commands);
+        commands.apply(&mut world, &mut resources);
+        assert_eq!(
+            (
+                commands.queue.bytes.as_ptr(),
+                commands.queue.bytes.capacity()
+            ),
+            bytes,
+            "the second frame reuses the buffer of the first"
+        );
+        assert_eq!(
+            (
+                commands.queue.metas.as_ptr(),
+                commands.queue.metas.capacity()
+            ),
+            metas
+        );
+        assert_eq!(*resources.get::<usize>().unwrap(), 100);
+        assert_eq!(world.get::<Position>(entities[10]).unwrap().0, 20.0);
+    }
+
+    #[test]
+    fn reserve() {
+        let world = World::default();
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+
+        // nothing has been queued yet, so the room has to come from the command type
+        commands.reserve_spawn::<(Position, Velocity)>(1000);
+        let capacity = commands.queue.bytes.capacity();
+        let metas_capacity = commands.queue.metas.capacity();
+        assert!(capacity >= 1000 * std::mem::size_of::<super::Insert<(Position, Velocity)>>());
+        assert!(metas_capacity >= 1000);
+        for i in 0..1000 {
+            commands.spawn((Position(i as f32), Velocity(0.0)));
+        }
+        assert_eq!(commands.queue.bytes.capacity(), capacity);
+        assert_eq!(commands.queue.metas.capacity(), metas_capacity);
+        assert_eq!(commands.len(), 1000);
+    }
+
+    #[test]
+    fn boxed_command() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+        let command: Box<dyn Command> = Box::new(CountPositions);
+        commands.spawn((Position(0.0),)).add_command_boxed(command);
+        commands.apply(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 1);
+    }
+
+    #[test]
+    fn unapplied_commands_are_dropped() {
+        struct Hold {
+            _held: Arc<()>,
+        }
+
+        impl Command for Hold {
+            fn write(self, _world: &mut World, _resources: &mut Resources) {}
+        }
+
+        let held = Arc::new(());
+        let mut commands = Commands::default();
+        commands
+            .add_command(Hold {
+                _held: held.clone(),
+            })
+            .insert_resource(held.clone());
+        assert_eq!(Arc::strong_count(&held), 3);
+        drop(commands);
+        assert_eq!(Arc::strong_count(&held), 1);
+    }
 }
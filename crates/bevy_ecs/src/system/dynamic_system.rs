@@ -0,0 +1,312 @@
+use crate::{BoxedSystem, Commands, FuncSystem, QueryError, Resource, Resources, SystemState};
+use bevy_hecs::{
+    ArchetypeComponent, ChangeTicks, Component, Entity, Location, Mut, QueryAccess, Ref, RefMut,
+    TypeAccess, World,
+};
+use std::{
+    any::{type_name, TypeId},
+    borrow::Cow,
+    marker::PhantomData,
+    ptr::NonNull,
+};
+use thiserror::Error;
+
+/// Builds a system whose component and resource accesses are declared up front instead of coming from the types of its
+/// parameters, for systems that are only known at runtime, like the ones defined by a script.
+///
+/// The system is scheduled by its declared accesses, just like a system created with
+/// [IntoSystem::system](crate::IntoSystem::system), and gets a [DynamicWorld] that checks every access against them.
+/// ```
+/// # use bevy_ecs::{prelude::*, DynamicSystem, QueryAccess};
+/// # use std::any::TypeId;
+/// struct Health(f32);
+///
+/// let system = DynamicSystem::new("heal")
+///     .with_query(QueryAccess::write::<Health>())
+///     .build(move |world| {
+///         let entities = world.query_entities(0).collect::<Vec<_>>();
+///         for entity in entities {
+///             let health = world.get_dynamic_mut(entity, TypeId::of::<Health>()).unwrap();
+///             // SAFE: the pointer points to a Health component
+///             unsafe { health.cast::<Health>().as_mut().0 += 1.0 };
+///         }
+///     });
+/// ```
+pub struct DynamicSystem {
+    state: SystemState,
+}
+
+impl DynamicSystem {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        let mut state = SystemState::new(name.into());
+        // there is no telling what the system does besides running its queries
+        state.has_side_channel = true;
+        DynamicSystem { state }
+    }
+
+    /// Declares a query of the system. Its entities are returned by [DynamicWorld::query_entities], at the index the
+    /// queries were declared in.
+    pub fn with_query(mut self, access: QueryAccess) -> Self {
+        let query_index = self.state.query_accesses.len();
+        self.state
+            .query_archetype_component_accesses
+            .push(TypeAccess::default());
+        self.state.query_sort_buffers.push(Default::default());
+        self.state.query_accesses.push(vec![access]);
+        self.state
+            .query_type_names
+            .push(format!("dynamic query {}", query_index).into());
+        self
+    }
+
+    /// Declares the resources the system reads and writes
+    pub fn with_resource_access(mut self, access: TypeAccess<TypeId>) -> Self {
+        self.state.resource_access.union(&access);
+        self
+    }
+
+    pub fn build(
+        self,
+        mut func: impl FnMut(&mut DynamicWorld) + Send + Sync + 'static,
+    ) -> BoxedSystem {
+        Box::new(FuncSystem {
+            state: self.state,
+            _marker: PhantomData,
+            func: move |_input, state: &mut SystemState, world, resources| {
+                let mut dynamic_world = DynamicWorld {
+                    world,
+                    resources,
+                    query_accesses: &state.query_accesses,
+                    component_access: &state.archetype_component_access,
+                    resource_access: &state.resource_access,
+                    ticks: ChangeTicks {
+                        last_change_tick: state.last_run_tick,
+                        change_tick: state.run_tick,
+                    },
+                    commands: &mut state.commands,
+                };
+                func(&mut dynamic_world);
+                Some(())
+            },
+            thread_local_func: |state, world, resources| {
+                state.apply_commands(world, resources);
+            },
+            init_func: |_state, _world, _resources| {},
+        })
+    }
+}
+
+/// The view of the world passed to a [DynamicSystem]. Every access is checked against the accesses the system declared,
+/// since they are what keeps it from running in parallel with conflicting systems.
+pub struct DynamicWorld<'a> {
+    world: &'a World,
+    resources: &'a Resources,
+    query_accesses: &'a [Vec<QueryAccess>],
+    component_access: &'a TypeAccess<ArchetypeComponent>,
+    resource_access: &'a TypeAccess<TypeId>,
+    ticks: ChangeTicks,
+    commands: &'a mut Commands,
+}
+
+impl<'a> DynamicWorld<'a> {
+    /// Iterates over the entities matching the query declared at `query_index` with [DynamicSystem::with_query]
+    pub fn query_entities(&self, query_index: usize) -> impl Iterator<Item = Entity> + '_ {
+        let query_accesses = self
+            .query_accesses
+            .get(query_index)
+            .map(|query_accesses| query_accesses.as_slice())
+            .unwrap_or_default();
+        self.world
+            .archetypes()
+            .enumerate()
+            .filter(move |(index, archetype)| {
+                query_accesses.iter().any(|query_access| {
+                    query_access
+                        .get_access(archetype, *index as u32, None)
+                        .is_some()
+                })
+            })
+            .flat_map(|(_, archetype)| archetype.iter_entities().copied())
+    }
+
+    /// Gets a reference to the entity's component of the given type. This fails if none of the declared queries read
+    /// the component for that entity.
+    pub fn get<T: Component>(&self, entity: Entity) -> Result<&T, QueryError> {
+        let location = self.component_location(entity, TypeId::of::<T>(), false)?;
+        // SAFE: the system is scheduled according to the accesses checked above
+        unsafe {
+            self.world
+                .get_at_location_unchecked(location)
+                .map_err(|_err| QueryError::MissingComponent(type_name::<T>()))
+        }
+    }
+
+    /// Gets a mutable reference to the entity's component of the given type. This fails if none of the declared
+    /// queries write the component for that entity.
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Result<Mut<'_, T>, QueryError> {
+        let location = self.component_location(entity, TypeId::of::<T>(), true)?;
+        // SAFE: the system is scheduled according to the accesses checked above, and borrowing self mutably keeps the
+        // component from being borrowed twice
+        unsafe {
+            self.world
+                .get_mut_at_location_unchecked(location, self.ticks.change_tick)
+                .map_err(|_err| QueryError::MissingComponent(type_name::<T>()))
+        }
+    }
+
+    /// Gets a pointer to the entity's component with the given [TypeId], which may only be read through. This fails if
+    /// none of the declared queries read the component for that entity.
+    pub fn get_dynamic(&self, entity: Entity, ty: TypeId) -> Result<NonNull<u8>, QueryError> {
+        let location = self.component_location(entity, ty, false)?;
+        // SAFE: the system is scheduled according to the accesses checked above
+        unsafe {
+            self.world
+                .get_dynamic_at_location_unchecked(location, ty, None)
+                .ok_or_else(|| QueryError::MissingComponent(self.type_name(ty)))
+        }
+    }
+
+    /// Gets a pointer to the entity's component with the given [TypeId], and marks the component as
+    /// [Mutated](bevy_hecs::Mutated). This fails if none of the declared queries write the component for that entity.
+    pub fn get_dynamic_mut(
+        &mut self,
+        entity: Entity,
+        ty: TypeId,
+    ) -> Result<NonNull<u8>, QueryError> {
+        let location = self.component_location(entity, ty, true)?;
+        // SAFE: the system is scheduled according to the accesses checked above
+        unsafe {
+            self.world
+                .get_dynamic_at_location_unchecked(location, ty, Some(self.ticks.change_tick))
+                .ok_or_else(|| QueryError::MissingComponent(self.type_name(ty)))
+        }
+    }
+
+    /// Borrows the resource `T`, which the system must have declared a read or write of
+    pub fn resource<T: Resource>(&self) -> Result<Ref<'_, T>, DynamicResourceError> {
+        if !self.resource_access.is_read_or_write(&TypeId::of::<T>()) {
+            return Err(DynamicResourceError::AccessNotDeclared(type_name::<T>()));
+        }
+        self.resources
+            .get::<T>()
+            .ok_or_else(|| DynamicResourceError::NoSuchResource(type_name::<T>()))
+    }
+
+    /// Mutably borrows the resource `T`, which the system must have declared a write of
+    pub fn resource_mut<T: Resource>(&mut self) -> Result<RefMut<'_, T>, DynamicResourceError> {
+        if !self.resource_access.is_write(&TypeId::of::<T>()) {
+            return Err(DynamicResourceError::AccessNotDeclared(type_name::<T>()));
+        }
+        self.resources
+            .get_mut::<T>()
+            .ok_or_else(|| DynamicResourceError::NoSuchResource(type_name::<T>()))
+    }
+
+    /// The system's [Commands], which are applied at the end of its stage
+    pub fn commands(&mut self) -> &mut Commands {
+        self.commands
+    }
+
+    fn component_location(
+        &self,
+        entity: Entity,
+        ty: TypeId,
+        write: bool,
+    ) -> Result<Location, QueryError> {
+        let location = self
+            .world
+            .get_entity_location(entity)
+            .ok_or(QueryError::NoSuchEntity)?;
+        if !self.world.has_component_type(entity, ty) {
+            return Err(QueryError::MissingComponent(self.type_name(ty)));
+        }
+        let archetype_component = ArchetypeComponent::new_ty(location.archetype, ty);
+        let has_access = if write {
+            self.component_access.is_write(&archetype_component)
+        } else {
+            self.component_access.is_read_or_write(&archetype_component)
+        };
+        if has_access {
+            Ok(location)
+        } else {
+            Err(QueryError::AccessNotDeclared(self.type_name(ty)))
+        }
+    }
+
+    /// The name of the component type `ty`, as far as the declared queries know it
+    fn type_name(&self, ty: TypeId) -> &'static str {
+        self.query_accesses
+            .iter()
+            .flatten()
+            .find_map(|query_access| query_access.get_type_name(ty))
+            .unwrap_or("Unknown")
+    }
+}
+
+/// An error that occurs when a [DynamicSystem] accesses a resource
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DynamicResourceError {
+    #[error("The system does not declare the access to the resource {0} needed here")]
+    AccessNotDeclared(&'static str),
+    #[error("The resource {0} does not exist")]
+    NoSuchResource(&'static str),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DynamicResourceError, DynamicSystem};
+    use crate::{QueryError, Resources, Schedule};
+    use bevy_hecs::{QueryAccess, TypeAccess, World};
+    use std::any::TypeId;
+
+    #[test]
+    fn checks_declared_access() {
+        struct A(u32);
+        struct B(u32);
+
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(0usize);
+        let entity = world.spawn((A(1), B(2)));
+
+        let mut resource_access = TypeAccess::default();
+        resource_access.add_read(TypeId::of::<usize>());
+        let system = DynamicSystem::new("dynamic")
+            .with_query(QueryAccess::union(vec![
+                QueryAccess::write::<A>(),
+                QueryAccess::read::<B>(),
+            ]))
+            .with_resource_access(resource_access)
+            .build(move |world| {
+                assert_eq!(world.query_entities(0).collect::<Vec<_>>(), vec![entity]);
+                assert!(world.query_entities(1).next().is_none());
+
+                let a = world.get_dynamic_mut(entity, TypeId::of::<A>()).unwrap();
+                // SAFE: the pointer points to an A component
+                unsafe { a.cast::<A>().as_mut().0 += 10 };
+                assert_eq!(world.get::<B>(entity).unwrap().0, 2);
+                assert_eq!(
+                    world.get_mut::<B>(entity).err(),
+                    Some(QueryError::AccessNotDeclared(std::any::type_name::<B>()))
+                );
+                assert_eq!(
+                    world.get_dynamic(entity, TypeId::of::<u8>()).err(),
+                    Some(QueryError::MissingComponent("Unknown"))
+                );
+
+                assert_eq!(*world.resource::<usize>().unwrap(), 0);
+                assert_eq!(
+                    world.resource_mut::<usize>().err(),
+                    Some(DynamicResourceError::AccessNotDeclared("usize"))
+                );
+            });
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", system);
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(world.get::<A>(entity).unwrap().0, 11);
+    }
+}
@@ -1,7 +1,19 @@
-use crate::{Commands, Resources, System, SystemId, SystemParam, ThreadLocalExecution};
-use bevy_hecs::{ArchetypeComponent, QueryAccess, TypeAccess, World};
+use crate::{
+    BoxedSystem, Commands, QuerySortBuffer, Resources, System, SystemId, SystemParam,
+    ThreadLocalExecution,
+};
+use bevy_hecs::{
+    increment_change_tick, ArchetypeComponent, ArchetypesGeneration, ChangeTicks, QueryAccess,
+    TypeAccess, World,
+};
 use parking_lot::Mutex;
-use std::{any::TypeId, borrow::Cow, sync::Arc};
+use std::{
+    any::{Any, TypeId},
+    borrow::Cow,
+    marker::PhantomData,
+    sync::Arc,
+};
+use thiserror::Error;
 
 pub struct SystemState {
     pub(crate) id: SystemId,
@@ -11,87 +23,293 @@ pub struct SystemState {
     pub(crate) resource_access: TypeAccess<TypeId>,
     pub(crate) query_archetype_component_accesses: Vec<TypeAccess<ArchetypeComponent>>,
     pub(crate) query_accesses: Vec<Vec<QueryAccess>>,
-    pub(crate) query_type_names: Vec<&'static str>,
+    pub(crate) query_type_names: Vec<Cow<'static, str>>,
+    /// The buffers of [Query::iter_sorted_by_key](crate::Query::iter_sorted_by_key), by query index
+    pub(crate) query_sort_buffers: Vec<Mutex<QuerySortBuffer>>,
+    /// The `#[derive(SystemParam)]` structs whose fields are currently being initialized, innermost last
+    pub(crate) param_structs: Vec<&'static str>,
     pub(crate) commands: Commands,
     pub(crate) arc_commands: Option<Arc<Mutex<Commands>>>,
     pub(crate) current_query_index: usize,
+    pub(crate) local_resources: Vec<Box<dyn Any + Send + Sync>>,
+    pub(crate) current_local_index: usize,
+    /// The tick of the last run that actually executed the system. Anything changed after it is new to the system.
+    pub(crate) last_run_tick: u64,
+    /// The tick of the current run, used to stamp the changes the system makes
+    pub(crate) run_tick: u64,
+    /// The archetypes generation the query accesses were last computed for
+    pub(crate) archetypes_generation: Option<ArchetypesGeneration>,
+    /// Set when the system uses thread local resources, which pins it to the main thread
+    pub(crate) is_non_send: bool,
+    /// Set when a parameter other than a query can have an effect, like a resource or [Commands]. Such systems always
+    /// run, even when none of their queries match.
+    pub(crate) has_side_channel: bool,
+    /// Opts the system out of being skipped, see [AlwaysRunExt]
+    pub(crate) always_run: bool,
+    /// Whether every query of the system matched zero archetypes the last time the accesses were computed
+    pub(crate) queries_match_nothing: bool,
 }
 
 impl SystemState {
+    pub(crate) fn new(name: Cow<'static, str>) -> Self {
+        SystemState {
+            name,
+            archetype_component_access: TypeAccess::default(),
+            resource_access: TypeAccess::default(),
+            is_initialized: false,
+            id: SystemId::new(),
+            commands: Commands::default(),
+            arc_commands: Default::default(),
+            query_archetype_component_accesses: Vec::new(),
+            query_sort_buffers: Vec::new(),
+            query_accesses: Vec::new(),
+            query_type_names: Vec::new(),
+            param_structs: Vec::new(),
+            current_query_index: 0,
+            local_resources: Vec::new(),
+            current_local_index: 0,
+            last_run_tick: 0,
+            run_tick: 0,
+            archetypes_generation: None,
+            is_non_send: false,
+            has_side_channel: false,
+            always_run: false,
+            queries_match_nothing: false,
+        }
+    }
+
+    pub(crate) fn apply_commands(&mut self, world: &mut World, resources: &mut Resources) {
+        self.commands
+            .apply_for_system(world, resources, Some(&self.name));
+        if let Some(ref commands) = self.arc_commands {
+            let mut commands = commands.lock();
+            commands.apply_for_system(world, resources, Some(&self.name));
+        }
+    }
+
+    pub(crate) fn change_ticks(&self) -> ChangeTicks {
+        ChangeTicks {
+            last_change_tick: self.last_run_tick,
+            change_tick: self.run_tick,
+        }
+    }
+
+    /// Marks the start of the fields of the `#[derive(SystemParam)]` struct `name`, so that conflicts between its fields
+    /// name the struct. Called by the derive before initializing the fields.
+    pub fn push_param_struct(&mut self, name: &'static str) {
+        self.param_structs.push(name);
+    }
+
+    /// Marks the end of the fields of the innermost `#[derive(SystemParam)]` struct
+    pub fn pop_param_struct(&mut self) {
+        self.param_structs.pop();
+    }
+
+    /// Describes the parameter `name`, along with the `#[derive(SystemParam)]` struct it is a field of
+    pub(crate) fn param_name(&self, name: &'static str) -> Cow<'static, str> {
+        match self.param_structs.last() {
+            Some(param_struct) => format!("{} in {}", name, param_struct).into(),
+            None => name.into(),
+        }
+    }
+
+    /// Adds a read of the resource `T` for the system parameter `Param`. Panics with a [ResourceConflictError] if an
+    /// earlier parameter of the system writes `T`.
+    pub(crate) fn add_resource_read<T: 'static, Param>(&mut self) {
+        let mut access = TypeAccess::default();
+        access.add_read(TypeId::of::<T>());
+        self.add_resource_access::<T, Param>(access);
+    }
+
+    /// Adds a write of the resource `T` for the system parameter `Param`. Panics with a [ResourceConflictError] if an
+    /// earlier parameter of the system accesses `T`.
+    pub(crate) fn add_resource_write<T: 'static, Param>(&mut self) {
+        let mut access = TypeAccess::default();
+        access.add_write(TypeId::of::<T>());
+        self.add_resource_access::<T, Param>(access);
+    }
+
+    fn add_resource_access<T: 'static, Param>(&mut self, mut access: TypeAccess<TypeId>) {
+        access.set_type_name(TypeId::of::<T>(), std::any::type_name::<T>());
+        if !access.is_compatible(&self.resource_access) {
+            panic!(
+                "{}",
+                ResourceConflictError {
+                    system_name: self.name.clone(),
+                    param: self.param_name(std::any::type_name::<Param>()),
+                    resource: std::any::type_name::<T>(),
+                }
+            );
+        }
+        self.resource_access.union(&access);
+        self.has_side_channel = true;
+    }
+
     pub fn reset_indices(&mut self) {
         self.current_query_index = 0;
+        self.current_local_index = 0;
+    }
+
+    /// Checks the queries of this system against each other using the archetypes currently in the `world`
+    pub fn validate(&self, world: &World) -> Result<(), QueryConflictError> {
+        let query_archetype_component_accesses = self
+            .query_accesses
+            .iter()
+            .map(|query_accesses| {
+                let mut component_access = TypeAccess::default();
+                for query_access in query_accesses.iter() {
+                    query_access.get_world_archetype_access(world, Some(&mut component_access));
+                }
+                component_access
+            })
+            .collect::<Vec<_>>();
+        self.find_conflict(&query_archetype_component_accesses)
     }
 
     pub fn update(&mut self, world: &World) {
+        // the accesses only depend on the world's archetypes, so they are still valid if none were added
+        let archetypes_generation = world.archetypes_generation();
+        if self.archetypes_generation == Some(archetypes_generation) {
+            return;
+        }
+
         self.archetype_component_access.clear();
-        let mut conflict_index = None;
-        let mut conflict_name = None;
-        for (i, (query_accesses, component_access)) in self
+        let mut has_conflict = false;
+        for (query_accesses, component_access) in self
             .query_accesses
             .iter()
             .zip(self.query_archetype_component_accesses.iter_mut())
-            .enumerate()
         {
             component_access.clear();
             for query_access in query_accesses.iter() {
                 query_access.get_world_archetype_access(world, Some(component_access));
             }
             if !component_access.is_compatible(&self.archetype_component_access) {
-                conflict_index = Some(i);
-                conflict_name = component_access
-                    .get_conflict(&self.archetype_component_access)
+                has_conflict = true;
+                break;
+            }
+            self.archetype_component_access.union(component_access);
+        }
+
+        if has_conflict {
+            if let Err(err) = self.find_conflict(&self.query_archetype_component_accesses) {
+                panic!("{}", err);
+            }
+        }
+
+        self.queries_match_nothing = !self.has_side_channel
+            && !self.always_run
+            && !self.query_accesses.is_empty()
+            && self.query_accesses.iter().flatten().all(|query_access| {
+                world.archetypes().enumerate().all(|(index, archetype)| {
+                    query_access
+                        .get_access(archetype, index as u32, None)
+                        .is_none()
+                })
+            });
+        self.archetypes_generation = Some(archetypes_generation);
+    }
+
+    /// Returns true if the system can be skipped because all of its parameters are queries, and none of them matched
+    /// an archetype when the accesses were last computed in [SystemState::update]
+    pub fn can_skip(&self) -> bool {
+        self.queries_match_nothing
+    }
+
+    fn find_conflict(
+        &self,
+        query_archetype_component_accesses: &[TypeAccess<ArchetypeComponent>],
+    ) -> Result<(), QueryConflictError> {
+        let mut system_access = TypeAccess::default();
+        for (conflict_index, component_access) in
+            query_archetype_component_accesses.iter().enumerate()
+        {
+            if component_access.is_compatible(&system_access) {
+                system_access.union(component_access);
+                continue;
+            }
+
+            let component =
+                component_access
+                    .get_conflict(&system_access)
                     .and_then(|archetype_component| {
-                        query_accesses
+                        self.query_accesses[conflict_index]
                             .iter()
                             .filter_map(|query_access| {
                                 query_access.get_type_name(archetype_component.component)
                             })
                             .next()
                     });
-                break;
-            }
-            self.archetype_component_access.union(component_access);
-        }
-        if let Some(conflict_index) = conflict_index {
-            let mut conflicts_with_index = None;
-            for prior_index in 0..conflict_index {
-                if !self.query_archetype_component_accesses[conflict_index]
-                    .is_compatible(&self.query_archetype_component_accesses[prior_index])
-                {
-                    conflicts_with_index = Some(prior_index);
-                }
-            }
-            panic!("System {} has conflicting queries. {} conflicts with the component access [{}] in this prior query: {}",
-                self.name,
-                self.query_type_names[conflict_index],
-                conflict_name.unwrap_or("Unknown"),
-                conflicts_with_index.map(|index| self.query_type_names[index]).unwrap_or("Unknown"));
+            let prior_query = (0..conflict_index)
+                .rev()
+                .find(|prior_index| {
+                    !component_access
+                        .is_compatible(&query_archetype_component_accesses[*prior_index])
+                })
+                .map(|prior_index| self.query_type_names[prior_index].clone());
+            return Err(QueryConflictError {
+                system_name: self.name.clone(),
+                query: self.query_type_names[conflict_index].clone(),
+                prior_query: prior_query.unwrap_or_else(|| "Unknown".into()),
+                component: component.unwrap_or("Unknown"),
+            });
         }
+
+        Ok(())
     }
 }
 
-pub struct FuncSystem<F, Init, ThreadLocalFunc>
+/// An error that occurs when two queries in the same system access the same components in incompatible ways
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("System {system_name} has conflicting queries. {query} conflicts with the component access [{component}] in this prior query: {prior_query}")]
+pub struct QueryConflictError {
+    pub system_name: Cow<'static, str>,
+    pub query: Cow<'static, str>,
+    pub prior_query: Cow<'static, str>,
+    pub component: &'static str,
+}
+
+/// An error that occurs when two parameters of the same system access a resource in incompatible ways, like `Res<T>`
+/// and `ResMut<T>`
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("System {system_name} has conflicting resource access. {param} conflicts with a prior access to the resource [{resource}]")]
+pub struct ResourceConflictError {
+    pub system_name: Cow<'static, str>,
+    pub param: Cow<'static, str>,
+    pub resource: &'static str,
+}
+
+pub struct FuncSystem<Input, Out, F, Init, ThreadLocalFunc>
 where
-    F: FnMut(&mut SystemState, &World, &Resources) + Send + Sync + 'static,
+    F: FnMut(Input, &mut SystemState, &World, &Resources) -> Option<Out> + Send + Sync + 'static,
     Init: FnMut(&mut SystemState, &World, &mut Resources) + Send + Sync + 'static,
     ThreadLocalFunc: FnMut(&mut SystemState, &mut World, &mut Resources) + Send + Sync + 'static,
 {
-    func: F,
-    thread_local_func: ThreadLocalFunc,
-    init_func: Init,
-    state: SystemState,
+    pub(crate) func: F,
+    pub(crate) thread_local_func: ThreadLocalFunc,
+    pub(crate) init_func: Init,
+    pub(crate) state: SystemState,
+    pub(crate) _marker: PhantomData<fn(Input) -> Out>,
 }
 
-impl<F, Init, ThreadLocalFunc> System for FuncSystem<F, Init, ThreadLocalFunc>
+impl<Input, Out, F, Init, ThreadLocalFunc> System
+    for FuncSystem<Input, Out, F, Init, ThreadLocalFunc>
 where
-    F: FnMut(&mut SystemState, &World, &Resources) + Send + Sync + 'static,
+    F: FnMut(Input, &mut SystemState, &World, &Resources) -> Option<Out> + Send + Sync + 'static,
     Init: FnMut(&mut SystemState, &World, &mut Resources) + Send + Sync + 'static,
     ThreadLocalFunc: FnMut(&mut SystemState, &mut World, &mut Resources) + Send + Sync + 'static,
 {
+    type In = Input;
+    type Out = Out;
+
     fn name(&self) -> std::borrow::Cow<'static, str> {
         self.state.name.clone()
     }
 
+    fn set_name(&mut self, name: Cow<'static, str>) {
+        self.state.name = name;
+    }
+
     fn id(&self) -> SystemId {
         self.state.id
     }
@@ -100,6 +318,10 @@ where
         self.state.update(world);
     }
 
+    fn validate(&self, world: &World) -> Result<(), QueryConflictError> {
+        self.state.validate(world)
+    }
+
     fn archetype_component_access(&self) -> &TypeAccess<ArchetypeComponent> {
         &self.state.archetype_component_access
     }
@@ -108,12 +330,32 @@ where
         &self.state.resource_access
     }
 
+    fn can_skip(&self) -> bool {
+        self.state.can_skip()
+    }
+
+    fn set_always_run(&mut self, always_run: bool) {
+        self.state.always_run = always_run;
+        // the skip is cached with the accesses, so they have to be computed again
+        self.state.archetypes_generation = None;
+    }
+
     fn thread_local_execution(&self) -> ThreadLocalExecution {
-        ThreadLocalExecution::NextFlush
+        if self.state.is_non_send {
+            ThreadLocalExecution::Immediate
+        } else {
+            ThreadLocalExecution::NextFlush
+        }
     }
 
-    fn run(&mut self, world: &World, resources: &Resources) {
-        (self.func)(&mut self.state, world, resources)
+    fn run(&mut self, input: Input, world: &World, resources: &Resources) -> Option<Out> {
+        self.state.run_tick = increment_change_tick();
+        let out = (self.func)(input, &mut self.state, world, resources);
+        // a system skipped because one of its params wasn't available hasn't seen the changes yet
+        if out.is_some() {
+            self.state.last_run_tick = self.state.run_tick;
+        }
+        out
     }
 
     fn run_thread_local(&mut self, world: &mut World, resources: &mut Resources) {
@@ -121,7 +363,13 @@ where
     }
 
     fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        // schedules initialize every system again whenever one is added, which must not add the same params twice
+        if self.state.is_initialized {
+            return;
+        }
         (self.init_func)(&mut self.state, world, resources);
+        // initializing can add queries, whose accesses haven't been computed yet
+        self.state.archetypes_generation = None;
         self.state.is_initialized = true;
     }
 
@@ -130,47 +378,37 @@ where
     }
 }
 
-pub trait IntoSystem<Params> {
-    fn system(self) -> Box<dyn System>;
+/// The value passed into a system by the system that runs before it in a [ChainSystem](crate::ChainSystem). It
+/// must be the first parameter of the system.
+pub struct In<T>(pub T);
+
+pub trait IntoSystem<Params, Out = (), Input = ()> {
+    fn system(self) -> BoxedSystem<Input, Out>;
 }
 
 macro_rules! impl_into_system {
     ($($param: ident),*) => {
-        impl<Func, $($param: SystemParam),*> IntoSystem<($($param,)*)> for Func
-        where Func: FnMut($($param),*) + Send + Sync + 'static,
+        impl<Func, Out, $($param: SystemParam),*> IntoSystem<($($param,)*), Out> for Func
+        where
+            Func: FnMut($($param),*) -> Out + Send + Sync + 'static,
+            Out: 'static,
         {
             #[allow(unused_variables)]
             #[allow(unused_unsafe)]
             #[allow(non_snake_case)]
-            fn system(mut self) -> Box<dyn System> {
+            fn system(mut self) -> BoxedSystem<(), Out> {
                 Box::new(FuncSystem {
-                    state: SystemState {
-                        name: std::any::type_name::<Self>().into(),
-                        archetype_component_access: TypeAccess::default(),
-                        resource_access: TypeAccess::default(),
-                        is_initialized: false,
-                        id: SystemId::new(),
-                        commands: Commands::default(),
-                        arc_commands: Default::default(),
-                        query_archetype_component_accesses: Vec::new(),
-                        query_accesses: Vec::new(),
-                        query_type_names: Vec::new(),
-                        current_query_index: 0,
-                    },
-                    func: move |state, world, resources| {
+                    state: SystemState::new(std::any::type_name::<Self>().into()),
+                    _marker: PhantomData,
+                    func: move |_input, state, world, resources| {
                         state.reset_indices();
                         unsafe {
-                            if let Some(($($param,)*)) = <($($param,)*)>::get_param(state, world, resources) {
-                                self($($param),*);
-                            }
+                            <($($param,)*)>::get_param(state, world, resources)
+                                .map(|($($param,)*)| self($($param),*))
                         }
                     },
                     thread_local_func: |state, world, resources| {
-                        state.commands.apply(world, resources);
-                        if let Some(ref commands) = state.arc_commands {
-                            let mut commands = commands.lock();
-                            commands.apply(world, resources);
-                        }
+                        state.apply_commands(world, resources);
                     },
                     init_func: |state, world, resources| {
                         $($param::init(state, world, resources);)*
@@ -179,9 +417,41 @@ macro_rules! impl_into_system {
             }
         }
 
+        impl<Func, Input, Out, $($param: SystemParam),*> IntoSystem<(In<Input>, $($param,)*), Out, Input> for Func
+        where
+            Func: FnMut(In<Input>, $($param),*) -> Out + Send + Sync + 'static,
+            Input: 'static,
+            Out: 'static,
+        {
+            #[allow(unused_variables)]
+            #[allow(unused_unsafe)]
+            #[allow(non_snake_case)]
+            fn system(mut self) -> BoxedSystem<Input, Out> {
+                Box::new(FuncSystem {
+                    state: SystemState::new(std::any::type_name::<Self>().into()),
+                    _marker: PhantomData,
+                    func: move |input, state, world, resources| {
+                        state.reset_indices();
+                        unsafe {
+                            <($($param,)*)>::get_param(state, world, resources)
+                                .map(|($($param,)*)| self(In(input), $($param),*))
+                        }
+                    },
+                    thread_local_func: |state, world, resources| {
+                        state.apply_commands(world, resources);
+                    },
+                    init_func: |state, world, resources| {
+                        // the input is passed in by the system before this one, which needs it to be consumed
+                        state.has_side_channel = true;
+                        $($param::init(state, world, resources);)*
+                    },
+                })
+            }
+        }
     };
 }
 
+// Systems that need more parameters than this can group them into tuples, which are SystemParams themselves
 impl_into_system!();
 impl_into_system!(A);
 impl_into_system!(A, B);
@@ -204,11 +474,24 @@ impl_into_system!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
 mod tests {
     use super::IntoSystem;
     use crate::{
-        resource::{ResMut, Resources},
-        schedule::Schedule,
-        ChangedRes, Query, QuerySet, System,
+        resource::{NonSend, NonSendMut, ResMut, Resources},
+        schedule::{ParallelExecutor, Schedule},
+        AlwaysRunExt, BoxedSystem, ChangedRes, Commands, Local, Query, QueryError, QuerySet,
+        RemovedComponents, Res, SystemNameExt, SystemParam, ThreadLocalExecution,
+    };
+    use bevy_hecs::{
+        ArchetypeComponent, ChangeTrackers, Changed, Entity, Mutated, Or, With, Without, World,
+    };
+    use bevy_tasks::{ComputeTaskPool, TaskPoolBuilder};
+    use std::{
+        any::{type_name, TypeId},
+        cell::Cell,
+        rc::Rc,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
     };
-    use bevy_hecs::{Entity, Or, With, World};
 
     #[derive(Debug, Eq, PartialEq)]
     struct A;
@@ -275,10 +558,96 @@ mod tests {
         assert!(*resources.get::<bool>().unwrap(), "system ran");
     }
 
+    #[test]
+    fn query_system_gets_mut() {
+        fn query_system(
+            mut ran: ResMut<bool>,
+            entity_query: Query<Entity, With<A>>,
+            mut b_query: Query<&mut B>,
+            mut a_c_query: Query<(&A, &mut C)>,
+            mut d_query: Query<&D>,
+        ) {
+            let entities = entity_query.iter().collect::<Vec<Entity>>();
+            assert_eq!(
+                b_query.get_component_mut::<B>(entities[0]).err(),
+                Some(QueryError::MissingComponent(type_name::<B>())),
+                "entity 0 should not have B"
+            );
+            *b_query
+                .get_component_mut::<B>(entities[1])
+                .expect("entity 1 should have B") = B;
+            assert_eq!(
+                b_query.get_component_mut::<A>(entities[1]).err(),
+                Some(QueryError::AccessNotDeclared(type_name::<A>())),
+                "entity 1 should have A, but b_query shouldn't have access to it"
+            );
+            assert_eq!(
+                a_c_query.get_component_mut::<A>(entities[2]).err(),
+                Some(QueryError::AccessNotDeclared(type_name::<A>())),
+                "entity 2 has A, but a_c_query only reads it"
+            );
+            *a_c_query
+                .get_component_mut::<C>(entities[2])
+                .expect("entity 2 has C, and a_c_query should be able to write it") = C;
+            assert_eq!(
+                a_c_query.get_mut(entities[3]).err(),
+                Some(QueryError::QueryDoesNotMatch),
+                "entity 3 doesn't have C"
+            );
+            let (_a, mut c) = a_c_query.get_mut(entities[4]).unwrap();
+            *c = C;
+            assert_eq!(
+                d_query.get_component_mut::<D>(entities[3]).err(),
+                Some(QueryError::AccessNotDeclared(type_name::<D>())),
+                "entity 3 has D, but d_query only reads it"
+            );
+
+            *ran = true;
+        }
+
+        fn collect_mutated(
+            b_query: Query<Entity, Mutated<B>>,
+            c_query: Query<Entity, Mutated<C>>,
+            d_query: Query<Entity, Mutated<D>>,
+            mut mutated: ResMut<Vec<Vec<Entity>>>,
+        ) {
+            mutated.push(b_query.iter().collect());
+            mutated.push(c_query.iter().collect());
+            mutated.push(d_query.iter().collect());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(false);
+        resources.insert(Vec::<Vec<Entity>>::new());
+        world.spawn((A,));
+        let with_b = world.spawn((A, B));
+        let with_c = world.spawn((A, C));
+        world.spawn((A, D));
+        let with_c_d = world.spawn((A, C, D));
+        world.clear_trackers();
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("detect");
+        schedule.add_system_to_stage("update", query_system.system());
+        schedule.add_system_to_stage("detect", collect_mutated.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert!(*resources.get::<bool>().unwrap(), "system ran");
+        assert_eq!(
+            *resources.get::<Vec<Vec<Entity>>>().unwrap(),
+            vec![vec![with_b], vec![with_c, with_c_d], vec![]],
+            "writes through get_mut and get_component_mut are detected like writes through iter_mut"
+        );
+    }
+
     #[test]
     fn or_query_set_system() {
         // Regression test for issue #762
         use crate::{Added, Changed, Mutated, Or};
+        #[allow(clippy::type_complexity)]
         let query_system = move |mut ran: ResMut<bool>,
                                  set: QuerySet<(
             Query<(), Or<(Changed<A>, Changed<B>)>>,
@@ -306,6 +675,58 @@ mod tests {
         assert!(*resources.get::<bool>().unwrap(), "system ran");
     }
 
+    #[test]
+    fn non_send_system() {
+        fn increment(counter: NonSend<Rc<Cell<i32>>>, mut runs: ResMut<usize>) {
+            counter.set(counter.get() + 1);
+            *runs += 1;
+        }
+
+        fn double(mut counter: NonSendMut<Rc<Cell<i32>>>) {
+            *counter = Rc::new(Cell::new(counter.get() * 2));
+        }
+
+        fn parallel(_query: Query<&A>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert_thread_local(Rc::new(Cell::new(1)));
+        resources.insert(0usize);
+        resources.insert(ComputeTaskPool(
+            TaskPoolBuilder::default().num_threads(4).build(),
+        ));
+        world.spawn((A,));
+
+        let mut increment = increment.system();
+        increment.initialize(&mut world, &mut resources);
+        assert_eq!(
+            increment.thread_local_execution(),
+            ThreadLocalExecution::Immediate
+        );
+        assert!(increment
+            .resource_access()
+            .is_read_or_write(&TypeId::of::<Rc<Cell<i32>>>()));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", parallel.system());
+        schedule.add_system_to_stage("update", increment);
+        schedule.add_system_to_stage("update", parallel.system());
+        schedule.add_system_to_stage("update", double.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        let mut executor = ParallelExecutor::default();
+        for _ in 0..3 {
+            executor.run(&mut schedule, &mut world, &mut resources);
+        }
+        // (((1 + 1) * 2 + 1) * 2 + 1) * 2
+        assert_eq!(
+            resources.get_thread_local::<Rc<Cell<i32>>>().unwrap().get(),
+            22
+        );
+        assert_eq!(*resources.get::<usize>().unwrap(), 3);
+    }
+
     #[test]
     fn changed_resource_system() {
         fn incr_e_on_flip(_run_on_flip: ChangedRes<bool>, mut query: Query<&mut i32>) {
@@ -335,8 +756,85 @@ mod tests {
         assert_eq!(*(world.get::<i32>(ent).unwrap()), 2);
     }
 
+    #[test]
+    fn res_is_changed() {
+        fn record(
+            value: Res<bool>,
+            mut counter: ResMut<u32>,
+            mut changes: ResMut<Vec<(bool, bool)>>,
+        ) {
+            // like with ChangedRes, the system's own mutations from its last run don't count
+            let counter_changed_before = counter.is_changed();
+            *counter += 1;
+            assert!(counter.is_changed());
+            changes.push((value.is_changed(), counter_changed_before));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(false);
+        resources.insert(0u32);
+        resources.insert(Vec::<(bool, bool)>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", record.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        // inserting counts as a change
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        *resources.get_mut::<bool>().unwrap() = true;
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(
+            *resources.get::<Vec<(bool, bool)>>().unwrap(),
+            vec![(true, true), (false, false), (true, false), (false, false)]
+        );
+    }
+
+    #[test]
+    fn changed_resource_inserted() {
+        fn count_changes(_changed: ChangedRes<bool>, mut changes: ResMut<usize>) {
+            *changes += 1;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(0usize);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", count_changes.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 0);
+
+        resources.insert(false);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 1);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 1);
+
+        // replacing the resource counts as a change too
+        resources.insert(false);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 2);
+
+        // so does inserting it lazily, but only when it was missing
+        resources.remove::<bool>();
+        resources.get_or_insert_with(|| true);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 3);
+        resources.get_or_insert_with(|| false);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 3);
+    }
+
     #[test]
     fn changed_resource_or_system() {
+        #[allow(clippy::type_complexity)]
         fn incr_e_on_flip(
             _or: Or<(Option<ChangedRes<bool>>, Option<ChangedRes<i32>>)>,
             mut query: Query<&mut i32>,
@@ -375,6 +873,124 @@ mod tests {
         assert_eq!(*(world.get::<i32>(ent).unwrap()), 3);
     }
 
+    #[test]
+    fn changed_query_in_earlier_stage() {
+        fn count_changed(query: Query<&i32, Changed<i32>>, mut changed: ResMut<Vec<usize>>) {
+            changed.push(query.iter().count());
+        }
+
+        fn mutate_once(mut query: Query<&mut i32>, mut ran: Local<bool>) {
+            if !*ran {
+                for mut i in query.iter_mut() {
+                    *i += 1;
+                }
+                *ran = true;
+            }
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<usize>::new());
+        world.spawn((0,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("first");
+        schedule.add_stage("last");
+        schedule.add_system_to_stage("first", count_changed.system());
+        schedule.add_system_to_stage("last", mutate_once.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        for _ in 0..3 {
+            schedule.run(&mut world, &mut resources);
+        }
+
+        // the spawn is seen in the first frame and the mutation made after the reader ran is seen in the second
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![1, 1, 0]);
+    }
+
+    #[test]
+    fn change_trackers_visit_every_entity() {
+        // (visited, uploaded) for every frame
+        fn sync(query: Query<(&i32, ChangeTrackers<i32>)>, mut synced: ResMut<Vec<(usize, i32)>>) {
+            let mut visited = 0;
+            let mut uploaded = 0;
+            for (value, trackers) in query.iter() {
+                visited += 1;
+                if trackers.is_changed() {
+                    uploaded += *value;
+                }
+            }
+            synced.push((visited, uploaded));
+        }
+
+        fn bump_first(mut query: Query<&mut i32>, mut frame: Local<usize>) {
+            *frame += 1;
+            if *frame == 2 {
+                if let Some(mut value) = query.iter_mut().find(|value| **value == 1) {
+                    *value = 10;
+                }
+            }
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<(usize, i32)>::new());
+        world.spawn_batch((1..=3).map(|i| (i,)));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("sync");
+        schedule.add_system_to_stage("update", bump_first.system());
+        schedule.add_system_to_stage("sync", sync.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        for _ in 0..3 {
+            schedule.run(&mut world, &mut resources);
+        }
+
+        assert_eq!(
+            *resources.get::<Vec<(usize, i32)>>().unwrap(),
+            vec![(3, 6), (3, 10), (3, 0)]
+        );
+    }
+
+    #[test]
+    fn changed_resource_in_earlier_stage() {
+        fn count_flips(_flip: ChangedRes<bool>, mut flips: ResMut<usize>) {
+            *flips += 1;
+        }
+
+        fn flip(mut flip: ResMut<bool>, mut frame: Local<usize>) {
+            if *frame == 1 {
+                *flip = !*flip;
+            }
+            *frame += 1;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(false);
+        resources.insert(0usize);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("first");
+        schedule.add_stage("last");
+        schedule.add_system_to_stage("first", count_flips.system());
+        schedule.add_system_to_stage("last", flip.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 1);
+
+        // flip changes the resource after count_flips ran, so it is only seen in the next frame
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 1);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 2);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 2);
+    }
+
     #[test]
     #[should_panic]
     fn conflicting_query_mut_system() {
@@ -388,36 +1004,468 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn conflicting_query_immut_system() {
-        fn sys(_q1: Query<&A>, _q2: Query<&mut A>) {}
+    #[should_panic(
+        expected = "has conflicting resource access. bevy_ecs::resource::resource_query::ResMut<'_, i32> conflicts with a prior access to the resource [i32]"
+    )]
+    fn conflicting_resource_mut_system() {
+        fn sys(_r1: Res<i32>, _r2: ResMut<i32>) {}
 
         let mut world = World::default();
         let mut resources = Resources::default();
-        world.spawn((A,));
+        resources.insert(0i32);
 
         run_system(&mut world, &mut resources, sys.system());
     }
 
     #[test]
-    fn query_set_system() {
-        fn sys(_set: QuerySet<(Query<&mut A>, Query<&B>)>) {}
+    #[should_panic(expected = "conflicts with a prior access to the resource [i32]")]
+    fn conflicting_resource_immut_system() {
+        fn sys(_r1: ResMut<i32>, _r2: Option<Res<i32>>) {}
 
         let mut world = World::default();
         let mut resources = Resources::default();
-        world.spawn((A,));
+        resources.insert(0i32);
 
         run_system(&mut world, &mut resources, sys.system());
     }
 
     #[test]
-    #[should_panic]
-    fn conflicting_query_with_query_set_system() {
-        fn sys(_query: Query<&mut A>, _set: QuerySet<(Query<&mut A>, Query<&B>)>) {}
+    fn compatible_resource_system() {
+        fn sys(_r1: Res<i32>, _r2: Res<i32>, _r3: ChangedRes<i32>, _r4: ResMut<u32>) {}
 
         let mut world = World::default();
         let mut resources = Resources::default();
-        world.spawn((A,));
+        resources.insert(0i32);
+        resources.insert(0u32);
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    #[should_panic]
+    fn conflicting_query_immut_system() {
+        fn sys(_q1: Query<&A>, _q2: Query<&mut A>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A,));
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    fn update_tracks_new_archetypes() {
+        fn sys(_query: Query<&A>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let a = world.spawn((A,));
+
+        let mut system = sys.system();
+        system.initialize(&mut world, &mut resources);
+        system.update(&world);
+        let archetype_a = world.get_entity_location(a).unwrap().archetype;
+        assert!(system
+            .archetype_component_access()
+            .is_read_or_write(&ArchetypeComponent::new::<A>(archetype_a)));
+
+        // unrelated archetypes don't add accesses, but the cached access is still recomputed for them
+        let b = world.spawn((B,));
+        system.update(&world);
+        let archetype_b = world.get_entity_location(b).unwrap().archetype;
+        assert!(!system
+            .archetype_component_access()
+            .is_read_or_write(&ArchetypeComponent::new::<B>(archetype_b)));
+
+        let ab = world.spawn((A, B));
+        system.update(&world);
+        let archetype_ab = world.get_entity_location(ab).unwrap().archetype;
+        assert!(system
+            .archetype_component_access()
+            .is_read_or_write(&ArchetypeComponent::new::<A>(archetype_ab)));
+    }
+
+    #[test]
+    fn query_matches_archetype_added_by_commands() {
+        fn spawn_once(commands: &mut Commands, mut spawned: Local<bool>) {
+            if !*spawned {
+                commands.spawn((A, B));
+                *spawned = true;
+            }
+        }
+
+        fn count(query: Query<&A>, mut counts: ResMut<Vec<usize>>) {
+            counts.push(query.iter().count());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<usize>::new());
+        world.spawn((A,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("spawn");
+        schedule.add_stage("count");
+        schedule.add_system_to_stage("spawn", spawn_once.system());
+        schedule.add_system_to_stage("count", count.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![2, 2]);
+    }
+
+    #[test]
+    fn named_system() {
+        let system = (|_query: Query<&mut A>| {})
+            .system()
+            .with_name("player_movement");
+        assert_eq!(system.name(), "player_movement");
+    }
+
+    #[test]
+    #[should_panic(expected = "System player_movement has conflicting queries")]
+    fn named_system_conflict() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A,));
+
+        let system = (|_q1: Query<&A>, _q2: Query<&mut A>| {})
+            .system()
+            .with_name("player_movement");
+        run_system(&mut world, &mut resources, system);
+    }
+
+    struct Time {
+        delta: f32,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Transform(f32);
+
+    #[derive(SystemParam)]
+    struct MovementParams<'a> {
+        time: Res<'a, Time>,
+        q: Query<'a, &'a mut Transform>,
+    }
+
+    #[test]
+    fn derived_system_param() {
+        fn movement(mut params: MovementParams, mut moves: Local<usize>) {
+            let delta = params.time.delta;
+            for mut transform in params.q.iter_mut() {
+                transform.0 += delta;
+                *moves += 1;
+            }
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Time { delta: 0.5 });
+        let entity = world.spawn((Transform(1.0),));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", movement.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(*world.get::<Transform>(entity).unwrap(), Transform(2.0));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "in this prior query: &mut bevy_ecs::system::into_system::tests::Transform in bevy_ecs::system::into_system::tests::MovementParams"
+    )]
+    fn derived_system_param_conflict() {
+        fn sys(_params: MovementParams, _transforms: Query<&Transform>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Time { delta: 0.5 });
+        world.spawn((Transform(1.0),));
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    fn schedule_reports_all_conflicts() {
+        fn conflicting_a(_q1: Query<&A>, _q2: Query<&mut A>) {}
+        fn conflicting_b(_q1: Query<&mut B>, _q2: Query<(&A, &mut B)>) {}
+        fn valid(_q1: Query<&mut A>, _q2: Query<&B>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A, B));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", conflicting_a.system());
+        schedule.add_system_to_stage("update", valid.system());
+        schedule.add_system_to_stage("update", conflicting_b.system());
+
+        let err = schedule
+            .try_initialize(&mut world, &mut resources)
+            .unwrap_err();
+        assert_eq!(err.conflicts.len(), 2);
+
+        assert_eq!(err.conflicts[0].query, std::any::type_name::<&mut A>());
+        assert_eq!(err.conflicts[0].prior_query, std::any::type_name::<&A>());
+        assert_eq!(err.conflicts[0].component, std::any::type_name::<A>());
+
+        assert_eq!(
+            err.conflicts[1].query,
+            std::any::type_name::<(&A, &mut B)>()
+        );
+        assert_eq!(
+            err.conflicts[1].prior_query,
+            std::any::type_name::<&mut B>()
+        );
+        assert_eq!(err.conflicts[1].component, std::any::type_name::<B>());
+    }
+
+    #[test]
+    fn schedule_reports_conflicts_until_fixed() {
+        fn conflicting(_q1: Query<&A>, _q2: Query<&mut A>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", conflicting.system());
+
+        let err = schedule
+            .try_initialize(&mut world, &mut resources)
+            .unwrap_err();
+        assert_eq!(err.conflicts.len(), 1);
+        let err = schedule
+            .try_initialize(&mut world, &mut resources)
+            .unwrap_err();
+        assert_eq!(err.conflicts.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "has conflicting queries")]
+    fn conflicting_query_without_system() {
+        fn sys(_q1: Query<&A, Without<B>>, _q2: Query<&mut A>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A,));
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    fn query_without_system() {
+        fn sys(_q1: Query<&A, Without<B>>, _q2: Query<&mut B>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A,));
+        world.spawn((A, B));
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    fn disjoint_query_with_without_system() {
+        fn sys(_q1: Query<&mut A, With<B>>, _q2: Query<&mut A, Without<B>>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A,));
+        world.spawn((A, B));
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    fn disjoint_query_with_system() {
+        // no entity has both B and C, so the queries never write the same A
+        fn sys(_q1: Query<&mut A, With<B>>, _q2: Query<&mut A, With<C>>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A, B));
+        world.spawn((A, C));
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    #[should_panic(expected = "has conflicting queries")]
+    fn overlapping_query_with_system() {
+        fn sys(_q1: Query<&mut A, With<B>>, _q2: Query<&mut A, With<C>>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A, B));
+        world.spawn((A, C));
+        world.spawn((A, B, C));
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    fn query_set_system() {
+        fn sys(_set: QuerySet<(Query<&mut A>, Query<&B>)>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A,));
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    fn six_query_set_system() {
+        #[allow(clippy::type_complexity)]
+        fn sys(
+            mut set: QuerySet<(
+                Query<&mut A>,
+                Query<&A>,
+                Query<(&A, &B)>,
+                Query<&mut B, With<C>>,
+                Query<&C>,
+                Query<Entity, With<D>>,
+            )>,
+            mut counts: ResMut<Vec<usize>>,
+        ) {
+            let counts = &mut *counts;
+            counts.push(set.q0_mut().iter_mut().count());
+            counts.push(set.q1().iter().count());
+            counts.push(set.q2().iter().count());
+            counts.push(set.q3_mut().iter_mut().count());
+            counts.push(set.q4().iter().count());
+            counts.push(set.q5().iter().count());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<usize>::new());
+        world.spawn((A,));
+        world.spawn((A, B));
+        world.spawn((B, C));
+        world.spawn((C, D));
+
+        run_system(&mut world, &mut resources, sys.system());
+        assert_eq!(
+            *resources.get::<Vec<usize>>().unwrap(),
+            vec![2, 2, 1, 1, 2, 1]
+        );
+    }
+
+    #[test]
+    fn filtered_query_set_system() {
+        #[allow(clippy::type_complexity)]
+        fn sys(
+            mut set: QuerySet<(
+                Query<&mut A, Without<B>>,
+                Query<&A, With<B>>,
+                Query<Entity, Or<(With<C>, With<D>)>>,
+                Query<(&mut B, Option<&C>), (With<A>, Without<D>)>,
+                Query<&mut C, Or<(Without<A>, With<D>)>>,
+            )>,
+            mut counts: ResMut<Vec<usize>>,
+        ) {
+            let counts = &mut *counts;
+            counts.push(set.q0_mut().iter_mut().count());
+            counts.push(set.q1().iter().count());
+            counts.push(set.q2().iter().count());
+            counts.push(set.q3_mut().iter_mut().count());
+            counts.push(set.q4_mut().iter_mut().count());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<usize>::new());
+        world.spawn((A,));
+        world.spawn((A, B));
+        world.spawn((A, B, C));
+        world.spawn((B, C));
+        world.spawn((C, D));
+        world.spawn((A, C, D));
+
+        run_system(&mut world, &mut resources, sys.system());
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![2, 2, 4, 2, 3]);
+    }
+
+    #[test]
+    fn nested_or_filter_access() {
+        #[allow(clippy::type_complexity)]
+        fn read_nested(
+            _query: Query<Entity, Or<(With<A>, (Without<B>, Or<(With<D>, Changed<C>)>))>>,
+        ) {
+        }
+        fn write_c_with_b(_query: Query<&mut C, With<B>>) {}
+        fn write_c_without_b(_query: Query<&mut C, Without<B>>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A, B, C));
+        world.spawn((C,));
+        world.spawn((C, D));
+
+        let mut access = |mut system: BoxedSystem| {
+            system.initialize(&mut world, &mut resources);
+            system.update(&world);
+            system.archetype_component_access().clone()
+        };
+        let nested = access(read_nested.system());
+        let with_b = access(write_c_with_b.system());
+        let without_b = access(write_c_without_b.system());
+        // entities with a B only match the nested filter through With<A>, which doesn't read C
+        assert!(nested.is_compatible(&with_b));
+        // Changed<C> reads C in every archetype without a B, even in the one where With<D> matches already
+        assert!(!nested.is_compatible(&without_b));
+    }
+
+    #[test]
+    fn conflicting_query_with_large_query_set_system() {
+        #[allow(clippy::type_complexity)]
+        fn sys(
+            _query: Query<&mut A>,
+            _set: QuerySet<(
+                Query<&B>,
+                Query<&C>,
+                Query<&D>,
+                Query<(&B, &C)>,
+                Query<(&C, &D)>,
+                Query<&A>,
+            )>,
+        ) {
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A, B, C, D));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", sys.system());
+        let err = schedule
+            .try_initialize(&mut world, &mut resources)
+            .unwrap_err();
+        assert_eq!(err.conflicts.len(), 1);
+        assert_eq!(
+            err.conflicts[0].prior_query,
+            std::any::type_name::<&mut A>()
+        );
+        assert_eq!(err.conflicts[0].component, std::any::type_name::<A>());
+    }
+
+    #[test]
+    #[should_panic]
+    fn conflicting_query_with_query_set_system() {
+        fn sys(_query: Query<&mut A>, _set: QuerySet<(Query<&mut A>, Query<&B>)>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A,));
 
         run_system(&mut world, &mut resources, sys.system());
     }
@@ -433,7 +1481,422 @@ mod tests {
         run_system(&mut world, &mut resources, sys.system());
     }
 
-    fn run_system(world: &mut World, resources: &mut Resources, system: Box<dyn System>) {
+    #[test]
+    fn nested_param_tuple_system() {
+        #[allow(clippy::type_complexity)]
+        fn sys(
+            mut ran: ResMut<bool>,
+            (small, medium, large): (Res<u8>, Res<u16>, Res<u32>),
+            ((a_query, mut b_query), c_query): ((Query<&A>, Query<&mut B>), Query<(&A, &C)>),
+        ) {
+            assert_eq!((*small, *medium, *large), (1, 2, 3));
+            assert_eq!(a_query.iter().count(), 2);
+            assert_eq!(b_query.iter_mut().count(), 1);
+            assert_eq!(c_query.iter().count(), 1);
+            *ran = true;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(false);
+        resources.insert(1u8);
+        resources.insert(2u16);
+        resources.insert(3u32);
+        world.spawn((A, B));
+        world.spawn((A, C));
+
+        run_system(&mut world, &mut resources, sys.system());
+
+        assert!(*resources.get::<bool>().unwrap(), "system ran");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "&mut bevy_ecs::system::into_system::tests::A conflicts with the component access [bevy_ecs::system::into_system::tests::A] in this prior query: &bevy_ecs::system::into_system::tests::A"
+    )]
+    fn conflicting_nested_param_tuple_system() {
+        fn sys(_a_query: Query<&A>, _nested: (Res<bool>, (Query<&B>, Query<&mut A>))) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(false);
+        world.spawn((A, B));
+
+        run_system(&mut world, &mut resources, sys.system());
+    }
+
+    #[test]
+    fn local_system() {
+        fn count(mut count: Local<u32>, mut counts: ResMut<Vec<u32>>) {
+            *count += 1;
+            counts.push(*count);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<u32>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", count.system());
+        schedule.add_system_to_stage("update", count.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(
+            *resources.get::<Vec<u32>>().unwrap(),
+            vec![1, 1, 2, 2, 3, 3],
+            "each system instance should have its own counter"
+        );
+        assert!(
+            !resources.contains::<u32>(),
+            "local state should not be stored as a global resource"
+        );
+    }
+
+    #[test]
+    fn local_system_resource_access() {
+        fn local(mut count: Local<usize>) {
+            *count += 1;
+        }
+        fn write(mut count: ResMut<usize>) {
+            *count += 1;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(0usize);
+
+        let mut local_system = local.system();
+        let mut write_system = write.system();
+        local_system.initialize(&mut world, &mut resources);
+        write_system.initialize(&mut world, &mut resources);
+
+        assert!(
+            local_system.resource_access().iter_reads().next().is_none()
+                && local_system
+                    .resource_access()
+                    .iter_writes()
+                    .next()
+                    .is_none(),
+            "Local should not register resource access"
+        );
+        assert!(local_system
+            .resource_access()
+            .is_compatible(write_system.resource_access()));
+    }
+
+    #[test]
+    fn local_system_inserted_value() {
+        fn count(mut count: Local<u32>, mut counts: ResMut<Vec<u32>>) {
+            *count += 1;
+            counts.push(*count);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<u32>::new());
+
+        let system = count.system();
+        let mut commands = Commands::default();
+        commands.insert_local_resource(system.id(), 10u32);
+        commands.apply(&mut world, &mut resources);
+
+        run_system(&mut world, &mut resources, system);
+
+        assert_eq!(*resources.get::<Vec<u32>>().unwrap(), vec![11]);
+    }
+
+    #[test]
+    fn optional_resource_system() {
+        fn sys(count: Option<Res<u32>>, total: Option<ResMut<u64>>, mut runs: ResMut<Vec<bool>>) {
+            if let (Some(count), Some(mut total)) = (count, total) {
+                *total += *count as u64;
+            }
+            runs.push(true);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<bool>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", sys.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Vec<bool>>().unwrap().len(), 1);
+
+        resources.insert(2u32);
+        resources.insert(0u64);
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Vec<bool>>().unwrap().len(), 3);
+        assert_eq!(*resources.get::<u64>().unwrap(), 4);
+    }
+
+    #[test]
+    fn optional_resource_access() {
+        fn sys(_count: Option<Res<u32>>, _total: Option<ResMut<u64>>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut system = sys.system();
+        system.initialize(&mut world, &mut resources);
+
+        let resource_access = system.resource_access();
+        assert!(resource_access.is_read_or_write(&std::any::TypeId::of::<u32>()));
+        assert!(!resource_access.is_write(&std::any::TypeId::of::<u32>()));
+        assert!(resource_access.is_write(&std::any::TypeId::of::<u64>()));
+    }
+
+    #[test]
+    fn removed_components_system() {
+        fn remove(commands: &mut Commands, query: Query<(Entity, &A, &B)>) {
+            for (entity, _a, _b) in query.iter() {
+                commands.remove_one::<A>(entity);
+            }
+        }
+        fn despawn(commands: &mut Commands, query: Query<(Entity, &C)>) {
+            for (entity, _c) in query.iter() {
+                commands.despawn(entity);
+            }
+        }
+        fn read(removed: RemovedComponents<A>, mut entities: ResMut<Vec<Entity>>) {
+            entities.extend(removed.iter());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<Entity>::new());
+        let removed_entity = world.spawn((A, B));
+        let despawned_entity = world.spawn((A, C));
+        world.spawn((A,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("remove");
+        schedule.add_stage("read");
+        schedule.add_system_to_stage("remove", remove.system());
+        schedule.add_system_to_stage("remove", despawn.system());
+        schedule.add_system_to_stage("read", read.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        let mut entities = resources.get::<Vec<Entity>>().unwrap().to_vec();
+        entities.sort();
+        let mut expected = vec![removed_entity, despawned_entity];
+        expected.sort();
+        assert_eq!(entities, expected);
+
+        // removals are cleared at the end of the run
+        resources.get_mut::<Vec<Entity>>().unwrap().clear();
+        schedule.run(&mut world, &mut resources);
+        assert!(resources.get::<Vec<Entity>>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn non_send_system_runs_on_main_thread() {
+        struct Handle(Rc<std::thread::ThreadId>);
+
+        fn record(handle: NonSend<Handle>, mut threads: ResMut<Vec<std::thread::ThreadId>>) {
+            assert_eq!(*handle.0, std::thread::current().id());
+            threads.push(std::thread::current().id());
+        }
+
+        fn parallel(_query: Query<&A>) {}
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert_thread_local(Handle(Rc::new(std::thread::current().id())));
+        resources.insert(Vec::<std::thread::ThreadId>::new());
+        resources.insert(ComputeTaskPool(
+            TaskPoolBuilder::default().num_threads(4).build(),
+        ));
+        world.spawn((A,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        for _ in 0..4 {
+            schedule.add_system_to_stage("update", parallel.system());
+            schedule.add_system_to_stage("update", record.system());
+        }
+        schedule.initialize(&mut world, &mut resources);
+
+        let mut executor = ParallelExecutor::default();
+        for _ in 0..3 {
+            executor.run(&mut schedule, &mut world, &mut resources);
+        }
+        let threads = resources.get::<Vec<std::thread::ThreadId>>().unwrap();
+        assert_eq!(threads.len(), 12);
+        assert!(threads
+            .iter()
+            .all(|thread| *thread == std::thread::current().id()));
+    }
+
+    #[test]
+    fn query_combinations_system() {
+        struct Position(i32);
+        struct Velocity(i32);
+
+        fn pairs(query: Query<(Entity, &A)>, mut combinations: ResMut<Vec<Vec<Entity>>>) {
+            combinations.extend(query.iter_combinations::<2>().map(|[a, b]| vec![a.0, b.0]));
+        }
+
+        fn triples(query: Query<Entity, With<A>>, mut combinations: ResMut<Vec<Vec<Entity>>>) {
+            combinations.extend(query.iter_combinations::<3>().map(|items| items.to_vec()));
+        }
+
+        fn too_many(query: Query<Entity, With<A>>, mut combinations: ResMut<Vec<Vec<Entity>>>) {
+            combinations.extend(query.iter_combinations::<5>().map(|items| items.to_vec()));
+        }
+
+        // every body pulls every other body towards itself
+        fn attract(mut query: Query<(&Position, &mut Velocity)>) {
+            let mut combinations = query.iter_combinations_mut::<2>();
+            while let Some([(position_a, mut velocity_a), (position_b, mut velocity_b)]) =
+                combinations.fetch_next()
+            {
+                velocity_a.0 += position_b.0 - position_a.0;
+                velocity_b.0 += position_a.0 - position_b.0;
+            }
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<Vec<Entity>>::new());
+        let mut entities = [
+            world.spawn((A,)),
+            world.spawn((A, B)),
+            world.spawn((A,)),
+            world.spawn((A, C)),
+        ];
+        world.spawn((B,));
+        entities.sort();
+
+        let mut check = |system: BoxedSystem, k: usize, count: usize| {
+            run_system(&mut world, &mut resources, system);
+            let combinations =
+                std::mem::take(&mut *resources.get_mut::<Vec<Vec<Entity>>>().unwrap());
+            assert_eq!(combinations.len(), count);
+            let mut sorted = combinations
+                .into_iter()
+                .map(|mut combination| {
+                    assert_eq!(combination.len(), k);
+                    combination.sort();
+                    combination.dedup();
+                    assert_eq!(
+                        combination.len(),
+                        k,
+                        "no entity appears twice in a combination"
+                    );
+                    assert!(combination.iter().all(|entity| entities.contains(entity)));
+                    combination
+                })
+                .collect::<Vec<_>>();
+            sorted.sort();
+            sorted.dedup();
+            assert_eq!(sorted.len(), count, "every combination is unique");
+        };
+        check(pairs.system(), 2, 6);
+        check(triples.system(), 3, 4);
+        check(too_many.system(), 5, 0);
+
+        let bodies = [
+            world.spawn((Position(0), Velocity(0))),
+            world.spawn((Position(1), Velocity(0), A)),
+            world.spawn((Position(3), Velocity(0))),
+        ];
+        run_system(&mut world, &mut resources, attract.system());
+        let velocities = bodies
+            .iter()
+            .map(|entity| world.get::<Velocity>(*entity).unwrap().0)
+            .collect::<Vec<_>>();
+        assert_eq!(velocities, vec![4, 1, -5]);
+    }
+
+    #[test]
+    fn skip_empty_queries() {
+        struct Burning;
+
+        fn counted(runs: &Arc<AtomicUsize>) -> impl FnMut(Query<&Burning>) + Send + Sync + 'static {
+            let runs = runs.clone();
+            move |_query| {
+                runs.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let only_query = Arc::new(AtomicUsize::new(0));
+        let always_run = Arc::new(AtomicUsize::new(0));
+        let entities = Arc::new(AtomicUsize::new(0));
+        let commands = Arc::new(AtomicUsize::new(0));
+        let runs = [&only_query, &always_run, &entities, &commands];
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", counted(&only_query).system());
+        schedule.add_system_to_stage("update", counted(&always_run).system().always_run());
+        // a query without components matches every archetype, including the empty one every world has
+        let entities_runs = entities.clone();
+        schedule.add_system_to_stage(
+            "update",
+            (move |_query: Query<Entity>| {
+                entities_runs.fetch_add(1, Ordering::Relaxed);
+            })
+            .system(),
+        );
+        // commands can have an effect without any matching entities
+        let commands_runs = commands.clone();
+        schedule.add_system_to_stage(
+            "update",
+            (move |_query: Query<&Burning>, commands: &mut Commands| {
+                commands.spawn((A,));
+                commands_runs.fetch_add(1, Ordering::Relaxed);
+            })
+            .system(),
+        );
+
+        let mut executor = ParallelExecutor::default();
+        for parallel in [false, true].iter() {
+            for runs in runs.iter() {
+                runs.store(0, Ordering::Relaxed);
+            }
+            let mut world = World::default();
+            let mut resources = Resources::default();
+            resources.insert(ComputeTaskPool(TaskPoolBuilder::default().build()));
+            schedule.initialize(&mut world, &mut resources);
+            executor.initialize(&mut resources);
+            let mut run = |world: &mut World, resources: &mut Resources| {
+                if *parallel {
+                    executor.run(&mut schedule, world, resources);
+                } else {
+                    schedule.run(world, resources);
+                }
+            };
+
+            run(&mut world, &mut resources);
+            run(&mut world, &mut resources);
+            let counts = runs
+                .iter()
+                .map(|runs| runs.load(Ordering::Relaxed))
+                .collect::<Vec<_>>();
+            assert_eq!(counts, vec![0, 2, 2, 2]);
+            assert_eq!(world.query::<&A>().count(), 2);
+
+            world.spawn((Burning,));
+            run(&mut world, &mut resources);
+            let counts = runs
+                .iter()
+                .map(|runs| runs.load(Ordering::Relaxed))
+                .collect::<Vec<_>>();
+            assert_eq!(counts, vec![1, 3, 3, 3]);
+        }
+    }
+
+    fn run_system(world: &mut World, resources: &mut Resources, system: BoxedSystem) {
         let mut schedule = Schedule::default();
         schedule.add_stage("update");
         schedule.add_system_to_stage("update", system);
@@ -1,7 +1,7 @@
 pub use super::Query;
 use crate::{
     resource::Resources,
-    system::{System, SystemId, ThreadLocalExecution},
+    system::{BoxedSystem, IntoSystem, System, SystemId, ThreadLocalExecution},
     TypeAccess,
 };
 use bevy_hecs::{ArchetypeComponent, World};
@@ -23,10 +23,17 @@ impl<Func> System for ThreadLocalSystemFn<Func>
 where
     Func: FnMut(&mut World, &mut Resources) + Send + Sync,
 {
+    type In = ();
+    type Out = ();
+
     fn name(&self) -> Cow<'static, str> {
         self.name.clone()
     }
 
+    fn set_name(&mut self, name: Cow<'static, str>) {
+        self.name = name;
+    }
+
     fn update(&mut self, _world: &World) {}
 
     fn archetype_component_access(&self) -> &TypeAccess<ArchetypeComponent> {
@@ -41,7 +48,9 @@ where
         ThreadLocalExecution::Immediate
     }
 
-    fn run(&mut self, _world: &World, _resources: &Resources) {}
+    fn run(&mut self, _input: (), _world: &World, _resources: &Resources) -> Option<()> {
+        Some(())
+    }
 
     fn run_thread_local(&mut self, world: &mut World, resources: &mut Resources) {
         (self.func)(world, resources);
@@ -58,16 +67,24 @@ where
     }
 }
 
-/// Converts `Self` into a thread local system
+/// Converts a closure or function that takes `(&mut World, &mut Resources)` into an exclusive system, for work that
+/// needs the whole world at once, like spawning many entities directly or serializing the world.
+///
+/// Exclusive systems run on the main thread and act as a barrier within their stage: every system added to the stage
+/// before one has finished when it starts, and the systems added after it only start once it is done. They don't
+/// declare any access, so nothing can run in parallel with them. Commands queued by earlier systems in the stage are
+/// not applied yet when an exclusive system runs, because commands are applied at the end of the stage.
+///
+/// Exclusive systems can also be created with [IntoSystem::system], like any other system.
 pub trait IntoThreadLocalSystem {
-    fn thread_local_system(self) -> Box<dyn System>;
+    fn thread_local_system(self) -> BoxedSystem;
 }
 
 impl<F> IntoThreadLocalSystem for F
 where
     F: FnMut(&mut World, &mut Resources) + Send + Sync + 'static,
 {
-    fn thread_local_system(mut self) -> Box<dyn System> {
+    fn thread_local_system(mut self) -> BoxedSystem {
         Box::new(ThreadLocalSystemFn {
             func: move |world, resources| (self)(world, resources),
             name: core::any::type_name::<F>().into(),
@@ -77,3 +94,100 @@ where
         })
     }
 }
+
+impl<F> IntoSystem<(&mut World, &mut Resources)> for F
+where
+    F: FnMut(&mut World, &mut Resources) + Send + Sync + 'static,
+{
+    fn system(self) -> BoxedSystem {
+        self.thread_local_system()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntoThreadLocalSystem;
+    use crate::{
+        resource::{ResMut, Resources},
+        schedule::{ParallelExecutor, Schedule},
+        system::{Commands, IntoSystem, Query},
+    };
+    use bevy_hecs::{Entity, With, World};
+
+    struct A;
+    struct B;
+
+    #[test]
+    fn thread_local_system_is_a_barrier() {
+        fn spawn_a(commands: &mut Commands) {
+            commands.spawn((A,));
+        }
+
+        fn exclusive(world: &mut World, resources: &mut Resources) {
+            let count = world.query::<&A>().count();
+            resources.get_mut::<Vec<usize>>().unwrap().push(count);
+            world.spawn((B,));
+        }
+
+        fn count_b(query: Query<&B>, mut counts: ResMut<Vec<usize>>) {
+            counts.push(query.iter().count());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<usize>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", spawn_a.system());
+        schedule.add_system_to_stage("update", exclusive.thread_local_system());
+        schedule.add_system_to_stage("update", count_b.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        let mut executor = ParallelExecutor::default();
+        executor.initialize(&mut resources);
+        executor.run(&mut schedule, &mut world, &mut resources);
+
+        // the command from spawn_a is only applied at the end of the stage, while the entity spawned by the
+        // exclusive system is visible to the systems after it
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![0, 1]);
+        assert_eq!(world.query::<&A>().count(), 1);
+    }
+
+    #[test]
+    fn exclusive_system_despawn() {
+        fn despawn_marked(world: &mut World, _resources: &mut Resources) {
+            let marked = world
+                .query_filtered::<Entity, With<A>>()
+                .collect::<Vec<_>>();
+            for entity in marked {
+                world.despawn(entity).unwrap();
+            }
+        }
+
+        fn count_marked(query: Query<(), With<A>>, mut counts: ResMut<Vec<usize>>) {
+            counts.push(query.iter().count());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<usize>::new());
+        world.spawn_batch((0..5).map(|_| (A,)));
+        world.spawn_batch((0..3).map(|_| (A, B)));
+        world.spawn((B,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", count_marked.system());
+        schedule.add_system_to_stage("update", despawn_marked.system());
+        schedule.add_system_to_stage("update", count_marked.system());
+        schedule.initialize(&mut world, &mut resources);
+
+        let mut executor = ParallelExecutor::default();
+        executor.initialize(&mut resources);
+        executor.run(&mut schedule, &mut world, &mut resources);
+
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![8, 0]);
+        assert_eq!(world.query::<&B>().count(), 1);
+    }
+}
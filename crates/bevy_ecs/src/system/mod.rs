@@ -1,14 +1,20 @@
+mod chain_system;
 mod commands;
+mod dynamic_system;
 mod into_system;
 mod into_thread_local;
 mod query;
+mod run_criteria;
 #[allow(clippy::module_inception)]
 mod system;
 mod system_param;
 
+pub use chain_system::*;
 pub use commands::*;
+pub use dynamic_system::*;
 pub use into_system::*;
 pub use into_thread_local::*;
 pub use query::*;
+pub use run_criteria::*;
 pub use system::*;
 pub use system_param::*;
@@ -1,31 +1,66 @@
+mod query_combinations;
+mod query_many;
 mod query_set;
+mod query_sorted;
 
+pub use query_combinations::*;
+pub use query_many::*;
 pub use query_set::*;
+pub use query_sorted::*;
 
 use bevy_hecs::{
-    ArchetypeComponent, Batch, BatchedIter, Component, ComponentError, Entity, Fetch, Mut,
-    Query as HecsQuery, QueryFilter, QueryIter, ReadOnlyFetch, TypeAccess, World,
+    Archetype, ArchetypeComponent, Batch, BatchedIter, ChangeTicks, Component, ComponentError,
+    Entity, EntityFilter, Fetch, Location, Mut, Query as HecsQuery, QueryAccess, QueryFilter,
+    QueryIter, ReadOnlyFetch, TypeAccess, World,
 };
 use bevy_tasks::ParallelIterator;
-use std::marker::PhantomData;
+use parking_lot::Mutex;
+use std::{
+    any::{type_name, TypeId},
+    marker::PhantomData,
+};
+use thiserror::Error;
 
 /// Provides scoped access to a World according to a given [HecsQuery]
 #[derive(Debug)]
 pub struct Query<'a, Q: HecsQuery, F: QueryFilter = ()> {
     pub(crate) world: &'a World,
     pub(crate) component_access: &'a TypeAccess<ArchetypeComponent>,
+    pub(crate) ticks: ChangeTicks,
+    /// The buffer of [Query::iter_sorted_by_key], which queries of a system keep between runs
+    pub(crate) sort_buffer: Option<&'a Mutex<QuerySortBuffer>>,
+    /// For queries made with [Query::transmute], the archetypes the query may access, by archetype index
+    matched_archetypes: Option<Vec<bool>>,
     _marker: PhantomData<(Q, F)>,
 }
 
-/// An error that occurs when using a [Query]
-#[derive(Debug)]
+/// An error that occurs when using a [Query] to access a single entity
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum QueryError {
-    CannotReadArchetype,
-    CannotWriteArchetype,
-    ComponentError(ComponentError),
+    #[error("The entity does not exist")]
     NoSuchEntity,
+    #[error("The entity does not match the query")]
+    QueryDoesNotMatch,
+    #[error("The entity does not have a {0} component")]
+    MissingComponent(&'static str),
+    #[error("The query does not declare the access to {0} needed for this entity")]
+    AccessNotDeclared(&'static str),
+    #[error("The entity {0:?} was requested more than once with mutable access")]
+    AliasedMutability(Entity),
 }
 
+/// An error that occurs when using [Query::single] or [Query::single_mut]. Holds the type name of the query.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum QuerySingleError {
+    #[error("No entities fit the query {0}")]
+    NoEntities(&'static str),
+    #[error("Multiple entities fit the query {0}")]
+    MultipleEntities(&'static str),
+}
+
+/// The fetch and filter that decide which entities of an archetype match a query
+type EntityMatcher<Q, F> = (<Q as HecsQuery>::Fetch, <F as QueryFilter>::EntityFilter);
+
 impl<'a, Q: HecsQuery, F: QueryFilter> Query<'a, Q, F> {
     /// # Safety
     /// This will create a Query that could violate memory safety rules. Make sure that this is only called in
@@ -34,14 +69,134 @@ impl<'a, Q: HecsQuery, F: QueryFilter> Query<'a, Q, F> {
     pub(crate) unsafe fn new(
         world: &'a World,
         component_access: &'a TypeAccess<ArchetypeComponent>,
+        ticks: ChangeTicks,
     ) -> Self {
         Self {
             world,
             component_access,
+            ticks,
+            sort_buffer: None,
+            matched_archetypes: None,
             _marker: PhantomData::default(),
         }
     }
 
+    /// Reinterprets the query as a query for `NewQ` with the same filter, so that a system with a
+    /// `Query<(&Transform, &Velocity)>` can pass it to a function that takes a `Query<&Transform>`. `NewQ` may fetch
+    /// fewer components than this query, and fetch immutably what this query fetches mutably.
+    ///
+    /// The new query only returns entities of the archetypes this query matches, so it never accesses anything the
+    /// system didn't declare, and doesn't conflict with other systems that this query doesn't conflict with. An entity
+    /// that has a `Transform` but no `Velocity` isn't returned by the `Query<&Transform>` above.
+    ///
+    /// # Panics
+    /// Panics if `NewQ` fetches a component this query doesn't fetch, or fetches mutably a component this query only
+    /// reads.
+    pub fn transmute<NewQ: HecsQuery>(&mut self) -> Query<'_, NewQ, F> {
+        // SAFE: the new query borrows this one mutably, and can only access what this query can
+        unsafe { self.transmute_unchecked() }
+    }
+
+    /// Reinterprets the query as a read-only query for `NewQ` with the same filter, which is usually inferred from the
+    /// function the query is passed to. See [Query::transmute].
+    ///
+    /// # Panics
+    /// Panics if `NewQ` fetches a component this query doesn't fetch.
+    pub fn as_readonly<NewQ: HecsQuery>(&self) -> Query<'_, NewQ, F>
+    where
+        NewQ::Fetch: ReadOnlyFetch,
+    {
+        // SAFE: the new query is read-only, and can only access what this query can
+        unsafe { self.transmute_unchecked() }
+    }
+
+    /// # Safety
+    /// The new query must not be able to fetch components mutably while other borrows of this query are live
+    unsafe fn transmute_unchecked<NewQ: HecsQuery>(&self) -> Query<'_, NewQ, F> {
+        let mut type_access = TypeAccess::default();
+        let access = <Q::Fetch as Fetch>::access();
+        access.get_type_access(&mut type_access);
+        let mut new_type_access = TypeAccess::default();
+        let new_access = <NewQ::Fetch as Fetch>::access();
+        new_access.get_type_access(&mut new_type_access);
+        for ty in new_type_access.iter_reads() {
+            if !type_access.is_read_or_write(ty) {
+                panic!(
+                    "Query<{}> can't be transmuted to Query<{}> because it doesn't fetch {}",
+                    type_name::<Q>(),
+                    type_name::<NewQ>(),
+                    new_access.get_type_name(*ty).unwrap_or("a component"),
+                );
+            }
+        }
+        for ty in new_type_access.iter_writes() {
+            if !type_access.is_write(ty) {
+                panic!(
+                    "Query<{}> can't be transmuted to Query<{}> because it doesn't fetch {} mutably",
+                    type_name::<Q>(),
+                    type_name::<NewQ>(),
+                    new_access.get_type_name(*ty).unwrap_or("a component"),
+                );
+            }
+        }
+
+        // an archetype is matched if this query matches it, and every component the new query accesses in it has been
+        // declared
+        let access = QueryAccess::union(vec![access, F::access()]);
+        let new_access = QueryAccess::union(vec![new_access, F::access()]);
+        let matched_archetypes = self
+            .world
+            .archetypes()
+            .enumerate()
+            .map(|(archetype_index, archetype)| {
+                let archetype_index = archetype_index as u32;
+                if !self.is_archetype_matched(archetype_index as usize)
+                    || access
+                        .get_access(archetype, archetype_index, None)
+                        .is_none()
+                {
+                    return false;
+                }
+                let mut archetype_access = TypeAccess::default();
+                new_access.get_access(archetype, archetype_index, Some(&mut archetype_access));
+                archetype_access
+                    .iter_reads()
+                    .all(|component| self.component_access.is_read_or_write(component))
+                    && archetype_access
+                        .iter_writes()
+                        .all(|component| self.component_access.is_write(component))
+            })
+            .collect();
+
+        Query {
+            world: self.world,
+            component_access: self.component_access,
+            ticks: self.ticks,
+            sort_buffer: None,
+            matched_archetypes: Some(matched_archetypes),
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn is_archetype_matched(&self, archetype_index: usize) -> bool {
+        match &self.matched_archetypes {
+            Some(matched_archetypes) => matched_archetypes[archetype_index],
+            None => true,
+        }
+    }
+
+    /// # Safety
+    /// Same as [World::query_unchecked]
+    #[inline]
+    unsafe fn query_unchecked<IterQ: HecsQuery>(&self) -> QueryIter<'_, IterQ, F> {
+        let iter = self.world.query_unchecked(self.ticks);
+        match &self.matched_archetypes {
+            Some(matched_archetypes) => iter.with_matched_archetypes(matched_archetypes),
+            None => iter,
+        }
+    }
+
     /// Iterates over the query results. This can only be called for read-only queries
     #[inline]
     pub fn iter(&self) -> QueryIter<'_, Q, F>
@@ -49,14 +204,14 @@ impl<'a, Q: HecsQuery, F: QueryFilter> Query<'a, Q, F> {
         Q::Fetch: ReadOnlyFetch,
     {
         // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
-        unsafe { self.world.query_unchecked() }
+        unsafe { self.query_unchecked() }
     }
 
     /// Iterates over the query results
     #[inline]
     pub fn iter_mut(&mut self) -> QueryIter<'_, Q, F> {
         // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
-        unsafe { self.world.query_unchecked() }
+        unsafe { self.query_unchecked() }
     }
 
     /// Iterates over the query results
@@ -65,107 +220,463 @@ impl<'a, Q: HecsQuery, F: QueryFilter> Query<'a, Q, F> {
     #[inline]
     pub unsafe fn iter_unsafe(&self) -> QueryIter<'_, Q, F> {
         // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
-        self.world.query_unchecked()
+        self.query_unchecked()
     }
 
+    /// Runs `f` on each query result, in the same order as [Query::iter]. This walks the matching archetypes directly
+    /// instead of stepping through a [QueryIter], which tends to optimize better in hot loops. This can only be called
+    /// for read-only queries
+    #[inline]
+    pub fn for_each(&self, f: impl FnMut(<Q::Fetch as Fetch<'_>>::Item))
+    where
+        Q::Fetch: ReadOnlyFetch,
+    {
+        // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
+        unsafe { self.for_each_unchecked(f) }
+    }
+
+    /// Runs `f` on each query result, in the same order as [Query::iter_mut]. See [Query::for_each] for details.
+    #[inline]
+    pub fn for_each_mut(&mut self, f: impl FnMut(<Q::Fetch as Fetch<'_>>::Item)) {
+        // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
+        unsafe { self.for_each_unchecked(f) }
+    }
+
+    /// # Safety
+    /// The caller must make sure the items handed to `f` don't alias any other live mutable borrows
+    #[inline]
+    unsafe fn for_each_unchecked<'w>(&'w self, mut f: impl FnMut(<Q::Fetch as Fetch<'w>>::Item)) {
+        for (archetype_index, archetype) in self.world.archetypes().enumerate() {
+            if !self.is_archetype_matched(archetype_index) {
+                continue;
+            }
+            let fetch = match <Q::Fetch as Fetch>::get(archetype, 0, self.ticks) {
+                Some(fetch) => fetch,
+                None => continue,
+            };
+            let filter = match F::get_entity_filter(archetype, self.ticks) {
+                Some(filter) => filter,
+                None => continue,
+            };
+            for offset in 0..archetype.len() {
+                if filter.matches_entity(offset) && fetch.matches_entity(offset) {
+                    f(fetch.fetch(offset));
+                }
+            }
+        }
+    }
+
+    /// Iterates over the query results in parallel, splitting each matched archetype into batches of at most
+    /// `batch_size` entities. Consume the returned [ParIter] with [ParallelIterator::for_each] and a
+    /// [TaskPool](bevy_tasks::TaskPool), usually the [ComputeTaskPool](bevy_tasks::ComputeTaskPool) resource; it
+    /// returns once every batch has been processed. This can only be called for read-only queries.
+    ///
+    /// Every batch is spawned as its own task. Small batches balance the work across threads better but pay that
+    /// overhead more often, while large batches can leave threads idle once the last few batches are running. Batches
+    /// never span archetypes, so queries over many small archetypes gain little from large batch sizes.
+    ///
+    /// The closure runs on other threads while the system is still running, so it must not use
+    /// [Commands](crate::Commands).
     #[inline]
     pub fn par_iter(&self, batch_size: usize) -> ParIter<'_, Q, F>
     where
         Q::Fetch: ReadOnlyFetch,
     {
         // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
-        unsafe { ParIter::new(self.world.query_batched_unchecked(batch_size)) }
+        unsafe { self.par_iter_unchecked(batch_size) }
     }
 
+    /// Iterates over the query results in parallel. See [Query::par_iter] for details.
+    ///
+    /// Batches never overlap, so every result is only handed to the closure once. The closure must not use
+    /// [Commands](crate::Commands).
     #[inline]
     pub fn par_iter_mut(&mut self, batch_size: usize) -> ParIter<'_, Q, F> {
         // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
-        unsafe { ParIter::new(self.world.query_batched_unchecked(batch_size)) }
+        unsafe { self.par_iter_unchecked(batch_size) }
     }
 
-    /// Gets the query result for the given `entity`
+    /// # Safety
+    /// Same as [World::query_batched_unchecked]
     #[inline]
-    pub fn get(&self, entity: Entity) -> Result<<Q::Fetch as Fetch>::Item, QueryError>
+    unsafe fn par_iter_unchecked(&self, batch_size: usize) -> ParIter<'_, Q, F> {
+        let batched_iter = self.world.query_batched_unchecked(batch_size, self.ticks);
+        ParIter::new(match &self.matched_archetypes {
+            Some(matched_archetypes) => batched_iter.with_matched_archetypes(matched_archetypes),
+            None => batched_iter,
+        })
+    }
+
+    /// Iterates over every combination of `K` distinct entities that match the query, like every pair of colliding
+    /// bodies with `K = 2`. This can only be called for read-only queries. See [QueryCombinationIter] for details.
+    ///
+    /// The matching entities are collected up front. There are `n! / (K! * (n - K)!)` combinations of `n` entities,
+    /// so the number of combinations grows quickly with `K`.
+    #[inline]
+    pub fn iter_combinations<const K: usize>(&self) -> QueryCombinationIter<'_, Q, F, K>
     where
         Q::Fetch: ReadOnlyFetch,
     {
-        // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
+        QueryCombinationIter::new(self.world, self.ticks, self.matched_archetypes.as_deref())
+    }
+
+    /// Iterates over every combination of `K` distinct entities that match the query, with mutable access. The
+    /// returned iterator isn't an [Iterator]: call [QueryCombinationIter::fetch_next] in a `while let` loop instead,
+    /// which ensures that the same entity's components are never borrowed mutably twice.
+    #[inline]
+    pub fn iter_combinations_mut<const K: usize>(&mut self) -> QueryCombinationIter<'_, Q, F, K> {
+        QueryCombinationIter::new(self.world, self.ticks, self.matched_archetypes.as_deref())
+    }
+
+    /// Iterates over the query results of `entities`, in the order they are given in. Entities that don't exist or
+    /// don't match the query are skipped. This can only be called for read-only queries.
+    #[inline]
+    pub fn iter_many<I: IntoIterator<Item = Entity>>(
+        &self,
+        entities: I,
+    ) -> QueryManyIter<'_, Q, F, I::IntoIter>
+    where
+        Q::Fetch: ReadOnlyFetch,
+    {
+        // SAFE: system runs without conflicts with other systems, and the query is read-only
         unsafe {
-            self.world
-                .query_one_unchecked::<Q, F>(entity)
-                .map_err(|_err| QueryError::NoSuchEntity)
+            QueryManyIter::new(
+                self.world,
+                entities.into_iter(),
+                self.ticks,
+                self.matched_archetypes.as_deref(),
+                false,
+            )
+        }
+    }
+
+    /// Iterates over the query results of `entities` with mutable access, in the order they are given in. Entities that
+    /// don't exist or don't match the query are skipped, and so are entities that come up again after their result
+    /// was returned, so that the same components are never borrowed mutably twice. Skipping duplicates keeps a set of
+    /// the returned entities, which [Query::iter_many] doesn't need.
+    #[inline]
+    pub fn iter_many_mut<I: IntoIterator<Item = Entity>>(
+        &mut self,
+        entities: I,
+    ) -> QueryManyIter<'_, Q, F, I::IntoIter> {
+        // SAFE: system runs without conflicts with other systems, and duplicate entities are skipped
+        unsafe {
+            QueryManyIter::new(
+                self.world,
+                entities.into_iter(),
+                self.ticks,
+                self.matched_archetypes.as_deref(),
+                true,
+            )
+        }
+    }
+
+    /// Iterates over the query results sorted by the key `f` returns for each of them. Results with equal keys keep
+    /// their iteration order. This can only be called for read-only queries.
+    ///
+    /// A system keeps the buffers of the sort between runs, so sorting the same entities again doesn't allocate. When
+    /// the entities are iterated in the same order as in the last sort, the sort starts out from the last sort's order,
+    /// which leaves little to do if only a few keys changed. The keys are kept in those buffers too, which is why they
+    /// have to be `Send + Sync + 'static`.
+    pub fn iter_sorted_by_key<'w, K: Ord + Send + Sync + 'static>(
+        &'w self,
+        f: impl Fn(&<Q::Fetch as Fetch<'w>>::Item) -> K,
+    ) -> QueryManyIter<'w, Q, F, SortedEntities<'w>>
+    where
+        Q::Fetch: ReadOnlyFetch,
+    {
+        // SAFE: system runs without conflicts with other systems, and the query is read-only
+        unsafe {
+            let entities = self.sort_entities(f);
+            QueryManyIter::new(
+                self.world,
+                entities,
+                self.ticks,
+                self.matched_archetypes.as_deref(),
+                false,
+            )
+        }
+    }
+
+    /// Iterates over the query results with mutable access, sorted by the key `f` returns for each of them. See
+    /// [Query::iter_sorted_by_key].
+    pub fn iter_sorted_by_key_mut<'w, K: Ord + Send + Sync + 'static>(
+        &'w mut self,
+        f: impl Fn(&<Q::Fetch as Fetch<'w>>::Item) -> K,
+    ) -> QueryManyIter<'w, Q, F, SortedEntities<'w>> {
+        // SAFE: system runs without conflicts with other systems, and the sorted entities are unique
+        unsafe {
+            let entities = self.sort_entities(f);
+            QueryManyIter::new(
+                self.world,
+                entities,
+                self.ticks,
+                self.matched_archetypes.as_deref(),
+                false,
+            )
+        }
+    }
+
+    /// # Safety
+    /// The query results are fetched one at a time and dropped once their key is computed, but the query must still
+    /// have access to the components it fetches
+    unsafe fn sort_entities<'w, K: Ord + Send + Sync + 'static>(
+        &'w self,
+        f: impl Fn(&<Q::Fetch as Fetch<'w>>::Item) -> K,
+    ) -> SortedEntities<'w> {
+        let items = self
+            .query_unchecked::<(Entity, Q)>()
+            .map(|(entity, item)| (entity, f(&item)));
+        match self.sort_buffer.and_then(|buffer| buffer.try_lock()) {
+            Some(mut buffer) => SortedEntities::new(buffer.sort(items), self.sort_buffer),
+            // the buffer is busy when `f` sorts the same query, which gets a temporary buffer instead
+            None => SortedEntities::new(QuerySortBuffer::default().sort(items), None),
         }
     }
 
-    /// Gets the query result for the given `entity`
+    /// Returns the number of entities that match the query. With filters that only depend on an entity's archetype,
+    /// like [With](bevy_hecs::With) and [Without](bevy_hecs::Without) of components stored in tables, this sums the
+    /// lengths of the matching archetypes. Change detection filters like [Changed](bevy_hecs::Changed) and components
+    /// stored in sparse sets have to be checked for every entity of the matching archetypes instead, although the
+    /// query items themselves are never fetched.
+    pub fn len(&self) -> usize {
+        self.matching_archetypes()
+            .map(|(archetype, matcher)| match matcher {
+                Some((fetch, filter)) => (0..archetype.len())
+                    // SAFE: filters only read the data they declared in their access, which the system holds
+                    .filter(|offset| unsafe {
+                        filter.matches_entity(*offset) && fetch.matches_entity(*offset)
+                    })
+                    .count(),
+                None => archetype.len(),
+            })
+            .sum()
+    }
+
+    /// Returns true if no entities match the query. This stops at the first match, so unlike [Query::len] it doesn't
+    /// check the rest of the entities when a change detection filter is used.
+    pub fn is_empty(&self) -> bool {
+        !self
+            .matching_archetypes()
+            .any(|(archetype, matcher)| match matcher {
+                Some((fetch, filter)) => (0..archetype.len())
+                    // SAFE: filters only read the data they declared in their access, which the system holds
+                    .any(|offset| unsafe {
+                        filter.matches_entity(offset) && fetch.matches_entity(offset)
+                    }),
+                None => true,
+            })
+    }
+
+    /// Returns every non-empty archetype that the query matches, along with the fetch and filter its entities have to
+    /// be checked against, or None if every entity in the archetype matches
+    fn matching_archetypes(
+        &self,
+    ) -> impl Iterator<Item = (&'_ Archetype, Option<EntityMatcher<Q, F>>)> + '_ {
+        let ticks = self.ticks;
+        self.world
+            .archetypes()
+            .enumerate()
+            .filter(move |(archetype_index, archetype)| {
+                !archetype.is_empty() && self.is_archetype_matched(*archetype_index)
+            })
+            .filter_map(move |(_, archetype)| {
+                // SAFE: the archetype isn't empty, and the fetch is only used to check which entities match the query
+                let fetch = unsafe { <Q::Fetch as Fetch>::get(archetype, 0, ticks) }?;
+                let filter = F::get_entity_filter(archetype, ticks)?;
+                if fetch.is_archetypal() && filter.is_archetypal() {
+                    Some((archetype, None))
+                } else {
+                    Some((archetype, Some((fetch, filter))))
+                }
+            })
+    }
+
+    /// Gets the result of a query that is expected to match exactly one entity. This can only be called for
+    /// read-only queries
+    pub fn single(&self) -> Result<<Q::Fetch as Fetch>::Item, QuerySingleError>
+    where
+        Q::Fetch: ReadOnlyFetch,
+    {
+        Self::expect_single(self.iter())
+    }
+
+    /// Gets the result of a query that is expected to match exactly one entity
+    pub fn single_mut(&mut self) -> Result<<Q::Fetch as Fetch>::Item, QuerySingleError> {
+        Self::expect_single(self.iter_mut())
+    }
+
+    fn expect_single<I: Iterator>(mut iter: I) -> Result<I::Item, QuerySingleError> {
+        let query_type_name = std::any::type_name::<Self>();
+        let single = iter
+            .next()
+            .ok_or(QuerySingleError::NoEntities(query_type_name))?;
+        // only look ahead by one result so we don't fetch every match
+        if iter.next().is_some() {
+            return Err(QuerySingleError::MultipleEntities(query_type_name));
+        }
+        Ok(single)
+    }
+
+    /// Gets the query result for the given `entity`. Fails with [QueryError::NoSuchEntity] if the entity doesn't
+    /// exist, or [QueryError::QueryDoesNotMatch] if it doesn't match the query and its filter.
+    #[inline]
+    pub fn get(&self, entity: Entity) -> Result<<Q::Fetch as Fetch>::Item, QueryError>
+    where
+        Q::Fetch: ReadOnlyFetch,
+    {
+        // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
+        unsafe { self.get_unchecked_mut(entity) }
+    }
+
+    /// Gets the mutable query result for the given `entity`. Fails like [Query::get]. Writes through the result are
+    /// picked up by change detection.
     #[inline]
     pub fn get_mut(&mut self, entity: Entity) -> Result<<Q::Fetch as Fetch>::Item, QueryError> {
         // SAFE: system runs without conflicts with other systems. same-system queries have runtime borrow checks when they conflict
-        unsafe {
-            self.world
-                .query_one_unchecked::<Q, F>(entity)
-                .map_err(|_err| QueryError::NoSuchEntity)
+        unsafe { self.get_unchecked_mut(entity) }
+    }
+
+    /// Gets the query results for all of the given `entities` at once, in the same order. Fails with the error of the
+    /// first entity that [Query::get] would fail for. This can only be called for read-only queries
+    #[inline]
+    pub fn get_many<const N: usize>(
+        &self,
+        entities: [Entity; N],
+    ) -> Result<[<Q::Fetch as Fetch>::Item; N], QueryError>
+    where
+        Q::Fetch: ReadOnlyFetch,
+    {
+        // SAFE: system runs without conflicts with other systems, and the query is read-only
+        unsafe { self.get_many_unchecked(entities) }
+    }
+
+    /// Gets the mutable query results for all of the given `entities` at once, like an attacker and its target. Fails
+    /// with [QueryError::AliasedMutability] if an entity is given more than once, and otherwise like
+    /// [Query::get_many].
+    #[inline]
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        entities: [Entity; N],
+    ) -> Result<[<Q::Fetch as Fetch>::Item; N], QueryError> {
+        for (index, entity) in entities.iter().enumerate() {
+            if entities[..index].contains(entity) {
+                return Err(QueryError::AliasedMutability(*entity));
+            }
         }
+        // SAFE: system runs without conflicts with other systems, and the entities are distinct, so their results
+        // don't alias
+        unsafe { self.get_many_unchecked(entities) }
     }
 
-    /// Gets the query result for the given `entity`
     /// # Safety
-    /// This allows aliased mutability. You must make sure this call does not result in multiple mutable references to the same component
+    /// The caller must make sure the results don't alias any other live mutable borrows
+    unsafe fn get_many_unchecked<const N: usize>(
+        &self,
+        entities: [Entity; N],
+    ) -> Result<[<Q::Fetch as Fetch>::Item; N], QueryError> {
+        let results = entities.map(|entity| self.get_unchecked_mut(entity));
+        if let Some(err) = results.iter().find_map(|result| result.as_ref().err()) {
+            return Err(err.clone());
+        }
+        Ok(results.map(Result::unwrap))
+    }
+
+    /// Gets the mutable query result for the given `entity` through a shared reference to the query, which bypasses the
+    /// borrow checker's guard against aliasing. Fails like [Query::get].
+    ///
+    /// This is an escape hatch for code that can prove its accesses are disjoint, for example because it only ever
+    /// looks up distinct entities. Prefer [Query::get_many_mut] or a [QuerySet](crate::QuerySet) where they fit.
+    ///
+    /// # Safety
+    /// While the returned result is alive, the caller must not create any other reference to the components it borrows
+    /// mutably. In particular, this must not be called twice for the same entity while the first result is in use, and
+    /// the result must not overlap with results of [Query::iter], [Query::get] or similar calls on this query. The query
+    /// itself still only reaches the components it declared access to, so other systems are unaffected.
     #[inline]
-    pub unsafe fn get_unsafe(
+    pub unsafe fn get_unchecked_mut(
         &self,
         entity: Entity,
     ) -> Result<<Q::Fetch as Fetch>::Item, QueryError> {
+        self.check_archetype_matched(entity)?;
         self.world
-            .query_one_unchecked::<Q, F>(entity)
-            .map_err(|_err| QueryError::NoSuchEntity)
+            .query_one_unchecked::<Q, F>(entity, self.ticks)
+            .map_err(|_err| self.entity_error(entity))
     }
 
-    /// Gets a reference to the entity's component of the given type. This will fail if the entity does not have
-    /// the given component type or if the given component type does not match this query.
-    pub fn get_component<T: Component>(&self, entity: Entity) -> Result<&T, QueryError> {
-        if let Some(location) = self.world.get_entity_location(entity) {
-            if self
-                .component_access
-                .is_read_or_write(&ArchetypeComponent::new::<T>(location.archetype))
-            {
-                // SAFE: we have already checked that the entity/component matches our archetype access. and systems are scheduled to run with safe archetype access
-                unsafe {
-                    self.world
-                        .get_at_location_unchecked(location)
-                        .map_err(QueryError::ComponentError)
-                }
-            } else {
-                Err(QueryError::CannotReadArchetype)
+    #[inline]
+    fn check_archetype_matched(&self, entity: Entity) -> Result<(), QueryError> {
+        if self.matched_archetypes.is_some() {
+            let location = self
+                .world
+                .get_entity_location(entity)
+                .ok_or(QueryError::NoSuchEntity)?;
+            if !self.is_archetype_matched(location.archetype as usize) {
+                return Err(QueryError::QueryDoesNotMatch);
             }
+        }
+        Ok(())
+    }
+
+    fn entity_error(&self, entity: Entity) -> QueryError {
+        if self.world.get_entity_location(entity).is_some() {
+            QueryError::QueryDoesNotMatch
         } else {
-            Err(QueryError::ComponentError(ComponentError::NoSuchEntity))
+            QueryError::NoSuchEntity
         }
     }
 
-    /// Gets a mutable reference to the entity's component of the given type. This will fail if the entity does not have
-    /// the given component type or if the given component type does not match this query.
+    /// Finds the entity's location, checking that it has a `T` component this query is allowed to access
+    fn component_location<T: Component>(
+        &self,
+        entity: Entity,
+        write: bool,
+    ) -> Result<Location, QueryError> {
+        let location = self
+            .world
+            .get_entity_location(entity)
+            .ok_or(QueryError::NoSuchEntity)?;
+        if !self.world.has_component_type(entity, TypeId::of::<T>()) {
+            return Err(QueryError::MissingComponent(type_name::<T>()));
+        }
+        let archetype_component = ArchetypeComponent::new::<T>(location.archetype);
+        let has_access = if write {
+            self.component_access.is_write(&archetype_component)
+        } else {
+            self.component_access.is_read_or_write(&archetype_component)
+        };
+        if has_access {
+            Ok(location)
+        } else {
+            Err(QueryError::AccessNotDeclared(type_name::<T>()))
+        }
+    }
+
+    /// Gets a reference to the entity's component of the given type. This will fail if the entity does not exist,
+    /// does not have the given component type, or if this query does not read the component for that entity.
+    pub fn get_component<T: Component>(&self, entity: Entity) -> Result<&T, QueryError> {
+        let location = self.component_location::<T>(entity, false)?;
+        // SAFE: we have already checked that the entity/component matches our archetype access. and systems are scheduled to run with safe archetype access
+        unsafe {
+            self.world
+                .get_at_location_unchecked(location)
+                .map_err(|_err| QueryError::MissingComponent(type_name::<T>()))
+        }
+    }
+
+    /// Gets a mutable reference to the entity's component of the given type. This will fail if the entity does not
+    /// exist, does not have the given component type, or if this query does not write the component for that entity.
+    /// Like [Query::get_mut], writes through the result mark the component as [Mutated](bevy_hecs::Mutated).
     pub fn get_component_mut<T: Component>(
         &mut self,
         entity: Entity,
     ) -> Result<Mut<'_, T>, QueryError> {
-        let location = match self.world.get_entity_location(entity) {
-            None => return Err(QueryError::ComponentError(ComponentError::NoSuchEntity)),
-            Some(location) => location,
-        };
-
-        if self
-            .component_access
-            .is_write(&ArchetypeComponent::new::<T>(location.archetype))
-        {
-            // SAFE: RefMut does exclusivity checks and we have already validated the entity
-            unsafe {
-                self.world
-                    .get_mut_at_location_unchecked(location)
-                    .map_err(QueryError::ComponentError)
-            }
-        } else {
-            Err(QueryError::CannotWriteArchetype)
+        let location = self.component_location::<T>(entity, true)?;
+        // SAFE: RefMut does exclusivity checks and we have already validated the entity
+        unsafe {
+            self.world
+                .get_mut_at_location_unchecked(location, self.ticks.change_tick)
+                .map_err(|_err| QueryError::MissingComponent(type_name::<T>()))
         }
     }
 
@@ -178,8 +689,13 @@ impl<'a, Q: HecsQuery, F: QueryFilter> Query<'a, Q, F> {
         entity: Entity,
     ) -> Result<Mut<'_, T>, QueryError> {
         self.world
-            .get_mut_unchecked(entity)
-            .map_err(QueryError::ComponentError)
+            .get_mut_unchecked(entity, self.ticks.change_tick)
+            .map_err(|err| match err {
+                ComponentError::NoSuchEntity => QueryError::NoSuchEntity,
+                ComponentError::MissingComponent(_) => {
+                    QueryError::MissingComponent(type_name::<T>())
+                }
+            })
     }
 
     pub fn removed<C: Component>(&self) -> &[Entity] {
@@ -216,3 +732,720 @@ impl<'w, Q: HecsQuery, F: QueryFilter> ParallelIterator<Batch<'w, Q, F>> for Par
         self.batched_iter.next()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{HecsQuery, Query, QueryError, QueryFilter, QuerySingleError, ReadOnlyFetch};
+    use crate::{
+        resource::{Res, ResMut, Resources},
+        schedule::Schedule,
+        system::{Commands, IntoSystem},
+    };
+    use bevy_hecs::{Entity, Mutated, Or, TypeAccess, With, Without, World};
+    use bevy_tasks::{ComputeTaskPool, ParallelIterator, TaskPool};
+
+    #[derive(Debug, Eq, PartialEq)]
+    struct A(usize);
+    struct B;
+
+    #[test]
+    fn single() {
+        let query_type_name = std::any::type_name::<Query<&A, With<B>>>();
+        let mut world = World::default();
+        let access = TypeAccess::default();
+        let query = unsafe { Query::<&A, With<B>>::new(&world, &access, world.change_ticks()) };
+        assert_eq!(
+            query.single(),
+            Err(QuerySingleError::NoEntities(query_type_name))
+        );
+
+        world.spawn((A(0),));
+        world.spawn((A(1), B));
+        let query = unsafe { Query::<&A, With<B>>::new(&world, &access, world.change_ticks()) };
+        assert_eq!(*query.single().unwrap(), A(1));
+
+        world.spawn((A(2), B));
+        let query = unsafe { Query::<&A, With<B>>::new(&world, &access, world.change_ticks()) };
+        let err = query.single().unwrap_err();
+        assert_eq!(err, QuerySingleError::MultipleEntities(query_type_name));
+        assert_eq!(
+            err.to_string(),
+            format!("Multiple entities fit the query {}", query_type_name)
+        );
+    }
+
+    #[test]
+    fn single_mut() {
+        let mut world = World::default();
+        let access = TypeAccess::default();
+        let mut query = unsafe { Query::<&mut A>::new(&world, &access, world.change_ticks()) };
+        assert!(matches!(
+            query.single_mut(),
+            Err(QuerySingleError::NoEntities(name)) if name == std::any::type_name::<Query<&mut A>>()
+        ));
+
+        let entity = world.spawn((A(0),));
+        let mut query = unsafe { Query::<&mut A>::new(&world, &access, world.change_ticks()) };
+        query.single_mut().unwrap().0 = 1;
+        assert_eq!(*world.get::<A>(entity).unwrap(), A(1));
+
+        world.spawn((A(2),));
+        let mut query = unsafe { Query::<&mut A>::new(&world, &access, world.change_ticks()) };
+        assert!(matches!(
+            query.single_mut(),
+            Err(QuerySingleError::MultipleEntities(name)) if name == std::any::type_name::<Query<&mut A>>()
+        ));
+    }
+
+    #[test]
+    fn len() {
+        struct C;
+
+        fn assert_len<Q: HecsQuery, F: QueryFilter>(world: &World, expected: usize)
+        where
+            Q::Fetch: ReadOnlyFetch,
+        {
+            let access = TypeAccess::default();
+            let query = unsafe { Query::<Q, F>::new(world, &access, world.change_ticks()) };
+            assert_eq!(query.len(), expected);
+            assert_eq!(query.iter().count(), expected);
+            assert_eq!(query.is_empty(), expected == 0);
+        }
+
+        let mut world = World::default();
+        assert_len::<&A, ()>(&world, 0);
+
+        world.spawn_batch((0..3).map(|i| (A(i),)));
+        world.spawn_batch((0..4).map(|i| (A(i), B)));
+        world.spawn_batch((0..5).map(|_| (B, C)));
+        let despawned = world.spawn((A(0), C));
+        world.despawn(despawned).unwrap();
+
+        assert_len::<&A, ()>(&world, 7);
+        assert_len::<&B, ()>(&world, 9);
+        assert_len::<(&A, &B), ()>(&world, 4);
+        assert_len::<Option<&A>, ()>(&world, 12);
+        assert_len::<&A, With<B>>(&world, 4);
+        assert_len::<&A, Without<B>>(&world, 3);
+        assert_len::<&A, With<C>>(&world, 0);
+        assert_len::<Entity, Or<(With<A>, With<C>)>>(&world, 12);
+
+        world.clear_trackers();
+        let entity = world.query::<Entity>().next().unwrap();
+        world.get_mut::<A>(entity).unwrap().0 = 10;
+        assert_len::<&A, Mutated<A>>(&world, 1);
+        assert_len::<&A, (With<B>, Mutated<A>)>(&world, 0);
+        assert_len::<Entity, Or<(Mutated<A>, With<C>)>>(&world, 6);
+    }
+
+    #[test]
+    fn world_query_matches_system_query() {
+        fn collect(
+            query: Query<Entity, (With<A>, Without<B>)>,
+            mutated: Query<Entity, Mutated<A>>,
+            mut results: ResMut<Vec<Vec<Entity>>>,
+        ) {
+            results.push(query.iter().collect());
+            results.push(mutated.iter().collect());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn_batch((0..3).map(|i| (A(i),)));
+        world.spawn_batch((0..3).map(|i| (A(i), B)));
+        world.spawn((B,));
+        resources.insert(Vec::<Vec<Entity>>::new());
+
+        world.clear_trackers();
+        for mut a in world.query_mut::<&mut A>().take(2) {
+            a.0 += 1;
+        }
+        let expected = vec![
+            world
+                .query_filtered::<Entity, (With<A>, Without<B>)>()
+                .collect::<Vec<_>>(),
+            world.query_filtered::<Entity, Mutated<A>>().collect(),
+        ];
+        assert_eq!(expected[0].len(), 3);
+        assert_eq!(expected[1].len(), 2);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", collect.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<Vec<Vec<Entity>>>().unwrap(), expected);
+    }
+
+    #[test]
+    fn get_component_errors() {
+        fn lookups(
+            mut query: Query<&A>,
+            entities: Res<Vec<Entity>>,
+            mut results: ResMut<Vec<Result<(), QueryError>>>,
+        ) {
+            let (with_a, with_b, despawned) = (entities[0], entities[1], entities[2]);
+            results.push(query.get_component::<A>(with_a).map(|_| ()));
+            results.push(query.get_component::<B>(with_a).map(|_| ()));
+            results.push(query.get_component::<A>(with_b).map(|_| ()));
+            results.push(query.get_component::<A>(despawned).map(|_| ()));
+            results.push(query.get_component_mut::<A>(with_a).map(|_| ()));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let with_a = world.spawn((A(0), B));
+        let with_b = world.spawn((B,));
+        let despawned = world.spawn((A(1),));
+        world.despawn(despawned).unwrap();
+        resources.insert(vec![with_a, with_b, despawned]);
+        resources.insert(Vec::<Result<(), QueryError>>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", lookups.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        let a_name = std::any::type_name::<A>();
+        let b_name = std::any::type_name::<B>();
+        assert_eq!(
+            *resources.get::<Vec<Result<(), QueryError>>>().unwrap(),
+            vec![
+                Ok(()),
+                Err(QueryError::AccessNotDeclared(b_name)),
+                Err(QueryError::MissingComponent(a_name)),
+                Err(QueryError::NoSuchEntity),
+                Err(QueryError::AccessNotDeclared(a_name)),
+            ]
+        );
+        assert_eq!(
+            QueryError::MissingComponent(a_name).to_string(),
+            format!("The entity does not have a {} component", a_name)
+        );
+    }
+
+    #[test]
+    fn write_read_only_component() {
+        fn writes(
+            mut read_only: Query<(Option<&A>, &B)>,
+            mut writable: Query<&mut A, Without<B>>,
+            entities: Res<Vec<Entity>>,
+            mut results: ResMut<Vec<Result<(), QueryError>>>,
+        ) {
+            let (with_b, without_b) = (entities[0], entities[1]);
+            results.push(read_only.get_component_mut::<A>(with_b).map(|_| ()));
+            results.push(read_only.set(with_b, A(100)));
+            results.push(writable.set(without_b, A(100)));
+            results.push(writable.set(with_b, A(100)));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let with_b = world.spawn((A(0), B));
+        let without_b = world.spawn((A(1),));
+        resources.insert(vec![with_b, without_b]);
+        resources.insert(Vec::<Result<(), QueryError>>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", writes.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        let a_name = std::any::type_name::<A>();
+        assert_eq!(
+            *resources.get::<Vec<Result<(), QueryError>>>().unwrap(),
+            vec![
+                Err(QueryError::AccessNotDeclared(a_name)),
+                Err(QueryError::AccessNotDeclared(a_name)),
+                Ok(()),
+                Err(QueryError::AccessNotDeclared(a_name)),
+            ]
+        );
+        assert_eq!(*world.get::<A>(with_b).unwrap(), A(0));
+        assert_eq!(*world.get::<A>(without_b).unwrap(), A(100));
+    }
+
+    #[test]
+    fn stale_entity_lookups() {
+        fn lookups(
+            mut query: Query<&mut A>,
+            stale: Res<Entity>,
+            mut results: ResMut<Vec<Result<(), QueryError>>>,
+            commands: &mut Commands,
+        ) {
+            results.push(query.get_mut(*stale).map(|_| ()));
+            results.push(query.get_component::<A>(*stale).map(|_| ()));
+            results.push(query.get_component_mut::<A>(*stale).map(|_| ()));
+            commands.despawn(*stale);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let stale = world.spawn((A(0),));
+        world.despawn(stale).unwrap();
+        let reused = world.spawn((A(1),));
+        assert_eq!(stale.id(), reused.id());
+        resources.insert(stale);
+        resources.insert(Vec::<Result<(), QueryError>>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", lookups.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(
+            *resources.get::<Vec<Result<(), QueryError>>>().unwrap(),
+            vec![Err(QueryError::NoSuchEntity); 3]
+        );
+        assert_eq!(
+            *world.get::<A>(reused).unwrap(),
+            A(1),
+            "despawning the stale entity leaves the entity that reused its ID alone"
+        );
+    }
+
+    #[test]
+    fn get_mut_system() {
+        struct Targets(Vec<Entity>);
+
+        fn increment(
+            mut query: Query<&mut A, With<B>>,
+            targets: Res<Targets>,
+            mut errors: ResMut<Vec<(Entity, QueryError)>>,
+        ) {
+            for entity in targets.0.iter() {
+                match query.get_mut(*entity) {
+                    Ok(mut a) => a.0 += 1,
+                    Err(err) => errors.push((*entity, err)),
+                }
+            }
+        }
+
+        fn collect_mutated(query: Query<&A, Mutated<A>>, mut mutated: ResMut<Vec<usize>>) {
+            mutated.extend(query.iter().map(|a| a.0));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let with_b = world.spawn((A(0), B));
+        let without_b = world.spawn((A(10),));
+        let despawned = world.spawn((A(20), B));
+        world.despawn(despawned).unwrap();
+        resources.insert(Targets(vec![with_b, without_b, despawned]));
+        resources.insert(Vec::<(Entity, QueryError)>::new());
+        resources.insert(Vec::<usize>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("detect");
+        schedule.add_system_to_stage("update", increment.system());
+        schedule.add_system_to_stage("detect", collect_mutated.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(*world.get::<A>(with_b).unwrap(), A(1));
+        assert_eq!(*world.get::<A>(without_b).unwrap(), A(10));
+        assert_eq!(
+            *resources.get::<Vec<(Entity, QueryError)>>().unwrap(),
+            vec![
+                (without_b, QueryError::QueryDoesNotMatch),
+                (despawned, QueryError::NoSuchEntity)
+            ]
+        );
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn iter_many() {
+        struct Selection(Vec<Entity>);
+
+        fn select(
+            query: Query<&A, With<B>>,
+            selection: Res<Selection>,
+            mut seen: ResMut<Vec<usize>>,
+        ) {
+            seen.extend(query.iter_many(selection.0.iter().copied()).map(|a| a.0));
+        }
+
+        fn increment(mut query: Query<&mut A, With<B>>, selection: Res<Selection>) {
+            for mut a in query.iter_many_mut(selection.0.iter().copied()) {
+                a.0 += 10;
+            }
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let first = world.spawn((A(1), B));
+        let second = world.spawn((A(2), B));
+        let unselected = world.spawn((A(3), B));
+        let without_b = world.spawn((A(4),));
+        let despawned = world.spawn((A(5), B));
+        world.despawn(despawned).unwrap();
+        resources.insert(Selection(vec![second, without_b, first, despawned, second]));
+        resources.insert(Vec::<usize>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("increment");
+        schedule.add_system_to_stage("update", select.system());
+        schedule.add_system_to_stage("increment", increment.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        // read-only iteration returns duplicates again, in the order of the selection
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![2, 1, 2]);
+        // mutable iteration skips the duplicate, so the entity is only incremented once
+        assert_eq!(*world.get::<A>(first).unwrap(), A(11));
+        assert_eq!(*world.get::<A>(second).unwrap(), A(12));
+        assert_eq!(*world.get::<A>(unselected).unwrap(), A(3));
+        assert_eq!(*world.get::<A>(without_b).unwrap(), A(4));
+    }
+
+    #[test]
+    fn iter_sorted_by_key() {
+        fn sorted(query: Query<(Entity, &A)>, mut seen: ResMut<Vec<Entity>>) {
+            seen.clear();
+            seen.extend(query.iter_sorted_by_key(|(_, a)| a.0 / 10).map(|(e, _)| e));
+        }
+
+        fn sorted_mut(mut query: Query<(&mut A, Option<&B>)>) {
+            let mut last = 0;
+            for (mut a, b) in query.iter_sorted_by_key_mut(|(a, _)| a.0) {
+                assert!(a.0 >= last, "mutable results come in sorted order");
+                last = a.0;
+                if b.is_some() {
+                    a.0 += 1;
+                }
+            }
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let entities = [25, 12, 21, 14, 3, 28]
+            .iter()
+            .map(|&key| world.spawn((A(key),)))
+            .collect::<Vec<_>>();
+        let marked = world.spawn((A(20), B));
+        resources.insert(Vec::<Entity>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("sort");
+        schedule.add_stage("mutate");
+        schedule.add_system_to_stage("sort", sorted.system());
+        schedule.add_system_to_stage("mutate", sorted_mut.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        // entities with equal keys keep their iteration order, which is the spawn order here
+        let expected = vec![
+            entities[4],
+            entities[1],
+            entities[3],
+            entities[0],
+            entities[2],
+            entities[5],
+            marked,
+        ];
+        assert_eq!(*resources.get::<Vec<Entity>>().unwrap(), expected);
+        assert_eq!(*world.get::<A>(marked).unwrap(), A(21));
+
+        // the next sort starts out from this one's order, and has to end up the same as a sort from scratch
+        world.get_mut::<A>(entities[4]).unwrap().0 = 30;
+        world.get_mut::<A>(entities[5]).unwrap().0 = 11;
+        schedule.run(&mut world, &mut resources);
+        let expected = vec![
+            entities[1],
+            entities[3],
+            entities[5],
+            entities[0],
+            entities[2],
+            marked,
+            entities[4],
+        ];
+        assert_eq!(*resources.get::<Vec<Entity>>().unwrap(), expected);
+    }
+
+    #[test]
+    fn get_unchecked_mut() {
+        fn swap(query: Query<&mut A>, pair: Res<(Entity, Entity)>) {
+            let (first, second) = *pair;
+            // SAFE: the entities are distinct, so the results don't alias
+            let (mut first, mut second) = unsafe {
+                (
+                    query.get_unchecked_mut(first).unwrap(),
+                    query.get_unchecked_mut(second).unwrap(),
+                )
+            };
+            std::mem::swap(&mut first.0, &mut second.0);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let first = world.spawn((A(1),));
+        let second = world.spawn((A(2), B));
+        resources.insert((first, second));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", swap.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(*world.get::<A>(first).unwrap(), A(2));
+        assert_eq!(*world.get::<A>(second).unwrap(), A(1));
+    }
+
+    #[test]
+    fn get_many() {
+        struct Health(u32);
+
+        fn attack(
+            mut healths: Query<&mut Health, With<B>>,
+            fights: Res<Vec<(Entity, Entity)>>,
+            mut errors: ResMut<Vec<QueryError>>,
+        ) {
+            for (attacker, defender) in fights.iter() {
+                match healths.get_many_mut([*attacker, *defender]) {
+                    Ok([mut attacker, mut defender]) => {
+                        defender.0 -= 10;
+                        attacker.0 += 5;
+                    }
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+
+        fn total(
+            healths: Query<&Health, With<B>>,
+            fights: Res<Vec<(Entity, Entity)>>,
+            mut totals: ResMut<Vec<u32>>,
+        ) {
+            let (attacker, defender) = fights[0];
+            // the same entity can be read more than once
+            let [a, b, c] = healths.get_many([attacker, defender, attacker]).unwrap();
+            totals.push(a.0 + b.0 + c.0);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let knight = world.spawn((Health(100), B));
+        let dragon = world.spawn((Health(100), B));
+        let bystander = world.spawn((Health(100),));
+        let despawned = world.spawn((Health(100), B));
+        world.despawn(despawned).unwrap();
+        resources.insert(vec![
+            (knight, dragon),
+            (dragon, dragon),
+            (knight, bystander),
+            (despawned, knight),
+        ]);
+        resources.insert(Vec::<QueryError>::new());
+        resources.insert(Vec::<u32>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("total");
+        schedule.add_system_to_stage("update", attack.system());
+        schedule.add_system_to_stage("total", total.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(world.get::<Health>(knight).unwrap().0, 105);
+        assert_eq!(world.get::<Health>(dragon).unwrap().0, 90);
+        assert_eq!(world.get::<Health>(bystander).unwrap().0, 100);
+        assert_eq!(
+            *resources.get::<Vec<QueryError>>().unwrap(),
+            vec![
+                QueryError::AliasedMutability(dragon),
+                QueryError::QueryDoesNotMatch,
+                QueryError::NoSuchEntity,
+            ]
+        );
+        assert_eq!(*resources.get::<Vec<u32>>().unwrap(), vec![105 + 90 + 105]);
+    }
+
+    #[test]
+    fn gravity_pairs() {
+        struct Mass(i64);
+        struct Force(i64);
+
+        fn gravity(
+            mut bodies: Query<(Entity, &Mass, &mut Force)>,
+            mut pairs: ResMut<Vec<(Entity, Entity)>>,
+        ) {
+            let mut combinations = bodies.iter_combinations_mut::<2>();
+            while let Some([(a, mass_a, mut force_a), (b, mass_b, mut force_b)]) =
+                combinations.fetch_next()
+            {
+                let force = mass_a.0 * mass_b.0;
+                force_a.0 += force;
+                force_b.0 -= force;
+                pairs.push((a, b));
+            }
+            assert!(bodies.iter_combinations_mut::<5>().fetch_next().is_none());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        for mass in 1..=4 {
+            world.spawn((Mass(mass), Force(0)));
+        }
+        world.spawn((Mass(100),));
+        resources.insert(Vec::<(Entity, Entity)>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", gravity.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        let pairs = resources.get::<Vec<(Entity, Entity)>>().unwrap();
+        assert_eq!(pairs.len(), 6);
+        let mut unordered = pairs
+            .iter()
+            .map(|(a, b)| if a < b { (*a, *b) } else { (*b, *a) })
+            .collect::<Vec<_>>();
+        unordered.sort();
+        unordered.dedup();
+        assert_eq!(unordered.len(), 6, "every pair is visited once");
+        assert!(pairs.iter().all(|(a, b)| a != b));
+        // every pair applied opposite forces, so they add up to nothing
+        assert_eq!(world.query::<&Force>().map(|force| force.0).sum::<i64>(), 0);
+    }
+
+    #[test]
+    fn for_each_matches_iter() {
+        fn double(mut query: Query<&mut A, Without<B>>) {
+            query.for_each_mut(|mut a| a.0 *= 2);
+        }
+
+        fn collect_mutated(query: Query<&A, Mutated<A>>, mut mutated: ResMut<Vec<usize>>) {
+            query.for_each(|a| mutated.push(a.0));
+            assert!(query.iter().map(|a| a.0).eq(mutated.iter().copied()));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn_batch((0..3).map(|i| (A(i),)));
+        world.spawn_batch((3..5).map(|i| (A(i), B)));
+        world.spawn((A(5), 0u32));
+        resources.insert(Vec::<usize>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_stage("detect");
+        schedule.add_system_to_stage("update", double.system());
+        schedule.add_system_to_stage("detect", collect_mutated.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        let mut mutated = resources.get::<Vec<usize>>().unwrap().to_vec();
+        mutated.sort_unstable();
+        assert_eq!(mutated, vec![0, 2, 4, 10]);
+    }
+
+    #[test]
+    fn par_iter_mut_system() {
+        fn double(pool: Res<ComputeTaskPool>, mut query: Query<&mut A>) {
+            query.par_iter_mut(8).for_each(&pool, |mut a| a.0 *= 2);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+        world.spawn_batch((0..100).map(|i| (A(i),)));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", double.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        let mut values = world.query::<&A>().map(|a| a.0).collect::<Vec<_>>();
+        values.sort_unstable();
+        assert_eq!(values, (0..100).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn par_iter_mut() {
+        let mut world = World::default();
+        world.spawn_batch((0..100).map(|i| (A(i),)));
+        world.spawn_batch((100..150).map(|i| (A(i), B)));
+
+        let pool = TaskPool::new();
+        let access = TypeAccess::default();
+        let mut query = unsafe { Query::<&mut A>::new(&world, &access, world.change_ticks()) };
+        query.par_iter_mut(16).for_each(&pool, |mut a| a.0 *= 2);
+
+        let query = unsafe { Query::<&A>::new(&world, &access, world.change_ticks()) };
+        let mut values = query.iter().map(|a| a.0).collect::<Vec<_>>();
+        values.sort_unstable();
+        assert_eq!(values, (0..150).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn transmute() {
+        fn total(query: &Query<&A>) -> usize {
+            query.iter().map(|a| a.0).sum()
+        }
+
+        fn double(mut query: Query<&mut A>) {
+            for mut a in query.iter_mut() {
+                a.0 *= 2;
+            }
+        }
+
+        fn system(
+            mut query: Query<(&mut A, &B)>,
+            unmatched: Res<Entity>,
+            mut totals: ResMut<Vec<usize>>,
+        ) {
+            totals.push(total(&query.as_readonly()));
+            assert_eq!(
+                query.as_readonly::<&A>().get(*unmatched).err(),
+                Some(QueryError::QueryDoesNotMatch)
+            );
+            double(query.transmute());
+            totals.push(total(&query.as_readonly()));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A(1), B));
+        world.spawn((A(2), B));
+        // not matched by the system's query, so the transmuted queries don't return it either
+        let unmatched = world.spawn((A(100),));
+        resources.insert(unmatched);
+        resources.insert(Vec::<usize>::new());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", system.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(*resources.get::<Vec<usize>>().unwrap(), vec![3, 6]);
+        assert_eq!(*world.get::<A>(unmatched).unwrap(), A(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "because it doesn't fetch")]
+    fn transmute_to_mutable_panics() {
+        fn system(mut query: Query<&A>) {
+            let _ = query.transmute::<&mut A>();
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.spawn((A(1),));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", system.system());
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+    }
+}
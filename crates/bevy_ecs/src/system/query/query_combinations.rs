@@ -0,0 +1,136 @@
+use bevy_hecs::{
+    Archetype, ChangeTicks, EntityFilter, Fetch, Query as HecsQuery, QueryFilter, ReadOnlyFetch,
+    World,
+};
+use std::marker::PhantomData;
+
+/// Iterates over every combination of `K` distinct entities that match a query, returned by
+/// [Query::iter_combinations](crate::Query::iter_combinations) and
+/// [Query::iter_combinations_mut](crate::Query::iter_combinations_mut).
+///
+/// Combinations are unordered: each set of `K` entities is returned once, with the entities in query iteration order.
+/// Use [QueryCombinationIter::fetch_next] to step through mutable combinations. Read-only combinations can also be
+/// consumed as an [Iterator].
+pub struct QueryCombinationIter<'w, Q: HecsQuery, F: QueryFilter, const K: usize> {
+    /// the archetype and offset of every matching entity
+    matches: Vec<(&'w Archetype, usize)>,
+    /// indices into `matches` of the next combination, or None once every combination has been returned
+    indices: Option<[usize; K]>,
+    ticks: ChangeTicks,
+    _marker: PhantomData<(Q, F)>,
+}
+
+impl<'w, Q: HecsQuery, F: QueryFilter, const K: usize> QueryCombinationIter<'w, Q, F, K> {
+    /// Only entities of the archetypes set in `matched_archetypes` are returned, if given
+    pub(crate) fn new(
+        world: &'w World,
+        ticks: ChangeTicks,
+        matched_archetypes: Option<&[bool]>,
+    ) -> Self {
+        let mut matches = Vec::new();
+        for (archetype_index, archetype) in world.archetypes().enumerate() {
+            let matched = match matched_archetypes {
+                Some(matched_archetypes) => matched_archetypes[archetype_index],
+                None => true,
+            };
+            if !matched || archetype.is_empty() {
+                continue;
+            }
+            // SAFE: the archetype isn't empty, and the fetch is only used to check which entities match the query
+            let fetch = match unsafe { <Q::Fetch as Fetch>::get(archetype, 0, ticks) } {
+                Some(fetch) => fetch,
+                None => continue,
+            };
+            if let Some(filter) = F::get_entity_filter(archetype, ticks) {
+                // SAFE: filters only read the data they declared in their access, which the system holds
+                matches.extend(
+                    (0..archetype.len())
+                        .filter(|offset| unsafe {
+                            filter.matches_entity(*offset) && fetch.matches_entity(*offset)
+                        })
+                        .map(|offset| (archetype, offset)),
+                );
+            }
+        }
+
+        Self {
+            indices: if K > 0 && K <= matches.len() {
+                Some(std::array::from_fn(|i| i))
+            } else {
+                None
+            },
+            matches,
+            ticks,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the indices of the current combination and moves on to the next one in lexicographic order
+    fn next_indices(&mut self) -> Option<[usize; K]> {
+        let current = self.indices?;
+        let len = self.matches.len();
+        let mut next = current;
+        // find the last index that can still move right, then reset every index after it to follow it directly
+        self.indices = (0..K).rev().find(|&i| next[i] < len - K + i).map(|i| {
+            next[i] += 1;
+            let start = next[i];
+            for (offset, index) in next[i + 1..].iter_mut().enumerate() {
+                *index = start + offset + 1;
+            }
+            next
+        });
+        Some(current)
+    }
+
+    /// Returns the next combination. The items borrow the iterator, so two combinations that share an entity can
+    /// never be held at the same time, even when the query fetches components mutably.
+    #[inline]
+    pub fn fetch_next(&mut self) -> Option<[<Q::Fetch as Fetch<'_>>::Item; K]> {
+        let indices = self.next_indices()?;
+        // SAFE: the indices of a combination are distinct, so none of its items alias. the returned items borrow self
+        // mutably, so they are dropped before the next combination is fetched
+        unsafe {
+            Some(fetch_combination::<Q, K>(
+                &self.matches,
+                indices,
+                self.ticks,
+            ))
+        }
+    }
+}
+
+impl<'w, Q: HecsQuery, F: QueryFilter, const K: usize> Iterator
+    for QueryCombinationIter<'w, Q, F, K>
+where
+    Q::Fetch: ReadOnlyFetch,
+{
+    type Item = [<Q::Fetch as Fetch<'w>>::Item; K];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let indices = self.next_indices()?;
+        // SAFE: the fetch is read-only, so items from different combinations can be held at the same time
+        unsafe {
+            Some(fetch_combination::<Q, K>(
+                &self.matches,
+                indices,
+                self.ticks,
+            ))
+        }
+    }
+}
+
+/// # Safety
+/// The caller must make sure the fetched items don't alias any other live mutable borrows
+unsafe fn fetch_combination<'a, Q: HecsQuery, const K: usize>(
+    matches: &[(&'a Archetype, usize)],
+    indices: [usize; K],
+    ticks: ChangeTicks,
+) -> [<Q::Fetch as Fetch<'a>>::Item; K] {
+    std::array::from_fn(|i| {
+        let (archetype, offset) = matches[indices[i]];
+        <Q::Fetch as Fetch>::get(archetype, offset, ticks)
+            .expect("the query matched this archetype when the iterator was created")
+            .fetch(0)
+    })
+}
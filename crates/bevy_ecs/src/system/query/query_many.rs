@@ -0,0 +1,82 @@
+use bevy_hecs::{ChangeTicks, Entity, Fetch, Query as HecsQuery, QueryFilter, World};
+use bevy_utils::HashSet;
+use std::marker::PhantomData;
+
+/// Iterates over the query results of a given list of entities, in the order of the list. Returned by
+/// [Query::iter_many](crate::Query::iter_many) and [Query::iter_many_mut](crate::Query::iter_many_mut).
+///
+/// Entities that don't exist or don't match the query are skipped. When iterating mutably, an entity that comes up
+/// again after it was returned is skipped as well, so its components are never borrowed mutably twice.
+pub struct QueryManyIter<'w, Q: HecsQuery, F: QueryFilter, I: Iterator<Item = Entity>> {
+    world: &'w World,
+    entities: I,
+    ticks: ChangeTicks,
+    /// the archetypes the query may access, by archetype index, if it can't access every archetype it matches
+    matched_archetypes: Option<&'w [bool]>,
+    /// the entities returned so far, only tracked for mutable iteration
+    returned: Option<HashSet<Entity>>,
+    _marker: PhantomData<(Q, F)>,
+}
+
+impl<'w, Q: HecsQuery, F: QueryFilter, I: Iterator<Item = Entity>> QueryManyIter<'w, Q, F, I> {
+    /// # Safety
+    /// The query must have access to the components it fetches. If the query can fetch components mutably,
+    /// `skip_duplicates` must be set, unless `entities` never yields the same entity twice.
+    pub(crate) unsafe fn new(
+        world: &'w World,
+        entities: I,
+        ticks: ChangeTicks,
+        matched_archetypes: Option<&'w [bool]>,
+        skip_duplicates: bool,
+    ) -> Self {
+        Self {
+            world,
+            entities,
+            ticks,
+            matched_archetypes,
+            returned: if skip_duplicates {
+                Some(HashSet::default())
+            } else {
+                None
+            },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'w, Q: HecsQuery, F: QueryFilter, I: Iterator<Item = Entity>> Iterator
+    for QueryManyIter<'w, Q, F, I>
+{
+    type Item = <Q::Fetch as Fetch<'w>>::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        for entity in &mut self.entities {
+            if let Some(returned) = self.returned.as_mut() {
+                if returned.contains(&entity) {
+                    continue;
+                }
+            }
+            if let Some(matched_archetypes) = self.matched_archetypes {
+                match self.world.get_entity_location(entity) {
+                    Some(location) if matched_archetypes[location.archetype as usize] => {}
+                    _ => continue,
+                }
+            }
+            // SAFE: the query has access to the components it fetches, and mutable fetches are never repeated for the
+            // same entity
+            if let Ok(item) = unsafe { self.world.query_one_unchecked::<Q, F>(entity, self.ticks) }
+            {
+                if let Some(returned) = self.returned.as_mut() {
+                    returned.insert(entity);
+                }
+                return Some(item);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.entities.size_hint().1)
+    }
+}
@@ -1,7 +1,7 @@
 use crate::Query;
 use bevy_hecs::{
-    impl_query_set, ArchetypeComponent, Fetch, Query as HecsQuery, QueryAccess, QueryFilter,
-    TypeAccess, World,
+    impl_query_set, ArchetypeComponent, ChangeTicks, Fetch, Query as HecsQuery, QueryAccess,
+    QueryFilter, TypeAccess, World,
 };
 
 pub struct QuerySet<T: QueryTuple> {
@@ -13,7 +13,11 @@ impl_query_set!();
 pub trait QueryTuple {
     /// # Safety
     /// this might cast world and component access to the relevant Self lifetimes. verify that this is safe in each impl
-    unsafe fn new(world: &World, component_access: &TypeAccess<ArchetypeComponent>) -> Self;
+    unsafe fn new(
+        world: &World,
+        component_access: &TypeAccess<ArchetypeComponent>,
+        ticks: ChangeTicks,
+    ) -> Self;
     fn get_accesses() -> Vec<QueryAccess>;
 }
 
@@ -24,9 +28,10 @@ impl<T: QueryTuple> QuerySet<T> {
     pub(crate) unsafe fn new(
         world: &World,
         component_access: &TypeAccess<ArchetypeComponent>,
+        ticks: ChangeTicks,
     ) -> Self {
         QuerySet {
-            value: T::new(world, component_access),
+            value: T::new(world, component_access, ticks),
         }
     }
 }
@@ -0,0 +1,113 @@
+use bevy_hecs::Entity;
+use parking_lot::Mutex;
+use std::any::Any;
+
+/// The buffers [Query::iter_sorted_by_key](crate::Query::iter_sorted_by_key) sorts into. A system keeps one per query
+/// between runs, so sorting the same entities again doesn't allocate.
+#[derive(Debug, Default)]
+pub struct QuerySortBuffer {
+    /// The matching entities in iteration order, as of the last sort
+    entities: Vec<Entity>,
+    /// The order the last sort put `entities` in, as indices into `entities`
+    order: Vec<usize>,
+    /// A `Vec<K>` holding the key of each entity
+    keys: Option<Box<dyn Any + Send + Sync>>,
+    /// The sorted entities, unless a [SortedEntities] has them
+    sorted: Vec<Entity>,
+}
+
+impl QuerySortBuffer {
+    /// Sorts the entities `items` yields by their keys, breaking ties by iteration order
+    pub(crate) fn sort<K: Ord + Send + Sync + 'static>(
+        &mut self,
+        items: impl Iterator<Item = (Entity, K)>,
+    ) -> Vec<Entity> {
+        let mut keys = match self.keys.take().map(|keys| keys.downcast::<Vec<K>>()) {
+            Some(Ok(keys)) => keys,
+            _ => Box::new(Vec::new()),
+        };
+        keys.clear();
+
+        let previous_len = self.entities.len();
+        let mut unchanged = true;
+        for (index, (entity, key)) in items.enumerate() {
+            match self.entities.get_mut(index) {
+                Some(previous) if *previous == entity => {}
+                Some(previous) => {
+                    *previous = entity;
+                    unchanged = false;
+                }
+                None => {
+                    self.entities.push(entity);
+                    unchanged = false;
+                }
+            }
+            keys.push(key);
+        }
+        if keys.len() != previous_len {
+            self.entities.truncate(keys.len());
+            unchanged = false;
+        }
+
+        // when the same entities are iterated in the same order, starting from the last order leaves only the entities
+        // whose keys changed out of place
+        if !unchanged || self.order.len() != keys.len() {
+            self.order.clear();
+            self.order.extend(0..keys.len());
+        }
+        // ties are broken by iteration order, so the unstable sort gives the same result as a stable one without
+        // allocating
+        self.order
+            .sort_unstable_by(|&a, &b| keys[a].cmp(&keys[b]).then(a.cmp(&b)));
+
+        let mut sorted = std::mem::take(&mut self.sorted);
+        sorted.clear();
+        sorted.extend(self.order.iter().map(|&index| self.entities[index]));
+        self.keys = Some(keys);
+        sorted
+    }
+}
+
+/// The entities of a query in sorted order. Gives its buffer back to the query's [QuerySortBuffer] when dropped.
+pub struct SortedEntities<'a> {
+    entities: Vec<Entity>,
+    next: usize,
+    buffer: Option<&'a Mutex<QuerySortBuffer>>,
+}
+
+impl<'a> SortedEntities<'a> {
+    pub(crate) fn new(entities: Vec<Entity>, buffer: Option<&'a Mutex<QuerySortBuffer>>) -> Self {
+        Self {
+            entities,
+            next: 0,
+            buffer,
+        }
+    }
+}
+
+impl<'a> Iterator for SortedEntities<'a> {
+    type Item = Entity;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let entity = *self.entities.get(self.next)?;
+        self.next += 1;
+        Some(entity)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.entities.len() - self.next;
+        (len, Some(len))
+    }
+}
+
+impl<'a> Drop for SortedEntities<'a> {
+    fn drop(&mut self) {
+        // if the buffer is busy, another sort of the same query is running and keeps its own vec
+        if let Some(mut buffer) = self.buffer.and_then(|buffer| buffer.try_lock()) {
+            if self.entities.capacity() > buffer.sorted.capacity() {
+                buffer.sorted = std::mem::take(&mut self.entities);
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{
+    resource::Resources,
+    system::{BoxedSystem, IntoSystem, QueryConflictError, System, SystemId, ThreadLocalExecution},
+};
+use bevy_hecs::{ArchetypeComponent, TypeAccess, World};
+use std::{any::TypeId, borrow::Cow};
+
+/// Determines whether a system or stage should run. Returned by run criteria systems.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ShouldRun {
+    /// Run once
+    Yes,
+    /// Skip this run
+    No,
+    /// Run, then evaluate the run criteria again. Stages and systems keep running until their criteria returns
+    /// something else, which lets them run several times in one frame.
+    YesAndCheckAgain,
+}
+
+/// A [System] that only runs when its run criteria system doesn't return [ShouldRun::No].
+///
+/// The wrapped system is still updated every time the schedule runs, so its archetype access stays current even
+/// while it is being skipped. When the criteria returns [ShouldRun::YesAndCheckAgain] the system runs again right
+/// away. Its thread local work, like applying commands, is done once after the last of those runs.
+pub struct RunCriteriaSystem {
+    system: BoxedSystem,
+    run_criteria: BoxedSystem<(), ShouldRun>,
+    archetype_component_access: TypeAccess<ArchetypeComponent>,
+    resource_access: TypeAccess<TypeId>,
+    ran: bool,
+}
+
+impl RunCriteriaSystem {
+    pub fn new(system: BoxedSystem, run_criteria: BoxedSystem<(), ShouldRun>) -> Self {
+        Self {
+            system,
+            run_criteria,
+            archetype_component_access: Default::default(),
+            resource_access: Default::default(),
+            ran: false,
+        }
+    }
+
+    fn update_access(&mut self) {
+        self.archetype_component_access.clear();
+        self.archetype_component_access
+            .union(self.system.archetype_component_access());
+        self.archetype_component_access
+            .union(self.run_criteria.archetype_component_access());
+        self.resource_access.clear();
+        self.resource_access.union(self.system.resource_access());
+        self.resource_access
+            .union(self.run_criteria.resource_access());
+    }
+}
+
+impl System for RunCriteriaSystem {
+    type In = ();
+    type Out = ();
+
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+
+    fn set_name(&mut self, name: Cow<'static, str>) {
+        self.system.set_name(name);
+    }
+
+    fn id(&self) -> SystemId {
+        self.system.id()
+    }
+
+    fn is_initialized(&self) -> bool {
+        self.system.is_initialized() && self.run_criteria.is_initialized()
+    }
+
+    fn update(&mut self, world: &World) {
+        self.system.update(world);
+        self.run_criteria.update(world);
+        self.update_access();
+    }
+
+    fn validate(&self, world: &World) -> Result<(), QueryConflictError> {
+        self.run_criteria.validate(world)?;
+        self.system.validate(world)
+    }
+
+    fn archetype_component_access(&self) -> &TypeAccess<ArchetypeComponent> {
+        &self.archetype_component_access
+    }
+
+    fn resource_access(&self) -> &TypeAccess<TypeId> {
+        &self.resource_access
+    }
+
+    fn thread_local_execution(&self) -> ThreadLocalExecution {
+        self.system.thread_local_execution()
+    }
+
+    fn run(&mut self, _input: (), world: &World, resources: &Resources) -> Option<()> {
+        let mut ran = false;
+        let mut out = None;
+        loop {
+            let should_run = self
+                .run_criteria
+                .run((), world, resources)
+                .unwrap_or(ShouldRun::No);
+            if should_run != ShouldRun::No {
+                ran = true;
+                out = self.system.run((), world, resources);
+            }
+            if should_run != ShouldRun::YesAndCheckAgain {
+                break;
+            }
+        }
+        self.ran = ran;
+        out
+    }
+
+    fn run_thread_local(&mut self, world: &mut World, resources: &mut Resources) {
+        self.run_criteria.run_thread_local(world, resources);
+        // thread local systems do their work here, so they need to be skipped too
+        if self.ran {
+            self.system.run_thread_local(world, resources);
+        }
+    }
+
+    fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        self.system.initialize(world, resources);
+        self.run_criteria.initialize(world, resources);
+        self.update_access();
+    }
+}
+
+/// Adds run criteria to a boxed [System]
+pub trait RunCriteriaExt {
+    /// Skips this system whenever `run_criteria` returns [ShouldRun::No]
+    fn run_if<Params>(self, run_criteria: impl IntoSystem<Params, ShouldRun>) -> BoxedSystem;
+}
+
+impl RunCriteriaExt for BoxedSystem {
+    fn run_if<Params>(self, run_criteria: impl IntoSystem<Params, ShouldRun>) -> BoxedSystem {
+        Box::new(RunCriteriaSystem::new(self, run_criteria.system()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RunCriteriaExt, ShouldRun};
+    use crate::{
+        resource::{Res, ResMut, Resources},
+        schedule::Schedule,
+        Commands, IntoSystem,
+    };
+    use bevy_hecs::World;
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum GameState {
+        Paused,
+        Playing,
+    }
+
+    fn playing(state: Res<GameState>) -> ShouldRun {
+        if *state == GameState::Playing {
+            ShouldRun::Yes
+        } else {
+            ShouldRun::No
+        }
+    }
+
+    #[test]
+    fn run_if() {
+        fn count(commands: &mut Commands, mut count: ResMut<usize>) {
+            *count += 1;
+            commands.spawn((*count,));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(GameState::Paused);
+        resources.insert(0usize);
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", count.system().run_if(playing));
+        schedule.initialize(&mut world, &mut resources);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 0);
+        assert_eq!(world.query::<&usize>().count(), 0);
+
+        *resources.get_mut::<GameState>().unwrap() = GameState::Playing;
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 2);
+        assert_eq!(world.query::<&usize>().count(), 2);
+
+        *resources.get_mut::<GameState>().unwrap() = GameState::Paused;
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 2);
+        assert_eq!(world.query::<&usize>().count(), 2);
+    }
+
+    #[test]
+    fn run_if_resource_access() {
+        fn system(mut count: ResMut<usize>) {
+            *count += 1;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(GameState::Playing);
+        resources.insert(0usize);
+
+        let mut system = system.system().run_if(playing);
+        system.initialize(&mut world, &mut resources);
+        system.update(&world);
+
+        let resource_access = system.resource_access();
+        assert!(resource_access.is_read_or_write(&std::any::TypeId::of::<GameState>()));
+        assert!(resource_access.is_write(&std::any::TypeId::of::<usize>()));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::resource::Resources;
+use crate::{resource::Resources, system::QueryConflictError};
 use bevy_hecs::{ArchetypeComponent, TypeAccess, World};
 use std::{any::TypeId, borrow::Cow};
 
@@ -9,6 +9,8 @@ pub enum ThreadLocalExecution {
     NextFlush,
 }
 
+/// Identifies a [System]. The id is picked when the system is created and never changes, so it refers to the same
+/// system for as long as the system is part of a [Schedule](crate::Schedule).
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct SystemId(pub usize);
 
@@ -21,14 +23,64 @@ impl SystemId {
 
 /// An ECS system that can be added to a [Schedule](crate::Schedule)
 pub trait System: Send + Sync {
+    type In;
+    type Out;
     fn name(&self) -> Cow<'static, str>;
+    /// Overrides the name returned by [System::name] and used in error messages
+    fn set_name(&mut self, name: Cow<'static, str>);
     fn id(&self) -> SystemId;
     fn is_initialized(&self) -> bool;
     fn update(&mut self, world: &World);
+    /// Checks that the system's accesses don't conflict with each other for the archetypes currently in the `world`
+    fn validate(&self, _world: &World) -> Result<(), QueryConflictError> {
+        Ok(())
+    }
+    /// Returns true if running the system would have no effect, so that executors can skip it. Only valid after
+    /// [System::update] was called for the current archetypes.
+    fn can_skip(&self) -> bool {
+        false
+    }
+    /// Makes [System::can_skip] always return false. Systems that are never skipped can ignore this.
+    fn set_always_run(&mut self, _always_run: bool) {}
     fn archetype_component_access(&self) -> &TypeAccess<ArchetypeComponent>;
     fn resource_access(&self) -> &TypeAccess<TypeId>;
     fn thread_local_execution(&self) -> ThreadLocalExecution;
-    fn run(&mut self, world: &World, resources: &Resources);
+    /// Runs the system, returning `None` if it was skipped because its parameters were unavailable
+    fn run(&mut self, input: Self::In, world: &World, resources: &Resources) -> Option<Self::Out>;
     fn run_thread_local(&mut self, world: &mut World, resources: &mut Resources);
     fn initialize(&mut self, _world: &mut World, _resources: &mut Resources) {}
 }
+
+/// A boxed [System], which is how systems are stored in a [Schedule](crate::Schedule)
+pub type BoxedSystem<In = (), Out = ()> = Box<dyn System<In = In, Out = Out>>;
+
+/// Renames boxed [System]s
+pub trait SystemNameExt {
+    /// Replaces the system's default name, which is the type name of the function or closure it was created from
+    fn with_name(self, name: impl Into<Cow<'static, str>>) -> Self;
+}
+
+impl<In, Out> SystemNameExt for BoxedSystem<In, Out> {
+    fn with_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.set_name(name.into());
+        self
+    }
+}
+
+/// Keeps boxed [System]s from being skipped.
+///
+/// A system whose parameters are all queries is skipped by the executors while none of its queries match an
+/// archetype, because it would only iterate over nothing. Systems with any other parameter, like a resource or
+/// [Commands](crate::Commands), always run.
+pub trait AlwaysRunExt {
+    /// Runs the system even when none of its queries match, for example because it relies on
+    /// [Query::is_empty](crate::Query::is_empty) to notice that something is missing
+    fn always_run(self) -> Self;
+}
+
+impl<In, Out> AlwaysRunExt for BoxedSystem<In, Out> {
+    fn always_run(mut self) -> Self {
+        self.set_always_run(true);
+        self
+    }
+}
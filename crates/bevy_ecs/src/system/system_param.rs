@@ -1,13 +1,14 @@
 use crate::{
-    ChangedRes, Commands, FromResources, Local, Query, QueryAccess, QuerySet, QueryTuple, Res,
-    ResMut, Resource, ResourceIndex, Resources, SystemState,
+    ChangedRes, Commands, FromResources, Local, NonSend, NonSendMut, Query, QueryAccess, QuerySet,
+    QuerySortBuffer, QueryTuple, Res, ResMut, Resource, ResourceIndex, Resources, SystemState,
 };
 pub use bevy_hecs::SystemParam;
 use bevy_hecs::{
-    ArchetypeComponent, Fetch, Or, Query as HecsQuery, QueryFilter, TypeAccess, World,
+    ArchetypeComponent, Component, Entity, Fetch, Or, Query as HecsQuery, QueryFilter, TypeAccess,
+    World,
 };
 use parking_lot::Mutex;
-use std::{any::TypeId, sync::Arc};
+use std::{marker::PhantomData, sync::Arc};
 
 pub trait SystemParam: Sized {
     fn init(system_state: &mut SystemState, world: &World, resources: &mut Resources);
@@ -32,19 +33,27 @@ impl<'a, Q: HecsQuery, F: QueryFilter> SystemParam for Query<'a, Q, F> {
         let world: &'a World = std::mem::transmute(world);
         let archetype_component_access: &'a TypeAccess<ArchetypeComponent> =
             std::mem::transmute(&system_state.query_archetype_component_accesses[query_index]);
+        let sort_buffer: &'a Mutex<QuerySortBuffer> =
+            std::mem::transmute(&system_state.query_sort_buffers[query_index]);
         system_state.current_query_index += 1;
-        Some(Query::new(world, archetype_component_access))
+        let mut query = Query::new(
+            world,
+            archetype_component_access,
+            system_state.change_ticks(),
+        );
+        query.sort_buffer = Some(sort_buffer);
+        Some(query)
     }
 
     fn init(system_state: &mut SystemState, _world: &World, _resources: &mut Resources) {
         system_state
             .query_archetype_component_accesses
             .push(TypeAccess::default());
+        system_state.query_sort_buffers.push(Default::default());
         let access = QueryAccess::union(vec![Q::Fetch::access(), F::access()]);
         system_state.query_accesses.push(vec![access]);
-        system_state
-            .query_type_names
-            .push(std::any::type_name::<Q>());
+        let query_type_name = system_state.param_name(std::any::type_name::<Q>());
+        system_state.query_type_names.push(query_type_name);
     }
 }
 
@@ -60,6 +69,7 @@ impl<T: QueryTuple> SystemParam for QuerySet<T> {
         Some(QuerySet::new(
             world,
             &system_state.query_archetype_component_accesses[query_index],
+            system_state.change_ticks(),
         ))
     }
 
@@ -67,15 +77,16 @@ impl<T: QueryTuple> SystemParam for QuerySet<T> {
         system_state
             .query_archetype_component_accesses
             .push(TypeAccess::default());
+        system_state.query_sort_buffers.push(Default::default());
         system_state.query_accesses.push(T::get_accesses());
-        system_state
-            .query_type_names
-            .push(std::any::type_name::<T>());
+        let query_type_name = system_state.param_name(std::any::type_name::<T>());
+        system_state.query_type_names.push(query_type_name);
     }
 }
 
 impl<'a> SystemParam for &'a mut Commands {
     fn init(system_state: &mut SystemState, world: &World, _resources: &mut Resources) {
+        system_state.has_side_channel = true;
         system_state
             .commands
             .set_entity_reserver(world.get_entity_reserver())
@@ -94,6 +105,7 @@ impl<'a> SystemParam for &'a mut Commands {
 
 impl SystemParam for Arc<Mutex<Commands>> {
     fn init(system_state: &mut SystemState, world: &World, _resources: &mut Resources) {
+        system_state.has_side_channel = true;
         system_state.arc_commands.get_or_insert_with(|| {
             let mut commands = Commands::default();
             commands.set_entity_reserver(world.get_entity_reserver());
@@ -113,24 +125,87 @@ impl SystemParam for Arc<Mutex<Commands>> {
 
 impl<'a, T: Resource> SystemParam for Res<'a, T> {
     fn init(system_state: &mut SystemState, _world: &World, _resources: &mut Resources) {
-        system_state.resource_access.add_read(TypeId::of::<T>());
+        system_state.add_resource_read::<T, Self>();
     }
 
     #[inline]
     unsafe fn get_param(
-        _system_state: &mut SystemState,
+        system_state: &mut SystemState,
         _world: &World,
         resources: &Resources,
     ) -> Option<Self> {
+        let (added, mutated) = resources.get_unsafe_added_and_mutated::<T>(ResourceIndex::Global);
+        let ticks = system_state.change_ticks();
         Some(Res::new(
             resources.get_unsafe_ref::<T>(ResourceIndex::Global),
+            ticks.is_changed(*added.as_ptr()) || ticks.is_changed(*mutated.as_ptr()),
         ))
     }
 }
 
 impl<'a, T: Resource> SystemParam for ResMut<'a, T> {
     fn init(system_state: &mut SystemState, _world: &World, _resources: &mut Resources) {
-        system_state.resource_access.add_write(TypeId::of::<T>());
+        system_state.add_resource_write::<T, Self>();
+    }
+
+    #[inline]
+    unsafe fn get_param(
+        system_state: &mut SystemState,
+        _world: &World,
+        resources: &Resources,
+    ) -> Option<Self> {
+        let (added, mutated) = resources.get_unsafe_added_and_mutated::<T>(ResourceIndex::Global);
+        Some(ResMut::new(
+            resources.get_unsafe_ref::<T>(ResourceIndex::Global),
+            added,
+            mutated,
+            system_state.change_ticks(),
+        ))
+    }
+}
+
+impl<'a, T: Resource> SystemParam for Option<Res<'a, T>> {
+    fn init(system_state: &mut SystemState, world: &World, resources: &mut Resources) {
+        Res::<T>::init(system_state, world, resources);
+    }
+
+    #[inline]
+    unsafe fn get_param(
+        system_state: &mut SystemState,
+        world: &World,
+        resources: &Resources,
+    ) -> Option<Self> {
+        if resources.contains_without_borrow::<T>(ResourceIndex::Global) {
+            Res::<T>::get_param(system_state, world, resources).map(Some)
+        } else {
+            Some(None)
+        }
+    }
+}
+
+impl<'a, T: Resource> SystemParam for Option<ResMut<'a, T>> {
+    fn init(system_state: &mut SystemState, world: &World, resources: &mut Resources) {
+        ResMut::<T>::init(system_state, world, resources);
+    }
+
+    #[inline]
+    unsafe fn get_param(
+        system_state: &mut SystemState,
+        world: &World,
+        resources: &Resources,
+    ) -> Option<Self> {
+        if resources.contains_without_borrow::<T>(ResourceIndex::Global) {
+            ResMut::<T>::get_param(system_state, world, resources).map(Some)
+        } else {
+            Some(None)
+        }
+    }
+}
+
+impl<'a, T: 'static> SystemParam for NonSend<'a, T> {
+    fn init(system_state: &mut SystemState, _world: &World, _resources: &mut Resources) {
+        system_state.add_resource_read::<T, Self>();
+        system_state.is_non_send = true;
     }
 
     #[inline]
@@ -139,15 +214,14 @@ impl<'a, T: Resource> SystemParam for ResMut<'a, T> {
         _world: &World,
         resources: &Resources,
     ) -> Option<Self> {
-        let (value, type_state) =
-            resources.get_unsafe_ref_with_type_state::<T>(ResourceIndex::Global);
-        Some(ResMut::new(value, type_state.mutated()))
+        Some(NonSend::new(resources.get_thread_local_unsafe_ref::<T>()))
     }
 }
 
-impl<'a, T: Resource> SystemParam for ChangedRes<'a, T> {
+impl<'a, T: 'static> SystemParam for NonSendMut<'a, T> {
     fn init(system_state: &mut SystemState, _world: &World, _resources: &mut Resources) {
-        system_state.resource_access.add_read(TypeId::of::<T>());
+        system_state.add_resource_write::<T, Self>();
+        system_state.is_non_send = true;
     }
 
     #[inline]
@@ -156,8 +230,29 @@ impl<'a, T: Resource> SystemParam for ChangedRes<'a, T> {
         _world: &World,
         resources: &Resources,
     ) -> Option<Self> {
+        Some(NonSendMut::new(
+            resources.get_thread_local_unsafe_ref::<T>(),
+        ))
+    }
+}
+
+impl<'a, T: Resource> SystemParam for ChangedRes<'a, T> {
+    fn init(system_state: &mut SystemState, _world: &World, _resources: &mut Resources) {
+        system_state.add_resource_read::<T, Self>();
+    }
+
+    #[inline]
+    unsafe fn get_param(
+        system_state: &mut SystemState,
+        _world: &World,
+        resources: &Resources,
+    ) -> Option<Self> {
+        if !resources.contains_without_borrow::<T>(ResourceIndex::Global) {
+            return None;
+        }
         let (added, mutated) = resources.get_unsafe_added_and_mutated::<T>(ResourceIndex::Global);
-        if *added.as_ptr() || *mutated.as_ptr() {
+        let ticks = system_state.change_ticks();
+        if ticks.is_changed(*added.as_ptr()) || ticks.is_changed(*mutated.as_ptr()) {
             Some(ChangedRes::new(
                 resources.get_unsafe_ref::<T>(ResourceIndex::Global),
             ))
@@ -169,20 +264,62 @@ impl<'a, T: Resource> SystemParam for ChangedRes<'a, T> {
 
 impl<'a, T: Resource + FromResources> SystemParam for Local<'a, T> {
     fn init(system_state: &mut SystemState, _world: &World, resources: &mut Resources) {
-        system_state.resource_access.add_write(TypeId::of::<T>());
-        if resources.get_local::<T>(system_state.id).is_none() {
-            let value = T::from_resources(resources);
-            resources.insert_local(system_state.id, value);
+        let mut value = resources.create::<T>();
+        // a value inserted ahead of time with Commands::insert_local_resource takes precedence
+        if let Some(mut inserted) = resources.get_local_mut::<T>(system_state.id) {
+            std::mem::swap(&mut value, &mut *inserted);
         }
+        system_state.local_resources.push(Box::new(value));
+        system_state.has_side_channel = true;
     }
 
     #[inline]
     unsafe fn get_param(
         system_state: &mut SystemState,
         _world: &World,
-        resources: &Resources,
+        _resources: &Resources,
     ) -> Option<Self> {
-        Some(Local::new(resources, system_state.id))
+        let local = system_state.local_resources[system_state.current_local_index]
+            .downcast_mut::<T>()
+            .unwrap();
+        system_state.current_local_index += 1;
+        Some(Local::new(local))
+    }
+}
+
+/// Entities that had a `T` component removed (or were despawned) earlier in the current schedule run.
+///
+/// Removals are cleared when the schedule clears its trackers at the end of each run, so a system only sees removals
+/// that were applied before it ran in the same frame.
+pub struct RemovedComponents<'a, T: Component> {
+    world: &'a World,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: Component> RemovedComponents<'a, T> {
+    /// Iterates the entities whose `T` component was removed
+    pub fn iter(&self) -> std::iter::Cloned<std::slice::Iter<'a, Entity>> {
+        self.world.removed::<T>().iter().cloned()
+    }
+}
+
+impl<'a, T: Component> SystemParam for RemovedComponents<'a, T> {
+    fn init(system_state: &mut SystemState, _world: &World, _resources: &mut Resources) {
+        // removals outlive the archetypes of the removed components
+        system_state.has_side_channel = true;
+    }
+
+    #[inline]
+    unsafe fn get_param(
+        _system_state: &mut SystemState,
+        world: &World,
+        _resources: &Resources,
+    ) -> Option<Self> {
+        let world: &'a World = std::mem::transmute(world);
+        Some(RemovedComponents {
+            world,
+            marker: PhantomData,
+        })
     }
 }
 
@@ -1,5 +1,5 @@
 use crate::{Axis, Input};
-use bevy_app::{EventReader, Events};
+use bevy_app::{Events, ManualEventReader};
 use bevy_ecs::{Local, Res, ResMut};
 use bevy_utils::HashMap;
 
@@ -200,7 +200,7 @@ impl ButtonAxisSettings {
 }
 
 pub fn gamepad_event_system(
-    mut event_reader: Local<EventReader<GamepadEventRaw>>,
+    mut event_reader: Local<ManualEventReader<GamepadEventRaw>>,
     mut button_input: ResMut<Input<GamepadButton>>,
     mut axis: ResMut<Axis<GamepadAxis>>,
     mut button_axis: ResMut<Axis<GamepadButton>>,
@@ -13,7 +13,7 @@ pub struct KeyboardInput {
 /// State used by the keyboard input system
 #[derive(Default)]
 pub struct KeyboardInputState {
-    keyboard_input_event_reader: EventReader<KeyboardInput>,
+    keyboard_input_event_reader: ManualEventReader<KeyboardInput>,
 }
 
 /// Updates the Input<KeyCode> resource with the latest KeyboardInput events
@@ -1,5 +1,5 @@
 use crate::{ElementState, Input};
-use bevy_app::prelude::{EventReader, Events};
+use bevy_app::prelude::{Events, ManualEventReader};
 use bevy_ecs::{Local, Res, ResMut};
 use bevy_math::Vec2;
 
@@ -44,7 +44,7 @@ pub struct MouseWheel {
 /// State used by the mouse button input system
 #[derive(Default)]
 pub struct MouseButtonInputState {
-    mouse_button_input_event_reader: EventReader<MouseButtonInput>,
+    mouse_button_input_event_reader: ManualEventReader<MouseButtonInput>,
 }
 
 /// Updates the Input<MouseButton> resource with the latest MouseButtonInput events
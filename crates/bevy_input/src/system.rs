@@ -3,7 +3,7 @@ use crate::{
     ElementState,
 };
 use bevy_app::{
-    prelude::{EventReader, Events},
+    prelude::{Events, ManualEventReader},
     AppExit,
 };
 use bevy_ecs::{Local, Res, ResMut};
@@ -11,7 +11,7 @@ use bevy_ecs::{Local, Res, ResMut};
 /// Local "exit on escape" system state
 #[derive(Default)]
 pub struct ExitOnEscapeState {
-    reader: EventReader<KeyboardInput>,
+    reader: ManualEventReader<KeyboardInput>,
 }
 
 /// Sends the AppExit event whenever the "esc" key is pressed.
@@ -23,7 +23,7 @@ pub fn exit_on_esc_system(
     for event in state.reader.iter(&keyboard_input_events) {
         if let Some(key_code) = event.key_code {
             if event.state == ElementState::Pressed && key_code == KeyCode::Escape {
-                app_exit_events.send(AppExit);
+                app_exit_events.send(AppExit::Success);
             }
         }
     }
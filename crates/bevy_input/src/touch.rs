@@ -1,4 +1,4 @@
-use bevy_app::{EventReader, Events};
+use bevy_app::{Events, ManualEventReader};
 use bevy_ecs::{Local, Res, ResMut};
 use bevy_math::Vec2;
 use bevy_utils::HashMap;
@@ -80,7 +80,7 @@ pub enum TouchPhase {
 
 #[derive(Default)]
 pub struct TouchSystemState {
-    touch_event_reader: EventReader<TouchInput>,
+    touch_event_reader: ManualEventReader<TouchInput>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -1,5 +1,13 @@
 use bevy_app::{PluginGroup, PluginGroupBuilder};
 
+/// The plugins of a typical app, in the order they are built. Individual plugins can be left out or replaced:
+/// ```ignore
+/// App::build().add_plugins_with(DefaultPlugins, |group| {
+///     group
+///         .disable::<bevy_audio::AudioPlugin>()
+///         .set(bevy_window::WindowPlugin { add_primary_window: false, ..Default::default() })
+/// });
+/// ```
 pub struct DefaultPlugins;
 
 impl PluginGroup for DefaultPlugins {
@@ -1,5 +1,5 @@
 use super::CameraProjection;
-use bevy_app::prelude::{EventReader, Events};
+use bevy_app::prelude::{Events, ManualEventReader};
 use bevy_ecs::{Added, Component, Entity, Local, Query, QuerySet, Res};
 use bevy_math::Mat4;
 use bevy_property::Properties;
@@ -29,8 +29,8 @@ impl Default for DepthCalculation {
 
 #[derive(Default)]
 pub struct CameraSystemState {
-    window_resized_event_reader: EventReader<WindowResized>,
-    window_created_event_reader: EventReader<WindowCreated>,
+    window_resized_event_reader: ManualEventReader<WindowResized>,
+    window_created_event_reader: ManualEventReader<WindowCreated>,
 }
 
 pub fn camera_system<T: CameraProjection + Component>(
@@ -2,15 +2,15 @@ use crate::{
     render_graph::{Node, ResourceSlotInfo, ResourceSlots},
     renderer::{RenderContext, RenderResourceId, RenderResourceType},
 };
-use bevy_app::prelude::{EventReader, Events};
+use bevy_app::prelude::{Events, ManualEventReader};
 use bevy_ecs::{Resources, World};
 use bevy_window::{WindowCreated, WindowId, WindowResized, Windows};
 use std::borrow::Cow;
 
 pub struct WindowSwapChainNode {
     window_id: WindowId,
-    window_created_event_reader: EventReader<WindowCreated>,
-    window_resized_event_reader: EventReader<WindowResized>,
+    window_created_event_reader: ManualEventReader<WindowCreated>,
+    window_resized_event_reader: ManualEventReader<WindowResized>,
 }
 
 impl WindowSwapChainNode {
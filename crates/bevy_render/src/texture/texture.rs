@@ -2,7 +2,7 @@ use super::{SamplerDescriptor, TextureDescriptor, TextureFormat};
 use crate::renderer::{
     RenderResource, RenderResourceContext, RenderResourceId, RenderResourceType,
 };
-use bevy_app::prelude::{EventReader, Events};
+use bevy_app::prelude::{Events, ManualEventReader};
 use bevy_asset::{AssetEvent, Assets, Handle};
 use bevy_ecs::{Res, ResMut};
 use bevy_math::Vec2;
@@ -150,7 +150,7 @@ impl Texture {
 
 #[derive(Default)]
 pub struct TextureResourceSystemState {
-    event_reader: EventReader<AssetEvent<Texture>>,
+    event_reader: ManualEventReader<AssetEvent<Texture>>,
 }
 
 impl RenderResource for Option<Handle<Texture>> {
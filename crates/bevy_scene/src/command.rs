@@ -8,7 +8,7 @@ pub struct SpawnScene {
 }
 
 impl Command for SpawnScene {
-    fn write(self: Box<Self>, _world: &mut World, resources: &mut Resources) {
+    fn write(self, _world: &mut World, resources: &mut Resources) {
         let mut spawner = resources.get_mut::<SceneSpawner>().unwrap();
         spawner.spawn(self.scene_handle);
     }
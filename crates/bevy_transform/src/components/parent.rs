@@ -41,3 +41,74 @@ impl DerefMut for Parent {
         &mut self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Parent;
+    use crate::components::Children;
+    use bevy_ecs::{Resources, World};
+    use bevy_type_registry::{ComponentRegistration, ComponentRegistry};
+
+    #[test]
+    fn clone_entities_into() {
+        let mut registry = ComponentRegistry::default();
+        registry.add_registration(
+            ComponentRegistration::build::<Parent>()
+                .map_entities()
+                .finish(),
+        );
+        registry.add_registration(
+            ComponentRegistration::build::<Children>()
+                .map_entities()
+                .finish(),
+        );
+
+        let mut source = World::default();
+        let parent = source.spawn(());
+        let child = source.spawn((Parent(parent),));
+        source.insert_one(parent, Children::with(&[child])).unwrap();
+
+        // the entities already in the destination keep referring to their own parents
+        let mut destination = World::default();
+        let existing_parent = destination.spawn(());
+        let existing_child = destination.spawn((Parent(existing_parent),));
+
+        let entity_map = registry
+            .clone_entities_into(
+                &source,
+                &mut destination,
+                &Resources::default(),
+                &[parent, child],
+            )
+            .unwrap();
+        let cloned_parent = entity_map.get(parent).unwrap();
+        let cloned_child = entity_map.get(child).unwrap();
+
+        assert_eq!(
+            *destination.get::<Parent>(cloned_child).unwrap(),
+            Parent(cloned_parent)
+        );
+        assert_eq!(
+            destination
+                .get::<Children>(cloned_parent)
+                .unwrap()
+                .0
+                .as_slice(),
+            &[cloned_child]
+        );
+        assert_eq!(
+            *destination.get::<Parent>(existing_child).unwrap(),
+            Parent(existing_parent)
+        );
+
+        // a child can't be cloned without the parent it refers to
+        assert!(registry
+            .clone_entities_into(
+                &source,
+                &mut World::default(),
+                &Resources::default(),
+                &[child]
+            )
+            .is_err());
+    }
+}
@@ -10,7 +10,7 @@ pub struct InsertChildren {
 }
 
 impl Command for InsertChildren {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
+    fn write(self, world: &mut World, _resources: &mut Resources) {
         for child in self.children.iter() {
             world
                 .insert(*child, (Parent(self.parent), PreviousParent(self.parent)))
@@ -45,7 +45,7 @@ pub struct ChildBuilder<'a> {
 }
 
 impl Command for PushChildren {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
+    fn write(self, world: &mut World, _resources: &mut Resources) {
         for child in self.children.iter() {
             world
                 .insert(*child, (Parent(self.parent), PreviousParent(self.parent)))
@@ -70,10 +70,8 @@ impl Command for PushChildren {
 
 impl<'a> ChildBuilder<'a> {
     pub fn spawn(&mut self, components: impl DynamicBundle + Send + Sync + 'static) -> &mut Self {
-        self.commands.spawn(components);
-        self.push_children
-            .children
-            .push(self.commands.current_entity().unwrap());
+        let entity = self.commands.spawn(components).id();
+        self.push_children.children.push(entity);
         self
     }
 
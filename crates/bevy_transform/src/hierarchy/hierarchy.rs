@@ -52,29 +52,32 @@ fn despawn_with_children_recursive(world: &mut World, entity: Entity) {
 
 // Should only be called by `despawn_with_children_recursive`!
 fn despawn_with_children_recursive_inner(world: &mut World, entity: Entity) {
-    if let Some(children) = world
+    let children = world
         .get::<Children>(entity)
         .ok()
-        .map(|children| children.0.iter().cloned().collect::<Vec<Entity>>())
-    {
-        for e in children {
-            despawn_with_children_recursive(world, e);
-        }
-    }
+        .map(|children| children.0.iter().cloned().collect::<Vec<Entity>>());
 
+    // the entity is despawned before its children, so a malformed hierarchy that loops back to it stops here
     if let Err(e) = world.despawn(entity) {
         debug!("Failed to despawn entity {:?}: {}", entity, e);
+        return;
+    }
+
+    for child in children.into_iter().flatten() {
+        despawn_with_children_recursive_inner(world, child);
     }
 }
 
 impl Command for DespawnRecursive {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
+    fn write(self, world: &mut World, _resources: &mut Resources) {
         despawn_with_children_recursive(world, self.entity);
     }
 }
 
 pub trait DespawnRecursiveExt {
-    /// Despawns the provided entity and its children.
+    /// Despawns the provided entity and its children. The hierarchy is walked depth-first when the command is applied,
+    /// and the entity is removed from the [Children] of its [Parent], if it has one. Entities that were already
+    /// despawned are skipped, so a hierarchy that loops back on itself doesn't recurse forever.
     fn despawn_recursive(&mut self, entity: Entity) -> &mut Self;
 }
 
@@ -88,8 +91,11 @@ impl DespawnRecursiveExt for Commands {
 #[cfg(test)]
 mod tests {
     use super::DespawnRecursiveExt;
-    use crate::{components::Children, hierarchy::BuildChildren};
-    use bevy_ecs::{Commands, Resources, World};
+    use crate::{
+        components::{Children, Parent},
+        hierarchy::BuildChildren,
+    };
+    use bevy_ecs::{Commands, Entity, Resources, World};
 
     #[test]
     fn despawn_recursive() {
@@ -149,4 +155,68 @@ mod tests {
             vec![(0u32, 0u64), (0u32, 0u64), (0u32, 0u64), (1u32, 1u64)]
         );
     }
+
+    #[test]
+    fn despawn_recursive_three_levels() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut command_buffer = Commands::default();
+        command_buffer.set_entity_reserver(world.get_entity_reserver());
+
+        let parent = command_buffer.spawn((4u32,)).id();
+        let mut root = None;
+        command_buffer.with_children(|parent| {
+            root = parent.spawn((0u32,)).current_entity();
+        });
+        let root = root.unwrap();
+        command_buffer.set_current_entity(root);
+        command_buffer.with_children(|parent| {
+            parent.spawn((1u32,)).with_children(|parent| {
+                parent.spawn((2u32,));
+                parent.spawn((2u32,));
+            });
+            parent.spawn((1u32,)).with_children(|parent| {
+                parent.spawn((2u32,));
+            });
+        });
+        let unrelated = command_buffer.spawn((3u32,)).id();
+        command_buffer.apply(&mut world, &mut resources);
+        assert_eq!(world.query::<&u32>().count(), 8);
+
+        command_buffer.despawn_recursive(root);
+        command_buffer.apply(&mut world, &mut resources);
+
+        // the root and all of its descendants are gone
+        let mut remaining = world.query::<Entity>().collect::<Vec<_>>();
+        remaining.sort();
+        assert_eq!(remaining, vec![parent, unrelated]);
+        assert!(
+            world.get::<Children>(parent).unwrap().is_empty(),
+            "the parent no longer lists the despawned root"
+        );
+    }
+
+    #[test]
+    fn despawn_recursive_cycle() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let a = world.spawn((0u32,));
+        let b = world.spawn((1u32,));
+        let c = world.spawn((2u32,));
+        let despawned = world.spawn((3u32,));
+        world.despawn(despawned).unwrap();
+
+        // a -> b -> c -> a, with c also pointing at an entity that no longer exists
+        world.insert(a, (Children::with(&[b]), Parent(c))).unwrap();
+        world.insert(b, (Children::with(&[c]), Parent(a))).unwrap();
+        world
+            .insert(c, (Children::with(&[a, despawned]), Parent(b)))
+            .unwrap();
+
+        let mut command_buffer = Commands::default();
+        command_buffer.despawn_recursive(b);
+        command_buffer.apply(&mut world, &mut resources);
+
+        assert_eq!(world.query::<Entity>().count(), 0);
+    }
 }
@@ -1,5 +1,5 @@
 use crate::components::*;
-use bevy_ecs::{Changed, Commands, Entity, IntoSystem, Query, System, Without};
+use bevy_ecs::{BoxedSystem, Changed, Commands, Entity, IntoSystem, Query, Without};
 use bevy_utils::HashMap;
 use smallvec::SmallVec;
 
@@ -65,7 +65,7 @@ pub fn parent_update_system(
     });
 }
 
-pub fn hierarchy_maintenance_systems() -> Vec<Box<dyn System>> {
+pub fn hierarchy_maintenance_systems() -> Vec<BoxedSystem> {
     vec![parent_update_system.system()]
 }
 
@@ -73,7 +73,7 @@ pub fn hierarchy_maintenance_systems() -> Vec<Box<dyn System>> {
 mod test {
     use super::*;
     use crate::{hierarchy::BuildChildren, transform_systems};
-    use bevy_ecs::{Resources, Schedule, World};
+    use bevy_ecs::{Local, Resources, Schedule, World};
     use bevy_math::Vec3;
 
     #[test]
@@ -157,4 +157,45 @@ mod test {
             vec![children[1]]
         );
     }
+
+    #[test]
+    fn link_entities_spawned_by_one_system() {
+        fn spawn_linked(commands: &mut Commands, mut spawned: Local<bool>) {
+            if *spawned {
+                return;
+            }
+            let parent = commands.spawn((Transform::default(),)).id();
+            commands.spawn((Transform::default(), Parent(parent)));
+            *spawned = true;
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("spawn");
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("spawn", spawn_linked.system());
+        for system in transform_systems() {
+            schedule.add_system_to_stage("update", system);
+        }
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+
+        let (child, parent) = world
+            .query::<(Entity, &Parent)>()
+            .map(|(entity, parent)| (entity, parent.0))
+            .next()
+            .unwrap();
+        assert!(world.get::<Transform>(parent).is_ok());
+        assert_eq!(
+            world
+                .get::<Children>(parent)
+                .unwrap()
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>(),
+            vec![child]
+        );
+    }
 }
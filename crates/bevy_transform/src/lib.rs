@@ -7,11 +7,11 @@ pub mod prelude {
 }
 
 use bevy_app::prelude::*;
-use bevy_ecs::prelude::*;
+use bevy_ecs::{prelude::*, BoxedSystem};
 use bevy_type_registry::RegisterType;
 use prelude::{Children, GlobalTransform, Parent, Transform};
 
-pub(crate) fn transform_systems() -> Vec<Box<dyn System>> {
+pub(crate) fn transform_systems() -> Vec<BoxedSystem> {
     let mut systems = Vec::with_capacity(5);
 
     systems.append(&mut hierarchy::hierarchy_maintenance_systems());
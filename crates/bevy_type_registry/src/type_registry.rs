@@ -75,6 +75,45 @@ impl ComponentRegistry {
     pub fn iter(&self) -> impl Iterator<Item = &ComponentRegistration> {
         self.registrations.values()
     }
+
+    /// Copies the registered components of `entities` from `source` onto new entities in `destination`, then maps the
+    /// entities referred to by the copied [MapEntities] components to their copies. Returns the map from each original
+    /// entity to its copy.
+    ///
+    /// Components that aren't registered are left behind, and entities that don't exist in `source` are skipped. Fails
+    /// if a copied component refers to an entity that wasn't copied along with it.
+    pub fn clone_entities_into(
+        &self,
+        source: &World,
+        destination: &mut World,
+        resources: &Resources,
+        entities: &[Entity],
+    ) -> Result<EntityMap, MapEntitiesError> {
+        let mut entity_map = EntityMap::default();
+        for &source_entity in entities {
+            if !source.contains(source_entity) || entity_map.get(source_entity).is_ok() {
+                continue;
+            }
+            let destination_entity = destination.spawn(());
+            for registration in self.iter() {
+                if source.has_component_type(source_entity, registration.ty) {
+                    registration.component_copy(
+                        source,
+                        destination,
+                        resources,
+                        source_entity,
+                        destination_entity,
+                    );
+                }
+            }
+            entity_map.insert(source_entity, destination_entity);
+        }
+
+        for registration in self.iter() {
+            registration.map_entities(destination, &entity_map)?;
+        }
+        Ok(entity_map)
+    }
 }
 
 #[derive(Clone)]
@@ -274,8 +313,11 @@ where
     {
         self.registration.map_entities_fn = |world: &mut World, entity_map: &EntityMap| {
             // TODO: add UntrackedMut<T> pointer that returns &mut T. This will avoid setting the "mutated" state
-            for mut component in &mut world.query_mut::<&mut T>() {
-                component.map_entities(entity_map)?;
+            // only the mapped entities are visited, the rest of the world may refer to entities the map doesn't know
+            for entity in entity_map.values() {
+                if let Ok(mut component) = world.get_mut::<T>(entity) {
+                    component.map_entities(entity_map)?;
+                }
             }
 
             Ok(())
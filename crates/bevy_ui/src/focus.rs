@@ -1,5 +1,5 @@
 use crate::Node;
-use bevy_app::{EventReader, Events};
+use bevy_app::{Events, ManualEventReader};
 use bevy_core::FloatOrd;
 use bevy_ecs::prelude::*;
 use bevy_input::{mouse::MouseButton, touch::Touches, Input};
@@ -34,7 +34,7 @@ impl Default for FocusPolicy {
 
 #[derive(Default)]
 pub struct State {
-    cursor_moved_event_reader: EventReader<CursorMoved>,
+    cursor_moved_event_reader: ManualEventReader<CursorMoved>,
     cursor_position: Vec2,
     hovered_entity: Option<Entity>,
 }
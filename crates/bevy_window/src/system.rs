@@ -1,13 +1,13 @@
 use crate::WindowCloseRequested;
 use bevy_app::{
-    prelude::{EventReader, Events},
+    prelude::{Events, ManualEventReader},
     AppExit,
 };
 use bevy_ecs::{Local, Res, ResMut};
 
 #[derive(Default)]
 pub struct ExitOnWindowCloseState {
-    event_reader: EventReader<WindowCloseRequested>,
+    event_reader: ManualEventReader<WindowCloseRequested>,
 }
 
 pub fn exit_on_window_close_system(
@@ -21,6 +21,6 @@ pub fn exit_on_window_close_system(
         .next()
         .is_some()
     {
-        app_exit_events.send(AppExit);
+        app_exit_events.send(AppExit::Success);
     }
 }
@@ -17,6 +17,7 @@ use bevy_window::{
     CreateWindow, CursorMoved, ReceivedCharacter, Window, WindowCloseRequested, WindowCreated,
     WindowResized, Windows,
 };
+use std::{cell::Cell, rc::Rc};
 use winit::{
     event::{self, DeviceEvent, Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
@@ -143,10 +144,12 @@ where
     panic!("Run return is not supported on this platform!")
 }
 
-pub fn winit_runner(mut app: App) {
+/// Runs the app in a winit event loop until an [AppExit] is sent. The app is dropped when the loop is destroyed, before
+/// the process exits with the code of the [AppExit] (or before this returns, if [WinitConfig::return_from_run] is set).
+pub fn winit_runner(mut app: App) -> AppExit {
     let mut event_loop = EventLoop::new();
-    let mut create_window_event_reader = EventReader::<CreateWindow>::default();
-    let mut app_exit_event_reader = EventReader::<AppExit>::default();
+    let mut create_window_event_reader = ManualEventReader::<CreateWindow>::default();
+    let mut app_exit_event_reader = ManualEventReader::<AppExit>::default();
 
     app.resources
         .insert_thread_local(EventLoopProxyPtr(
@@ -168,21 +171,19 @@ pub fn winit_runner(mut app: App) {
         .get::<WinitConfig>()
         .map_or(false, |config| config.return_from_run);
 
+    let app_exit = Rc::new(Cell::new(None));
+    let handler_app_exit = app_exit.clone();
+
     let event_handler = move |event: Event<()>,
                               event_loop: &EventLoopWindowTarget<()>,
                               control_flow: &mut ControlFlow| {
-        *control_flow = if cfg!(feature = "metal-auto-capture") {
+        *control_flow = if cfg!(feature = "metal-auto-capture") || handler_app_exit.get().is_some()
+        {
             ControlFlow::Exit
         } else {
             ControlFlow::Poll
         };
 
-        if let Some(app_exit_events) = app.resources.get_mut::<Events<AppExit>>() {
-            if app_exit_event_reader.latest(&app_exit_events).is_some() {
-                *control_flow = ControlFlow::Exit;
-            }
-        }
-
         match event {
             event::Event::WindowEvent {
                 event: WindowEvent::Resized(size),
@@ -306,6 +307,23 @@ pub fn winit_runner(mut app: App) {
                     &mut create_window_event_reader,
                 );
                 app.update();
+
+                if let Some(app_exit_events) = app.resources.get::<Events<AppExit>>() {
+                    if let Some(exit) =
+                        AppExit::from_events(app_exit_event_reader.iter(&app_exit_events))
+                    {
+                        handler_app_exit.set(Some(exit));
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
+            }
+            event::Event::LoopDestroyed => {
+                // winit doesn't drop the event handler before exiting the process, so drop the app here
+                drop(std::mem::take(&mut app));
+                let exit = handler_app_exit.get().unwrap_or_default();
+                if !should_return_from_run && exit.is_error() {
+                    std::process::exit(exit.code() as i32);
+                }
             }
             _ => (),
         }
@@ -315,12 +333,13 @@ pub fn winit_runner(mut app: App) {
     } else {
         run(event_loop, event_handler);
     }
+    app_exit.get().unwrap_or_default()
 }
 
 fn handle_create_window_events(
     resources: &mut Resources,
     event_loop: &EventLoopWindowTarget<()>,
-    create_window_event_reader: &mut EventReader<CreateWindow>,
+    create_window_event_reader: &mut ManualEventReader<CreateWindow>,
 ) {
     let mut winit_windows = resources.get_mut::<WinitWindows>().unwrap();
     let mut windows = resources.get_mut::<Windows>().unwrap();
@@ -5,7 +5,7 @@ struct Input(String);
 
 /// This example demonstrates you can create a custom runner (to update an app manually). It reads
 /// lines from stdin and prints them from within the ecs.
-fn my_runner(mut app: App) {
+fn my_runner(mut app: App) -> AppExit {
     app.initialize();
 
     println!("Type stuff into the console");
@@ -16,6 +16,7 @@ fn my_runner(mut app: App) {
         }
         app.update();
     }
+    AppExit::Success
 }
 
 fn print_system(input: Res<Input>) {
@@ -9,7 +9,7 @@ fn main() {
 
 #[derive(Default)]
 struct State {
-    event_reader: EventReader<ReceivedCharacter>,
+    event_reader: ManualEventReader<ReceivedCharacter>,
 }
 
 /// This system prints out all char events as they come in
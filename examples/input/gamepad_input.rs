@@ -16,7 +16,7 @@ fn main() {
 #[derive(Default)]
 struct GamepadLobby {
     gamepads: HashSet<Gamepad>,
-    gamepad_event_reader: EventReader<GamepadEvent>,
+    gamepad_event_reader: ManualEventReader<GamepadEvent>,
 }
 
 fn connection_system(mut lobby: ResMut<GamepadLobby>, gamepad_event: Res<Events<GamepadEvent>>) {
@@ -10,11 +10,8 @@ fn main() {
         .run();
 }
 
-fn gamepad_events(
-    mut event_reader: Local<EventReader<GamepadEvent>>,
-    gamepad_event: Res<Events<GamepadEvent>>,
-) {
-    for event in event_reader.iter(&gamepad_event) {
+fn gamepad_events(mut event_reader: EventReader<GamepadEvent>) {
+    for event in event_reader.iter() {
         match &event {
             GamepadEvent(gamepad, GamepadEventType::Connected) => {
                 println!("{:?} Connected", gamepad);
@@ -9,7 +9,7 @@ fn main() {
 
 #[derive(Default)]
 struct State {
-    event_reader: EventReader<KeyboardInput>,
+    event_reader: ManualEventReader<KeyboardInput>,
 }
 
 /// This system prints out all keyboard events as they come in
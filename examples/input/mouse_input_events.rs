@@ -13,10 +13,10 @@ fn main() {
 
 #[derive(Default)]
 struct State {
-    mouse_button_event_reader: EventReader<MouseButtonInput>,
-    mouse_motion_event_reader: EventReader<MouseMotion>,
-    cursor_moved_event_reader: EventReader<CursorMoved>,
-    mouse_wheel_event_reader: EventReader<MouseWheel>,
+    mouse_button_event_reader: ManualEventReader<MouseButtonInput>,
+    mouse_motion_event_reader: ManualEventReader<MouseMotion>,
+    cursor_moved_event_reader: ManualEventReader<CursorMoved>,
+    mouse_wheel_event_reader: ManualEventReader<MouseWheel>,
 }
 
 /// This system prints out all mouse events as they come in
@@ -9,7 +9,7 @@ fn main() {
 
 #[derive(Default)]
 struct State {
-    event_reader: EventReader<TouchInput>,
+    event_reader: ManualEventReader<TouchInput>,
 }
 
 fn touch_event_system(mut state: Local<State>, touch_events: Res<Events<TouchInput>>) {
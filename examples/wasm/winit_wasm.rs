@@ -47,11 +47,11 @@ struct CounterState {
 
 #[derive(Default)]
 struct TrackInputState {
-    keys: EventReader<KeyboardInput>,
-    cursor: EventReader<CursorMoved>,
-    motion: EventReader<MouseMotion>,
-    mousebtn: EventReader<MouseButtonInput>,
-    scroll: EventReader<MouseWheel>,
+    keys: ManualEventReader<KeyboardInput>,
+    cursor: ManualEventReader<CursorMoved>,
+    motion: ManualEventReader<MouseMotion>,
+    mousebtn: ManualEventReader<MouseButtonInput>,
+    scroll: ManualEventReader<MouseWheel>,
 }
 
 fn track_input_events(